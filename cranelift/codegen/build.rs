@@ -14,9 +14,11 @@
 // The build script expects to be run from the directory where this build.rs file lives. The
 // current directory is used to find the sources.
 
+use cranelift_codegen_lower_meta as lower_meta;
 use cranelift_codegen_meta as meta;
 
 use std::env;
+use std::path::PathBuf;
 use std::process;
 use std::time::Instant;
 
@@ -61,6 +63,21 @@ fn main() {
         process::exit(1);
     }
 
+    // The x64 backend isn't an ISA of the meta crate; its lowering rules are compiled here.
+    if env::var("CARGO_FEATURE_X64").is_ok() {
+        let rules = crate_dir
+            .join("src")
+            .join("isa")
+            .join("x64")
+            .join("lower.rules");
+        println!("cargo:rerun-if-changed={}", rules.display());
+        let out = PathBuf::from(&out_dir).join("lower-x64.rs");
+        if let Err(err) = lower_meta::generate(&rules, &out) {
+            eprintln!("Error: {}", err);
+            process::exit(1);
+        }
+    }
+
     if env::var("CRANELIFT_VERBOSE").is_ok() {
         for isa in &isas {
             println!("cargo:warning=Includes support for {} ISA", isa.to_string());
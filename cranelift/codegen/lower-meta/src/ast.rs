@@ -0,0 +1,508 @@
+//! The lowering rules, and their checking as they are read from s-expressions.
+
+use crate::error::{Error, Pos};
+use crate::sexpr::SExpr;
+use cranelift_codegen_meta::inst_info::{InstructionInfo, OperandInfo};
+use std::collections::HashMap;
+
+/// A lowering rule: when its pattern matches an instruction, its clauses run in order and emit
+/// the machine instructions the instruction lowers to.
+#[derive(Debug)]
+pub(crate) struct Rule {
+    pub pos: Pos,
+    /// Rules with a higher priority are tried first.
+    pub priority: i64,
+    /// The pattern matching the instruction being lowered; always a [Pattern::Inst].
+    pub pattern: Pattern,
+    pub clauses: Vec<Clause>,
+}
+
+impl Rule {
+    /// Can this rule fail after its pattern matched?
+    pub fn is_guarded(&self) -> bool {
+        self.clauses.iter().any(|clause| match clause {
+            Clause::If(_) | Clause::IfLet(..) => true,
+            Clause::Let(..) | Clause::Emit(_) => false,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum Pattern {
+    /// Match any value, possibly of a given type.
+    Wildcard { ty: Option<TypeSpec> },
+    /// Bind a value, possibly of a given type, or an immediate.
+    Bind { name: String, ty: Option<TypeSpec> },
+    /// Match a value produced by an instruction, and the instruction's operands.
+    Inst {
+        inst: InstructionInfo,
+        ty: Option<TypeSpec>,
+        operands: Vec<Pattern>,
+    },
+}
+
+/// The type of a value or the controlling type of an instruction in a pattern.
+#[derive(Debug, PartialEq)]
+pub(crate) enum TypeSpec {
+    /// The type must be this one, named as in `ir::types`.
+    Is(String),
+    /// Bind the type.
+    Bind(String),
+}
+
+#[derive(Debug)]
+pub(crate) enum Clause {
+    /// Fail unless the expression is true.
+    If(Expr),
+    /// Fail unless the expression is `Some`, and bind its contents.
+    IfLet(String, Expr),
+    /// Bind the value of the expression.
+    Let(String, Expr),
+    /// Emit the machine instruction built by the expression.
+    Emit(Expr),
+}
+
+#[derive(Debug)]
+pub(crate) enum Expr {
+    /// A variable bound by the pattern or by an earlier clause.
+    Var(String),
+    /// A literal or a Rust item, passed through as is.
+    Rust(String),
+    /// The register of the given result of the instruction being lowered.
+    Out(usize),
+    /// A call of a Rust function.
+    Call(String, Vec<Expr>),
+}
+
+/// Names that generated code uses for itself.
+fn is_reserved(name: &str) -> bool {
+    name == "ctx" || name.starts_with("insn")
+}
+
+/// Is this the name of a type in `ir::types`?
+fn is_type_name(name: &str) -> bool {
+    if name == "iflags" || name == "fflags" {
+        return true;
+    }
+    let mut parts = name.splitn(2, 'x');
+    let lane = parts.next().unwrap();
+    let lanes_ok = parts.next().map_or(true, |n| {
+        !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())
+    });
+    let mut chars = lane.chars();
+    let kind_ok = match chars.next() {
+        Some('i') | Some('b') | Some('f') | Some('r') => true,
+        _ => false,
+    };
+    let bits = chars.as_str();
+    kind_ok && !bits.is_empty() && bits.bytes().all(|b| b.is_ascii_digit()) && lanes_ok
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Reads rules from s-expressions, checking them along the way.
+pub(crate) struct Parser {
+    instructions: HashMap<String, InstructionInfo>,
+}
+
+/// The variables bound so far in a rule.
+type Scope = HashMap<String, Pos>;
+
+impl Parser {
+    pub fn new(instructions: Vec<InstructionInfo>) -> Self {
+        let instructions = instructions
+            .into_iter()
+            .map(|inst| (inst.name.clone(), inst))
+            .collect();
+        Self { instructions }
+    }
+
+    pub fn rules(&self, exprs: &[SExpr]) -> Result<Vec<Rule>, Error> {
+        exprs.iter().map(|expr| self.rule(expr)).collect()
+    }
+
+    /// Read `(rule [PRIORITY] PATTERN CLAUSE...)`.
+    fn rule(&self, expr: &SExpr) -> Result<Rule, Error> {
+        let pos = expr.pos();
+        let items = match expr {
+            SExpr::List(items, _) if head(items) == Some("rule") => &items[1..],
+            _ => return Err(Error::at(pos, "expected a `(rule ...)`")),
+        };
+        let (priority, items) = match items.first() {
+            Some(SExpr::Atom(atom, pos)) => {
+                let priority = atom
+                    .parse()
+                    .map_err(|_| Error::at(*pos, format!("invalid priority `{}`", atom)))?;
+                (priority, &items[1..])
+            }
+            _ => (0, items),
+        };
+        let (pattern, items) = match items.split_first() {
+            Some((pattern @ SExpr::List(..), items)) => (pattern, items),
+            _ => return Err(Error::at(pos, "expected the pattern of the rule")),
+        };
+
+        let mut scope = Scope::new();
+        let pattern = self.pattern(pattern, None, &mut scope)?;
+        let mut clauses = vec![];
+        let mut emitted = false;
+        for item in items {
+            let clause = self.clause(item, &mut scope)?;
+            match clause {
+                Clause::If(_) | Clause::IfLet(..) if emitted => {
+                    return Err(Error::at(
+                        item.pos(),
+                        "conditions must come before any instruction is emitted",
+                    ))
+                }
+                Clause::Emit(_) => emitted = true,
+                _ => {}
+            }
+            clauses.push(clause);
+        }
+
+        Ok(Rule {
+            pos,
+            priority,
+            pattern,
+            clauses,
+        })
+    }
+
+    /// Read a pattern. `operand` is the kind of operand the pattern matches, or `None` for the
+    /// instruction being lowered.
+    fn pattern(
+        &self,
+        expr: &SExpr,
+        operand: Option<&OperandInfo>,
+        scope: &mut Scope,
+    ) -> Result<Pattern, Error> {
+        match (expr, operand) {
+            (SExpr::Atom(atom, pos), Some(operand)) => {
+                let (name, ty) = split_type(atom);
+                if let OperandInfo::Immediate(_) = operand {
+                    if ty.is_some() {
+                        return Err(Error::at(*pos, "immediates have no type to match"));
+                    }
+                }
+                let ty = match ty {
+                    Some(ty) => Some(self.type_spec(ty, *pos, scope)?),
+                    None => None,
+                };
+                if name == "_" {
+                    Ok(Pattern::Wildcard { ty })
+                } else {
+                    bind(name, *pos, scope)?;
+                    Ok(Pattern::Bind {
+                        name: name.to_string(),
+                        ty,
+                    })
+                }
+            }
+            (SExpr::List(_, pos), Some(OperandInfo::Immediate(_))) => Err(Error::at(
+                *pos,
+                "immediates can only be bound to a variable or ignored",
+            )),
+            (SExpr::List(items, pos), _) => {
+                let (opcode, opcode_pos) = match items.first() {
+                    Some(SExpr::Atom(atom, pos)) => (atom, *pos),
+                    _ => return Err(Error::at(*pos, "expected an instruction pattern")),
+                };
+                let (name, ty) = split_type(opcode);
+                let inst = self.instructions.get(name).ok_or_else(|| {
+                    Error::at(opcode_pos, format!("unknown instruction `{}`", name))
+                })?;
+                if inst.num_results == 0 && operand.is_some() {
+                    return Err(Error::at(
+                        opcode_pos,
+                        format!("`{}` has no result to match", name),
+                    ));
+                }
+                let ty = match ty {
+                    Some(ty) => Some(self.type_spec(ty, opcode_pos, scope)?),
+                    None => None,
+                };
+                if inst.operands.contains(&OperandInfo::VariableArgs) {
+                    return Err(Error::at(
+                        opcode_pos,
+                        format!("`{}` has variable arguments, which can't be matched", name),
+                    ));
+                }
+                let patterns = &items[1..];
+                if patterns.len() != inst.operands.len() {
+                    return Err(Error::at(
+                        *pos,
+                        format!(
+                            "`{}` has {} operands but the pattern has {}",
+                            name,
+                            inst.operands.len(),
+                            patterns.len()
+                        ),
+                    ));
+                }
+                let operands = patterns
+                    .iter()
+                    .zip(&inst.operands)
+                    .map(|(pattern, operand)| self.pattern(pattern, Some(operand), scope))
+                    .collect::<Result<_, _>>()?;
+                Ok(Pattern::Inst {
+                    inst: inst.clone(),
+                    ty,
+                    operands,
+                })
+            }
+            (SExpr::Atom(_, pos), None) => Err(Error::at(
+                *pos,
+                "the pattern of a rule must match an instruction",
+            )),
+        }
+    }
+
+    fn type_spec(&self, ty: &str, pos: Pos, scope: &mut Scope) -> Result<TypeSpec, Error> {
+        if is_type_name(ty) {
+            Ok(TypeSpec::Is(ty.to_uppercase()))
+        } else {
+            bind(ty, pos, scope)?;
+            Ok(TypeSpec::Bind(ty.to_string()))
+        }
+    }
+
+    /// Read `(if EXPR)`, `(if-let NAME EXPR)`, `(let NAME EXPR)` or `(emit EXPR)`.
+    fn clause(&self, expr: &SExpr, scope: &mut Scope) -> Result<Clause, Error> {
+        let pos = expr.pos();
+        let items = match expr {
+            SExpr::List(items, _) => items,
+            SExpr::Atom(..) => return Err(Error::at(pos, "expected a clause")),
+        };
+        match (head(items), items.len()) {
+            (Some("if"), 2) => Ok(Clause::If(self.expr(&items[1], scope)?)),
+            (Some("emit"), 2) => Ok(Clause::Emit(self.expr(&items[1], scope)?)),
+            (Some(kind @ "if-let"), 3) | (Some(kind @ "let"), 3) => {
+                let expr = self.expr(&items[2], scope)?;
+                let name = match &items[1] {
+                    SExpr::Atom(name, pos) => {
+                        bind(name, *pos, scope)?;
+                        name.clone()
+                    }
+                    SExpr::List(_, pos) => return Err(Error::at(*pos, "expected a variable")),
+                };
+                Ok(if kind == "let" {
+                    Clause::Let(name, expr)
+                } else {
+                    Clause::IfLet(name, expr)
+                })
+            }
+            _ => Err(Error::at(
+                pos,
+                "expected `(if EXPR)`, `(if-let NAME EXPR)`, `(let NAME EXPR)` or `(emit EXPR)`",
+            )),
+        }
+    }
+
+    fn expr(&self, expr: &SExpr, scope: &Scope) -> Result<Expr, Error> {
+        match expr {
+            SExpr::Atom(atom, pos) => {
+                let starts_lowercase = atom.starts_with(|c: char| c.is_ascii_lowercase());
+                if scope.contains_key(atom) {
+                    Ok(Expr::Var(atom.clone()))
+                } else if starts_lowercase
+                    && !atom.contains("::")
+                    && atom != "true"
+                    && atom != "false"
+                {
+                    Err(Error::at(*pos, format!("unbound variable `{}`", atom)))
+                } else {
+                    Ok(Expr::Rust(atom.clone()))
+                }
+            }
+            SExpr::List(items, pos) => match items.split_first() {
+                Some((SExpr::Atom(f, _), args)) if f == "out" => match args {
+                    [SExpr::Atom(n, pos)] => n
+                        .parse()
+                        .map(Expr::Out)
+                        .map_err(|_| Error::at(*pos, "expected the index of a result")),
+                    _ => Err(Error::at(*pos, "expected `(out N)`")),
+                },
+                Some((SExpr::Atom(f, _), args)) => {
+                    let args = args
+                        .iter()
+                        .map(|arg| self.expr(arg, scope))
+                        .collect::<Result<_, _>>()?;
+                    Ok(Expr::Call(f.clone(), args))
+                }
+                _ => Err(Error::at(*pos, "expected a function call")),
+            },
+        }
+    }
+}
+
+fn head(items: &[SExpr]) -> Option<&str> {
+    match items.first() {
+        Some(SExpr::Atom(atom, _)) => Some(atom),
+        _ => None,
+    }
+}
+
+/// Split `name.type` into its two parts.
+fn split_type(atom: &str) -> (&str, Option<&str>) {
+    let mut parts = atom.splitn(2, '.');
+    (parts.next().unwrap(), parts.next())
+}
+
+fn bind(name: &str, pos: Pos, scope: &mut Scope) -> Result<(), Error> {
+    if !is_identifier(name) || name.starts_with(|c: char| c.is_ascii_uppercase()) {
+        return Err(Error::at(pos, format!("invalid variable name `{}`", name)));
+    }
+    if is_reserved(name) {
+        return Err(Error::at(pos, format!("`{}` is a reserved name", name)));
+    }
+    if let Some(previous) = scope.insert(name.to_string(), pos) {
+        return Err(Error::at(
+            pos,
+            format!("`{}` is already bound at {}", name, previous),
+        ));
+    }
+    Ok(())
+}
+
+/// Does `a` match everything that `b` matches?
+pub(crate) fn subsumes(a: &Pattern, b: &Pattern) -> bool {
+    let ty = |p: &Pattern| match p {
+        Pattern::Wildcard { ty } | Pattern::Bind { ty, .. } | Pattern::Inst { ty, .. } => {
+            match ty {
+                Some(TypeSpec::Is(ty)) => Some(ty.clone()),
+                _ => None,
+            }
+        }
+    };
+    let types_ok = match (ty(a), ty(b)) {
+        (None, _) => true,
+        (Some(a), Some(b)) => a == b,
+        (Some(_), None) => false,
+    };
+    types_ok
+        && match (a, b) {
+            (Pattern::Wildcard { .. }, _) | (Pattern::Bind { .. }, _) => true,
+            (Pattern::Inst { .. }, Pattern::Wildcard { .. })
+            | (Pattern::Inst { .. }, Pattern::Bind { .. }) => false,
+            (
+                Pattern::Inst {
+                    inst: inst_a,
+                    operands: operands_a,
+                    ..
+                },
+                Pattern::Inst {
+                    inst: inst_b,
+                    operands: operands_b,
+                    ..
+                },
+            ) => {
+                inst_a.name == inst_b.name
+                    && operands_a
+                        .iter()
+                        .zip(operands_b)
+                        .all(|(a, b)| subsumes(a, b))
+            }
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sexpr;
+    use cranelift_codegen_meta::inst_info;
+
+    fn parse(source: &str) -> Result<Vec<Rule>, Error> {
+        Parser::new(inst_info::all()).rules(&sexpr::parse(source).unwrap())
+    }
+
+    fn error(source: &str) -> String {
+        parse(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn rule() {
+        let rules = parse(
+            "(rule 2 (iadd.ty x.i32 (iconst k))
+               (if-let imm (simm32 ty k))
+               (emit (Inst::add x imm (out 0))))",
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].priority, 2);
+        assert!(rules[0].is_guarded());
+        match &rules[0].pattern {
+            Pattern::Inst { inst, ty, operands } => {
+                assert_eq!(inst.camel_name, "Iadd");
+                assert_eq!(ty, &Some(TypeSpec::Bind("ty".to_string())));
+                match &operands[..] {
+                    [Pattern::Bind { name, ty }, Pattern::Inst { inst, .. }] => {
+                        assert_eq!(name, "x");
+                        assert_eq!(ty, &Some(TypeSpec::Is("I32".to_string())));
+                        assert_eq!(inst.name, "iconst");
+                    }
+                    _ => panic!("unexpected operands {:?}", operands),
+                }
+            }
+            pattern => panic!("unexpected pattern {:?}", pattern),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            error("(rule (frobnicate x))"),
+            "1:8: unknown instruction `frobnicate`"
+        );
+        assert_eq!(
+            error("(rule (iadd x))"),
+            "1:7: `iadd` has 2 operands but the pattern has 1"
+        );
+        assert_eq!(
+            error("(rule (iadd x x))"),
+            "1:15: `x` is already bound at 1:13"
+        );
+        assert_eq!(
+            error("(rule (iconst k) (emit (f y)))"),
+            "1:27: unbound variable `y`"
+        );
+        assert_eq!(
+            error("(rule (iadd_imm x (iconst k)))"),
+            "1:19: immediates can only be bound to a variable or ignored"
+        );
+        assert_eq!(
+            error("(rule (iconst k) (emit (f k)) (if (g k)))"),
+            "1:31: conditions must come before any instruction is emitted"
+        );
+        assert_eq!(
+            error("(rule (iadd (store _ x y _) y))"),
+            "1:14: `store` has no result to match"
+        );
+        assert_eq!(
+            error("(rule (call f))"),
+            "1:8: `call` has variable arguments, which can't be matched"
+        );
+    }
+
+    #[test]
+    fn subsumption() {
+        let rules = parse(
+            "(rule (iadd x y))
+             (rule (iadd x (iconst k)))
+             (rule (iadd.i64 x y))",
+        )
+        .unwrap();
+        let (general, constant, typed) = (&rules[0], &rules[1], &rules[2]);
+        assert!(subsumes(&general.pattern, &constant.pattern));
+        assert!(subsumes(&general.pattern, &typed.pattern));
+        assert!(!subsumes(&constant.pattern, &general.pattern));
+        assert!(!subsumes(&typed.pattern, &general.pattern));
+        assert!(!subsumes(&typed.pattern, &constant.pattern));
+    }
+}
@@ -0,0 +1,273 @@
+//! Generating the Rust function that lowers instructions with the rules.
+
+use crate::ast::{subsumes, Clause, Expr, Pattern, Rule, TypeSpec};
+use crate::error::Error;
+use cranelift_codegen_meta::inst_info::{InstructionInfo, OperandInfo};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Lines of Rust code, indented by four spaces per level.
+struct Writer {
+    code: String,
+    indent: usize,
+}
+
+impl Writer {
+    fn line<S: AsRef<str>>(&mut self, line: S) {
+        for _ in 0..self.indent {
+            self.code.push_str("    ");
+        }
+        self.code.push_str(line.as_ref());
+        self.code.push('\n');
+    }
+
+    /// Write a line ending with an opening brace.
+    fn open<S: AsRef<str>>(&mut self, line: S) {
+        self.line(line);
+        self.indent += 1;
+    }
+
+    fn close(&mut self) {
+        self.indent -= 1;
+        self.line("}");
+    }
+}
+
+/// Generate `lower_with_rules`, which lowers an instruction with the first rule that applies to
+/// it. `file_name` is only used in comments.
+pub(crate) fn generate(rules: Vec<Rule>, file_name: &str) -> Result<String, Error> {
+    // The rules of each opcode, sorted by decreasing priority; the sort keeps rules of the same
+    // priority in the order they are written in.
+    let mut by_opcode: BTreeMap<String, Vec<Rule>> = BTreeMap::new();
+    for rule in rules {
+        let opcode = match &rule.pattern {
+            Pattern::Inst { inst, .. } => inst.camel_name.clone(),
+            _ => unreachable!("the pattern of a rule is an instruction"),
+        };
+        by_opcode.entry(opcode).or_default().push(rule);
+    }
+    for rules in by_opcode.values_mut() {
+        rules.sort_by(|a, b| b.priority.cmp(&a.priority));
+        check_reachable(rules)?;
+    }
+
+    let mut w = Writer {
+        code: String::new(),
+        indent: 0,
+    };
+    w.line(format!(
+        "// Generated by cranelift-codegen-lower-meta from {}; do not edit.",
+        file_name
+    ));
+    w.line("");
+    w.line(
+        "/// Lower `insn` with the first of the rules that matches it and whose conditions hold.",
+    );
+    w.line("/// Returns false if there is no such rule, in which case nothing was emitted.");
+    w.line("#[cfg_attr(feature = \"cargo-clippy\", allow(clippy::collapsible_if))]");
+    w.open("fn lower_with_rules<'a>(ctx: Ctx<'a>, insn: IRInst) -> bool {");
+    w.open("match ctx.data(insn).opcode() {");
+    for (opcode, rules) in &by_opcode {
+        w.open(format!("Opcode::{} => {{", opcode));
+        for rule in rules {
+            w.line(format!("// {}:{}", file_name, rule.pos));
+            w.open("{");
+            RuleGenerator::default().rule(&mut w, rule);
+            w.close();
+        }
+        w.close();
+    }
+    w.line("_ => {}");
+    w.close();
+    w.line("false");
+    w.close();
+    Ok(w.code)
+}
+
+/// Check that each rule can be used: that no rule tried before it always applies where it does.
+fn check_reachable(rules: &[Rule]) -> Result<(), Error> {
+    for (i, rule) in rules.iter().enumerate() {
+        if let Some(earlier) = rules[..i]
+            .iter()
+            .find(|earlier| !earlier.is_guarded() && subsumes(&earlier.pattern, &rule.pattern))
+        {
+            return Err(Error::at(
+                rule.pos,
+                format!(
+                    "this rule is never used, since the rule at {} always applies first",
+                    earlier.pos
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The state of the generation of one rule.
+#[derive(Default)]
+struct RuleGenerator {
+    /// The expressions computing the variables bound by the pattern that haven't been needed yet.
+    bindings: HashMap<String, String>,
+    /// The results of `insn` whose registers have been fetched.
+    outputs: HashSet<usize>,
+    /// The number of instructions matched by the pattern besides `insn`.
+    num_insns: usize,
+    /// The number of blocks opened by conditions.
+    depth: usize,
+}
+
+impl RuleGenerator {
+    fn rule(&mut self, w: &mut Writer, rule: &Rule) {
+        match &rule.pattern {
+            Pattern::Inst { inst, ty, operands } => {
+                self.inst(w, "insn", inst, ty, operands);
+            }
+            _ => unreachable!("the pattern of a rule is an instruction"),
+        }
+
+        for clause in &rule.clauses {
+            let expr = match clause {
+                Clause::If(expr)
+                | Clause::IfLet(_, expr)
+                | Clause::Let(_, expr)
+                | Clause::Emit(expr) => expr,
+            };
+            self.prepare(w, expr);
+            match clause {
+                Clause::If(expr) => self.open(w, format!("if {} {{", rust(expr))),
+                Clause::IfLet(name, expr) => {
+                    self.open(w, format!("if let Some({}) = {} {{", name, rust(expr)))
+                }
+                Clause::Let(name, expr) => w.line(format!("let {} = {};", name, rust(expr))),
+                Clause::Emit(expr) => w.line(format!("ctx.emit({});", rust(expr))),
+            }
+        }
+
+        w.line("return true;");
+        for _ in 0..self.depth {
+            w.close();
+        }
+    }
+
+    /// Open a block that is entered only if the pattern or a condition matches.
+    fn open(&mut self, w: &mut Writer, line: String) {
+        w.open(line);
+        self.depth += 1;
+    }
+
+    /// Match the instruction `insn` and its operands.
+    fn inst(
+        &mut self,
+        w: &mut Writer,
+        insn: &str,
+        inst: &InstructionInfo,
+        ty: &Option<TypeSpec>,
+        operands: &[Pattern],
+    ) {
+        self.ty(w, ty, format!("ctx.ty({})", insn));
+        for (pattern, operand) in operands.iter().zip(&inst.operands) {
+            match *operand {
+                OperandInfo::Value(index) => self.value(w, insn, index, pattern),
+                OperandInfo::Immediate(field) => {
+                    if let Pattern::Bind { name, .. } = pattern {
+                        self.bindings.insert(
+                            name.clone(),
+                            format!(
+                                "match *ctx.data({}) {{ InstructionData::{} {{ {}, .. }} => {}, \
+                                 _ => unreachable!() }}",
+                                insn, inst.format, field, field
+                            ),
+                        );
+                    }
+                }
+                OperandInfo::VariableArgs => unreachable!("variable arguments can't be matched"),
+            }
+        }
+    }
+
+    /// Match the `index`th value operand of `insn`.
+    fn value(&mut self, w: &mut Writer, insn: &str, index: usize, pattern: &Pattern) {
+        match pattern {
+            Pattern::Wildcard { ty } => {
+                self.ty(w, ty, format!("ctx.input_ty({}, {})", insn, index));
+            }
+            Pattern::Bind { name, ty } => {
+                self.ty(w, ty, format!("ctx.input_ty({}, {})", insn, index));
+                self.bindings.insert(
+                    name.clone(),
+                    format!("input_to_reg(ctx, {}, {})", insn, index),
+                );
+            }
+            Pattern::Inst { inst, ty, operands } => {
+                // The value isn't fetched from a register, so its producer is only lowered if
+                // something else uses it.
+                self.num_insns += 1;
+                let producer = format!("insn{}", self.num_insns);
+                self.open(
+                    w,
+                    format!(
+                        "if let Some(({}, 0)) = ctx.get_input({}, {}).inst {{",
+                        producer, insn, index
+                    ),
+                );
+                self.open(
+                    w,
+                    format!(
+                        "if ctx.data({}).opcode() == Opcode::{} {{",
+                        producer, inst.camel_name
+                    ),
+                );
+                self.inst(w, &producer, inst, ty, operands);
+            }
+        }
+    }
+
+    /// Check or bind the type computed by `expr`.
+    fn ty(&mut self, w: &mut Writer, ty: &Option<TypeSpec>, expr: String) {
+        match ty {
+            Some(TypeSpec::Is(name)) => self.open(w, format!("if {} == types::{} {{", expr, name)),
+            Some(TypeSpec::Bind(name)) => {
+                self.bindings.insert(name.clone(), expr);
+            }
+            None => {}
+        }
+    }
+
+    /// Bind the variables of the pattern and the output registers that `expr` uses, if they
+    /// aren't bound yet. This is done as late as possible, so that the inputs of a rule that
+    /// doesn't apply aren't marked as used.
+    fn prepare(&mut self, w: &mut Writer, expr: &Expr) {
+        match expr {
+            Expr::Var(name) => {
+                if let Some(binding) = self.bindings.remove(name) {
+                    w.line(format!("let {} = {};", name, binding));
+                }
+            }
+            Expr::Out(index) => {
+                if self.outputs.insert(*index) {
+                    w.line(format!(
+                        "let insn_out{} = output_to_reg(ctx, insn, {});",
+                        index, index
+                    ));
+                }
+            }
+            Expr::Call(_, args) => {
+                for arg in args {
+                    self.prepare(w, arg);
+                }
+            }
+            Expr::Rust(_) => {}
+        }
+    }
+}
+
+/// The Rust code of an expression whose variables are bound.
+fn rust(expr: &Expr) -> String {
+    match expr {
+        Expr::Var(name) | Expr::Rust(name) => name.clone(),
+        Expr::Out(index) => format!("insn_out{}", index),
+        Expr::Call(f, args) => {
+            let args: Vec<String> = args.iter().map(rust).collect();
+            format!("{}({})", f, args.join(", "))
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Errors in lowering rules.
+use std::fmt;
+use std::io;
+
+/// A position in a rules file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pos {
+    /// The line, starting at 1.
+    pub line: usize,
+    /// The column, starting at 1.
+    pub col: usize,
+}
+
+impl fmt::Display for Pos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// An error found when reading or compiling lowering rules.
+#[derive(Debug)]
+pub struct Error {
+    file: Option<String>,
+    pos: Option<Pos>,
+    msg: String,
+}
+
+impl Error {
+    /// Create an error at the given position in the rules.
+    pub(crate) fn at<S: Into<String>>(pos: Pos, msg: S) -> Self {
+        Self {
+            file: None,
+            pos: Some(pos),
+            msg: msg.into(),
+        }
+    }
+
+    /// Record the name of the file the error is in.
+    pub(crate) fn in_file(mut self, file: &str) -> Self {
+        self.file = Some(file.to_string());
+        self
+    }
+
+    /// The position of the error, if it is about the rules rather than reading or writing files.
+    pub fn pos(&self) -> Option<Pos> {
+        self.pos
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        if let Some(pos) = self.pos {
+            write!(f, "{}:", pos)?;
+        }
+        if self.file.is_some() || self.pos.is_some() {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self {
+            file: None,
+            pos: None,
+            msg: e.to_string(),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! This crate compiles the lowering rules of cranelift-codegen's backends to Rust.
+//!
+//! Lowering rules are written as s-expressions, with `;` starting a comment:
+//!
+//! ```text
+//! (rule [PRIORITY] PATTERN CLAUSE...)
+//! ```
+//!
+//! The pattern matches the instruction being lowered, which is written as `(opcode OPERAND...)`
+//! with the operands in the order they have in CLIF. An operand is either `_`, which matches
+//! anything, a variable name, which binds the operand, or, for value operands, a nested pattern
+//! matching the instruction that produces the value. An opcode or a value operand may be followed
+//! by `.T`: if `T` names a type, such as `i32`, the controlling type of the instruction or the type
+//! of the value must be that type; otherwise the type is bound to the variable `T`.
+//!
+//! A value bound by the pattern is the register it is in; the register of the `N`th result of the
+//! instruction being lowered is `(out N)`. An immediate is bound to its value in the
+//! `InstructionData`. The clauses then run in order:
+//!
+//! - `(if EXPR)`: the rule applies only if `EXPR` is true;
+//! - `(if-let NAME EXPR)`: the rule applies only if `EXPR` is `Some`, and `NAME` is bound to its
+//!   contents;
+//! - `(let NAME EXPR)`: `NAME` is bound to the value of `EXPR`;
+//! - `(emit EXPR)`: the machine instruction `EXPR` is emitted.
+//!
+//! Conditions must come before any `emit`. An expression is a bound variable, a call `(f ARG...)`
+//! of a Rust function, or any other Rust item or literal, such as `Inst::imm_r`, `CC::NZ` or `56`.
+//!
+//! The rule that lowers an instruction is the first one whose pattern matches and whose conditions
+//! hold; rules with a higher priority, which is 0 by default, are tried first. A rule that could
+//! never be used because an earlier rule always applies instead is an error.
+//!
+//! The rules compile to a function, `lower_with_rules(ctx, insn) -> bool`, which returns false if
+//! no rule applies. It must be included in a module that provides `Ctx`, `IRInst`, `Opcode`,
+//! `InstructionData`, `types`, and the `input_to_reg` and `output_to_reg` helpers, as well as the
+//! functions and items the rules use.
+
+#![deny(missing_docs)]
+
+mod ast;
+mod codegen;
+mod error;
+mod sexpr;
+
+pub use crate::error::{Error, Pos};
+
+use cranelift_codegen_meta::inst_info;
+use std::fs;
+use std::path::Path;
+
+/// Compile the lowering rules in `source`, which were read from `file_name`, to Rust.
+pub fn compile(source: &str, file_name: &str) -> Result<String, Error> {
+    let compile = || {
+        let exprs = sexpr::parse(source)?;
+        let rules = ast::Parser::new(inst_info::all()).rules(&exprs)?;
+        codegen::generate(rules, file_name)
+    };
+    compile().map_err(|e| e.in_file(file_name))
+}
+
+/// Compile the lowering rules in the file `rules` to the Rust file `out`.
+pub fn generate(rules: &Path, out: &Path) -> Result<(), Error> {
+    let source = fs::read_to_string(rules)?;
+    let file_name = rules.file_name().map_or_else(
+        || rules.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    let code = compile(&source, &file_name)?;
+    fs::write(out, code)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_code() {
+        let code = compile(
+            "(rule (iadd x y)
+               (emit (Inst::add x y (out 0))))
+             (rule 1 (iadd.ty x (iconst k))
+               (if-let imm (simm32 ty k))
+               (emit (Inst::add_imm x imm (out 0))))",
+            "test.rules",
+        )
+        .unwrap();
+        let expected = "\
+fn lower_with_rules<'a>(ctx: Ctx<'a>, insn: IRInst) -> bool {
+    match ctx.data(insn).opcode() {
+        Opcode::Iadd => {
+            // test.rules:3:14
+            {
+                if let Some((insn1, 0)) = ctx.get_input(insn, 1).inst {
+                    if ctx.data(insn1).opcode() == Opcode::Iconst {
+                        let ty = ctx.ty(insn);
+                        let k = match *ctx.data(insn1) { InstructionData::UnaryImm { imm, .. } => imm, _ => unreachable!() };
+                        if let Some(imm) = simm32(ty, k) {
+                            let x = input_to_reg(ctx, insn, 0);
+                            let insn_out0 = output_to_reg(ctx, insn, 0);
+                            ctx.emit(Inst::add_imm(x, imm, insn_out0));
+                            return true;
+                        }
+                    }
+                }
+            }
+            // test.rules:1:1
+            {
+                let x = input_to_reg(ctx, insn, 0);
+                let y = input_to_reg(ctx, insn, 1);
+                let insn_out0 = output_to_reg(ctx, insn, 0);
+                ctx.emit(Inst::add(x, y, insn_out0));
+                return true;
+            }
+        }
+        _ => {}
+    }
+    false
+}
+";
+        assert!(code.ends_with(expected), "{}", code);
+    }
+
+    #[test]
+    fn unused_rule() {
+        let err = compile(
+            "(rule (iadd x y) (emit (f x y)))
+             (rule (iadd x (iconst k)) (emit (g x k)))",
+            "test.rules",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "test.rules:2:14: this rule is never used, since the rule at 1:1 always applies first"
+        );
+    }
+}
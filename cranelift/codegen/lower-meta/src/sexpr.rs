@@ -0,0 +1,131 @@
+//! Reading the s-expressions that lowering rules are written in.
+//!
+//! An s-expression is either an atom, a run of characters other than whitespace and parentheses,
+//! or a parenthesized list of s-expressions. A `;` starts a comment that runs to the end of the
+//! line.
+
+use crate::error::{Error, Pos};
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum SExpr {
+    Atom(String, Pos),
+    List(Vec<SExpr>, Pos),
+}
+
+impl SExpr {
+    pub fn pos(&self) -> Pos {
+        match *self {
+            SExpr::Atom(_, pos) | SExpr::List(_, pos) => pos,
+        }
+    }
+}
+
+/// Read all the s-expressions in `source`.
+pub(crate) fn parse(source: &str) -> Result<Vec<SExpr>, Error> {
+    // The lists being read, innermost last, with the position of their opening parenthesis.
+    let mut stack: Vec<(Vec<SExpr>, Pos)> = vec![];
+    let mut top = vec![];
+    let mut chars = source.chars().peekable();
+    let mut pos = Pos { line: 1, col: 1 };
+
+    while let Some(&c) = chars.peek() {
+        let start = pos;
+        let expr = match c {
+            '\n' => {
+                chars.next();
+                pos.line += 1;
+                pos.col = 1;
+                continue;
+            }
+            _ if c.is_whitespace() => {
+                chars.next();
+                pos.col += 1;
+                continue;
+            }
+            ';' => {
+                while chars.peek().map_or(false, |&c| c != '\n') {
+                    chars.next();
+                    pos.col += 1;
+                }
+                continue;
+            }
+            '(' => {
+                chars.next();
+                pos.col += 1;
+                stack.push((vec![], start));
+                continue;
+            }
+            ')' => {
+                chars.next();
+                pos.col += 1;
+                match stack.pop() {
+                    Some((items, open)) => SExpr::List(items, open),
+                    None => return Err(Error::at(start, "unmatched `)`")),
+                }
+            }
+            _ => {
+                let mut atom = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == ';' {
+                        break;
+                    }
+                    atom.push(c);
+                    chars.next();
+                    pos.col += 1;
+                }
+                SExpr::Atom(atom, start)
+            }
+        };
+        match stack.last_mut() {
+            Some((items, _)) => items.push(expr),
+            None => top.push(expr),
+        }
+    }
+
+    match stack.pop() {
+        Some((_, open)) => Err(Error::at(open, "unclosed `(`")),
+        None => Ok(top),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom(s: &str, line: usize, col: usize) -> SExpr {
+        SExpr::Atom(s.to_string(), Pos { line, col })
+    }
+
+    #[test]
+    fn lists() {
+        let exprs = parse("; A comment.\n(rule (iadd x y) ; another\n  (emit (f)))").unwrap();
+        assert_eq!(
+            exprs,
+            vec![SExpr::List(
+                vec![
+                    atom("rule", 2, 2),
+                    SExpr::List(
+                        vec![atom("iadd", 2, 8), atom("x", 2, 13), atom("y", 2, 15)],
+                        Pos { line: 2, col: 7 }
+                    ),
+                    SExpr::List(
+                        vec![
+                            atom("emit", 3, 4),
+                            SExpr::List(vec![atom("f", 3, 10)], Pos { line: 3, col: 9 })
+                        ],
+                        Pos { line: 3, col: 3 }
+                    ),
+                ],
+                Pos { line: 2, col: 1 }
+            )]
+        );
+    }
+
+    #[test]
+    fn unbalanced() {
+        let err = parse("(rule\n  (iadd x y)").unwrap_err();
+        assert_eq!(err.pos(), Some(Pos { line: 1, col: 1 }));
+        let err = parse("(iadd x y))").unwrap_err();
+        assert_eq!(err.pos(), Some(Pos { line: 1, col: 11 }));
+    }
+}
@@ -0,0 +1,106 @@
+//! A description of the shared instructions for code generators that live outside of this crate,
+//! such as the compiler of lowering rules in `cranelift-codegen-lower-meta`.
+
+use crate::shared;
+
+/// An input operand of an instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OperandInfo {
+    /// An SSA value; this is its index among the value operands.
+    Value(usize),
+    /// An immediate or an entity reference, stored in the named field of the `InstructionData`
+    /// variant.
+    Immediate(&'static str),
+    /// The variable number of trailing value operands.
+    VariableArgs,
+}
+
+/// An instruction, as seen by the code that matches on `Opcode` and `InstructionData`.
+#[derive(Clone, Debug)]
+pub struct InstructionInfo {
+    /// The name of the instruction in CLIF, e.g. `iadd_imm`.
+    pub name: String,
+    /// The name of the `Opcode` variant, e.g. `IaddImm`.
+    pub camel_name: String,
+    /// The name of the `InstructionData` variant of the instruction's format.
+    pub format: &'static str,
+    /// The input operands, in the order they are written in CLIF.
+    pub operands: Vec<OperandInfo>,
+    /// The number of results of the instruction.
+    pub num_results: usize,
+}
+
+/// Describe all the instructions of the shared instruction set.
+pub fn all() -> Vec<InstructionInfo> {
+    let shared_defs = shared::define();
+    shared_defs
+        .all_instructions
+        .values()
+        .map(|inst| {
+            let mut num_values = 0;
+            let mut imm_fields = inst.format.imm_fields.iter();
+            let operands = inst
+                .operands_in
+                .iter()
+                .map(|operand| {
+                    if operand.is_varargs() {
+                        OperandInfo::VariableArgs
+                    } else if operand.is_value() {
+                        num_values += 1;
+                        OperandInfo::Value(num_values - 1)
+                    } else {
+                        let field = imm_fields
+                            .next()
+                            .expect("an immediate field for each immediate operand");
+                        OperandInfo::Immediate(field.member)
+                    }
+                })
+                .collect();
+            InstructionInfo {
+                name: inst.name.clone(),
+                camel_name: inst.camel_name.clone(),
+                format: inst.format.name,
+                operands,
+                num_results: inst.value_results.len(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operands() {
+        let all = all();
+        let find = |name: &str| all.iter().find(|inst| inst.name == name).unwrap();
+
+        let icmp = find("icmp");
+        assert_eq!(icmp.camel_name, "Icmp");
+        assert_eq!(icmp.format, "IntCompare");
+        assert_eq!(
+            icmp.operands,
+            vec![
+                OperandInfo::Immediate("cond"),
+                OperandInfo::Value(0),
+                OperandInfo::Value(1)
+            ]
+        );
+
+        let iadd_imm = find("iadd_imm");
+        assert_eq!(
+            iadd_imm.operands,
+            vec![OperandInfo::Value(0), OperandInfo::Immediate("imm")]
+        );
+
+        let call = find("call");
+        assert_eq!(
+            call.operands,
+            vec![
+                OperandInfo::Immediate("func_ref"),
+                OperandInfo::VariableArgs
+            ]
+        );
+    }
+}
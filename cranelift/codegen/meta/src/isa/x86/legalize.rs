@@ -73,6 +73,7 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
     let popcnt = insts.by_name("popcnt");
     let sdiv = insts.by_name("sdiv");
     let selectif = insts.by_name("selectif");
+    let select_spectre_guard = insts.by_name("select_spectre_guard");
     let smulhi = insts.by_name("smulhi");
     let srem = insts.by_name("srem");
     let tls_value = insts.by_name("tls_value");
@@ -351,6 +352,7 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
 
     expand.custom_legalize(ineg, "convert_ineg");
     expand.custom_legalize(tls_value, "expand_tls_value");
+    expand.custom_legalize(select_spectre_guard, "expand_select_spectre_guard");
     widen.custom_legalize(ineg, "convert_ineg");
 
     // To reduce compilation times, separate out large blocks of legalizations by theme.
@@ -5,6 +5,7 @@ mod cdsl;
 mod srcgen;
 
 pub mod error;
+pub mod inst_info;
 pub mod isa;
 
 mod gen_binemit;
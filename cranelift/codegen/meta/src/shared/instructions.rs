@@ -1733,6 +1733,24 @@ pub(crate) fn define(
         .operands_out(vec![a]),
     );
 
+    ig.push(
+        Inst::new(
+            "select_spectre_guard",
+            r#"
+        Conditional select intended for Spectre guards.
+
+        This has the same semantics as `select`, but it is always compiled to
+        a conditional move rather than a branch, and optimizations leave it
+        alone. It is used to clamp an address on the failing side of a bounds
+        check, so that a misspeculated access can't reach outside the heap or
+        table.
+        "#,
+            &formats.ternary,
+        )
+        .operands_in(vec![c, x, y])
+        .operands_out(vec![a]),
+    );
+
     let cc = &Operand::new("cc", &imm.intcc).with_doc("Controlling condition code");
     let flags = &Operand::new("flags", iflags).with_doc("The machine's flag register");
 
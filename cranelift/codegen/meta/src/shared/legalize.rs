@@ -107,6 +107,7 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
     let sdiv = insts.by_name("sdiv");
     let sdiv_imm = insts.by_name("sdiv_imm");
     let select = insts.by_name("select");
+    let select_spectre_guard = insts.by_name("select_spectre_guard");
     let sextend = insts.by_name("sextend");
     let sshr = insts.by_name("sshr");
     let sshr_imm = insts.by_name("sshr_imm");
@@ -644,6 +645,13 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
         );
     }
 
+    // ISAs without a conditional move fall back to a plain `select`; the ones that have one
+    // provide their own expansion.
+    expand.legalize(
+        def!(a = select_spectre_guard(c, x, y)),
+        vec![def!(a = select(c, x, y))],
+    );
+
     // Expand integer operations with carry for RISC architectures that don't have
     // the flags.
     let intcc_ult = Literal::enumerator_for(&imm.intcc, "ult");
@@ -144,6 +144,18 @@ pub(crate) fn define() -> SettingGroup {
         false,
     );
 
+    settings.add_bool(
+        "enable_bounds_check_spectre_mitigation",
+        r#"Harden explicit heap and table bounds checks against Spectre attacks.
+
+        When enabled, the address computed by a `heap_addr` or `table_addr` instruction that needs
+        an explicit bounds check is clamped with a `select_spectre_guard` instruction, so that it
+        is zero whenever the bounds check fails. A branch that skips the trap under misspeculation
+        then can't be used to read or write outside of the heap or table.
+        "#,
+        false,
+    );
+
     settings.add_bool("enable_simd", "Enable the use of SIMD instructions.", false);
 
     settings.add_bool(
@@ -0,0 +1,253 @@
+//! Alias analysis and redundant load elimination.
+//!
+//! Memory accesses are sorted into the categories given by their `MemFlags`: `heap`, `table` and
+//! `vmctx`. Accesses in different categories never alias each other, so a store to the heap
+//! leaves the VM context as it was. Accesses without a category may alias anything.
+//!
+//! For each category, a dataflow analysis finds the last instruction which may have written to
+//! it at every point of the function: a store in the category, any other instruction which may
+//! store to memory, or the entry to a block where control flow with different last stores
+//! merges. Two loads from the same address with the same last store load the same value, and so
+//! do a store and a load following it. Loads with the `readonly` flag never see a store.
+//!
+//! The pass replaces a load with the value of such an earlier load or store when that one
+//! dominates it. Loads without a category or the `readonly` flag are left alone, since the
+//! memory they read may change without Cranelift seeing it.
+
+use crate::cursor::{Cursor, FuncCursor};
+use crate::dominator_tree::DominatorTree;
+use crate::entity::SecondaryMap;
+use crate::flowgraph::ControlFlowGraph;
+use crate::fx::FxHashMap;
+use crate::ir::immediates::Offset32;
+use crate::ir::{Block, Function, Inst, InstructionData, MemFlags, Opcode, Type, Value};
+use crate::timing;
+
+/// The last instruction which may have written to a category of memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum MemoryState {
+    /// Nothing has written to the memory since the function was entered.
+    Entry,
+    /// The instruction was the last to write to the memory.
+    Store(Inst),
+    /// Control flow with different last stores merges at the entry of the block.
+    BlockEntry(Block),
+}
+
+/// The memory state of each category at a point of the function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct LastStores {
+    heap: MemoryState,
+    table: MemoryState,
+    vmctx: MemoryState,
+}
+
+impl LastStores {
+    fn new() -> Self {
+        Self {
+            heap: MemoryState::Entry,
+            table: MemoryState::Entry,
+            vmctx: MemoryState::Entry,
+        }
+    }
+
+    /// Update the state with the effects of `inst`.
+    fn update(&mut self, func: &Function, inst: Inst) {
+        let opcode = func.dfg[inst].opcode();
+        match store_flags(&func.dfg[inst]) {
+            Some(flags) if flags.heap() => self.heap = MemoryState::Store(inst),
+            Some(flags) if flags.table() => self.table = MemoryState::Store(inst),
+            Some(flags) if flags.vmctx() => self.vmctx = MemoryState::Store(inst),
+            _ => {
+                if opcode.can_store() || opcode.is_call() || opcode.other_side_effects() {
+                    self.heap = MemoryState::Store(inst);
+                    self.table = MemoryState::Store(inst);
+                    self.vmctx = MemoryState::Store(inst);
+                }
+            }
+        }
+    }
+
+    /// Get the state a load with `flags` depends on, if the load can be optimized.
+    fn get(&self, flags: MemFlags) -> Option<MemoryState> {
+        if flags.readonly() {
+            Some(MemoryState::Entry)
+        } else if flags.heap() {
+            Some(self.heap)
+        } else if flags.table() {
+            Some(self.table)
+        } else if flags.vmctx() {
+            Some(self.vmctx)
+        } else {
+            None
+        }
+    }
+
+    /// Merge the state of another predecessor of `block` into this one.
+    fn meet_from(&mut self, other: &Self, block: Block) {
+        let meet = |a: MemoryState, b: MemoryState| {
+            if a == b {
+                a
+            } else {
+                MemoryState::BlockEntry(block)
+            }
+        };
+        self.heap = meet(self.heap, other.heap);
+        self.table = meet(self.table, other.table);
+        self.vmctx = meet(self.vmctx, other.vmctx);
+    }
+}
+
+/// Get the flags of a store instruction.
+fn store_flags(data: &InstructionData) -> Option<MemFlags> {
+    match *data {
+        InstructionData::Store { flags, .. } | InstructionData::StoreComplex { flags, .. } => {
+            Some(flags)
+        }
+        _ => None,
+    }
+}
+
+/// A load, or a store providing the value of a load, as found in the table of known values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct MemoryLoc {
+    opcode: Opcode,
+    ty: Type,
+    address: Value,
+    offset: Offset32,
+    state: MemoryState,
+}
+
+/// Compute the memory state at the entry of every block reachable from the entry block.
+fn compute_block_input_states(
+    func: &Function,
+    cfg: &ControlFlowGraph,
+    domtree: &DominatorTree,
+) -> SecondaryMap<Block, Option<LastStores>> {
+    let mut block_input: SecondaryMap<Block, Option<LastStores>> = SecondaryMap::new();
+    // The state at each branch instruction, which is the state at the entry of its targets.
+    let mut branch_state: SecondaryMap<Inst, Option<LastStores>> = SecondaryMap::new();
+    let entry = func.layout.entry_block();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &block in domtree.cfg_postorder().iter().rev() {
+            let input = if Some(block) == entry {
+                Some(LastStores::new())
+            } else {
+                // Predecessors which haven't been visited yet are ignored; the next iteration
+                // takes them into account.
+                let mut input: Option<LastStores> = None;
+                for pred in cfg.pred_iter(block) {
+                    if let Some(state) = branch_state[pred.inst] {
+                        match input {
+                            Some(ref mut input) => input.meet_from(&state, block),
+                            None => input = Some(state),
+                        }
+                    }
+                }
+                input
+            };
+            if input == block_input[block] {
+                continue;
+            }
+            block_input[block] = input;
+            changed = true;
+
+            let mut state = input.unwrap();
+            for inst in func.layout.block_insts(block) {
+                state.update(func, inst);
+                if func.dfg[inst].opcode().is_branch() {
+                    branch_state[inst] = Some(state);
+                }
+            }
+        }
+    }
+
+    block_input
+}
+
+/// Replace loads whose value is known from an earlier load or store in `func`.
+pub fn do_remove_redundant_loads(
+    func: &mut Function,
+    cfg: &ControlFlowGraph,
+    domtree: &DominatorTree,
+) {
+    let _tt = timing::alias_analysis();
+    debug_assert!(domtree.is_valid());
+
+    let block_input = compute_block_input_states(func, cfg, domtree);
+
+    // The first instruction which produced the value of a memory location, and that value.
+    let mut known_values: FxHashMap<MemoryLoc, (Inst, Value)> = FxHashMap::default();
+    let mut pos = FuncCursor::new(func);
+
+    for &block in domtree.cfg_postorder().iter().rev() {
+        let mut state = block_input[block].unwrap();
+        pos.goto_top(block);
+        while let Some(inst) = pos.next_inst() {
+            pos.func.dfg.resolve_aliases_in_arguments(inst);
+
+            match pos.func.dfg[inst] {
+                InstructionData::Load {
+                    opcode,
+                    arg,
+                    flags,
+                    offset,
+                } => {
+                    if let Some(mem_state) = state.get(flags) {
+                        let result = pos.func.dfg.first_result(inst);
+                        let loc = MemoryLoc {
+                            opcode,
+                            ty: pos.func.dfg.value_type(result),
+                            address: arg,
+                            offset,
+                            state: mem_state,
+                        };
+                        match known_values.get(&loc) {
+                            Some(&(prev, value))
+                                if domtree.dominates(prev, inst, &pos.func.layout) =>
+                            {
+                                pos.func.dfg.clear_results(inst);
+                                pos.func.dfg.change_to_alias(result, value);
+                                pos.remove_inst_and_step_back();
+                                continue;
+                            }
+                            _ => {
+                                known_values.insert(loc, (inst, result));
+                            }
+                        }
+                    }
+                }
+                InstructionData::Store {
+                    opcode: Opcode::Store,
+                    args,
+                    flags,
+                    offset,
+                } => {
+                    state.update(pos.func, inst);
+                    // A store in a category is the last store of that category, so a load from
+                    // the same location following it loads the stored value.
+                    if let Some(mem_state) = state.get(flags) {
+                        if mem_state == MemoryState::Store(inst) {
+                            let value = pos.func.dfg.resolve_aliases(args[0]);
+                            let loc = MemoryLoc {
+                                opcode: Opcode::Load,
+                                ty: pos.func.dfg.value_type(value),
+                                address: args[1],
+                                offset,
+                                state: mem_state,
+                            };
+                            known_values.insert(loc, (inst, value));
+                        }
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+
+            state.update(pos.func, inst);
+        }
+    }
+}
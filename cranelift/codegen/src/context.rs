@@ -9,6 +9,7 @@
 //! contexts concurrently. Typically, you would have one context per compilation thread and only a
 //! single ISA instance.
 
+use crate::alias_analysis::do_remove_redundant_loads;
 use crate::binemit::{
     relax_branches, shrink_instructions, CodeInfo, MemoryCodeSink, RelocSink, StackmapSink,
     TrapSink,
@@ -172,6 +173,9 @@ impl Context {
             self.compute_loop_analysis();
             self.licm(isa)?;
             self.simple_gvn(isa)?;
+            if opt_level == OptLevel::Speed || opt_level == OptLevel::SpeedAndSize {
+                self.remove_redundant_loads(isa)?;
+            }
         }
 
         self.compute_domtree();
@@ -370,6 +374,15 @@ impl Context {
         self.verify_if(fisa)
     }
 
+    /// Remove loads whose value is known from an earlier load or store.
+    pub fn remove_redundant_loads<'a, FOI: Into<FlagsOrIsa<'a>>>(
+        &mut self,
+        fisa: FOI,
+    ) -> CodegenResult<()> {
+        do_remove_redundant_loads(&mut self.func, &self.cfg, &self.domtree);
+        self.verify_if(fisa)
+    }
+
     /// Perform LICM on the function.
     pub fn licm(&mut self, isa: &dyn TargetIsa) -> CodegenResult<()> {
         do_licm(
@@ -6,9 +6,12 @@ enum FlagBit {
     Notrap,
     Aligned,
     Readonly,
+    Heap,
+    Table,
+    Vmctx,
 }
 
-const NAMES: [&str; 3] = ["notrap", "aligned", "readonly"];
+const NAMES: [&str; 6] = ["notrap", "aligned", "readonly", "heap", "table", "vmctx"];
 
 /// Flags for memory operations like load/store.
 ///
@@ -103,6 +106,49 @@ impl MemFlags {
     pub fn set_readonly(&mut self) {
         self.set(FlagBit::Readonly)
     }
+
+    /// Test if the `heap` flag is set.
+    ///
+    /// The `heap`, `table` and `vmctx` flags sort memory accesses into disjoint categories for
+    /// alias analysis: an access in one category never aliases an access in another. Accesses
+    /// without a category may alias any of them. The verifier rejects accesses with more than one
+    /// category.
+    ///
+    /// The `heap` category is for accesses to the memories of the program being compiled.
+    pub fn heap(self) -> bool {
+        self.read(FlagBit::Heap)
+    }
+
+    /// Set the `heap` flag.
+    pub fn set_heap(&mut self) {
+        self.set(FlagBit::Heap)
+    }
+
+    /// Test if the `table` flag is set.
+    ///
+    /// The `table` category is for accesses to the elements of tables, such as the function
+    /// pointers used by indirect calls.
+    pub fn table(self) -> bool {
+        self.read(FlagBit::Table)
+    }
+
+    /// Set the `table` flag.
+    pub fn set_table(&mut self) {
+        self.set(FlagBit::Table)
+    }
+
+    /// Test if the `vmctx` flag is set.
+    ///
+    /// The `vmctx` category is for accesses to the data structures of the runtime, reached
+    /// through the VM context or global values.
+    pub fn vmctx(self) -> bool {
+        self.read(FlagBit::Vmctx)
+    }
+
+    /// Set the `vmctx` flag.
+    pub fn set_vmctx(&mut self) {
+        self.set(FlagBit::Vmctx)
+    }
 }
 
 impl fmt::Display for MemFlags {
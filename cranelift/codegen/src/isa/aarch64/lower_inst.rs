@@ -1023,8 +1023,10 @@ pub(crate) fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(
             // Nothing.
         }
 
-        Opcode::Select | Opcode::Selectif => {
-            let cond = if op == Opcode::Select {
+        Opcode::Select | Opcode::SelectSpectreGuard | Opcode::Selectif => {
+            // `select_spectre_guard` relies on this being a `csel` rather
+            // than a branch.
+            let cond = if op == Opcode::Select || op == Opcode::SelectSpectreGuard {
                 let (cmp_op, narrow_mode) = if ty_bits(ctx.input_ty(insn, 0)) > 32 {
                     (ALUOp::SubS64, NarrowValueMode::ZeroExtend64)
                 } else {
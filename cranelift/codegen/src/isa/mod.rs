@@ -66,6 +66,8 @@ use crate::settings::SetResult;
 use crate::timing;
 use alloc::borrow::Cow;
 use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::any::Any;
 use core::fmt;
 use core::fmt::{Debug, Formatter};
@@ -222,6 +224,12 @@ pub trait TargetIsa: fmt::Display + Send + Sync {
     /// Get the ISA-independent flags that were used to make this trait object.
     fn flags(&self) -> &settings::Flags;
 
+    /// Get the ISA-dependent flags that were used to make this trait object, such as the CPU
+    /// features the generated code may use, as `(name, value)` pairs with TOML values.
+    fn isa_flags(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
     /// Get the default calling convention of this target.
     fn default_call_conv(&self) -> CallConv {
         CallConv::triple_default(self.triple())
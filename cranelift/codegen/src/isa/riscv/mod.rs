@@ -17,6 +17,8 @@ use crate::isa::{EncInfo, RegClass, RegInfo, TargetIsa};
 use crate::regalloc;
 use alloc::borrow::Cow;
 use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::any::Any;
 use core::fmt;
 use target_lexicon::{PointerWidth, Triple};
@@ -69,6 +71,10 @@ impl TargetIsa for Isa {
         &self.shared_flags
     }
 
+    fn isa_flags(&self) -> Vec<(String, String)> {
+        shared_settings::flag_values(&self.isa_flags)
+    }
+
     fn register_info(&self) -> RegInfo {
         registers::INFO.clone()
     }
@@ -865,6 +865,45 @@ pub(crate) fn emit(inst: &Inst, sink: &mut MachBuffer<Inst>) {
             }
         }
 
+        Inst::Cmove {
+            is_64,
+            cc,
+            src,
+            dst: reg_g,
+        } => {
+            // CMOVcc is 0F 40+cc /r.
+            let rex = if *is_64 {
+                RexFlags::set_w()
+            } else {
+                RexFlags::clear_w()
+            };
+            let opcode = 0x0F40 + cc.get_enc() as u32;
+            match src {
+                RegMem::Reg { reg: reg_e } => {
+                    emit_std_reg_reg(
+                        sink,
+                        LegacyPrefix::None,
+                        opcode,
+                        2,
+                        reg_g.to_reg(),
+                        *reg_e,
+                        rex,
+                    );
+                }
+                RegMem::Mem { addr } => {
+                    emit_std_reg_mem(
+                        sink,
+                        LegacyPrefix::None,
+                        opcode,
+                        2,
+                        reg_g.to_reg(),
+                        addr,
+                        rex,
+                    );
+                }
+            }
+        }
+
         Inst::Push64 { src } => {
             match src {
                 RegMemImm::Reg { reg } => {
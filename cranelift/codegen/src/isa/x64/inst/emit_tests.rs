@@ -2201,6 +2201,24 @@ fn test_x64_emit() {
         "cmpb    %r13b, %r14b",
     ));
 
+    // ========================================================
+    // Cmove
+    insns.push((
+        Inst::cmove(true, CC::NZ, RegMem::reg(rcx), w_rdx),
+        "480F45D1",
+        "cmovnzq %rcx, %rdx",
+    ));
+    insns.push((
+        Inst::cmove(false, CC::Z, RegMem::reg(r15), w_rdx),
+        "410F44D7",
+        "cmovzl  %r15d, %edx",
+    ));
+    insns.push((
+        Inst::cmove(true, CC::NZ, RegMem::mem(Addr::imm_reg(99, rdi)), w_r8),
+        "4C0F454763",
+        "cmovnzq 99(%rdi), %r8",
+    ));
+
     // ========================================================
     // Push64
     insns.push((Inst::push64(RegMemImm::reg(rdi)), "57", "pushq   %rdi"));
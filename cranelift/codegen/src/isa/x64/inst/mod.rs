@@ -107,6 +107,15 @@ pub(crate) enum Inst {
         dst: Reg,
     },
 
+    /// cmov (l q) cc (reg addr) reg
+    /// Moves `src` into `dst` if the condition holds; never branches.
+    Cmove {
+        is_64: bool,
+        cc: CC,
+        src: RegMem,
+        dst: Writable<Reg>,
+    },
+
     /// pushq (reg addr imm)
     Push64 { src: RegMemImm },
 
@@ -280,6 +289,16 @@ impl Inst {
         Inst::Cmp_RMI_R { size, src, dst }
     }
 
+    pub(crate) fn cmove(is_64: bool, cc: CC, src: RegMem, dst: Writable<Reg>) -> Inst {
+        debug_assert!(dst.to_reg().get_class() == RegClass::I64);
+        Inst::Cmove {
+            is_64,
+            cc,
+            src,
+            dst,
+        }
+    }
+
     pub(crate) fn push64(src: RegMemImm) -> Inst {
         Inst::Push64 { src }
     }
@@ -470,6 +489,17 @@ impl ShowWithRRU for Inst {
                 src.show_rru_sized(mb_rru, *size),
                 show_ireg_sized(*dst, mb_rru, *size)
             ),
+            Inst::Cmove {
+                is_64,
+                cc,
+                src,
+                dst,
+            } => format!(
+                "{} {}, {}",
+                ljustify2(format!("cmov{}", cc.to_string()), suffixLQ(*is_64)),
+                src.show_rru_sized(mb_rru, sizeLQ(*is_64)),
+                show_ireg_sized(dst.to_reg(), mb_rru, sizeLQ(*is_64))
+            ),
             Inst::Push64 { src } => {
                 format!("{} {}", ljustify("pushq".to_string()), src.show_rru(mb_rru))
             }
@@ -590,6 +620,15 @@ fn x64_get_regs(inst: &Inst, collector: &mut RegUsageCollector) {
             src.get_regs_as_uses(collector);
             collector.add_use(*dst); // yes, really `add_use`
         }
+        Inst::Cmove {
+            is_64: _,
+            cc: _,
+            src,
+            dst,
+        } => {
+            src.get_regs_as_uses(collector);
+            collector.add_mod(*dst);
+        }
         Inst::Push64 { src } => {
             src.get_regs_as_uses(collector);
             collector.add_mod(Writable::from_reg(regs::rsp()));
@@ -772,6 +811,15 @@ fn x64_map_regs<RUM: RegUsageMapper>(inst: &mut Inst, mapper: &RUM) {
             src.map_uses(mapper);
             map_use(mapper, dst);
         }
+        Inst::Cmove {
+            is_64: _,
+            cc: _,
+            ref mut src,
+            ref mut dst,
+        } => {
+            src.map_uses(mapper);
+            map_mod(mapper, dst);
+        }
         Inst::Push64 { ref mut src } => src.map_uses(mapper),
         Inst::Pop64 { ref mut dst } => {
             map_def(mapper, dst);
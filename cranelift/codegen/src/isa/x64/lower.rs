@@ -6,13 +6,14 @@
 use log::trace;
 use regalloc::{Reg, RegClass, Writable};
 
+use crate::ir::immediates::Imm64;
 use crate::ir::types;
 use crate::ir::Inst as IRInst;
 use crate::ir::{condcodes::IntCC, InstructionData, Opcode, Type};
 
 use crate::machinst::lower::*;
 use crate::machinst::*;
-use crate::result::CodegenResult;
+use crate::result::{CodegenError, CodegenResult};
 
 use crate::isa::x64::inst::args::*;
 use crate::isa::x64::inst::*;
@@ -96,11 +97,72 @@ fn output_to_reg<'a>(ctx: Ctx<'a>, iri: IRInst, output: usize) -> Writable<Reg>
     ctx.get_output(iri, output)
 }
 
+//=============================================================================
+// Helpers for the lowering rules.
+
+fn imm64_bits(imm: Imm64) -> u64 {
+    let imm: i64 = imm.into();
+    imm as u64
+}
+
+fn is_32_or_64(ty: Type) -> bool {
+    ty == types::I32 || ty == types::I64
+}
+
+/// The size in bytes of a value of type `ty` when it's compared, at least 1 for booleans.
+fn ty_size_bytes(ty: Type) -> u8 {
+    u8::max(1, (ty.bits() / 8) as u8)
+}
+
+/// Move the constant `imm` of type `ty` into `dst`.
+fn imm_to_reg(ty: Type, imm: Imm64, dst: Writable<Reg>) -> Inst {
+    let bits = imm64_bits(imm);
+    if ty.bits() < 64 {
+        // Only the low 32 bits matter; `imm_r` wants them sign-extended.
+        Inst::imm_r(false, bits as i32 as i64 as u64, dst)
+    } else {
+        // The 32-bit move zero-extends, so it only works for non-negative 32-bit values.
+        Inst::imm_r(bits > 0x7FFF_FFFF, bits, dst)
+    }
+}
+
+/// The immediate operand of an ALU instruction on values of type `ty` that stands for `imm`, if
+/// `imm` fits in one.
+fn simm32(ty: Type, imm: Imm64) -> Option<u32> {
+    let bits = imm64_bits(imm);
+    if !is_int_ty(ty) {
+        None
+    } else if !int_ty_is_64(ty) || low32willSXto64(bits) {
+        Some(bits as u32)
+    } else {
+        None
+    }
+}
+
+/// The amount of a shift by `imm` of a value of type `ty`, masked as the shift instructions do.
+fn shift_amount(ty: Type, imm: Imm64) -> u8 {
+    (imm64_bits(imm) & u64::from(ty.bits() - 1)) as u8
+}
+
+/// How far to shift a value of type `from` left, and then right, to extend it to type `to`.
+fn extend_shift(from: Type, to: Type) -> u8 {
+    (to.bits() - from.bits()) as u8
+}
+
+//=============================================================================
+// Rule-based lowering, generated from `lower.rules`.
+
+include!(concat!(env!("OUT_DIR"), "/lower-x64.rs"));
+
 //=============================================================================
 // Top-level instruction lowering entry point, for one instruction.
 
 /// Actually codegen an instruction's results into registers.
-fn lower_insn_to_regs<'a>(ctx: Ctx<'a>, inst: IRInst) {
+fn lower_insn_to_regs<'a>(ctx: Ctx<'a>, inst: IRInst) -> CodegenResult<()> {
+    if lower_with_rules(ctx, inst) {
+        return Ok(());
+    }
+
     let op = ctx.data(inst).opcode();
     let ty = if ctx.num_outputs(inst) == 1 {
         Some(ctx.output_ty(inst, 0))
@@ -110,77 +172,6 @@ fn lower_insn_to_regs<'a>(ctx: Ctx<'a>, inst: IRInst) {
 
     // This is all outstandingly feeble.  TODO: much better!
     match op {
-        Opcode::Iconst => {
-            if let Some(w64) = iri_to_u64_immediate(ctx, inst) {
-                // Get exactly the bit pattern in 'w64' into the dest.  No
-                // monkeying with sign extension etc.
-                let dst_is_64 = w64 > 0xFFFF_FFFF;
-                let dst = output_to_reg(ctx, inst, 0);
-                ctx.emit(Inst::imm_r(dst_is_64, w64, dst));
-            } else {
-                unimplemented!();
-            }
-        }
-
-        Opcode::Iadd | Opcode::Isub => {
-            let dst = output_to_reg(ctx, inst, 0);
-            let lhs = input_to_reg(ctx, inst, 0);
-            let rhs = input_to_reg(ctx, inst, 1);
-            let is_64 = int_ty_is_64(ty.unwrap());
-            let alu_op = if op == Opcode::Iadd {
-                AluRmiROpcode::Add
-            } else {
-                AluRmiROpcode::Sub
-            };
-            ctx.emit(Inst::mov_r_r(true, lhs, dst));
-            ctx.emit(Inst::alu_rmi_r(is_64, alu_op, RegMemImm::reg(rhs), dst));
-        }
-
-        Opcode::Ishl | Opcode::Ushr | Opcode::Sshr => {
-            // TODO: implement imm shift value into insn
-            let dst_ty = ctx.output_ty(inst, 0);
-            assert_eq!(ctx.input_ty(inst, 0), dst_ty);
-            assert!(dst_ty == types::I32 || dst_ty == types::I64);
-
-            let lhs = input_to_reg(ctx, inst, 0);
-            let rhs = input_to_reg(ctx, inst, 1);
-            let dst = output_to_reg(ctx, inst, 0);
-
-            let shift_kind = match op {
-                Opcode::Ishl => ShiftKind::Left,
-                Opcode::Ushr => ShiftKind::RightZ,
-                Opcode::Sshr => ShiftKind::RightS,
-                _ => unreachable!(),
-            };
-
-            let is_64 = dst_ty == types::I64;
-            let w_rcx = Writable::from_reg(regs::rcx());
-            ctx.emit(Inst::mov_r_r(true, lhs, dst));
-            ctx.emit(Inst::mov_r_r(true, rhs, w_rcx));
-            ctx.emit(Inst::shift_r(is_64, shift_kind, None /*%cl*/, dst));
-        }
-
-        Opcode::Uextend | Opcode::Sextend => {
-            // TODO: this is all extremely lame, all because Mov{ZX,SX}_M_R
-            // don't accept a register source operand.  They should be changed
-            // so as to have _RM_R form.
-            // TODO2: if the source operand is a load, incorporate that.
-            let zero_extend = op == Opcode::Uextend;
-            let src_ty = ctx.input_ty(inst, 0);
-            let dst_ty = ctx.output_ty(inst, 0);
-            let src = input_to_reg(ctx, inst, 0);
-            let dst = output_to_reg(ctx, inst, 0);
-
-            ctx.emit(Inst::mov_r_r(true, src, dst));
-            match (src_ty, dst_ty, zero_extend) {
-                (types::I8, types::I64, false) => {
-                    ctx.emit(Inst::shift_r(true, ShiftKind::Left, Some(56), dst));
-                    ctx.emit(Inst::shift_r(true, ShiftKind::RightS, Some(56), dst));
-                }
-                _ => unimplemented!(),
-            }
-        }
-
         Opcode::FallthroughReturn | Opcode::Return => {
             for i in 0..ctx.num_inputs(inst) {
                 let src_reg = input_to_reg(ctx, inst, i);
@@ -244,8 +235,19 @@ fn lower_insn_to_regs<'a>(ctx: Ctx<'a>, inst: IRInst) {
         | Opcode::SshrImm => {
             panic!("ALU+imm and ALU+carry ops should not appear here!");
         }
+
+        Opcode::SelectSpectreGuard => {
+            // Only integers can be moved conditionally, and falling back to a
+            // branch would defeat the purpose of the instruction.
+            return Err(CodegenError::Unsupported(format!(
+                "select_spectre_guard with type {}",
+                ty.unwrap()
+            )));
+        }
         _ => unimplemented!("unimplemented lowering for opcode {:?}", op),
     }
+
+    Ok(())
 }
 
 //=============================================================================
@@ -255,8 +257,7 @@ impl LowerBackend for X64Backend {
     type MInst = Inst;
 
     fn lower<C: LowerCtx<I = Inst>>(&self, ctx: &mut C, ir_inst: IRInst) -> CodegenResult<()> {
-        lower_insn_to_regs(ctx, ir_inst);
-        Ok(())
+        lower_insn_to_regs(ctx, ir_inst)
     }
 
     fn lower_branch_group<C: LowerCtx<I = Inst>>(
@@ -110,3 +110,48 @@ pub(crate) fn isa_builder(triple: Triple) -> IsaBuilder {
         },
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cursor::{Cursor, FuncCursor};
+    use crate::ir::types::*;
+    use crate::ir::{AbiParam, ExternalName, Function, InstBuilder, Signature};
+    use crate::isa::CallConv;
+    use crate::settings::Configurable;
+    use core::str::FromStr;
+
+    #[test]
+    fn test_fold_iconst_into_iadd() {
+        let name = ExternalName::testcase("test0");
+        let mut sig = Signature::new(CallConv::SystemV);
+        sig.params.push(AbiParam::new(I32));
+        sig.returns.push(AbiParam::new(I32));
+        let mut func = Function::with_name_signature(name, sig);
+
+        let bb0 = func.dfg.make_block();
+        let arg0 = func.dfg.append_block_param(bb0, I32);
+
+        let mut pos = FuncCursor::new(&mut func);
+        pos.insert_block(bb0);
+        let v0 = pos.ins().iconst(I32, 0x1234);
+        let v1 = pos.ins().iadd(arg0, v0);
+        pos.ins().return_(&[v1]);
+
+        let mut shared_flags = settings::builder();
+        shared_flags.set("opt_level", "none").unwrap();
+        let backend = X64Backend::new_with_flags(
+            Triple::from_str("x86_64").unwrap(),
+            settings::Flags::new(shared_flags),
+        );
+        let disasm = backend
+            .compile_function(&mut func, true)
+            .unwrap()
+            .disasm
+            .unwrap();
+
+        // The constant is an immediate of the add rather than being moved into a register.
+        assert!(disasm.contains("addl    $4660,"), "{}", disasm);
+        assert!(!disasm.contains("movl    $4660,"), "{}", disasm);
+    }
+}
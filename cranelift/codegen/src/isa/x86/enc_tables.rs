@@ -1411,6 +1411,40 @@ fn convert_insertlane(
     }
 }
 
+/// Expand `select_spectre_guard` to a `cmov`, instead of the branches `select` is expanded to.
+fn expand_select_spectre_guard(
+    inst: ir::Inst,
+    func: &mut ir::Function,
+    _cfg: &mut ControlFlowGraph,
+    _isa: &dyn TargetIsa,
+) {
+    let (cond, x, y) = match func.dfg[inst] {
+        ir::InstructionData::Ternary {
+            opcode: ir::Opcode::SelectSpectreGuard,
+            args,
+        } => (args[0], args[1], args[2]),
+        _ => panic!(
+            "Expected select_spectre_guard: {}",
+            func.dfg.display_inst(inst, None)
+        ),
+    };
+    let ty = func.dfg.ctrl_typevar(inst);
+
+    let mut pos = FuncCursor::new(func).at_inst(inst);
+    pos.use_srcloc(inst);
+
+    let cond = if pos.func.dfg.value_type(cond).is_bool() {
+        pos.ins().bint(I32, cond)
+    } else {
+        cond
+    };
+    let flags = pos.ins().ifcmp_imm(cond, 0);
+    pos.func
+        .dfg
+        .replace(inst)
+        .selectif(ty, IntCC::NotEqual, flags, x, y);
+}
+
 /// For SIMD or scalar integer negation, convert `ineg` to `vconst + isub` or `iconst + isub`.
 fn convert_ineg(
     inst: ir::Inst,
@@ -23,6 +23,8 @@ use crate::result::CodegenResult;
 use crate::timing;
 use alloc::borrow::Cow;
 use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::any::Any;
 use core::fmt;
 use target_lexicon::{PointerWidth, Triple};
@@ -86,6 +88,10 @@ impl TargetIsa for Isa {
         &self.shared_flags
     }
 
+    fn isa_flags(&self) -> Vec<(String, String)> {
+        shared_settings::flag_values(&self.isa_flags)
+    }
+
     fn uses_cpu_flags(&self) -> bool {
         true
     }
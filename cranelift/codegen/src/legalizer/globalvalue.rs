@@ -109,8 +109,10 @@ fn load_addr(
         pos.ins().global_value(ptr_ty, base)
     };
 
-    // Global-value loads are always notrap and aligned. They may be readonly.
+    // Global-value loads are always notrap and aligned. They may be readonly. What they load
+    // belongs to the VM context, which is never accessed through a heap or a table.
     let mut mflags = ir::MemFlags::trusted();
+    mflags.set_vmctx();
     if readonly {
         mflags.set_readonly();
     }
@@ -94,7 +94,16 @@ fn dynamic_addr(
     }
     pos.ins().trapnz(oob, ir::TrapCode::HeapOutOfBounds);
 
-    compute_addr(isa, inst, heap, addr_ty, offset, offset_ty, pos.func);
+    compute_addr(
+        isa,
+        inst,
+        heap,
+        addr_ty,
+        offset,
+        offset_ty,
+        pos.func,
+        Some(oob),
+    );
 }
 
 /// Expand a `heap_addr` for a static heap.
@@ -146,6 +155,7 @@ fn static_addr(
     // With that we have an optimization here where with 32-bit offsets and
     // `bound - access_size >= 4GB` we can omit a bounds check.
     let limit = bound - access_size;
+    let mut spectre_oob = None;
     if offset_ty != ir::types::I32 || limit < 0xffff_ffff {
         let oob = if limit & 1 == 1 {
             // Prefer testing `offset >= limit - 1` when limit is odd because an even number is
@@ -157,12 +167,25 @@ fn static_addr(
                 .icmp_imm(IntCC::UnsignedGreaterThan, offset, limit as i64)
         };
         pos.ins().trapnz(oob, ir::TrapCode::HeapOutOfBounds);
+        spectre_oob = Some(oob);
     }
 
-    compute_addr(isa, inst, heap, addr_ty, offset, offset_ty, pos.func);
+    compute_addr(
+        isa,
+        inst,
+        heap,
+        addr_ty,
+        offset,
+        offset_ty,
+        pos.func,
+        spectre_oob,
+    );
 }
 
 /// Emit code for the base address computation of a `heap_addr` instruction.
+///
+/// `oob` is the result of the bounds check, if there is one, which is used to
+/// clamp the address when Spectre mitigations are enabled.
 fn compute_addr(
     isa: &dyn TargetIsa,
     inst: ir::Inst,
@@ -171,6 +194,7 @@ fn compute_addr(
     mut offset: ir::Value,
     offset_ty: ir::Type,
     func: &mut ir::Function,
+    oob: Option<ir::Value>,
 ) {
     let mut pos = FuncCursor::new(func).at_inst(inst);
     pos.use_srcloc(inst);
@@ -198,5 +222,19 @@ fn compute_addr(
         pos.ins().global_value(addr_ty, base_gv)
     };
 
-    pos.func.dfg.replace(inst).iadd(base, offset);
+    match oob {
+        Some(oob) if isa.flags().enable_bounds_check_spectre_mitigation() => {
+            // Use the null address when the bounds check fails, in case the
+            // trap above is speculatively skipped.
+            let addr = pos.ins().iadd(base, offset);
+            let zero = pos.ins().iconst(addr_ty, 0);
+            pos.func
+                .dfg
+                .replace(inst)
+                .select_spectre_guard(oob, zero, addr);
+        }
+        _ => {
+            pos.func.dfg.replace(inst).iadd(base, offset);
+        }
+    }
 }
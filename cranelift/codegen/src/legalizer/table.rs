@@ -15,7 +15,7 @@ pub fn expand_table_addr(
     inst: ir::Inst,
     func: &mut ir::Function,
     _cfg: &mut ControlFlowGraph,
-    _isa: &dyn TargetIsa,
+    isa: &dyn TargetIsa,
 ) {
     // Unpack the instruction.
     let (table, index, element_offset) = match func.dfg[inst] {
@@ -31,11 +31,12 @@ pub fn expand_table_addr(
         _ => panic!("Wanted table_addr: {}", func.dfg.display_inst(inst, None)),
     };
 
-    dynamic_addr(inst, table, index, element_offset, func);
+    dynamic_addr(isa, inst, table, index, element_offset, func);
 }
 
 /// Expand a `table_addr` for a dynamic table.
 fn dynamic_addr(
+    isa: &dyn TargetIsa,
     inst: ir::Inst,
     table: ir::Table,
     index: ir::Value,
@@ -58,6 +59,7 @@ fn dynamic_addr(
     pos.ins().trapnz(oob, ir::TrapCode::TableOutOfBounds);
 
     compute_addr(
+        isa,
         inst,
         table,
         addr_ty,
@@ -65,11 +67,16 @@ fn dynamic_addr(
         index_ty,
         element_offset,
         pos.func,
+        oob,
     );
 }
 
 /// Emit code for the base address computation of a `table_addr` instruction.
+///
+/// `oob` is the result of the bounds check, which is used to clamp the address
+/// when Spectre mitigations are enabled.
 fn compute_addr(
+    isa: &dyn TargetIsa,
     inst: ir::Inst,
     table: ir::Table,
     addr_ty: ir::Type,
@@ -77,6 +84,7 @@ fn compute_addr(
     index_ty: ir::Type,
     element_offset: Offset32,
     func: &mut ir::Function,
+    oob: ir::Value,
 ) {
     let mut pos = FuncCursor::new(func).at_inst(inst);
     pos.use_srcloc(inst);
@@ -103,7 +111,20 @@ fn compute_addr(
         offset = pos.ins().imul_imm(index, element_size as i64);
     }
 
-    if element_offset == Offset32::new(0) {
+    if isa.flags().enable_bounds_check_spectre_mitigation() {
+        // Use the null address when the bounds check fails, in case the trap
+        // above is speculatively skipped.
+        let mut addr = pos.ins().iadd(base, offset);
+        if element_offset != Offset32::new(0) {
+            let imm: i64 = element_offset.into();
+            addr = pos.ins().iadd_imm(addr, imm);
+        }
+        let zero = pos.ins().iconst(addr_ty, 0);
+        pos.func
+            .dfg
+            .replace(inst)
+            .select_spectre_guard(oob, zero, addr);
+    } else if element_offset == Offset32::new(0) {
         pos.func.dfg.replace(inst).iadd(base, offset);
     } else {
         let imm: i64 = element_offset.into();
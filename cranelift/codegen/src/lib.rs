@@ -87,6 +87,7 @@ pub mod write;
 pub use crate::entity::packed_option;
 
 mod abi;
+mod alias_analysis;
 mod bitset;
 mod constant_hash;
 mod context;
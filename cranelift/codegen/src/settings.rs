@@ -24,6 +24,7 @@ use crate::constant_hash::{probe, simple_hash};
 use crate::isa::TargetIsa;
 use alloc::boxed::Box;
 use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use core::fmt;
 use core::str;
 use thiserror::Error;
@@ -336,6 +337,19 @@ pub mod detail {
 // `cranelift-codegen/meta/src/shared/settings.rs`.
 include!(concat!(env!("OUT_DIR"), "/settings.rs"));
 
+/// Split the `name = value` lines of a displayed group of flags into pairs, skipping the
+/// `[group]` header. Values are formatted as TOML values, like `true` or `"speed"`.
+pub(crate) fn flag_values(flags: &dyn fmt::Display) -> Vec<(String, String)> {
+    flags
+        .to_string()
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, " = ");
+            Some((parts.next()?.to_string(), parts.next()?.to_string()))
+        })
+        .collect()
+}
+
 /// Wrapper containing flags and optionally a `TargetIsa` trait object.
 ///
 /// A few passes need to access the flags but only optionally a target ISA. The `FlagsOrIsa`
@@ -368,7 +382,7 @@ impl<'a> From<&'a dyn TargetIsa> for FlagsOrIsa<'a> {
 mod tests {
     use super::Configurable;
     use super::SetError::*;
-    use super::{builder, Flags};
+    use super::{builder, flag_values, Flags};
     use alloc::string::ToString;
 
     #[test]
@@ -392,6 +406,7 @@ enable_float = true
 enable_nan_canonicalization = false
 enable_pinned_reg = false
 use_pinned_reg_as_heap_base = false
+enable_bounds_check_spectre_mitigation = false
 enable_simd = false
 enable_atomics = true
 enable_safepoints = false
@@ -442,4 +457,14 @@ enable_jump_tables = true
         assert_eq!(f.enable_simd(), false);
         assert_eq!(f.opt_level(), super::OptLevel::Speed);
     }
+
+    #[test]
+    fn values() {
+        let mut b = builder();
+        b.set("opt_level", "speed").unwrap();
+        let values = flag_values(&Flags::new(b));
+        assert!(!values.iter().any(|(name, _)| name.starts_with('[')));
+        assert!(values.contains(&("opt_level".to_string(), "\"speed\"".to_string())));
+        assert!(values.contains(&("enable_simd".to_string(), "false".to_string())));
+    }
 }
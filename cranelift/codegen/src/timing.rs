@@ -61,6 +61,7 @@ define_passes! {
     legalize: "Legalization",
     gvn: "Global value numbering",
     licm: "Loop invariant code motion",
+    alias_analysis: "Alias analysis and redundant load elimination",
     unreachable_code: "Remove unreachable blocks",
     remove_constant_phis: "Remove constant phi-nodes",
 
@@ -41,6 +41,10 @@
 //! - All return instructions must have return value operands matching the current
 //!   function signature.
 //!
+//! Memory flags
+//!
+//! - Memory accesses must have at most one alias analysis category.
+//!
 //! Global values
 //!
 //! - Detect cycles in global values.
@@ -1941,6 +1945,28 @@ impl<'a> Verifier<'a> {
         }
     }
 
+    fn verify_memflags(&self, inst: Inst, errors: &mut VerifierErrors) -> VerifierStepResult<()> {
+        let flags = match self.func.dfg[inst] {
+            ir::InstructionData::Load { flags, .. }
+            | ir::InstructionData::LoadComplex { flags, .. }
+            | ir::InstructionData::Store { flags, .. }
+            | ir::InstructionData::StoreComplex { flags, .. } => flags,
+            _ => return Ok(()),
+        };
+
+        // The alias analysis categories are disjoint.
+        let categories = [flags.heap(), flags.table(), flags.vmctx()];
+        if categories.iter().filter(|&&category| category).count() > 1 {
+            return errors.fatal((
+                inst,
+                self.context(inst),
+                "A memory access can have at most one of the `heap`, `table` and `vmctx` MemFlags",
+            ));
+        }
+
+        Ok(())
+    }
+
     fn verify_safepoint_unused(
         &self,
         inst: Inst,
@@ -2012,6 +2038,7 @@ impl<'a> Verifier<'a> {
                 self.typecheck(inst, errors)?;
                 self.verify_encoding(inst, errors)?;
                 self.immediate_constraints(inst, errors)?;
+                self.verify_memflags(inst, errors)?;
             }
 
             self.encodable_as_bb(block, errors)?;
@@ -37,6 +37,7 @@ mod runner;
 mod runone;
 mod subtest;
 
+mod test_alias_analysis;
 mod test_binemit;
 mod test_cat;
 mod test_compile;
@@ -121,6 +122,7 @@ pub fn run_passes(
 /// a `.clif` test file.
 fn new_subtest(parsed: &TestCommand) -> subtest::SubtestResult<Box<dyn subtest::SubTest>> {
     match parsed.command {
+        "alias-analysis" => test_alias_analysis::subtest(parsed),
         "binemit" => test_binemit::subtest(parsed),
         "cat" => test_cat::subtest(parsed),
         "compile" => test_compile::subtest(parsed),
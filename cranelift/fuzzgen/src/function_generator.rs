@@ -0,0 +1,483 @@
+//! Generate random, valid [Function]s with the [FunctionBuilder].
+//!
+//! A generated function starts by defining a set of variables, from its parameters and from
+//! constants, and then fills a sequence of blocks with instructions that each compute a new value
+//! for one of these variables from the others. Blocks only ever branch to blocks later in the
+//! sequence, so every generated function terminates; the [FunctionBuilder] takes care of turning
+//! the variables into SSA values and block parameters.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use cranelift_codegen::entity::EntityRef;
+use cranelift_codegen::ir::condcodes::{FloatCC, IntCC};
+use cranelift_codegen::ir::immediates::{Ieee32, Ieee64};
+use cranelift_codegen::ir::types::{B1, F32, F64, I16, I32, I64, I8};
+use cranelift_codegen::ir::{
+    AbiParam, Block, ExternalName, Function, InstBuilder, Opcode, Signature, Type, Value,
+};
+use cranelift_codegen::isa::CallConv;
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+
+/// The types of the values in generated functions. Wider booleans and vectors are left out since
+/// the host backends can't lower every operation on them yet.
+const TYPES: [Type; 7] = [B1, I8, I16, I32, I64, F32, F64];
+
+/// The types of the values that can be compared with `icmp` or `fcmp`.
+const COMPARABLE_TYPES: [Type; 6] = [I8, I16, I32, I64, F32, F64];
+
+/// The integer instructions to generate. Signed division and remainder are left out because they
+/// trap on overflow, which the interpreter and the compiled code report differently.
+const INT_OPCODES: [Opcode; 29] = [
+    Opcode::Iconst,
+    Opcode::Iadd,
+    Opcode::Isub,
+    Opcode::Imul,
+    Opcode::Umulhi,
+    Opcode::Smulhi,
+    Opcode::Udiv,
+    Opcode::Urem,
+    Opcode::Ineg,
+    Opcode::Band,
+    Opcode::Bor,
+    Opcode::Bxor,
+    Opcode::Bnot,
+    Opcode::Ishl,
+    Opcode::Ushr,
+    Opcode::Sshr,
+    Opcode::Rotl,
+    Opcode::Rotr,
+    Opcode::Clz,
+    Opcode::Ctz,
+    Opcode::Popcnt,
+    Opcode::IaddImm,
+    Opcode::Icmp,
+    Opcode::Select,
+    Opcode::Uextend,
+    Opcode::Sextend,
+    Opcode::Ireduce,
+    Opcode::FcvtToSintSat,
+    Opcode::FcvtToUintSat,
+];
+
+/// The integer instructions to generate for `i8` and `i16`, which the x86 backend widens to
+/// `i32`. Shifts and rotates are left out because the widened instructions don't mask the shift
+/// amount to the narrow width, and so are the high multiplications and the conversions from
+/// floats, which aren't widened at all.
+const NARROW_INT_OPCODES: [Opcode; 20] = [
+    Opcode::Iconst,
+    Opcode::Iadd,
+    Opcode::Isub,
+    Opcode::Imul,
+    Opcode::Udiv,
+    Opcode::Urem,
+    Opcode::Ineg,
+    Opcode::Band,
+    Opcode::Bor,
+    Opcode::Bxor,
+    Opcode::Bnot,
+    Opcode::Clz,
+    Opcode::Ctz,
+    Opcode::Popcnt,
+    Opcode::IaddImm,
+    Opcode::Icmp,
+    Opcode::Select,
+    Opcode::Uextend,
+    Opcode::Sextend,
+    Opcode::Ireduce,
+];
+
+/// The floating point instructions to generate. Nothing here observes the bits of a NaN, such as a
+/// bitcast to an integer or `fcopysign`: they are not specified, so they may differ between the
+/// interpreter and the compiled code.
+const FLOAT_OPCODES: [Opcode; 17] = [
+    Opcode::F32const,
+    Opcode::F64const,
+    Opcode::Fadd,
+    Opcode::Fsub,
+    Opcode::Fmul,
+    Opcode::Fdiv,
+    Opcode::Sqrt,
+    Opcode::Fneg,
+    Opcode::Fabs,
+    Opcode::Fmin,
+    Opcode::Fmax,
+    Opcode::Select,
+    Opcode::Fpromote,
+    Opcode::Fdemote,
+    Opcode::FcvtFromSint,
+    Opcode::FcvtFromUint,
+    Opcode::Bitcast,
+];
+
+/// The boolean instructions to generate. `bnot` is left out because the x86 backend inverts every
+/// bit of the register holding a `b1`.
+const BOOL_OPCODES: [Opcode; 6] = [
+    Opcode::Bconst,
+    Opcode::Band,
+    Opcode::Bor,
+    Opcode::Bxor,
+    Opcode::Icmp,
+    Opcode::Select,
+];
+
+/// The integer comparisons to generate; the overflow conditions aren't supported by `icmp`.
+const INT_CONDITIONS: [IntCC; 10] = [
+    IntCC::Equal,
+    IntCC::NotEqual,
+    IntCC::SignedLessThan,
+    IntCC::SignedGreaterThanOrEqual,
+    IntCC::SignedGreaterThan,
+    IntCC::SignedLessThanOrEqual,
+    IntCC::UnsignedLessThan,
+    IntCC::UnsignedGreaterThanOrEqual,
+    IntCC::UnsignedGreaterThan,
+    IntCC::UnsignedLessThanOrEqual,
+];
+
+const FLOAT_CONDITIONS: [FloatCC; 14] = [
+    FloatCC::Ordered,
+    FloatCC::Unordered,
+    FloatCC::Equal,
+    FloatCC::NotEqual,
+    FloatCC::OrderedNotEqual,
+    FloatCC::UnorderedOrEqual,
+    FloatCC::LessThan,
+    FloatCC::LessThanOrEqual,
+    FloatCC::GreaterThan,
+    FloatCC::GreaterThanOrEqual,
+    FloatCC::UnorderedOrLessThan,
+    FloatCC::UnorderedOrLessThanOrEqual,
+    FloatCC::UnorderedOrGreaterThan,
+    FloatCC::UnorderedOrGreaterThanOrEqual,
+];
+
+const MAX_PARAMS: usize = 6;
+const MAX_RETURNS: usize = 3;
+const MAX_VARIABLES: usize = 16;
+const MAX_BLOCKS: usize = 8;
+const MAX_INSTS_PER_BLOCK: usize = 16;
+
+/// Builds a random [Function] from the data of an [Unstructured].
+pub struct FunctionGenerator<'r, 'data> {
+    u: &'r mut Unstructured<'data>,
+    variables: Vec<(Variable, Type)>,
+}
+
+impl<'r, 'data> FunctionGenerator<'r, 'data> {
+    /// Create a generator consuming the data of `u`.
+    pub fn new(u: &'r mut Unstructured<'data>) -> Self {
+        Self {
+            u,
+            variables: vec![],
+        }
+    }
+
+    /// Generate a function named `%fuzz` using the calling convention `call_conv`.
+    pub fn generate(mut self, call_conv: CallConv) -> Result<Function> {
+        let signature = self.signature(call_conv)?;
+        let mut func =
+            Function::with_name_signature(ExternalName::testcase("fuzz"), signature.clone());
+        let mut context = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut func, &mut context);
+
+        let block_count = self.u.int_in_range(1..=MAX_BLOCKS)?;
+        let blocks: Vec<Block> = (0..block_count).map(|_| builder.create_block()).collect();
+        builder.append_block_params_for_function_params(blocks[0]);
+
+        for (i, &block) in blocks.iter().enumerate() {
+            // All the predecessors of a block come before it, so it can be sealed right away.
+            builder.switch_to_block(block);
+            builder.seal_block(block);
+            if i == 0 {
+                self.define_variables(&mut builder, block)?;
+            }
+            for _ in 0..self.u.int_in_range(0..=MAX_INSTS_PER_BLOCK)? {
+                self.instruction(&mut builder)?;
+            }
+            self.terminator(&mut builder, &blocks[i + 1..], &signature.returns)?;
+        }
+
+        builder.finalize();
+        Ok(func)
+    }
+
+    fn signature(&mut self, call_conv: CallConv) -> Result<Signature> {
+        let mut signature = Signature::new(call_conv);
+        for _ in 0..self.u.int_in_range(0..=MAX_PARAMS)? {
+            signature.params.push(AbiParam::new(self.choose(&TYPES)?));
+        }
+        for _ in 0..self.u.int_in_range(1..=MAX_RETURNS)? {
+            signature.returns.push(AbiParam::new(self.choose(&TYPES)?));
+        }
+        Ok(signature)
+    }
+
+    /// Define a variable for each function parameter, at least one variable of each type and then
+    /// a few more, initialized with constants.
+    fn define_variables(&mut self, builder: &mut FunctionBuilder, entry: Block) -> Result<()> {
+        for param in builder.block_params(entry).to_vec() {
+            let ty = builder.func.dfg.value_type(param);
+            let var = self.declare_variable(builder, ty);
+            builder.def_var(var, param);
+        }
+        let extra = self.u.int_in_range(0..=MAX_VARIABLES)?;
+        for i in 0..TYPES.len() + extra {
+            let ty = match TYPES.get(i) {
+                Some(&ty) => ty,
+                None => self.choose(&TYPES)?,
+            };
+            let var = self.declare_variable(builder, ty);
+            let value = self.constant(builder, ty)?;
+            builder.def_var(var, value);
+        }
+        Ok(())
+    }
+
+    fn declare_variable(&mut self, builder: &mut FunctionBuilder, ty: Type) -> Variable {
+        let var = Variable::new(self.variables.len());
+        builder.declare_var(var, ty);
+        self.variables.push((var, ty));
+        var
+    }
+
+    /// Assign the result of a random instruction to a random variable.
+    fn instruction(&mut self, builder: &mut FunctionBuilder) -> Result<()> {
+        let (var, ty) = self.variables[self.u.int_in_range(0..=self.variables.len() - 1)?];
+        let value = if ty.is_bool() {
+            self.bool_instruction(builder)?
+        } else if ty.is_int() {
+            self.int_instruction(builder, ty)?
+        } else {
+            self.float_instruction(builder, ty)?
+        };
+        builder.def_var(var, value);
+        Ok(())
+    }
+
+    fn int_instruction(&mut self, builder: &mut FunctionBuilder, ty: Type) -> Result<Value> {
+        let opcode = if ty.bits() < 32 {
+            self.choose(&NARROW_INT_OPCODES)?
+        } else {
+            self.choose(&INT_OPCODES)?
+        };
+        let x = self.operand(builder, ty)?;
+        let y = self.operand(builder, ty)?;
+        let value = match opcode {
+            Opcode::Iconst => self.constant(builder, ty)?,
+            Opcode::Iadd => builder.ins().iadd(x, y),
+            Opcode::Isub => builder.ins().isub(x, y),
+            Opcode::Imul => builder.ins().imul(x, y),
+            Opcode::Umulhi => builder.ins().umulhi(x, y),
+            Opcode::Smulhi => builder.ins().smulhi(x, y),
+            Opcode::Udiv | Opcode::Urem => {
+                // Make sure the divisor isn't zero.
+                let y = builder.ins().bor_imm(y, 1);
+                if opcode == Opcode::Udiv {
+                    builder.ins().udiv(x, y)
+                } else {
+                    builder.ins().urem(x, y)
+                }
+            }
+            Opcode::Ineg => builder.ins().ineg(x),
+            Opcode::Band => builder.ins().band(x, y),
+            Opcode::Bor => builder.ins().bor(x, y),
+            Opcode::Bxor => builder.ins().bxor(x, y),
+            Opcode::Bnot => builder.ins().bnot(x),
+            Opcode::Ishl => builder.ins().ishl(x, y),
+            Opcode::Ushr => builder.ins().ushr(x, y),
+            Opcode::Sshr => builder.ins().sshr(x, y),
+            Opcode::Rotl => builder.ins().rotl(x, y),
+            Opcode::Rotr => builder.ins().rotr(x, y),
+            Opcode::Clz => builder.ins().clz(x),
+            Opcode::Ctz => builder.ins().ctz(x),
+            Opcode::Popcnt => builder.ins().popcnt(x),
+            Opcode::IaddImm => builder.ins().iadd_imm(x, i64::arbitrary(self.u)?),
+            Opcode::Icmp => {
+                let condition = self.choose(&INT_CONDITIONS)?;
+                let c = builder.ins().icmp(condition, x, y);
+                builder.ins().bint(ty, c)
+            }
+            Opcode::Select => {
+                let c = self.condition(builder)?;
+                builder.ins().select(c, x, y)
+            }
+            Opcode::Uextend | Opcode::Sextend | Opcode::Ireduce => {
+                // Convert from another integer type, which is reduced if it's wider than `ty` and
+                // extended, with the signedness of `opcode`, if it's narrower.
+                let others: Vec<Type> = [I8, I16, I32, I64]
+                    .iter()
+                    .copied()
+                    .filter(|&other| other != ty)
+                    .collect();
+                let from = self.choose(&others)?;
+                let x = self.operand(builder, from)?;
+                if from.bits() > ty.bits() {
+                    builder.ins().ireduce(ty, x)
+                } else if opcode == Opcode::Sextend {
+                    builder.ins().sextend(ty, x)
+                } else {
+                    builder.ins().uextend(ty, x)
+                }
+            }
+            Opcode::FcvtToSintSat | Opcode::FcvtToUintSat => {
+                let from = self.choose(&[F32, F64])?;
+                let x = self.operand(builder, from)?;
+                if opcode == Opcode::FcvtToSintSat {
+                    builder.ins().fcvt_to_sint_sat(ty, x)
+                } else {
+                    builder.ins().fcvt_to_uint_sat(ty, x)
+                }
+            }
+            _ => unreachable!(),
+        };
+        Ok(value)
+    }
+
+    fn bool_instruction(&mut self, builder: &mut FunctionBuilder) -> Result<Value> {
+        let opcode = self.choose(&BOOL_OPCODES)?;
+        let x = self.operand(builder, B1)?;
+        let y = self.operand(builder, B1)?;
+        let value = match opcode {
+            Opcode::Bconst => self.constant(builder, B1)?,
+            Opcode::Band => builder.ins().band(x, y),
+            Opcode::Bor => builder.ins().bor(x, y),
+            Opcode::Bxor => builder.ins().bxor(x, y),
+            Opcode::Icmp => self.condition(builder)?,
+            Opcode::Select => {
+                let c = self.condition(builder)?;
+                builder.ins().select(c, x, y)
+            }
+            _ => unreachable!(),
+        };
+        Ok(value)
+    }
+
+    fn float_instruction(&mut self, builder: &mut FunctionBuilder, ty: Type) -> Result<Value> {
+        let opcode = self.choose(&FLOAT_OPCODES)?;
+        let x = self.operand(builder, ty)?;
+        let y = self.operand(builder, ty)?;
+        let value = match opcode {
+            Opcode::F32const | Opcode::F64const => self.constant(builder, ty)?,
+            Opcode::Fadd => builder.ins().fadd(x, y),
+            Opcode::Fsub => builder.ins().fsub(x, y),
+            Opcode::Fmul => builder.ins().fmul(x, y),
+            Opcode::Fdiv => builder.ins().fdiv(x, y),
+            Opcode::Sqrt => builder.ins().sqrt(x),
+            Opcode::Fneg => builder.ins().fneg(x),
+            Opcode::Fabs => builder.ins().fabs(x),
+            Opcode::Fmin => builder.ins().fmin(x, y),
+            Opcode::Fmax => builder.ins().fmax(x, y),
+            Opcode::Select => {
+                let c = self.condition(builder)?;
+                builder.ins().select(c, x, y)
+            }
+            Opcode::Fpromote | Opcode::Fdemote => {
+                // Only one of the conversions between the two float types applies to `ty`.
+                if ty == F64 {
+                    let x = self.operand(builder, F32)?;
+                    builder.ins().fpromote(ty, x)
+                } else {
+                    let x = self.operand(builder, F64)?;
+                    builder.ins().fdemote(ty, x)
+                }
+            }
+            Opcode::FcvtFromSint | Opcode::FcvtFromUint => {
+                let from = self.choose(&[I32, I64])?;
+                let x = self.operand(builder, from)?;
+                if opcode == Opcode::FcvtFromSint {
+                    builder.ins().fcvt_from_sint(ty, x)
+                } else {
+                    builder.ins().fcvt_from_uint(ty, x)
+                }
+            }
+            Opcode::Bitcast => {
+                let from = if ty == F32 { I32 } else { I64 };
+                let x = self.operand(builder, from)?;
+                builder.ins().bitcast(ty, x)
+            }
+            _ => unreachable!(),
+        };
+        Ok(value)
+    }
+
+    /// Compare two values of a random type, producing a boolean.
+    fn condition(&mut self, builder: &mut FunctionBuilder) -> Result<Value> {
+        let ty = self.choose(&COMPARABLE_TYPES)?;
+        let x = self.operand(builder, ty)?;
+        let y = self.operand(builder, ty)?;
+        let value = if ty.is_int() {
+            let condition = self.choose(&INT_CONDITIONS)?;
+            builder.ins().icmp(condition, x, y)
+        } else {
+            let condition = self.choose(&FLOAT_CONDITIONS)?;
+            builder.ins().fcmp(condition, x, y)
+        };
+        Ok(value)
+    }
+
+    /// End the current block, either by returning or by branching to some of the `successors`.
+    fn terminator(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        successors: &[Block],
+        returns: &[AbiParam],
+    ) -> Result<()> {
+        if successors.is_empty() {
+            let mut values = vec![];
+            for param in returns {
+                values.push(self.operand(builder, param.value_type)?);
+            }
+            builder.ins().return_(&values);
+            return Ok(());
+        }
+
+        if bool::arbitrary(self.u)? {
+            let ty = self.choose(&[B1, I32, I64])?;
+            let c = self.operand(builder, ty)?;
+            let target = self.choose(successors)?;
+            if bool::arbitrary(self.u)? {
+                builder.ins().brz(c, target, &[]);
+            } else {
+                builder.ins().brnz(c, target, &[]);
+            }
+        }
+        let target = self.choose(successors)?;
+        builder.ins().jump(target, &[]);
+        Ok(())
+    }
+
+    /// Read the current value of a random variable of type `ty`.
+    fn operand(&mut self, builder: &mut FunctionBuilder, ty: Type) -> Result<Value> {
+        let candidates: Vec<Variable> = self
+            .variables
+            .iter()
+            .filter(|&&(_, var_ty)| var_ty == ty)
+            .map(|&(var, _)| var)
+            .collect();
+        let var = self.choose(&candidates)?;
+        Ok(builder.use_var(var))
+    }
+
+    fn constant(&mut self, builder: &mut FunctionBuilder, ty: Type) -> Result<Value> {
+        let value = match ty {
+            B1 => builder.ins().bconst(ty, bool::arbitrary(self.u)?),
+            I8 => builder.ins().iconst(ty, i64::from(i8::arbitrary(self.u)?)),
+            I16 => builder.ins().iconst(ty, i64::from(i16::arbitrary(self.u)?)),
+            I32 => builder.ins().iconst(ty, i64::from(i32::arbitrary(self.u)?)),
+            I64 => builder.ins().iconst(ty, i64::arbitrary(self.u)?),
+            F32 => {
+                let bits = u32::arbitrary(self.u)?;
+                builder.ins().f32const(Ieee32::with_bits(bits))
+            }
+            F64 => {
+                let bits = u64::arbitrary(self.u)?;
+                builder.ins().f64const(Ieee64::with_bits(bits))
+            }
+            _ => unreachable!(),
+        };
+        Ok(value)
+    }
+
+    fn choose<T: Copy>(&mut self, choices: &[T]) -> Result<T> {
+        Ok(choices[self.u.int_in_range(0..=choices.len() - 1)?])
+    }
+}
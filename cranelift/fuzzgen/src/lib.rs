@@ -0,0 +1,201 @@
+//! Random Cranelift IR generation for fuzzing.
+//!
+//! A [TestCase] is generated from the raw input of a fuzzer: it holds a function built with the
+//! `FunctionBuilder`, the arguments to call it with and the optimization level to compile it at.
+//! [run_differential] compiles the function for the host, the same way `test run` does, and checks
+//! that it returns the same results as `cranelift-interpreter`. This reaches lowering bugs that
+//! fuzzing at the level of wasm never gets to.
+//!
+//! The generated functions are limited to arithmetic, comparisons, conversions and forward
+//! branches on scalar `b1`, `i8`, `i16`, `i32`, `i64`, `f32` and `f64` values. They have no loops,
+//! memory accesses, calls, traps, or values of wider boolean or vector types, so lowering bugs in
+//! those aren't found.
+
+#![deny(missing_docs)]
+
+mod function_generator;
+
+pub use crate::function_generator::FunctionGenerator;
+
+use arbitrary::{Arbitrary, Unstructured};
+use cranelift_codegen::ir::types::{B1, F32, F64, I16, I32, I64, I8};
+use cranelift_codegen::ir::{Function, Type};
+use cranelift_codegen::isa::CallConv;
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_filetests::SingleFunctionCompiler;
+use cranelift_interpreter::environment::Environment;
+use cranelift_interpreter::interpreter::Interpreter;
+use cranelift_reader::DataValue;
+use std::fmt;
+use target_lexicon::Triple;
+
+/// The `opt_level` settings to compile generated functions with.
+const OPT_LEVELS: [&str; 3] = ["none", "speed", "speed_and_size"];
+
+const MAX_INPUTS: usize = 4;
+
+/// A generated function and the inputs to run it with.
+pub struct TestCase {
+    /// The function, named `%fuzz`, which uses the host's default calling convention.
+    pub func: Function,
+    /// The arguments of each call to `func`.
+    pub inputs: Vec<Vec<DataValue>>,
+    /// The `opt_level` setting to compile `func` with.
+    pub opt_level: &'static str,
+}
+
+/// Print the test case as a CLIF test file, so that a failure can be reproduced with `clif-util
+/// test`.
+impl fmt::Debug for TestCase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "test interpret")?;
+        writeln!(f, "test run")?;
+        writeln!(f, "set opt_level={}", self.opt_level)?;
+        writeln!(f)?;
+        write!(f, "{}", self.func)?;
+        for args in &self.inputs {
+            let args: Vec<String> = args.iter().map(ToString::to_string).collect();
+            writeln!(f, "; print: {}({})", self.func.name, args.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Arbitrary for TestCase {
+    fn arbitrary(u: &mut Unstructured) -> arbitrary::Result<Self> {
+        let call_conv = CallConv::triple_default(&Triple::host());
+        let func = FunctionGenerator::new(u).generate(call_conv)?;
+        let mut inputs = vec![];
+        for _ in 0..u.int_in_range(1..=MAX_INPUTS)? {
+            let mut args = vec![];
+            for param in &func.signature.params {
+                args.push(data_value(u, param.value_type)?);
+            }
+            inputs.push(args);
+        }
+        let opt_level = OPT_LEVELS[u.int_in_range(0..=OPT_LEVELS.len() - 1)?];
+        Ok(Self {
+            func,
+            inputs,
+            opt_level,
+        })
+    }
+}
+
+/// Generate an arbitrary value of type `ty`, which is one of the types of [FunctionGenerator].
+fn data_value(u: &mut Unstructured, ty: Type) -> arbitrary::Result<DataValue> {
+    let value = match ty {
+        B1 => DataValue::B(Arbitrary::arbitrary(u)?),
+        I8 => DataValue::I8(Arbitrary::arbitrary(u)?),
+        I16 => DataValue::I16(Arbitrary::arbitrary(u)?),
+        I32 => DataValue::I32(Arbitrary::arbitrary(u)?),
+        I64 => DataValue::I64(Arbitrary::arbitrary(u)?),
+        F32 => DataValue::F32(f32::from_bits(Arbitrary::arbitrary(u)?)),
+        F64 => DataValue::F64(f64::from_bits(Arbitrary::arbitrary(u)?)),
+        _ => unreachable!(),
+    };
+    Ok(value)
+}
+
+/// Compile the function of `testcase` for the host and call it with each of the inputs, checking
+/// that it returns the same results as the interpreter.
+///
+/// Panics if the results differ, or if either the compilation or the interpretation of the
+/// function fails: the generated functions are valid and never trap.
+pub fn run_differential(testcase: &TestCase) {
+    let mut flags = settings::builder();
+    flags
+        .set("opt_level", testcase.opt_level)
+        .expect("a valid opt_level");
+    let mut compiler = SingleFunctionCompiler::with_host_isa(settings::Flags::new(flags));
+    let compiled = match compiler.compile(testcase.func.clone()) {
+        Ok(compiled) => compiled,
+        Err(e) => panic!("failed to compile {}: {:?}", testcase.func.name, e),
+    };
+
+    let interpreter = Interpreter::new(Environment::from(testcase.func.clone()));
+    let name = testcase.func.name.to_string();
+    for args in &testcase.inputs {
+        let expected = match interpreter.call_by_name(&name, args) {
+            Ok(control_flow) => control_flow.unwrap_return(),
+            Err(trap) => panic!("failed to interpret {}{:?}: {}", name, args, trap),
+        };
+        let actual = compiled.call(args);
+        assert!(
+            same_results(&expected, &actual),
+            "{}{:?} returned {:?} but the interpreter returned {:?}",
+            name,
+            args,
+            actual,
+            expected
+        );
+    }
+}
+
+/// Compare the results of two calls. Floats are compared by their bits, except that all NaNs are
+/// equal since their bits are not specified.
+fn same_results(a: &[DataValue], b: &[DataValue]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|pair| match pair {
+            (DataValue::F32(x), DataValue::F32(y)) => {
+                x.to_bits() == y.to_bits() || x.is_nan() && y.is_nan()
+            }
+            (DataValue::F64(x), DataValue::F64(y)) => {
+                x.to_bits() == y.to_bits() || x.is_nan() && y.is_nan()
+            }
+            (x, y) => x == y,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cranelift_codegen::verify_function;
+
+    /// Generate test cases from some fixed, pseudo-random inputs.
+    fn testcases() -> impl Iterator<Item = TestCase> {
+        (0..64u64).filter_map(|seed| {
+            let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+            let data: Vec<u8> = (0..4096)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect();
+            TestCase::arbitrary(&mut Unstructured::new(&data)).ok()
+        })
+    }
+
+    #[test]
+    fn generated_functions_verify() {
+        let flags = settings::Flags::new(settings::builder());
+        for testcase in testcases() {
+            if let Err(errors) = verify_function(&testcase.func, &flags) {
+                panic!("{:?}\n{}", testcase, errors);
+            }
+        }
+    }
+
+    #[test]
+    fn generated_functions_interpret() {
+        for testcase in testcases() {
+            let interpreter = Interpreter::new(Environment::from(testcase.func.clone()));
+            for args in &testcase.inputs {
+                let results = interpreter
+                    .call_by_name("%fuzz", args)
+                    .unwrap_or_else(|trap| panic!("{:?}\n{}", testcase, trap))
+                    .unwrap_return();
+                assert_eq!(results.len(), testcase.func.signature.returns.len());
+            }
+        }
+    }
+
+    #[test]
+    fn generated_functions_run_on_the_host() {
+        for testcase in testcases().take(8) {
+            run_differential(&testcase);
+        }
+    }
+}
@@ -1,6 +1,6 @@
 //! Implements a call frame (activation record) for the Cranelift interpreter.
 
-use cranelift_codegen::ir::{Function, Value as ValueRef};
+use cranelift_codegen::ir::{Function, StackSlot, Type, Value as ValueRef};
 use cranelift_reader::DataValue;
 use log::trace;
 use std::collections::HashMap;
@@ -16,6 +16,21 @@ pub struct Frame<'a> {
     pub function: &'a Function,
     /// The current mapping of SSA value-references to their actual values.
     registers: HashMap<ValueRef, DataValue>,
+    /// The CPU flags values, which have no [DataValue] representation.
+    flags: HashMap<ValueRef, Flags>,
+    /// The addresses of the function's stack slots in the interpreter's memory.
+    stack_slots: HashMap<StackSlot, u64>,
+}
+
+/// The state captured by a CPU flags value, from which its condition codes are evaluated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Flags {
+    /// The result of comparing the bits of two integers of type `ty`, e.g. by `ifcmp`.
+    Compare { a: u64, b: u64, ty: Type },
+    /// The carry (or borrow) and signed overflow out of an addition or subtraction.
+    Carry { carry: bool, overflow: bool },
+    /// The result of comparing two floating point numbers with `ffcmp`.
+    Float { a: f64, b: f64 },
 }
 
 impl<'a> Frame<'a> {
@@ -27,6 +42,8 @@ impl<'a> Frame<'a> {
         Self {
             function,
             registers: HashMap::with_capacity(function.dfg.num_values()),
+            flags: HashMap::new(),
+            stack_slots: HashMap::new(),
         }
     }
 
@@ -59,19 +76,49 @@ impl<'a> Frame<'a> {
         }
     }
 
-    /// Rename all of the SSA references in `old_names` to those in `new_names`. This will remove
-    /// any old references that are not in `old_names`. TODO This performs an extra allocation that
-    /// could be removed if we copied the values in the right order (i.e. when modifying in place,
-    /// we need to avoid changing a value before it is referenced).
+    /// Retrieve the flags value associated with an SSA reference.
+    pub fn get_flags(&self, name: &ValueRef) -> Flags {
+        trace!("Get flags {}", name);
+        *self
+            .flags
+            .get(name)
+            .unwrap_or_else(|| panic!("unknown flags value: {}", name))
+    }
+
+    /// Assign the flags value `flags` to the SSA reference `name`.
+    pub fn set_flags(&mut self, name: ValueRef, flags: Flags) {
+        trace!("Set flags {} -> {:?}", name, flags);
+        self.flags.insert(name, flags);
+    }
+
+    /// Retrieve the address of a stack slot.
+    pub fn stack_slot_address(&self, slot: StackSlot) -> u64 {
+        *self
+            .stack_slots
+            .get(&slot)
+            .unwrap_or_else(|| panic!("unallocated stack slot: {}", slot))
+    }
+
+    /// Record the address the stack slot `slot` was allocated at.
+    pub fn set_stack_slot_address(&mut self, slot: StackSlot, address: u64) {
+        self.stack_slots.insert(slot, address);
+    }
+
+    /// Return the addresses of all of the stack slots, e.g. to free them.
+    pub fn stack_slot_addresses(&self) -> impl Iterator<Item = u64> + '_ {
+        self.stack_slots.values().copied()
+    }
+
+    /// Rename all of the SSA references in `old_names` to those in `new_names`, as when branching
+    /// to a block with the values in `old_names` as arguments. The other references keep their
+    /// values since the target block may use any value defined in a block dominating it.
     pub fn rename(&mut self, old_names: &[ValueRef], new_names: &[ValueRef]) {
         trace!("Renaming {:?} -> {:?}", old_names, new_names);
         assert_eq!(old_names.len(), new_names.len());
-        let mut registers = HashMap::with_capacity(self.registers.len());
-        for (on, nn) in old_names.iter().zip(new_names) {
-            let v = self.registers.get(on).unwrap().clone();
-            registers.insert(*nn, v);
-        }
-        self.registers = registers;
+        // Read all of the values before assigning any, since the same references may appear in
+        // both lists (e.g. when a loop passes its parameters back to itself in another order).
+        let values = self.get_all(old_names);
+        self.set_all(new_names, values);
     }
 }
 
@@ -111,6 +158,25 @@ mod tests {
         assert_eq!(frame.get(&a), &fortytwo);
     }
 
+    #[test]
+    fn rename() {
+        let func = empty_function();
+        let mut frame = Frame::new(&func);
+
+        let a = ValueRef::with_number(1).unwrap();
+        let b = ValueRef::with_number(2).unwrap();
+        let c = ValueRef::with_number(3).unwrap();
+        frame.set(a, DataValue::I32(1));
+        frame.set(b, DataValue::I32(2));
+        frame.set(c, DataValue::I32(3));
+
+        // Swap `a` and `b`, which must not affect `c`.
+        frame.rename(&[a, b], &[b, a]);
+        assert_eq!(frame.get(&a), &DataValue::I32(2));
+        assert_eq!(frame.get(&b), &DataValue::I32(1));
+        assert_eq!(frame.get(&c), &DataValue::I32(3));
+    }
+
     #[test]
     #[should_panic]
     fn no_existing_value() {
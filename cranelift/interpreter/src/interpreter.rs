@@ -3,18 +3,32 @@
 //! This module contains the logic for interpreting Cranelift instructions.
 
 use crate::environment::Environment;
-use crate::frame::Frame;
-use crate::interpreter::Trap::InvalidType;
-use cranelift_codegen::ir::condcodes::IntCC;
+use crate::frame::{Flags, Frame};
+use crate::memory::Memory;
+use crate::value::{self, mask, sign_extend};
+use cranelift_codegen::ir::condcodes::{FloatCC, IntCC};
 use cranelift_codegen::ir::{
-    Block, FuncRef, Function, Inst, InstructionData, InstructionData::*, Opcode, Opcode::*, Type,
-    Value as ValueRef, ValueList,
+    types, AbiParam, ArgumentPurpose, Block, FuncRef, Function, GlobalValue, GlobalValueData,
+    HeapStyle, Inst, InstructionData, Opcode, Opcode::*, TrapCode, Type, Value as ValueRef,
 };
 use cranelift_reader::{DataValue, DataValueCastFailure};
 use log::trace;
-use std::ops::{Add, Div, Mul, Sub};
+use std::cell::{Cell, RefCell};
+use std::convert::TryFrom;
 use thiserror::Error;
 
+/// The maximum number of nested calls; deeper calls trap with a stack overflow rather than
+/// overflowing the interpreter's own stack.
+const MAX_CALL_DEPTH: usize = 128;
+
+/// The alignment of the stack slots allocated in the interpreter's memory.
+const STACK_SLOT_ALIGNMENT: u64 = 16;
+
+/// The address `func_addr` gives to the first function of the [Environment]; the others follow
+/// it. These addresses are below the first address [Memory] allocates, so they never refer to
+/// data.
+const FUNCTION_ADDRESS_BASE: u64 = 0x1000;
+
 /// The valid control flow states.
 pub enum ControlFlow {
     Continue,
@@ -41,10 +55,14 @@ pub enum Trap {
     Unknown,
     #[error("invalid type for {1}: expected {0}")]
     InvalidType(String, ValueRef),
+    #[error("invalid value {0} for type {1}")]
+    InvalidValue(DataValue, Type),
     #[error("invalid cast")]
     InvalidCast(#[from] DataValueCastFailure),
     #[error("the instruction is not implemented (perhaps for the given types): {0}")]
     Unsupported(Inst),
+    #[error("values of type {0} are not supported")]
+    UnsupportedType(Type),
     #[error("reached an unreachable statement")]
     Unreachable,
     #[error("invalid control flow: {0}")]
@@ -53,6 +71,8 @@ pub enum Trap {
     InvalidFunctionReference(FuncRef),
     #[error("invalid function name: {0}")]
     InvalidFunctionName(String),
+    #[error("the interpreted code trapped: {0}")]
+    Trapped(TrapCode),
 }
 
 /// The Cranelift interpreter; it contains immutable elements such as the function environment and
@@ -60,22 +80,52 @@ pub enum Trap {
 #[derive(Default)]
 pub struct Interpreter {
     pub env: Environment,
+    /// The address space loads and stores access. Stack slots are allocated in it for the
+    /// duration of each call; other memory, such as heaps, can be allocated by the embedder before
+    /// calling a function.
+    pub memory: RefCell<Memory>,
+    /// The number of calls currently executing.
+    depth: Cell<usize>,
 }
 
-/// Helper for more concise matching.
-macro_rules! binary_op {
-    ( $op:path[$arg1:ident, $arg2:ident]; [ $( $data_value_ty:ident ),* ]; $inst:ident ) => {
-        match ($arg1, $arg2) {
-            $( (DataValue::$data_value_ty(a), DataValue::$data_value_ty(b)) => { Ok(DataValue::$data_value_ty($op(a, b))) } )*
-            _ => Err(Trap::Unsupported($inst)),
-        }
+/// Apply the floating point expression `$body`, in which `$a` holds the operands of a lane, to
+/// the lanes of the `$args` of type `$ty`. The expression is evaluated in the precision of the
+/// lanes.
+macro_rules! float_op {
+    ($ty:expr, $args:expr, |$a:ident| $body:expr) => {
+        lanewise($ty, $ty, $args, |lanes| match lanes[0] {
+            DataValue::F32(_) => {
+                let $a = lanes.iter().map(f32_value).collect::<Result<Vec<_>, _>>()?;
+                Ok(DataValue::F32($body))
+            }
+            DataValue::F64(_) => {
+                let $a = lanes.iter().map(f64_value).collect::<Result<Vec<_>, _>>()?;
+                Ok(DataValue::F64($body))
+            }
+            ref value => Err(Trap::InvalidValue(value.clone(), $ty)),
+        })
     };
 }
 
+/// What executing an instruction produces, besides its effects on memory.
+enum Outcome {
+    /// The values of the instruction's results.
+    Results(Vec<DataValue>),
+    /// A value and a flags value, e.g. the sum and carry of `iadd_ifcout`.
+    ResultAndFlags(DataValue, Flags),
+    /// The flags value which is the instruction's only result.
+    Flags(Flags),
+    /// Where execution continues, if not at the next instruction.
+    Control(ControlFlow),
+}
+
 impl Interpreter {
     /// Construct a new [Interpreter] using the given [Environment].
     pub fn new(env: Environment) -> Self {
-        Self { env }
+        Self {
+            env,
+            ..Self::default()
+        }
     }
 
     /// Call a function by name; this is a helpful proxy for [Interpreter::call_by_index].
@@ -103,9 +153,13 @@ impl Interpreter {
         }
     }
 
-    /// Interpret a call to a [Function] given its [DataValue] arguments.
+    /// Interpret a call to a [Function] given its [DataValue] arguments. The function's stack
+    /// slots are allocated in memory for the duration of the call.
     fn call(&self, function: &Function, arguments: &[DataValue]) -> Result<ControlFlow, Trap> {
         trace!("Call: {}({:?})", function.name, arguments);
+        if self.depth.get() >= MAX_CALL_DEPTH {
+            return Err(Trap::Trapped(TrapCode::StackOverflow));
+        }
         let first_block = function
             .layout
             .blocks()
@@ -114,7 +168,23 @@ impl Interpreter {
         let parameters = function.dfg.block_params(first_block);
         let mut frame = Frame::new(function);
         frame.set_all(parameters, arguments.to_vec());
-        self.block(&mut frame, first_block)
+        for (slot, data) in function.stack_slots.iter() {
+            let address = self
+                .memory
+                .borrow_mut()
+                .allocate(u64::from(data.size), STACK_SLOT_ALIGNMENT);
+            frame.set_stack_slot_address(slot, address);
+        }
+
+        self.depth.set(self.depth.get() + 1);
+        let result = self.block(&mut frame, first_block);
+        self.depth.set(self.depth.get() - 1);
+
+        let mut memory = self.memory.borrow_mut();
+        for address in frame.stack_slot_addresses() {
+            memory.free(address);
+        }
+        result
     }
 
     /// Interpret a [Block] in a [Function]. This drives the interpretation over sequences of
@@ -138,188 +208,1056 @@ impl Interpreter {
         Err(Trap::Unreachable)
     }
 
-    /// Interpret a single [instruction](Inst). This contains a `match`-based dispatch to the
-    /// implementations.
+    /// Interpret a single [instruction](Inst), assigning its results in the `frame`.
     fn inst(&self, frame: &mut Frame, inst: Inst) -> Result<ControlFlow, Trap> {
-        use ControlFlow::{Continue, ContinueAt};
         trace!("Inst: {}", &frame.function.dfg.display_inst(inst, None));
+        let results = frame.function.dfg.inst_results(inst);
+        match self.eval(frame, inst)? {
+            Outcome::Results(values) => {
+                frame.set_all(results, values);
+                Ok(ControlFlow::Continue)
+            }
+            Outcome::ResultAndFlags(value, flags) => {
+                frame.set(results[0], value);
+                frame.set_flags(results[1], flags);
+                Ok(ControlFlow::Continue)
+            }
+            Outcome::Flags(flags) => {
+                frame.set_flags(results[0], flags);
+                Ok(ControlFlow::Continue)
+            }
+            Outcome::Control(control_flow) => Ok(control_flow),
+        }
+    }
 
-        let data = &frame.function.dfg[inst];
-        match data {
-            Binary { opcode, args } => {
-                let arg1 = frame.get(&args[0]);
-                let arg2 = frame.get(&args[1]);
-                let result = match opcode {
-                    Iadd => binary_op!(Add::add[arg1, arg2]; [I8, I16, I32, I64]; inst),
-                    Isub => binary_op!(Sub::sub[arg1, arg2]; [I8, I16, I32, I64]; inst),
-                    Imul => binary_op!(Mul::mul[arg1, arg2]; [I8, I16, I32, I64]; inst),
-                    Fadd => binary_op!(Add::add[arg1, arg2]; [F32, F64]; inst),
-                    Fsub => binary_op!(Sub::sub[arg1, arg2]; [F32, F64]; inst),
-                    Fmul => binary_op!(Mul::mul[arg1, arg2]; [F32, F64]; inst),
-                    Fdiv => binary_op!(Div::div[arg1, arg2]; [F32, F64]; inst),
-                    _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
-                }?;
-                frame.set(first_result(frame.function, inst), result);
-                Ok(Continue)
+    /// Evaluate a single [instruction](Inst). This contains a `match`-based dispatch to the
+    /// implementations.
+    fn eval(&self, frame: &Frame, inst: Inst) -> Result<Outcome, Trap> {
+        use ControlFlow::{Continue, ContinueAt};
+
+        let function = frame.function;
+        let dfg = &function.dfg;
+        let data = &dfg[inst];
+        let opcode = data.opcode();
+        let args = dfg.inst_args(inst);
+        let arg = |n: usize| frame.get(&args[n]).clone();
+        let arg_type = |n: usize| dfg.value_type(args[n]);
+        let ty = dfg
+            .inst_results(inst)
+            .first()
+            .map_or(types::INVALID, |&result| dfg.value_type(result));
+        let unsupported = || Trap::Unsupported(inst);
+        let single =
+            |value: DataValue| -> Result<Outcome, Trap> { Ok(Outcome::Results(vec![value])) };
+
+        match opcode {
+            // Constants.
+            Iconst | F32const | F64const | Bconst => {
+                let bits = match *data {
+                    InstructionData::UnaryImm { imm, .. } => imm.bits() as u64,
+                    InstructionData::UnaryIeee32 { imm, .. } => u64::from(imm.bits()),
+                    InstructionData::UnaryIeee64 { imm, .. } => imm.bits(),
+                    InstructionData::UnaryBool { imm, .. } => imm as u64,
+                    _ => unreachable!(),
+                };
+                single(value::from_bits(bits, ty)?)
             }
+            Vconst => match *data {
+                InstructionData::UnaryConst {
+                    constant_handle, ..
+                } => {
+                    let bytes: Vec<u8> =
+                        dfg.constants.get(constant_handle).iter().copied().collect();
+                    single(value::from_bytes(&bytes, ty)?)
+                }
+                _ => unreachable!(),
+            },
 
-            BinaryImm64 { opcode, arg, imm } => {
-                let imm = DataValue::from_integer(*imm, type_of(*arg, frame.function))?;
-                let arg = frame.get(&arg);
+            // Integer arithmetic and bitwise operations.
+            Iadd | Isub | Imul | Umulhi | Smulhi | Udiv | Sdiv | Urem | Srem | Imin | Imax
+            | Umin | Umax | AvgRound | UaddSat | SaddSat | UsubSat | SsubSat | Band | Bor
+            | Bxor | BandNot | BorNot | BxorNot => {
+                single(int_lanewise(ty, ty, &[arg(0), arg(1)], |x| {
+                    int_binary(opcode, ty, x[0], x[1])
+                })?)
+            }
+            IaddImm | ImulImm | UdivImm | SdivImm | UremImm | SremImm | IrsubImm | BandImm
+            | BorImm | BxorImm => {
+                let a = value::to_bits(&arg(0), ty)?;
+                let imm = imm64(data) & mask(u32::from(ty.lane_bits()));
                 let result = match opcode {
-                    IaddImm => binary_op!(Add::add[arg, imm]; [I8, I16, I32, I64]; inst),
-                    IrsubImm => binary_op!(Sub::sub[imm, arg]; [I8, I16, I32, I64]; inst),
-                    ImulImm => binary_op!(Mul::mul[arg, imm]; [I8, I16, I32, I64]; inst),
-                    _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
+                    IaddImm => int_binary(Iadd, ty, a, imm),
+                    ImulImm => int_binary(Imul, ty, a, imm),
+                    UdivImm => int_binary(Udiv, ty, a, imm),
+                    SdivImm => int_binary(Sdiv, ty, a, imm),
+                    UremImm => int_binary(Urem, ty, a, imm),
+                    SremImm => int_binary(Srem, ty, a, imm),
+                    IrsubImm => int_binary(Isub, ty, imm, a),
+                    BandImm => int_binary(Band, ty, a, imm),
+                    BorImm => int_binary(Bor, ty, a, imm),
+                    _ => int_binary(Bxor, ty, a, imm),
                 }?;
-                frame.set(first_result(frame.function, inst), result);
-                Ok(Continue)
-            }
-
-            Branch {
-                opcode,
-                args,
-                destination,
-            } => match opcode {
-                Brnz => {
-                    let mut args = value_refs(frame.function, args);
-                    let first = args.remove(0);
-                    match frame.get(&first) {
-                        DataValue::B(false)
-                        | DataValue::I8(0)
-                        | DataValue::I16(0)
-                        | DataValue::I32(0)
-                        | DataValue::I64(0) => Ok(Continue),
-                        DataValue::B(true)
-                        | DataValue::I8(_)
-                        | DataValue::I16(_)
-                        | DataValue::I32(_)
-                        | DataValue::I64(_) => Ok(ContinueAt(*destination, args)),
-                        _ => Err(Trap::InvalidType("boolean or integer".to_string(), args[0])),
+                single(value::from_bits(result, ty)?)
+            }
+            Ishl | Ushr | Sshr | Rotl | Rotr | IshlImm | UshrImm | SshrImm | RotlImm | RotrImm => {
+                // The shift amount is a scalar, even when shifting the lanes of a vector.
+                let amount = match *data {
+                    InstructionData::BinaryImm64 { imm, .. } => imm.bits() as u64,
+                    _ => value::to_bits(&arg(1), arg_type(1))?,
+                };
+                let opcode = match opcode {
+                    IshlImm => Ishl,
+                    UshrImm => Ushr,
+                    SshrImm => Sshr,
+                    RotlImm => Rotl,
+                    RotrImm => Rotr,
+                    opcode => opcode,
+                };
+                single(int_lanewise(ty, ty, &[arg(0)], |x| {
+                    int_binary(opcode, ty, x[0], amount)
+                })?)
+            }
+            Ineg | Bnot | Bitrev | Clz | Cls | Ctz | Popcnt => {
+                single(int_lanewise(ty, ty, &[arg(0)], |x| {
+                    Ok(int_unary(opcode, ty, x[0]))
+                })?)
+            }
+            IaddCin | IaddIfcin | IaddCout | IaddIfcout | IaddCarry | IaddIfcarry | IsubBin
+            | IsubIfbin | IsubBout | IsubIfbout | IsubBorrow | IsubIfborrow => {
+                let carry_in = match opcode {
+                    IaddCin | IaddCarry | IsubBin | IsubBorrow => value::is_true(&arg(2))?,
+                    IaddIfcin | IaddIfcarry | IsubIfbin | IsubIfborrow => {
+                        match frame.get_flags(&args[2]) {
+                            Flags::Carry { carry, .. } => carry,
+                            _ => return Err(unsupported()),
+                        }
                     }
+                    _ => false,
+                };
+                let subtract = matches!(
+                    opcode,
+                    IsubBin | IsubIfbin | IsubBout | IsubIfbout | IsubBorrow | IsubIfborrow
+                );
+                let (a, b) = (value::to_bits(&arg(0), ty)?, value::to_bits(&arg(1), ty)?);
+                let (result, carry, overflow) = add_with_carry(ty, a, b, carry_in, subtract);
+                let result = value::from_bits(result, ty)?;
+                match opcode {
+                    IaddCin | IaddIfcin | IsubBin | IsubIfbin => single(result),
+                    IaddIfcout | IaddIfcarry | IsubIfbout | IsubIfborrow => Ok(
+                        Outcome::ResultAndFlags(result, Flags::Carry { carry, overflow }),
+                    ),
+                    _ => Ok(Outcome::Results(vec![result, DataValue::B(carry)])),
                 }
-                _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
-            },
-            InstructionData::Call { args, func_ref, .. } => {
-                // Find the function to call.
-                let func_name = function_name_of_func_ref(*func_ref, frame.function);
-
-                // Call function.
-                let args = frame.get_all(args.as_slice(&frame.function.dfg.value_lists));
-                let result = self.call_by_name(&func_name, &args)?;
-
-                // Save results.
-                if let ControlFlow::Return(returned_values) = result {
-                    let ssa_values = frame.function.dfg.inst_results(inst);
-                    assert_eq!(
-                        ssa_values.len(),
-                        returned_values.len(),
-                        "expected result length ({}) to match SSA values length ({}): {}",
-                        returned_values.len(),
-                        ssa_values.len(),
-                        frame.function.dfg.display_inst(inst, None)
-                    );
-                    frame.set_all(ssa_values, returned_values);
-                    Ok(Continue)
-                } else {
-                    Err(Trap::InvalidControlFlow(format!(
-                        "did not return from: {}",
-                        frame.function.dfg.display_inst(inst, None)
+            }
+
+            // Comparisons and selects.
+            Icmp | IcmpImm => {
+                let in_ty = arg_type(0);
+                let b = match opcode {
+                    IcmpImm => value::from_bits(imm64(data), in_ty)?,
+                    _ => arg(1),
+                };
+                let cond = int_cond_code(data);
+                single(icmp(cond, in_ty, ty, &arg(0), &b)?)
+            }
+            Ifcmp | IfcmpImm => {
+                let in_ty = arg_type(0);
+                let b = match opcode {
+                    IfcmpImm => imm64(data) & mask(u32::from(in_ty.bits())),
+                    _ => value::to_bits(&arg(1), in_ty)?,
+                };
+                Ok(Outcome::Flags(Flags::Compare {
+                    a: value::to_bits(&arg(0), in_ty)?,
+                    b,
+                    ty: in_ty,
+                }))
+            }
+            Trueif => {
+                let flags = frame.get_flags(&args[0]);
+                single(DataValue::B(
+                    int_flags_cond(int_cond_code(data), flags).ok_or_else(unsupported)?,
+                ))
+            }
+            Fcmp => {
+                let cond = float_cond_code(data);
+                single(lanewise(arg_type(0), ty, &[arg(0), arg(1)], |x| {
+                    Ok(DataValue::B(float_cond(
+                        cond,
+                        float_value(&x[0])?,
+                        float_value(&x[1])?,
                     )))
+                })?)
+            }
+            Ffcmp => Ok(Outcome::Flags(Flags::Float {
+                a: float_value(&arg(0))?,
+                b: float_value(&arg(1))?,
+            })),
+            Trueff => {
+                let flags = frame.get_flags(&args[0]);
+                single(DataValue::B(
+                    float_flags_cond(float_cond_code(data), flags).ok_or_else(unsupported)?,
+                ))
+            }
+            Select | SelectSpectreGuard => single(if value::is_true(&arg(0))? {
+                arg(1)
+            } else {
+                arg(2)
+            }),
+            Selectif => {
+                let flags = frame.get_flags(&args[0]);
+                let cond = int_flags_cond(int_cond_code(data), flags).ok_or_else(unsupported)?;
+                single(if cond { arg(1) } else { arg(2) })
+            }
+            Bitselect => single(int_lanewise(ty, ty, &[arg(0), arg(1), arg(2)], |x| {
+                Ok((x[1] & x[0]) | (x[2] & !x[0]))
+            })?),
+
+            // Vector operations.
+            Splat => single(value::from_lanes(
+                &vec![arg(0); ty.lane_count() as usize],
+                ty,
+            )?),
+            Extractlane => match *data {
+                InstructionData::BinaryImm8 { imm, .. } => {
+                    let lanes = value::lanes(&arg(0), arg_type(0))?;
+                    single(lanes[usize::from(imm)].clone())
+                }
+                _ => unreachable!(),
+            },
+            Insertlane => match *data {
+                InstructionData::TernaryImm8 { imm, .. } => {
+                    let mut lanes = value::lanes(&arg(0), ty)?;
+                    lanes[usize::from(imm)] = arg(1);
+                    single(value::from_lanes(&lanes, ty)?)
                 }
+                _ => unreachable!(),
+            },
+            Swizzle => {
+                let bytes = value::vector_bytes(&arg(0), arg_type(0))?;
+                let indices = value::vector_bytes(&arg(1), arg_type(1))?;
+                let mut result = [0; 16];
+                for (byte, &index) in result.iter_mut().zip(indices.iter()) {
+                    *byte = bytes.get(usize::from(index)).copied().unwrap_or(0);
+                }
+                single(DataValue::V128(result))
             }
-            InstructionData::Jump {
-                opcode,
-                destination,
-                args,
-            } => match opcode {
-                Opcode::Fallthrough => {
-                    Ok(ContinueAt(*destination, value_refs(frame.function, args)))
+            Shuffle => match *data {
+                InstructionData::Shuffle { mask, .. } => {
+                    let mut bytes = value::vector_bytes(&arg(0), ty)?.to_vec();
+                    bytes.extend_from_slice(&value::vector_bytes(&arg(1), ty)?);
+                    let mut result = [0; 16];
+                    for (byte, &index) in result.iter_mut().zip(dfg.immediates[mask].iter()) {
+                        *byte = bytes.get(usize::from(index)).copied().unwrap_or(0);
+                    }
+                    single(DataValue::V128(result))
                 }
-                Opcode::Jump => Ok(ContinueAt(*destination, value_refs(frame.function, args))),
-                _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
+                _ => unreachable!(),
             },
-            IntCompareImm {
-                opcode,
-                arg,
-                cond,
-                imm,
-            } => match opcode {
-                IcmpImm => {
-                    let arg_value = match *frame.get(arg) {
-                        DataValue::I8(i) => Ok(i as i64),
-                        DataValue::I16(i) => Ok(i as i64),
-                        DataValue::I32(i) => Ok(i as i64),
-                        DataValue::I64(i) => Ok(i),
-                        _ => Err(InvalidType("integer".to_string(), *arg)),
-                    }?;
-                    let imm_value = (*imm).into();
-                    let result = match cond {
-                        IntCC::UnsignedLessThanOrEqual => arg_value <= imm_value,
-                        IntCC::Equal => arg_value == imm_value,
-                        _ => unimplemented!(
-                            "interpreter does not support condition code yet: {}",
-                            cond
-                        ),
-                    };
-                    let res = first_result(frame.function, inst);
-                    frame.set(res, DataValue::B(result));
-                    Ok(Continue)
+            VanyTrue | VallTrue => {
+                let lanes = value::lanes(&arg(0), arg_type(0))?;
+                let lanes = lanes
+                    .iter()
+                    .map(value::is_true)
+                    .collect::<Result<Vec<_>, _>>()?;
+                single(DataValue::B(match opcode {
+                    VanyTrue => lanes.iter().any(|&lane| lane),
+                    _ => lanes.iter().all(|&lane| lane),
+                }))
+            }
+            Vselect => {
+                let conditions = value::lanes(&arg(0), arg_type(0))?;
+                let (xs, ys) = (value::lanes(&arg(1), ty)?, value::lanes(&arg(2), ty)?);
+                let mut lanes = Vec::with_capacity(xs.len());
+                for ((c, x), y) in conditions.iter().zip(xs).zip(ys) {
+                    lanes.push(if value::is_true(c)? { x } else { y });
                 }
-                _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
+                single(value::from_lanes(&lanes, ty)?)
+            }
+            ScalarToVector => single(value::from_lanes(&[arg(0)], ty)?),
+            Vsplit => {
+                let bytes = value::vector_bytes(&arg(0), arg_type(0))?;
+                let half = ty.bytes() as usize;
+                let (mut low, mut high) = ([0; 16], [0; 16]);
+                low[..half].copy_from_slice(&bytes[..half]);
+                high[..half].copy_from_slice(&bytes[half..2 * half]);
+                Ok(Outcome::Results(vec![
+                    DataValue::V128(low),
+                    DataValue::V128(high),
+                ]))
+            }
+            Vconcat => {
+                let half = arg_type(0).bytes() as usize;
+                let mut bytes = value::vector_bytes(&arg(0), arg_type(0))?;
+                bytes[half..2 * half]
+                    .copy_from_slice(&value::vector_bytes(&arg(1), arg_type(1))?[..half]);
+                single(DataValue::V128(bytes))
+            }
+
+            // Conversions.
+            Copy | Spill | Fill | FillNop | CopyNop => single(arg(0)),
+            Bitcast | RawBitcast => single(value::from_bytes(
+                &value::to_bytes(&arg(0), arg_type(0))?,
+                ty,
+            )?),
+            Uextend | Ireduce | Bextend | Breduce => {
+                single(value::from_bits(value::to_bits(&arg(0), arg_type(0))?, ty)?)
+            }
+            Sextend => {
+                let bits = value::to_signed(&arg(0), arg_type(0))?;
+                single(value::from_bits(bits as u64, ty)?)
+            }
+            Bint | Bmask => {
+                let lane_type = ty.lane_type();
+                let ones = match opcode {
+                    Bint => 1,
+                    _ => mask(u32::from(lane_type.bits())),
+                };
+                single(lanewise(arg_type(0), ty, &[arg(0)], |x| {
+                    let bits = if value::is_true(&x[0])? { ones } else { 0 };
+                    value::from_bits(bits, lane_type)
+                })?)
+            }
+            Isplit => {
+                let bits = value::to_bits(&arg(0), arg_type(0))?;
+                Ok(Outcome::Results(vec![
+                    value::from_bits(bits, ty)?,
+                    value::from_bits(bits >> ty.bits(), ty)?,
+                ]))
+            }
+            Iconcat => {
+                let low = value::to_bits(&arg(0), arg_type(0))?;
+                let high = value::to_bits(&arg(1), arg_type(1))?;
+                single(value::from_bits(low | high << arg_type(0).bits(), ty)?)
+            }
+            Fpromote => single(DataValue::F64(f64::from(f32_value(&arg(0))?))),
+            Fdemote => single(DataValue::F32(f64_value(&arg(0))? as f32)),
+            FcvtToUint | FcvtToUintSat | FcvtToSint | FcvtToSintSat => {
+                let lane_type = ty.lane_type();
+                let signed = matches!(opcode, FcvtToSint | FcvtToSintSat);
+                let saturate = matches!(opcode, FcvtToUintSat | FcvtToSintSat);
+                single(lanewise(arg_type(0), ty, &[arg(0)], |x| {
+                    let bits = float_to_int(float_value(&x[0])?, lane_type, signed, saturate)?;
+                    value::from_bits(bits, lane_type)
+                })?)
+            }
+            FcvtFromUint | FcvtFromSint => {
+                let in_lane_type = arg_type(0).lane_type();
+                let lane_type = ty.lane_type();
+                let signed = opcode == FcvtFromSint;
+                single(lanewise(arg_type(0), ty, &[arg(0)], |x| {
+                    let bits = value::to_bits(&x[0], in_lane_type)?;
+                    let signed_bits = sign_extend(bits, u32::from(in_lane_type.bits()));
+                    match lane_type {
+                        types::F32 if signed => Ok(DataValue::F32(signed_bits as f32)),
+                        types::F32 => Ok(DataValue::F32(bits as f32)),
+                        types::F64 if signed => Ok(DataValue::F64(signed_bits as f64)),
+                        types::F64 => Ok(DataValue::F64(bits as f64)),
+                        _ => Err(Trap::UnsupportedType(lane_type)),
+                    }
+                })?)
+            }
+
+            // Floating point arithmetic.
+            Fadd | Fsub | Fmul | Fdiv | Sqrt | Fma | Fneg | Fabs | Fcopysign | Fmin | Fmax
+            | Ceil | Floor | Trunc | Nearest => single(float_op!(ty, &frame.get_all(args), |a| {
+                match opcode {
+                    Fadd => a[0] + a[1],
+                    Fsub => a[0] - a[1],
+                    Fmul => a[0] * a[1],
+                    Fdiv => a[0] / a[1],
+                    Sqrt => a[0].sqrt(),
+                    Fma => a[0].mul_add(a[1], a[2]),
+                    Fneg => -a[0],
+                    Fabs => a[0].abs(),
+                    Fcopysign => a[0].copysign(a[1]),
+                    // Unlike Rust's `min` and `max`, NaNs propagate and -0.0 is less than 0.0.
+                    Fmin | Fmax if a[0].is_nan() || a[1].is_nan() => a[0] + a[1],
+                    Fmin if a[0] == a[1] && a[0].is_sign_negative() => a[0],
+                    Fmax if a[0] == a[1] && a[0].is_sign_positive() => a[0],
+                    Fmin | Fmax if a[0] == a[1] => a[1],
+                    Fmin => a[0].min(a[1]),
+                    Fmax => a[0].max(a[1]),
+                    Ceil => a[0].ceil(),
+                    Floor => a[0].floor(),
+                    Trunc => a[0].trunc(),
+                    // Round half to even, where Rust's `round` rounds half away from zero.
+                    _ => {
+                        let rounded = a[0].round();
+                        if (rounded - a[0]).abs() == 0.5 {
+                            2.0 * (a[0] / 2.0).round()
+                        } else {
+                            rounded
+                        }
+                    }
+                }
+            })?),
+
+            // Memory.
+            Load | LoadComplex | Uload8 | Uload8Complex | Sload8 | Sload8Complex | Uload16
+            | Uload16Complex | Sload16 | Sload16Complex | Uload32 | Uload32Complex | Sload32
+            | Sload32Complex => {
+                let address = self.address(frame, args, data)?;
+                let (size, signed) = match opcode {
+                    Load | LoadComplex => return single(self.load(address, ty)?),
+                    Uload8 | Uload8Complex => (1, false),
+                    Sload8 | Sload8Complex => (1, true),
+                    Uload16 | Uload16Complex => (2, false),
+                    Sload16 | Sload16Complex => (2, true),
+                    Uload32 | Uload32Complex => (4, false),
+                    _ => (4, true),
+                };
+                let mut bits = value::read_le(&self.read(address, size)?);
+                if signed {
+                    bits = sign_extend(bits, 8 * size as u32) as u64;
+                }
+                single(value::from_bits(bits, ty)?)
+            }
+            Uload8x8 | Uload8x8Complex | Sload8x8 | Sload8x8Complex | Uload16x4
+            | Uload16x4Complex | Sload16x4 | Sload16x4Complex | Uload32x2 | Uload32x2Complex
+            | Sload32x2 | Sload32x2Complex => {
+                let address = self.address(frame, args, data)?;
+                let signed = matches!(
+                    opcode,
+                    Sload8x8
+                        | Sload8x8Complex
+                        | Sload16x4
+                        | Sload16x4Complex
+                        | Sload32x2
+                        | Sload32x2Complex
+                );
+                // Each lane is loaded from half as many bytes and extended.
+                let bytes = self.read(address, 8)?;
+                let lane_type = ty.lane_type();
+                let lane_bytes = lane_type.bytes() as usize / 2;
+                let lanes = bytes
+                    .chunks(lane_bytes)
+                    .map(|chunk| {
+                        let bits = value::read_le(chunk);
+                        let bits = if signed {
+                            sign_extend(bits, 8 * lane_bytes as u32) as u64
+                        } else {
+                            bits
+                        };
+                        value::from_bits(bits, lane_type)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                single(value::from_lanes(&lanes, ty)?)
+            }
+            Store | StoreComplex | Istore8 | Istore8Complex | Istore16 | Istore16Complex
+            | Istore32 | Istore32Complex => {
+                let address = self.address(frame, &args[1..], data)?;
+                let mut bytes = value::to_bytes(&arg(0), arg_type(0))?;
+                match opcode {
+                    Istore8 | Istore8Complex => bytes.truncate(1),
+                    Istore16 | Istore16Complex => bytes.truncate(2),
+                    Istore32 | Istore32Complex => bytes.truncate(4),
+                    _ => {}
+                }
+                self.write(address, &bytes)?;
+                Ok(Outcome::Results(vec![]))
+            }
+            StackLoad | StackAddr | StackStore => {
+                let (slot, offset) = match *data {
+                    InstructionData::StackLoad {
+                        stack_slot, offset, ..
+                    }
+                    | InstructionData::StackStore {
+                        stack_slot, offset, ..
+                    } => (stack_slot, offset),
+                    _ => unreachable!(),
+                };
+                let offset: i64 = offset.into();
+                let address = frame.stack_slot_address(slot).wrapping_add(offset as u64);
+                match opcode {
+                    StackLoad => single(self.load(address, ty)?),
+                    StackAddr => single(value::from_bits(address, ty)?),
+                    _ => {
+                        self.write(address, &value::to_bytes(&arg(0), arg_type(0))?)?;
+                        Ok(Outcome::Results(vec![]))
+                    }
+                }
+            }
+            Opcode::GlobalValue => match *data {
+                InstructionData::UnaryGlobalValue { global_value, .. } => single(value::from_bits(
+                    self.global_value(frame, global_value, inst)?,
+                    ty,
+                )?),
+                _ => unreachable!(),
             },
-            MultiAry { opcode, args } => match opcode {
-                Return => {
-                    let rs: Vec<DataValue> = args
-                        .as_slice(&frame.function.dfg.value_lists)
-                        .iter()
-                        .map(|r| frame.get(r).clone())
-                        .collect();
-                    Ok(ControlFlow::Return(rs))
+            HeapAddr => match *data {
+                InstructionData::HeapAddr { heap, imm, .. } => {
+                    let heap = &function.heaps[heap];
+                    let index = value::to_bits(&arg(0), arg_type(0))?;
+                    let size: u32 = imm.into();
+                    let bound = match heap.style {
+                        HeapStyle::Dynamic { bound_gv } => {
+                            self.global_value(frame, bound_gv, inst)?
+                        }
+                        HeapStyle::Static { bound } => bound.into(),
+                    };
+                    match index.checked_add(u64::from(size)) {
+                        Some(end) if end <= bound => {}
+                        _ => return Err(Trap::Trapped(TrapCode::HeapOutOfBounds)),
+                    }
+                    let base = self.global_value(frame, heap.base, inst)?;
+                    single(value::from_bits(base.wrapping_add(index), ty)?)
                 }
-                _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
+                _ => unreachable!(),
             },
-            NullAry { opcode } => match opcode {
-                Nop => Ok(Continue),
-                _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
+            TableAddr => match *data {
+                InstructionData::TableAddr { table, offset, .. } => {
+                    let table = &function.tables[table];
+                    let index = value::to_bits(&arg(0), arg_type(0))?;
+                    if index >= self.global_value(frame, table.bound_gv, inst)? {
+                        return Err(Trap::Trapped(TrapCode::TableOutOfBounds));
+                    }
+                    let base = self.global_value(frame, table.base_gv, inst)?;
+                    let element_size: u64 = table.element_size.into();
+                    let offset: i64 = offset.into();
+                    let address = base
+                        .wrapping_add(index.wrapping_mul(element_size))
+                        .wrapping_add(offset as u64);
+                    single(value::from_bits(address, ty)?)
+                }
+                _ => unreachable!(),
             },
-            UnaryImm { opcode, imm } => match opcode {
-                Iconst => {
-                    let res = first_result(frame.function, inst);
-                    let imm_value = DataValue::from_integer(*imm, type_of(res, frame.function))?;
-                    frame.set(res, imm_value);
-                    Ok(Continue)
+
+            // Calls.
+            FuncAddr => match *data {
+                InstructionData::FuncAddr { func_ref, .. } => {
+                    let func_name = function_name_of_func_ref(func_ref, function);
+                    let func_ref = self
+                        .env
+                        .index_of(&func_name)
+                        .ok_or(Trap::InvalidFunctionName(func_name))?;
+                    let address = FUNCTION_ADDRESS_BASE + u64::from(func_ref.as_u32());
+                    single(value::from_bits(address, ty)?)
                 }
-                _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
+                _ => unreachable!(),
             },
-            UnaryBool { opcode, imm } => match opcode {
-                Bconst => {
-                    let res = first_result(frame.function, inst);
-                    frame.set(res, DataValue::B(*imm));
-                    Ok(Continue)
+            Call | CallIndirect => {
+                let (callee, arguments) = match *data {
+                    InstructionData::Call { func_ref, .. } => {
+                        let func_name = function_name_of_func_ref(func_ref, function);
+                        let callee = self
+                            .env
+                            .get_by_name(&func_name)
+                            .ok_or(Trap::InvalidFunctionName(func_name))?;
+                        (callee, args)
+                    }
+                    InstructionData::CallIndirect { sig_ref, .. } => {
+                        let callee = self.function_at(value::to_bits(&arg(0), arg_type(0))?)?;
+                        let signature = &dfg.signatures[sig_ref];
+                        if value_types(&callee.signature.params) != value_types(&signature.params)
+                            || value_types(&callee.signature.returns)
+                                != value_types(&signature.returns)
+                        {
+                            return Err(Trap::Trapped(TrapCode::BadSignature));
+                        }
+                        (callee, &args[1..])
+                    }
+                    _ => unreachable!(),
+                };
+
+                let returned_values = self
+                    .call(callee, &frame.get_all(arguments))?
+                    .unwrap_return();
+                let ssa_values = dfg.inst_results(inst);
+                assert_eq!(
+                    ssa_values.len(),
+                    returned_values.len(),
+                    "expected result length ({}) to match SSA values length ({}): {}",
+                    returned_values.len(),
+                    ssa_values.len(),
+                    dfg.display_inst(inst, None)
+                );
+                Ok(Outcome::Results(returned_values))
+            }
+
+            // Control flow.
+            Jump | Fallthrough => Ok(Outcome::Control(ContinueAt(
+                data.branch_destination().unwrap(),
+                args.to_vec(),
+            ))),
+            Brz | Brnz | BrIcmp | Brif | Brff => {
+                let taken = match opcode {
+                    Brz => !value::is_true(&arg(0))?,
+                    Brnz => value::is_true(&arg(0))?,
+                    BrIcmp => value::is_true(&icmp(
+                        int_cond_code(data),
+                        arg_type(0),
+                        types::B1,
+                        &arg(0),
+                        &arg(1),
+                    )?)?,
+                    Brif => int_flags_cond(int_cond_code(data), frame.get_flags(&args[0]))
+                        .ok_or_else(unsupported)?,
+                    _ => float_flags_cond(float_cond_code(data), frame.get_flags(&args[0]))
+                        .ok_or_else(unsupported)?,
+                };
+                Ok(Outcome::Control(if taken {
+                    ContinueAt(
+                        data.branch_destination().unwrap(),
+                        dfg.inst_variable_args(inst).to_vec(),
+                    )
+                } else {
+                    Continue
+                }))
+            }
+            BrTable => match *data {
+                InstructionData::BranchTable {
+                    destination, table, ..
+                } => {
+                    let index = value::to_bits(&arg(0), arg_type(0))?;
+                    let block = usize::try_from(index)
+                        .ok()
+                        .and_then(|index| function.jump_tables[table].as_slice().get(index))
+                        .copied()
+                        .unwrap_or(destination);
+                    Ok(Outcome::Control(ContinueAt(block, vec![])))
                 }
-                _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
+                _ => unreachable!(),
             },
+            Return | FallthroughReturn => {
+                Ok(Outcome::Control(ControlFlow::Return(frame.get_all(args))))
+            }
+            Nop | Debugtrap | Safepoint => Ok(Outcome::Results(vec![])),
+
+            // Traps.
+            Opcode::Trap | ResumableTrap => Err(Trap::Trapped(data.trap_code().unwrap())),
+            Trapz | Trapnz | ResumableTrapnz | Trapif | Trapff => {
+                let trapped = match opcode {
+                    Trapz => !value::is_true(&arg(0))?,
+                    Trapnz | ResumableTrapnz => value::is_true(&arg(0))?,
+                    Trapif => int_flags_cond(int_cond_code(data), frame.get_flags(&args[0]))
+                        .ok_or_else(unsupported)?,
+                    _ => float_flags_cond(float_cond_code(data), frame.get_flags(&args[0]))
+                        .ok_or_else(unsupported)?,
+                };
+                if trapped {
+                    Err(Trap::Trapped(data.trap_code().unwrap()))
+                } else {
+                    Ok(Outcome::Results(vec![]))
+                }
+            }
+
+            _ => Err(unsupported()),
+        }
+    }
+
+    /// Compute the address accessed by a load or store from its address operands, which are
+    /// added together, and its offset.
+    fn address(
+        &self,
+        frame: &Frame,
+        operands: &[ValueRef],
+        data: &InstructionData,
+    ) -> Result<u64, Trap> {
+        let offset: i64 = match *data {
+            InstructionData::Load { offset, .. }
+            | InstructionData::LoadComplex { offset, .. }
+            | InstructionData::Store { offset, .. }
+            | InstructionData::StoreComplex { offset, .. } => offset.into(),
+            _ => unreachable!(),
+        };
+        let mut address = offset as u64;
+        for operand in operands {
+            let ty = frame.function.dfg.value_type(*operand);
+            address = address.wrapping_add(value::to_bits(frame.get(operand), ty)?);
+        }
+        Ok(address)
+    }
+
+    /// Read `size` bytes of memory, trapping if any of them isn't allocated.
+    fn read(&self, address: u64, size: u64) -> Result<Vec<u8>, Trap> {
+        match self.memory.borrow().read(address, size) {
+            Some(bytes) => Ok(bytes.to_vec()),
+            None => Err(Trap::Trapped(TrapCode::HeapOutOfBounds)),
+        }
+    }
+
+    /// Write `bytes` to memory, trapping if any of them isn't allocated.
+    fn write(&self, address: u64, bytes: &[u8]) -> Result<(), Trap> {
+        if self.memory.borrow_mut().write(address, bytes) {
+            Ok(())
+        } else {
+            Err(Trap::Trapped(TrapCode::HeapOutOfBounds))
+        }
+    }
+
+    /// Load a value of type `ty` from memory.
+    fn load(&self, address: u64, ty: Type) -> Result<DataValue, Trap> {
+        value::from_bytes(&self.read(address, u64::from(ty.bytes()))?, ty)
+    }
 
-            _ => unimplemented!("interpreter does not support instruction yet: {:?}", data),
+    /// Compute the bits of a global value.
+    fn global_value(&self, frame: &Frame, gv: GlobalValue, inst: Inst) -> Result<u64, Trap> {
+        let function = frame.function;
+        match function.global_values[gv] {
+            GlobalValueData::VMContext => {
+                let vmctx = function
+                    .special_param(ArgumentPurpose::VMContext)
+                    .ok_or(Trap::Unsupported(inst))?;
+                value::to_bits(frame.get(&vmctx), function.dfg.value_type(vmctx))
+            }
+            GlobalValueData::Load {
+                base,
+                offset,
+                global_type,
+                ..
+            } => {
+                let offset: i64 = offset.into();
+                let address = self
+                    .global_value(frame, base, inst)?
+                    .wrapping_add(offset as u64);
+                let bytes = self.read(address, u64::from(global_type.bytes()))?;
+                Ok(value::read_le(&bytes))
+            }
+            GlobalValueData::IAddImm {
+                base,
+                offset,
+                global_type,
+            } => {
+                let base = self.global_value(frame, base, inst)?;
+                Ok(base.wrapping_add(offset.bits() as u64) & mask(u32::from(global_type.bits())))
+            }
+            GlobalValueData::Symbol { .. } => Err(Trap::Unsupported(inst)),
         }
     }
+
+    /// Find the function whose address `func_addr` gave as `address`.
+    fn function_at(&self, address: u64) -> Result<&Function, Trap> {
+        address
+            .checked_sub(FUNCTION_ADDRESS_BASE)
+            .and_then(|index| u32::try_from(index).ok())
+            .and_then(FuncRef::with_number)
+            .and_then(|func_ref| self.env.get_by_func_ref(func_ref))
+            .ok_or(Trap::Trapped(TrapCode::IndirectCallToNull))
+    }
 }
 
-/// Return the first result of an instruction.
-///
-/// This helper cushions the interpreter from changes to the [Function] API.
-#[inline]
-fn first_result(function: &Function, inst: Inst) -> ValueRef {
-    function.dfg.first_result(inst)
+/// Apply `op` to each lane of the vector `args` of type `in_ty`, producing a vector of type
+/// `out_ty`. Scalar `args` are passed to `op` as they are.
+fn lanewise<F>(in_ty: Type, out_ty: Type, args: &[DataValue], op: F) -> Result<DataValue, Trap>
+where
+    F: Fn(&[DataValue]) -> Result<DataValue, Trap>,
+{
+    if !in_ty.is_vector() {
+        return op(args);
+    }
+    let lanes = args
+        .iter()
+        .map(|arg| value::lanes(arg, in_ty))
+        .collect::<Result<Vec<_>, _>>()?;
+    let results = (0..in_ty.lane_count() as usize)
+        .map(|lane| {
+            let lane_args: Vec<DataValue> = lanes.iter().map(|l| l[lane].clone()).collect();
+            op(&lane_args)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    value::from_lanes(&results, out_ty)
 }
 
-/// Return a list of IR values as a vector.
-///
-/// This helper cushions the interpreter from changes to the [Function] API.
-#[inline]
-fn value_refs(function: &Function, args: &ValueList) -> Vec<ValueRef> {
-    args.as_slice(&function.dfg.value_lists).to_vec()
+/// Like [lanewise], but `op` operates on the bits of the integer, boolean or float lanes.
+fn int_lanewise<F>(in_ty: Type, out_ty: Type, args: &[DataValue], op: F) -> Result<DataValue, Trap>
+where
+    F: Fn(&[u64]) -> Result<u64, Trap>,
+{
+    let (in_lane_type, out_lane_type) = (in_ty.lane_type(), out_ty.lane_type());
+    lanewise(in_ty, out_ty, args, |lanes| {
+        let bits = lanes
+            .iter()
+            .map(|lane| value::to_bits(lane, in_lane_type))
+            .collect::<Result<Vec<_>, _>>()?;
+        value::from_bits(op(&bits)?, out_lane_type)
+    })
+}
+
+/// Compute a binary integer operation on the bits of two lanes of type `ty`.
+fn int_binary(opcode: Opcode, ty: Type, a: u64, b: u64) -> Result<u64, Trap> {
+    let bits = u32::from(ty.lane_bits());
+    let (sa, sb) = (sign_extend(a, bits), sign_extend(b, bits));
+    let (min, max) = signed_range(bits);
+    let clamp = |x: i128| x.max(min).min(max) as u64;
+    let result = match opcode {
+        Iadd => a.wrapping_add(b),
+        Isub => a.wrapping_sub(b),
+        Imul => a.wrapping_mul(b),
+        Umulhi => ((u128::from(a) * u128::from(b)) >> bits) as u64,
+        Smulhi => ((i128::from(sa) * i128::from(sb)) >> bits) as u64,
+        Udiv | Urem if b == 0 => return Err(Trap::Trapped(TrapCode::IntegerDivisionByZero)),
+        Udiv => a / b,
+        Urem => a % b,
+        Sdiv | Srem if sb == 0 => return Err(Trap::Trapped(TrapCode::IntegerDivisionByZero)),
+        Sdiv if i128::from(sa) == min && sb == -1 => {
+            return Err(Trap::Trapped(TrapCode::IntegerOverflow))
+        }
+        Sdiv => sa.wrapping_div(sb) as u64,
+        Srem => sa.wrapping_rem(sb) as u64,
+        Imin => sa.min(sb) as u64,
+        Imax => sa.max(sb) as u64,
+        Umin => a.min(b),
+        Umax => a.max(b),
+        AvgRound => ((u128::from(a) + u128::from(b) + 1) >> 1) as u64,
+        UaddSat => a.saturating_add(b).min(mask(bits)),
+        SaddSat => clamp(i128::from(sa) + i128::from(sb)),
+        UsubSat => a.saturating_sub(b),
+        SsubSat => clamp(i128::from(sa) - i128::from(sb)),
+        Band => a & b,
+        Bor => a | b,
+        Bxor => a ^ b,
+        BandNot => a & !b,
+        BorNot => a | !b,
+        BxorNot => a ^ !b,
+        Ishl => a << (b % u64::from(bits)),
+        Ushr => a >> (b % u64::from(bits)),
+        Sshr => (sa >> (b % u64::from(bits))) as u64,
+        Rotl | Rotr => {
+            let n = (b % u64::from(bits)) as u32;
+            let (left, right) = match opcode {
+                Rotl => (n, (bits - n) % bits),
+                _ => ((bits - n) % bits, n),
+            };
+            let a = a & mask(bits);
+            (a << left) | (a >> right)
+        }
+        _ => unreachable!("not a binary integer operation: {}", opcode),
+    };
+    Ok(result)
+}
+
+/// Compute a unary integer operation on the bits of a lane of type `ty`.
+fn int_unary(opcode: Opcode, ty: Type, a: u64) -> u64 {
+    let bits = u32::from(ty.lane_bits());
+    let unused = 64 - bits;
+    match opcode {
+        Ineg => a.wrapping_neg(),
+        Bnot => !a,
+        Bitrev => a.reverse_bits() >> unused,
+        Clz => (a.leading_zeros() - unused).into(),
+        Cls => {
+            let a = sign_extend(a, bits);
+            let a = if a < 0 { !a } else { a };
+            (a.leading_zeros() - unused - 1).into()
+        }
+        Ctz => a.trailing_zeros().min(bits).into(),
+        Popcnt => (a & mask(bits)).count_ones().into(),
+        _ => unreachable!("not a unary integer operation: {}", opcode),
+    }
+}
+
+/// Compute `a + b + carry_in`, or `a - b - carry_in` if `subtract`, for integers of type `ty`.
+/// Returns the result, its carry (or borrow) out, and whether the signed operation overflowed.
+fn add_with_carry(ty: Type, a: u64, b: u64, carry_in: bool, subtract: bool) -> (u64, bool, bool) {
+    let bits = u32::from(ty.bits());
+    let (sa, sb) = (
+        i128::from(sign_extend(a, bits)),
+        i128::from(sign_extend(b, bits)),
+    );
+    let (a, b, carry_in) = (i128::from(a), i128::from(b), i128::from(carry_in));
+    let (unsigned, signed) = if subtract {
+        (a - b - carry_in, sa - sb - carry_in)
+    } else {
+        (a + b + carry_in, sa + sb + carry_in)
+    };
+    let (min, max) = signed_range(bits);
+    (
+        unsigned as u64 & mask(bits),
+        !(0..=i128::from(mask(bits))).contains(&unsigned),
+        !(min..=max).contains(&signed),
+    )
+}
+
+/// Return the range of the signed integers of `bits` bits.
+fn signed_range(bits: u32) -> (i128, i128) {
+    (-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+}
+
+/// Compare the (vector) integers `a` and `b` of type `in_ty`, producing booleans of type `out_ty`.
+fn icmp(
+    cond: IntCC,
+    in_ty: Type,
+    out_ty: Type,
+    a: &DataValue,
+    b: &DataValue,
+) -> Result<DataValue, Trap> {
+    let lane_type = in_ty.lane_type();
+    lanewise(in_ty, out_ty, &[a.clone(), b.clone()], |x| {
+        let (a, b) = (
+            value::to_bits(&x[0], lane_type)?,
+            value::to_bits(&x[1], lane_type)?,
+        );
+        Ok(DataValue::B(int_cond(cond, a, b, lane_type)))
+    })
+}
+
+/// Evaluate the integer condition `cond` on the bits `a` and `b` of integers of type `ty`.
+fn int_cond(cond: IntCC, a: u64, b: u64, ty: Type) -> bool {
+    let bits = u32::from(ty.bits());
+    let (sa, sb) = (sign_extend(a, bits), sign_extend(b, bits));
+    let overflow = || {
+        let (min, max) = signed_range(bits);
+        let difference = i128::from(sa) - i128::from(sb);
+        !(min..=max).contains(&difference)
+    };
+    match cond {
+        IntCC::Equal => a == b,
+        IntCC::NotEqual => a != b,
+        IntCC::SignedLessThan => sa < sb,
+        IntCC::SignedGreaterThanOrEqual => sa >= sb,
+        IntCC::SignedGreaterThan => sa > sb,
+        IntCC::SignedLessThanOrEqual => sa <= sb,
+        IntCC::UnsignedLessThan => a < b,
+        IntCC::UnsignedGreaterThanOrEqual => a >= b,
+        IntCC::UnsignedGreaterThan => a > b,
+        IntCC::UnsignedLessThanOrEqual => a <= b,
+        IntCC::Overflow => overflow(),
+        IntCC::NotOverflow => !overflow(),
+    }
+}
+
+/// Evaluate the integer condition `cond` on `flags`, or return `None` if the flags don't define
+/// it.
+fn int_flags_cond(cond: IntCC, flags: Flags) -> Option<bool> {
+    match flags {
+        Flags::Compare { a, b, ty } => Some(int_cond(cond, a, b, ty)),
+        Flags::Carry { carry, overflow } => match cond {
+            IntCC::UnsignedLessThan => Some(carry),
+            IntCC::UnsignedGreaterThanOrEqual => Some(!carry),
+            IntCC::Overflow => Some(overflow),
+            IntCC::NotOverflow => Some(!overflow),
+            _ => None,
+        },
+        Flags::Float { .. } => None,
+    }
+}
+
+/// Evaluate the floating point condition `cond` on `a` and `b`.
+fn float_cond(cond: FloatCC, a: f64, b: f64) -> bool {
+    let unordered = a.is_nan() || b.is_nan();
+    match cond {
+        FloatCC::Ordered => !unordered,
+        FloatCC::Unordered => unordered,
+        FloatCC::Equal => a == b,
+        FloatCC::NotEqual => a != b,
+        FloatCC::OrderedNotEqual => !unordered && a != b,
+        FloatCC::UnorderedOrEqual => unordered || a == b,
+        FloatCC::LessThan => a < b,
+        FloatCC::LessThanOrEqual => a <= b,
+        FloatCC::GreaterThan => a > b,
+        FloatCC::GreaterThanOrEqual => a >= b,
+        FloatCC::UnorderedOrLessThan => unordered || a < b,
+        FloatCC::UnorderedOrLessThanOrEqual => unordered || a <= b,
+        FloatCC::UnorderedOrGreaterThan => unordered || a > b,
+        FloatCC::UnorderedOrGreaterThanOrEqual => unordered || a >= b,
+    }
+}
+
+/// Evaluate the floating point condition `cond` on `flags`, or return `None` if the flags don't
+/// define it.
+fn float_flags_cond(cond: FloatCC, flags: Flags) -> Option<bool> {
+    match flags {
+        Flags::Float { a, b } => Some(float_cond(cond, a, b)),
+        _ => None,
+    }
+}
+
+/// Convert the float `x` to an integer of type `ty`, truncating towards zero. Out of range values
+/// and NaNs trap, or saturate if `saturate` is set.
+fn float_to_int(x: f64, ty: Type, signed: bool, saturate: bool) -> Result<u64, Trap> {
+    let bits = u32::from(ty.bits());
+    let (min, max) = if signed {
+        let (min, max) = signed_range(bits);
+        (min as f64, max as f64 + 1.0)
+    } else {
+        (0.0, mask(bits) as f64 + 1.0)
+    };
+    let truncated = x.trunc();
+    if x.is_nan() {
+        if saturate {
+            Ok(0)
+        } else {
+            Err(Trap::Trapped(TrapCode::BadConversionToInteger))
+        }
+    } else if !(min..max).contains(&truncated) {
+        if !saturate {
+            Err(Trap::Trapped(TrapCode::IntegerOverflow))
+        } else if truncated < min {
+            Ok(min as i64 as u64 & mask(bits))
+        } else if signed {
+            Ok(mask(bits - 1))
+        } else {
+            Ok(mask(bits))
+        }
+    } else if signed {
+        Ok(truncated as i64 as u64 & mask(bits))
+    } else {
+        Ok(truncated as u64)
+    }
+}
+
+/// Return the `f32` in `value`.
+fn f32_value(value: &DataValue) -> Result<f32, Trap> {
+    match *value {
+        DataValue::F32(f) => Ok(f),
+        _ => Err(Trap::InvalidValue(value.clone(), types::F32)),
+    }
+}
+
+/// Return the `f64` in `value`.
+fn f64_value(value: &DataValue) -> Result<f64, Trap> {
+    match *value {
+        DataValue::F64(f) => Ok(f),
+        _ => Err(Trap::InvalidValue(value.clone(), types::F64)),
+    }
+}
+
+/// Return the `f32` or `f64` in `value`, widened to an `f64`.
+fn float_value(value: &DataValue) -> Result<f64, Trap> {
+    match *value {
+        DataValue::F32(f) => Ok(f64::from(f)),
+        _ => f64_value(value),
+    }
+}
+
+/// Return the types of the parameters or returns of a signature.
+fn value_types(params: &[AbiParam]) -> Vec<Type> {
+    params.iter().map(|param| param.value_type).collect()
+}
+
+/// Return the 64-bit immediate of an instruction, as bits.
+fn imm64(data: &InstructionData) -> u64 {
+    match *data {
+        InstructionData::BinaryImm64 { imm, .. } | InstructionData::IntCompareImm { imm, .. } => {
+            imm.bits() as u64
+        }
+        _ => unreachable!("no 64-bit immediate: {:?}", data),
+    }
+}
+
+/// Return the integer condition code of an instruction.
+fn int_cond_code(data: &InstructionData) -> IntCC {
+    match *data {
+        InstructionData::IntCompare { cond, .. }
+        | InstructionData::IntCompareImm { cond, .. }
+        | InstructionData::IntCond { cond, .. }
+        | InstructionData::IntCondTrap { cond, .. }
+        | InstructionData::IntSelect { cond, .. }
+        | InstructionData::BranchInt { cond, .. }
+        | InstructionData::BranchIcmp { cond, .. } => cond,
+        _ => unreachable!("no integer condition code: {:?}", data),
+    }
+}
+
+/// Return the floating point condition code of an instruction.
+fn float_cond_code(data: &InstructionData) -> FloatCC {
+    match *data {
+        InstructionData::FloatCompare { cond, .. }
+        | InstructionData::FloatCond { cond, .. }
+        | InstructionData::FloatCondTrap { cond, .. }
+        | InstructionData::BranchFloat { cond, .. } => cond,
+        _ => unreachable!("no floating point condition code: {:?}", data),
+    }
 }
 
 /// Return the (external) function name of `func_ref` in a local `function`. Note that this may
@@ -337,12 +1275,6 @@ fn function_name_of_func_ref(func_ref: FuncRef, function: &Function) -> String {
         .to_string()
 }
 
-/// Helper for calculating the type of an IR value. TODO move to Frame?
-#[inline]
-fn type_of(value: ValueRef, function: &Function) -> Type {
-    function.dfg.value_type(value)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -373,4 +1305,173 @@ mod tests {
 
         assert_eq!(result, vec![DataValue::B(true)])
     }
+
+    /// Build an interpreter for the functions in `code`.
+    fn interpreter(code: &str) -> Interpreter {
+        let mut env = Environment::default();
+        for func in parse_functions(code).unwrap() {
+            env.add(func.name.to_string(), func);
+        }
+        Interpreter::new(env)
+    }
+
+    /// Call `%test` in `interpreter`, expecting it to trap with `code`.
+    fn assert_traps(interpreter: &Interpreter, args: &[DataValue], code: TrapCode) {
+        match interpreter.call_by_name("%test", args) {
+            Err(Trap::Trapped(trap)) => assert_eq!(trap, code),
+            Err(trap) => panic!("unexpected failure: {}", trap),
+            Ok(_) => panic!("expected a trap: {}", code),
+        }
+    }
+
+    #[test]
+    fn arithmetic_traps() {
+        let interpreter = interpreter(
+            "function %test(i32, i32) -> i32 {
+            block0(v0: i32, v1: i32):
+                v2 = sdiv v0, v1
+                return v2
+            }",
+        );
+        let args = |a, b| [DataValue::I32(a), DataValue::I32(b)];
+        assert_traps(&interpreter, &args(1, 0), TrapCode::IntegerDivisionByZero);
+        assert_traps(
+            &interpreter,
+            &args(i32::min_value(), -1),
+            TrapCode::IntegerOverflow,
+        );
+        let result = interpreter.call_by_name("%test", &args(-7, 2)).unwrap();
+        assert_eq!(result.unwrap_return(), vec![DataValue::I32(-3)]);
+    }
+
+    #[test]
+    fn conversion_traps() {
+        let interpreter = interpreter(
+            "function %test(f32) -> i32 {
+            block0(v0: f32):
+                v1 = fcvt_to_sint.i32 v0
+                return v1
+            }",
+        );
+        assert_traps(
+            &interpreter,
+            &[DataValue::F32(std::f32::NAN)],
+            TrapCode::BadConversionToInteger,
+        );
+        assert_traps(
+            &interpreter,
+            &[DataValue::F32(3e9)],
+            TrapCode::IntegerOverflow,
+        );
+        let result = interpreter
+            .call_by_name("%test", &[DataValue::F32(-2.5)])
+            .unwrap();
+        assert_eq!(result.unwrap_return(), vec![DataValue::I32(-2)]);
+    }
+
+    #[test]
+    fn explicit_traps() {
+        let interpreter = interpreter(
+            "function %test(i32) {
+            block0(v0: i32):
+                trapz v0, user7
+                v1 = ifcmp_imm v0, 10
+                trapif ugt v1, heap_oob
+                return
+            }",
+        );
+        assert_traps(&interpreter, &[DataValue::I32(0)], TrapCode::User(7));
+        assert_traps(
+            &interpreter,
+            &[DataValue::I32(11)],
+            TrapCode::HeapOutOfBounds,
+        );
+        assert!(interpreter
+            .call_by_name("%test", &[DataValue::I32(10)])
+            .is_ok());
+    }
+
+    #[test]
+    fn heap_access() {
+        // Like the code generated for a wasm memory: the VM context holds the base and bound of
+        // a dynamic heap.
+        let interpreter = interpreter(
+            "function %test(i32, i64 vmctx) -> i32 {
+                gv0 = vmctx
+                gv1 = load.i64 notrap aligned gv0
+                gv2 = load.i64 notrap aligned gv0+8
+                heap0 = dynamic gv1, bound gv2, offset_guard 0, index_type i32
+
+            block0(v0: i32, v1: i64):
+                v2 = heap_addr.i64 heap0, v0, 4
+                v3 = iconst.i32 42
+                istore8 v3, v2
+                v4 = load.i32 v2
+                return v4
+            }",
+        );
+
+        let mut memory = interpreter.memory.borrow_mut();
+        let heap = memory.allocate(16, 8);
+        let vmctx = memory.allocate(16, 8);
+        assert!(memory.write(heap, &[0xff; 16]));
+        assert!(memory.write(vmctx, &heap.to_le_bytes()));
+        assert!(memory.write(vmctx + 8, &16u64.to_le_bytes()));
+        drop(memory);
+
+        let args = |index| [DataValue::I32(index), DataValue::I64(vmctx as i64)];
+        let result = interpreter.call_by_name("%test", &args(4)).unwrap();
+        assert_eq!(result.unwrap_return(), vec![DataValue::I32(-214)]);
+        assert_traps(&interpreter, &args(13), TrapCode::HeapOutOfBounds);
+    }
+
+    #[test]
+    fn indirect_calls() {
+        let interpreter = interpreter(
+            "function %add1(i32) -> i32 {
+            block0(v0: i32):
+                v1 = iadd_imm v0, 1
+                return v1
+            }
+
+            function %test(i32) -> i32 {
+                sig0 = (i32) -> i32
+                sig1 = (i64) -> i32
+                fn0 = %add1 sig0
+
+            block0(v0: i32):
+                v1 = func_addr.i64 fn0
+                v2 = call_indirect sig0, v1(v0)
+                brz v0, block2
+                jump block1
+
+            block1:
+                v3 = sextend.i64 v0
+                v4 = call_indirect sig1, v1(v3)
+                return v4
+
+            block2:
+                return v2
+            }",
+        );
+        let result = interpreter
+            .call_by_name("%test", &[DataValue::I32(0)])
+            .unwrap();
+        assert_eq!(result.unwrap_return(), vec![DataValue::I32(1)]);
+        assert_traps(&interpreter, &[DataValue::I32(1)], TrapCode::BadSignature);
+    }
+
+    #[test]
+    fn stack_overflow() {
+        let interpreter = interpreter(
+            "function %test() {
+                fn0 = %test()
+
+            block0:
+                call fn0()
+                return
+            }",
+        );
+        assert_traps(&interpreter, &[], TrapCode::StackOverflow);
+    }
 }
@@ -0,0 +1,139 @@
+//! Implements the emulated address space which the Cranelift interpreter's loads and stores
+//! access.
+
+use log::trace;
+
+/// The address of the first allocation; lower addresses, including null, are never mapped.
+const FIRST_ADDRESS: u64 = 0x1_0000;
+
+/// The number of unmapped bytes left after each allocation, so that accesses overflowing an
+/// allocation fault instead of reaching the next one.
+const GUARD_SIZE: u64 = 0x1000;
+
+/// A contiguous range of addresses backed by bytes.
+#[derive(Debug)]
+struct Region {
+    base: u64,
+    data: Vec<u8>,
+}
+
+/// An emulated address space, made of the regions of memory allocated in it. Stack slots are
+/// allocated in it when a function is called, and an embedder may allocate other memory, such as
+/// the heaps and VM context of a function, to pass their addresses to the functions it calls.
+///
+/// Addresses are never reused, so a dangling address faults rather than reaching a newer
+/// allocation.
+#[derive(Debug)]
+pub struct Memory {
+    /// The live regions, ordered by their base addresses.
+    regions: Vec<Region>,
+    /// The address after the end of the last allocation.
+    next: u64,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self {
+            regions: Vec::new(),
+            next: FIRST_ADDRESS,
+        }
+    }
+}
+
+impl Memory {
+    /// Allocate `size` zeroed bytes aligned to `align` bytes and return their address.
+    pub fn allocate(&mut self, size: u64, align: u64) -> u64 {
+        let align = align.max(1);
+        let base = (self.next + align - 1) / align * align;
+        self.next = base + size + GUARD_SIZE;
+        trace!("Allocate {} bytes at {:#x}", size, base);
+        self.regions.push(Region {
+            base,
+            data: vec![0; size as usize],
+        });
+        base
+    }
+
+    /// Free the allocation at `address`, making its bytes inaccessible. Returns false if nothing
+    /// is allocated at `address`.
+    pub fn free(&mut self, address: u64) -> bool {
+        trace!("Free {:#x}", address);
+        match self.regions.binary_search_by_key(&address, |r| r.base) {
+            Ok(index) => {
+                self.regions.remove(index);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Find the region containing the `size` bytes at `address` and the offset of `address` in
+    /// it.
+    fn find(&self, address: u64, size: u64) -> Option<(usize, usize)> {
+        let index = match self.regions.binary_search_by_key(&address, |r| r.base) {
+            Ok(index) => index,
+            Err(0) => return None,
+            Err(index) => index - 1,
+        };
+        let region = &self.regions[index];
+        let offset = address - region.base;
+        if offset.checked_add(size)? <= region.data.len() as u64 {
+            Some((index, offset as usize))
+        } else {
+            None
+        }
+    }
+
+    /// Read the `size` bytes at `address`, or `None` if any of them isn't allocated.
+    pub fn read(&self, address: u64, size: u64) -> Option<&[u8]> {
+        let (index, offset) = self.find(address, size)?;
+        Some(&self.regions[index].data[offset..offset + size as usize])
+    }
+
+    /// Write `bytes` to `address`. Returns false, without writing anything, if any of the bytes
+    /// written isn't allocated.
+    pub fn write(&mut self, address: u64, bytes: &[u8]) -> bool {
+        match self.find(address, bytes.len() as u64) {
+            Some((index, offset)) => {
+                self.regions[index].data[offset..offset + bytes.len()].copy_from_slice(bytes);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocation() {
+        let mut memory = Memory::default();
+        let a = memory.allocate(8, 8);
+        let b = memory.allocate(3, 16);
+        assert_eq!(a % 8, 0);
+        assert_eq!(b % 16, 0);
+        assert!(b >= a + 8);
+
+        assert!(memory.write(a, &[1, 2, 3, 4, 5, 6, 7, 8]));
+        assert!(memory.write(b, &[9, 10, 11]));
+        assert_eq!(memory.read(a + 2, 4), Some(&[3, 4, 5, 6][..]));
+        assert_eq!(memory.read(b, 3), Some(&[9, 10, 11][..]));
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut memory = Memory::default();
+        let a = memory.allocate(8, 8);
+        assert_eq!(memory.read(0, 1), None);
+        assert_eq!(memory.read(a - 1, 2), None);
+        assert_eq!(memory.read(a + 4, 5), None);
+        assert_eq!(memory.read(u64::max_value(), 2), None);
+        assert!(!memory.write(a + 8, &[0]));
+
+        assert!(memory.free(a));
+        assert_eq!(memory.read(a, 1), None);
+        assert!(!memory.free(a));
+    }
+}
@@ -0,0 +1,183 @@
+//! Conversions between the [DataValue]s the interpreter computes with and their bits.
+//!
+//! Integer, boolean and floating point values of a type are converted to and from the bits
+//! representing them, zero-extended to 64 bits, so that the interpreter can implement most
+//! operations once for all widths. Vectors are split into their lanes, each of which is a value
+//! of the lane type.
+
+use crate::interpreter::Trap;
+use cranelift_codegen::ir::{types, Type};
+use cranelift_reader::DataValue;
+
+/// Return a mask of the low `bits` bits.
+pub(crate) fn mask(bits: u32) -> u64 {
+    if bits >= 64 {
+        u64::max_value()
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+/// Sign-extend the low `bits` bits of `value`.
+pub(crate) fn sign_extend(value: u64, bits: u32) -> i64 {
+    let shift = 64 - bits.min(64);
+    ((value << shift) as i64) >> shift
+}
+
+/// Return the bits of the scalar `value` of type `ty`. Booleans are all ones when true, like
+/// boolean vector lanes.
+pub(crate) fn to_bits(value: &DataValue, ty: Type) -> Result<u64, Trap> {
+    let bits = match *value {
+        DataValue::B(b) if ty.is_bool() => {
+            if b {
+                mask(ty.bits().into())
+            } else {
+                0
+            }
+        }
+        DataValue::I8(i) => i as u8 as u64,
+        DataValue::I16(i) => i as u16 as u64,
+        DataValue::I32(i) => i as u32 as u64,
+        DataValue::I64(i) => i as u64,
+        DataValue::F32(f) => f.to_bits() as u64,
+        DataValue::F64(f) => f.to_bits(),
+        _ => return Err(Trap::InvalidValue(value.clone(), ty)),
+    };
+    Ok(bits)
+}
+
+/// Build the scalar value of type `ty` from its bits; bits above the width of `ty` are ignored.
+pub(crate) fn from_bits(bits: u64, ty: Type) -> Result<DataValue, Trap> {
+    let value = match ty {
+        types::I8 => DataValue::I8(bits as i8),
+        types::I16 => DataValue::I16(bits as i16),
+        types::I32 => DataValue::I32(bits as i32),
+        types::I64 => DataValue::I64(bits as i64),
+        types::F32 => DataValue::F32(f32::from_bits(bits as u32)),
+        types::F64 => DataValue::F64(f64::from_bits(bits)),
+        _ if ty.is_bool() => DataValue::B(bits & mask(ty.bits().into()) != 0),
+        _ => return Err(Trap::UnsupportedType(ty)),
+    };
+    Ok(value)
+}
+
+/// Return the integer `value` of type `ty`, sign-extended.
+pub(crate) fn to_signed(value: &DataValue, ty: Type) -> Result<i64, Trap> {
+    Ok(sign_extend(to_bits(value, ty)?, ty.bits().into()))
+}
+
+/// Return whether the boolean or integer `value` is true, i.e. not zero.
+pub(crate) fn is_true(value: &DataValue) -> Result<bool, Trap> {
+    match *value {
+        DataValue::B(b) => Ok(b),
+        DataValue::I8(i) => Ok(i != 0),
+        DataValue::I16(i) => Ok(i != 0),
+        DataValue::I32(i) => Ok(i != 0),
+        DataValue::I64(i) => Ok(i != 0),
+        _ => Err(Trap::InvalidValue(value.clone(), types::B1)),
+    }
+}
+
+/// Return the bytes of the vector `value`.
+pub(crate) fn vector_bytes(value: &DataValue, ty: Type) -> Result<[u8; 16], Trap> {
+    match *value {
+        DataValue::V128(bytes) => Ok(bytes),
+        _ => Err(Trap::InvalidValue(value.clone(), ty)),
+    }
+}
+
+/// Split the vector `value` of type `ty` into its lanes.
+pub(crate) fn lanes(value: &DataValue, ty: Type) -> Result<Vec<DataValue>, Trap> {
+    let bytes = vector_bytes(value, ty)?;
+    let lane_type = ty.lane_type();
+    let lane_bytes = lane_type.bytes() as usize;
+    (0..ty.lane_count() as usize)
+        .map(|lane| {
+            let bits = read_le(&bytes[lane * lane_bytes..(lane + 1) * lane_bytes]);
+            from_bits(bits, lane_type)
+        })
+        .collect()
+}
+
+/// Build a vector of type `ty` from its lanes.
+pub(crate) fn from_lanes(lanes: &[DataValue], ty: Type) -> Result<DataValue, Trap> {
+    let lane_type = ty.lane_type();
+    let lane_bytes = lane_type.bytes() as usize;
+    let mut bytes = [0; 16];
+    for (lane, value) in lanes.iter().enumerate() {
+        let bits = to_bits(value, lane_type)?;
+        write_le(&mut bytes[lane * lane_bytes..(lane + 1) * lane_bytes], bits);
+    }
+    Ok(DataValue::V128(bytes))
+}
+
+/// Read a little-endian integer of up to 8 bytes.
+pub(crate) fn read_le(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |bits, &byte| (bits << 8) | u64::from(byte))
+}
+
+/// Write the low bytes of `bits` to `bytes` in little-endian order.
+pub(crate) fn write_le(bytes: &mut [u8], bits: u64) {
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = (bits >> (8 * i)) as u8;
+    }
+}
+
+/// Decode a value of type `ty` from its in-memory representation.
+pub(crate) fn from_bytes(bytes: &[u8], ty: Type) -> Result<DataValue, Trap> {
+    if ty.is_vector() {
+        let mut vector = [0; 16];
+        vector[..bytes.len()].copy_from_slice(bytes);
+        Ok(DataValue::V128(vector))
+    } else {
+        from_bits(read_le(bytes), ty)
+    }
+}
+
+/// Encode a value of type `ty` in its in-memory representation.
+pub(crate) fn to_bytes(value: &DataValue, ty: Type) -> Result<Vec<u8>, Trap> {
+    if ty.is_vector() {
+        Ok(vector_bytes(value, ty)?[..ty.bytes() as usize].to_vec())
+    } else {
+        let mut bytes = vec![0; ty.bytes() as usize];
+        write_le(&mut bytes, to_bits(value, ty)?);
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits() {
+        assert_eq!(to_bits(&DataValue::I8(-1), types::I8).unwrap(), 0xff);
+        assert_eq!(to_bits(&DataValue::B(true), types::B16).unwrap(), 0xffff);
+        assert_eq!(to_bits(&DataValue::B(true), types::B1).unwrap(), 1);
+        assert_eq!(from_bits(0x1_80, types::I8).unwrap(), DataValue::I8(-128));
+        assert_eq!(sign_extend(0x80, 8), -128);
+        assert_eq!(sign_extend(0x7f, 8), 127);
+        assert_eq!(
+            from_bits(1.5f64.to_bits(), types::F64).unwrap(),
+            DataValue::F64(1.5)
+        );
+    }
+
+    #[test]
+    fn vector_lanes() {
+        let lanes_in = vec![
+            DataValue::I32(1),
+            DataValue::I32(-2),
+            DataValue::I32(3),
+            DataValue::I32(-4),
+        ];
+        let vector = from_lanes(&lanes_in, types::I32X4).unwrap();
+        if let DataValue::V128(bytes) = vector {
+            assert_eq!(&bytes[..8], &[1, 0, 0, 0, 0xfe, 0xff, 0xff, 0xff]);
+        }
+        assert_eq!(lanes(&vector, types::I32X4).unwrap(), lanes_in);
+    }
+}
@@ -109,7 +109,8 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 GlobalVariable::Const(val) => val,
                 GlobalVariable::Memory { gv, offset, ty } => {
                     let addr = builder.ins().global_value(environ.pointer_type(), gv);
-                    let flags = ir::MemFlags::trusted();
+                    let mut flags = ir::MemFlags::trusted();
+                    flags.set_vmctx();
                     builder.ins().load(ty, flags, addr, offset)
                 }
                 GlobalVariable::Custom => environ.translate_custom_global_get(
@@ -124,7 +125,8 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 GlobalVariable::Const(_) => panic!("global #{} is a constant", *global_index),
                 GlobalVariable::Memory { gv, offset, ty } => {
                     let addr = builder.ins().global_value(environ.pointer_type(), gv);
-                    let flags = ir::MemFlags::trusted();
+                    let mut flags = ir::MemFlags::trusted();
+                    flags.set_vmctx();
                     let mut val = state.pop1();
                     // Ensure SIMD values are cast to their default Cranelift type, I8x16.
                     if ty.is_vector() {
@@ -1812,7 +1814,10 @@ fn prepare_load<FE: FuncEnvironment + ?Sized>(
     // Note that we don't set `is_aligned` here, even if the load instruction's
     // alignment immediate says it's aligned, because WebAssembly's immediate
     // field is just a hint, while Cranelift's aligned flag needs a guarantee.
-    let flags = MemFlags::new();
+    // Linear memory is never accessed through the VM context or a table, which
+    // lets alias analysis keep the values it loaded across heap stores.
+    let mut flags = MemFlags::new();
+    flags.set_heap();
 
     Ok((flags, base, offset.into()))
 }
@@ -1859,8 +1864,9 @@ fn translate_store<FE: FuncEnvironment + ?Sized>(
         environ.pointer_type(),
         builder,
     );
-    // See the comments in `translate_load` about the flags.
-    let flags = MemFlags::new();
+    // See the comments in `prepare_load` about the flags.
+    let mut flags = MemFlags::new();
+    flags.set_heap();
     builder
         .ins()
         .Store(opcode, val_ty, flags, offset.into(), val, base);
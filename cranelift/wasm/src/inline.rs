@@ -0,0 +1,364 @@
+//! Inlining of direct calls between translated functions.
+//!
+//! Each function of a module is translated to Cranelift IR on its own, so a call to a small
+//! helper function pays for the whole calling convention. The inliner splices a copy of the
+//! callee's body into the caller in place of the call: the call becomes a jump to the copy of the
+//! callee's entry block, and the callee's returns become jumps to a new block holding the rest of
+//! the caller's block, whose parameters are the results of the call.
+//!
+//! The instructions copied from the callee take the source location of the call, so that traps
+//! in them are reported at the call like before, just without the callee's frame. The callee's
+//! value labels are dropped since they describe the callee's locals, not the caller's.
+
+use cranelift_codegen::ir::{
+    self, ArgumentPurpose, Block, ExternalName, Function, GlobalValueData, HeapStyle,
+    InstructionData, JumpTableData, Opcode, Value, ValueList,
+};
+use cranelift_entity::packed_option::ReservedValue;
+use cranelift_entity::{EntityRef, SecondaryMap};
+use std::vec::Vec;
+
+/// Inline the direct calls in `func` to the functions `callee` returns for their names.
+///
+/// Only the calls present in `func` before inlining are inlined, so calls in the inlined bodies
+/// stay calls. Returns the number of calls inlined.
+pub fn inline_calls<'a, F>(func: &mut Function, mut callee: F) -> usize
+where
+    F: FnMut(&ExternalName) -> Option<&'a Function>,
+{
+    let mut sites = Vec::new();
+    for block in func.layout.blocks() {
+        for inst in func.layout.block_insts(block) {
+            if let InstructionData::Call { func_ref, .. } = func.dfg[inst] {
+                if let Some(body) = callee(&func.dfg.ext_funcs[func_ref].name) {
+                    if can_inline(func, inst, body) {
+                        sites.push((inst, body));
+                    }
+                }
+            }
+        }
+    }
+
+    for &(call, body) in &sites {
+        inline_call(func, call, body);
+    }
+    sites.len()
+}
+
+/// Check that `call` in `func` can be replaced with the body of `callee`.
+fn can_inline(func: &Function, call: ir::Inst, callee: &Function) -> bool {
+    let args = func.dfg.inst_args(call);
+    let entry = match callee.layout.entry_block() {
+        Some(entry) => entry,
+        None => return false,
+    };
+    let params = callee.dfg.block_params(entry);
+    if args.len() != params.len()
+        || args
+            .iter()
+            .zip(params)
+            .any(|(&arg, &param)| func.dfg.value_type(arg) != callee.dfg.value_type(param))
+    {
+        return false;
+    }
+
+    // The callee's `vmctx` global value refers to its own VM context parameter, which only
+    // carries over to the caller if it's passed the caller's VM context.
+    match callee
+        .signature
+        .special_param_index(ArgumentPurpose::VMContext)
+    {
+        Some(index) => {
+            func.special_param(ArgumentPurpose::VMContext)
+                == Some(func.dfg.resolve_aliases(args[index]))
+        }
+        None => !callee
+            .global_values
+            .values()
+            .any(|gv| matches!(gv, GlobalValueData::VMContext)),
+    }
+}
+
+/// Replace `call` in `func` with a copy of the body of `callee`.
+fn inline_call(func: &mut Function, call: ir::Inst, callee: &Function) {
+    let srcloc = func.srclocs[call];
+
+    // Copy the entities the callee's instructions refer to.
+    let sigs: Vec<_> = callee
+        .dfg
+        .signatures
+        .values()
+        .map(|sig| func.import_signature(sig.clone()))
+        .collect();
+    let func_refs: Vec<_> = callee
+        .dfg
+        .ext_funcs
+        .values()
+        .map(|ext_func| {
+            let mut ext_func = ext_func.clone();
+            ext_func.signature = sigs[ext_func.signature.index()];
+            func.import_function(ext_func)
+        })
+        .collect();
+    let global_values: Vec<_> = callee
+        .global_values
+        .values()
+        .map(|gv| func.create_global_value(gv.clone()))
+        .collect();
+    for &gv in &global_values {
+        match func.global_values[gv] {
+            GlobalValueData::Load { ref mut base, .. }
+            | GlobalValueData::IAddImm { ref mut base, .. } => {
+                *base = global_values[base.index()];
+            }
+            GlobalValueData::VMContext | GlobalValueData::Symbol { .. } => {}
+        }
+    }
+    let heaps: Vec<_> = callee
+        .heaps
+        .values()
+        .map(|heap| {
+            let mut heap = heap.clone();
+            heap.base = global_values[heap.base.index()];
+            if let HeapStyle::Dynamic { ref mut bound_gv } = heap.style {
+                *bound_gv = global_values[bound_gv.index()];
+            }
+            func.create_heap(heap)
+        })
+        .collect();
+    let tables: Vec<_> = callee
+        .tables
+        .values()
+        .map(|table| {
+            let mut table = table.clone();
+            table.base_gv = global_values[table.base_gv.index()];
+            table.bound_gv = global_values[table.bound_gv.index()];
+            func.create_table(table)
+        })
+        .collect();
+    let stack_slots: Vec<_> = callee
+        .stack_slots
+        .values()
+        .map(|ss| func.create_stack_slot(ss.clone()))
+        .collect();
+
+    // Split the caller's block after the call. The rest of the block receives the results of the
+    // call as its parameters.
+    let cont = func.dfg.make_block();
+    let next = func
+        .layout
+        .next_inst(call)
+        .expect("a call can't terminate a block");
+    func.layout.split_block(cont, next);
+    let results = func.dfg.inst_results(call).to_vec();
+    func.dfg.clear_results(call);
+    for result in results {
+        func.dfg.attach_block_param(cont, result);
+    }
+
+    // Create the callee's blocks between the call and the rest of the caller's block.
+    let mut blocks = SecondaryMap::with_default(Block::reserved_value());
+    let mut values = SecondaryMap::with_default(Value::reserved_value());
+    let mut after = func.layout.inst_block(call).unwrap();
+    for block in callee.layout.blocks() {
+        let new_block = func.dfg.make_block();
+        for &param in callee.dfg.block_params(block) {
+            values[param] = func
+                .dfg
+                .append_block_param(new_block, callee.dfg.value_type(param));
+        }
+        func.layout.insert_block_after(new_block, after);
+        blocks[block] = new_block;
+        after = new_block;
+    }
+    let jump_tables: Vec<_> = callee
+        .jump_tables
+        .values()
+        .map(|jt| {
+            let mut new_jt = JumpTableData::with_capacity(jt.len());
+            for &block in jt.iter() {
+                new_jt.push_entry(blocks[block]);
+            }
+            func.create_jump_table(new_jt)
+        })
+        .collect();
+
+    // Copy the instructions. Their arguments are mapped once all of the values they may refer to
+    // have been created.
+    let mut insts = Vec::new();
+    for block in callee.layout.blocks() {
+        for inst in callee.layout.block_insts(block) {
+            let mut data = callee.dfg[inst].clone();
+            if let Some(list) = data.take_value_list() {
+                let args = list.as_slice(&callee.dfg.value_lists);
+                data.put_value_list(ValueList::from_slice(args, &mut func.dfg.value_lists));
+            }
+            match data {
+                InstructionData::Call {
+                    ref mut func_ref, ..
+                }
+                | InstructionData::FuncAddr {
+                    ref mut func_ref, ..
+                } => *func_ref = func_refs[func_ref.index()],
+                InstructionData::CallIndirect {
+                    ref mut sig_ref, ..
+                } => *sig_ref = sigs[sig_ref.index()],
+                InstructionData::UnaryGlobalValue {
+                    ref mut global_value,
+                    ..
+                } => *global_value = global_values[global_value.index()],
+                InstructionData::HeapAddr { ref mut heap, .. } => *heap = heaps[heap.index()],
+                InstructionData::TableAddr { ref mut table, .. } => *table = tables[table.index()],
+                InstructionData::StackLoad {
+                    ref mut stack_slot, ..
+                }
+                | InstructionData::StackStore {
+                    ref mut stack_slot, ..
+                } => *stack_slot = stack_slots[stack_slot.index()],
+                InstructionData::BranchTable {
+                    ref mut destination,
+                    ref mut table,
+                    ..
+                } => {
+                    *destination = blocks[*destination];
+                    *table = jump_tables[table.index()];
+                }
+                InstructionData::BranchTableEntry { ref mut table, .. }
+                | InstructionData::BranchTableBase { ref mut table, .. }
+                | InstructionData::IndirectJump { ref mut table, .. } => {
+                    *table = jump_tables[table.index()]
+                }
+                InstructionData::UnaryConst {
+                    ref mut constant_handle,
+                    ..
+                } => {
+                    let constant = callee.dfg.constants.get(*constant_handle).clone();
+                    *constant_handle = func.dfg.constants.insert(constant);
+                }
+                InstructionData::Shuffle { ref mut mask, .. } => {
+                    *mask = func
+                        .dfg
+                        .immediates
+                        .push(callee.dfg.immediates[*mask].clone());
+                }
+                _ => {}
+            }
+            if let Some(destination) = data.branch_destination_mut() {
+                *destination = blocks[*destination];
+            }
+            if data.opcode().is_return() {
+                // Return to the rest of the caller's block instead.
+                data = InstructionData::Jump {
+                    opcode: Opcode::Jump,
+                    destination: cont,
+                    args: data.take_value_list().unwrap(),
+                };
+            }
+
+            let ctrl_typevar = callee.dfg.ctrl_typevar(inst);
+            let new_inst = func.dfg.make_inst(data);
+            func.dfg.make_inst_results(new_inst, ctrl_typevar);
+            for (&result, &new_result) in callee
+                .dfg
+                .inst_results(inst)
+                .iter()
+                .zip(func.dfg.inst_results(new_inst))
+            {
+                values[result] = new_result;
+            }
+            func.layout.append_inst(new_inst, blocks[block]);
+            func.srclocs[new_inst] = srcloc;
+            insts.push(new_inst);
+        }
+    }
+    for inst in insts {
+        for arg in func.dfg.inst_args_mut(inst) {
+            *arg = values[callee.dfg.resolve_aliases(*arg)];
+        }
+    }
+
+    // Finally jump from the call to the copy of the callee's entry block.
+    let entry = blocks[callee.layout.entry_block().unwrap()];
+    let args = func.dfg.inst_args(call).to_vec();
+    func.dfg.replace(call).jump(entry, &args);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::inline_calls;
+    use cranelift_codegen::ir::{ExternalName, Function, Opcode};
+    use cranelift_codegen::{settings, verify_function};
+    use cranelift_reader::parse_functions;
+
+    /// Count the instructions of `func` with the opcode `opcode`.
+    fn count(func: &Function, opcode: Opcode) -> usize {
+        func.layout
+            .blocks()
+            .flat_map(|block| func.layout.block_insts(block))
+            .filter(|&inst| func.dfg[inst].opcode() == opcode)
+            .count()
+    }
+
+    /// Inline the calls in the last of the functions in `text` to the others.
+    fn inline(text: &str) -> (Function, usize) {
+        let mut funcs = parse_functions(text).unwrap();
+        let mut func = funcs.pop().unwrap();
+        let inlined = inline_calls(&mut func, |name: &ExternalName| {
+            funcs.iter().find(|callee| callee.name == *name)
+        });
+        verify_function(&func, &settings::Flags::new(settings::builder())).unwrap();
+        (func, inlined)
+    }
+
+    #[test]
+    fn call_is_replaced() {
+        let (func, inlined) = inline(
+            "function %add(i32, i32) -> i32 {
+             block0(v0: i32, v1: i32):
+                 v2 = iadd v0, v1
+                 return v2
+             }
+
+             function %caller(i32) -> i32 {
+                 fn0 = %add(i32, i32) -> i32
+
+             block0(v0: i32):
+                 v1 = call fn0(v0, v0)
+                 v2 = imul v1, v0
+                 return v2
+             }",
+        );
+        assert_eq!(inlined, 1);
+        assert_eq!(count(&func, Opcode::Call), 0);
+        assert_eq!(count(&func, Opcode::Iadd), 1);
+        assert_eq!(count(&func, Opcode::Return), 1);
+    }
+
+    #[test]
+    fn inlined_calls_stay_calls() {
+        let (func, inlined) = inline(
+            "function %leaf(i32) -> i32 {
+             block0(v0: i32):
+                 return v0
+             }
+
+             function %middle(i32) -> i32 {
+                 fn0 = %leaf(i32) -> i32
+
+             block0(v0: i32):
+                 v1 = call fn0(v0)
+                 return v1
+             }
+
+             function %caller(i32) -> i32 {
+                 fn0 = %middle(i32) -> i32
+
+             block0(v0: i32):
+                 v1 = call fn0(v0)
+                 return v1
+             }",
+        );
+        assert_eq!(inlined, 1);
+        assert_eq!(count(&func, Opcode::Call), 1);
+    }
+}
@@ -52,6 +52,7 @@ use std::collections::{
 mod code_translator;
 mod environ;
 mod func_translator;
+mod inline;
 mod module_translator;
 mod sections_translator;
 mod state;
@@ -62,6 +63,7 @@ pub use crate::environ::{
     TargetEnvironment, WasmError, WasmFuncType, WasmResult, WasmType,
 };
 pub use crate::func_translator::FuncTranslator;
+pub use crate::inline::inline_calls;
 pub use crate::module_translator::translate_module;
 pub use crate::state::func_state::FuncTranslationState;
 pub use crate::state::module_state::ModuleTranslationState;
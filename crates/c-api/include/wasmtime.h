@@ -173,7 +173,8 @@ WASM_API_EXTERN void wasmtime_interrupt_handle_interrupt(wasmtime_interrupt_hand
 
 // Returns `true` if the trap is a WASI "exit" trap and has a return status. If
 // `true` is returned then the exit status is returned through the `status`
-// pointer. If `false` is returned then this is not a wasi exit trap.
+// pointer. If `false` is returned then this is not a wasi exit trap. A program
+// terminated by a signal raised with `proc_raise` has the status `128 + signal`.
 WASM_API_EXTERN bool wasmtime_trap_exit_status(const wasm_trap_t*, int *status);
 
 ///////////////////////////////////////////////////////////////////////////////
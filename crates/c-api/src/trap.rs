@@ -94,7 +94,11 @@ pub extern "C" fn wasm_trap_trace(raw: &wasm_trap_t, out: &mut wasm_frame_vec_t)
 #[no_mangle]
 pub extern "C" fn wasmtime_trap_exit_status(raw: &wasm_trap_t, status: &mut i32) -> bool {
     let trap = raw.trap.borrow();
-    match trap.i32_exit_status() {
+    #[cfg(feature = "wasi")]
+    let exit_status = wasmtime_wasi::exit_status(&trap);
+    #[cfg(not(feature = "wasi"))]
+    let exit_status = trap.i32_exit_status();
+    match exit_status {
         Some(i) => {
             *status = i;
             true
@@ -20,7 +20,13 @@ use std::time::Duration;
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 struct Config {
-    cache: CacheConfig,
+    // a config without a cache section keeps the default cache configuration
+    cache: Option<CacheConfig>,
+    // the engine and WASI sections are read by the CLI
+    #[serde(rename = "engine")]
+    _engine: Option<toml::Value>,
+    #[serde(rename = "wasi")]
+    _wasi: Option<toml::Value>,
 }
 
 /// Global configuration for how the cache is managed
@@ -128,14 +134,26 @@ pub fn create_new_config<P: AsRef<Path> + Debug>(config_file: Option<P>) -> Resu
         )
     })?;
 
-    let content = "\
-# Comment out certain settings to use default values.
+    let content = r#"# Comment out certain settings to use default values.
 # For more settings, please refer to the documentation:
 # https://bytecodealliance.github.io/wasmtime/cli-cache.html
 
 [cache]
 enabled = true
-";
+
+# Settings of the engine, used by the CLI when given `--config`. Flags passed
+# on the command line take precedence.
+# [engine]
+# opt-level = 2
+# wasm-simd = true
+# cranelift-flags = { has_avx2 = true }
+
+# WASI settings, used by `wasmtime run` when given `--config`.
+# [wasi]
+# dirs = ["."]
+# map-dirs = { "/tmp" = "/var/tmp/service" }
+# env = { RUST_LOG = "info" }
+"#;
 
     fs::write(&config_file, &content).with_context(|| {
         format!(
@@ -415,7 +433,11 @@ impl CacheConfig {
                     "failed to parse config file: {}",
                     config_file.display()
                 ))?;
-                Ok(config.cache)
+                match config.cache {
+                    Some(cache) => Ok(cache),
+                    None if user_custom_file => Self::load_and_parse_file(None),
+                    None => Ok(Self::new_cache_enabled_template()),
+                }
             }
         }
     }
@@ -52,6 +52,24 @@ fn test_disabled() {
     assert!(!conf.enabled());
 }
 
+#[test]
+fn test_cli_sections() {
+    let dir = tempfile::tempdir().expect("Can't create temporary directory");
+    let config_path = dir.path().join("cache-config.toml");
+    let config_content = "[engine]\n\
+                          opt-level = 2\n\
+                          [wasi]\n\
+                          dirs = [\".\"]\n";
+    fs::write(&config_path, config_content).expect("Failed to write test config file");
+    // without a cache section, the default cache configuration is kept
+    let conf = CacheConfig::from_file(Some(&config_path)).unwrap();
+    let default = CacheConfig::from_file(None).unwrap();
+    assert_eq!(conf.enabled(), default.enabled());
+    if conf.enabled() {
+        assert_eq!(conf.directory(), default.directory());
+    }
+}
+
 #[test]
 fn test_unrecognized_settings() {
     let (_td, cd, cp) = test_prolog();
@@ -97,9 +97,12 @@ use cranelift_codegen::ir::{self, ExternalName};
 use cranelift_codegen::machinst::buffer::MachSrcLoc;
 use cranelift_codegen::print_errors::pretty_error;
 use cranelift_codegen::{binemit, isa, Context};
-use cranelift_entity::PrimaryMap;
+use cranelift_entity::{EntityRef, PrimaryMap};
 use cranelift_wasm::{DefinedFuncIndex, FuncIndex, FuncTranslator, ModuleTranslationState};
-use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use rayon::prelude::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
+    IntoParallelRefMutIterator, ParallelIterator,
+};
 use std::convert::TryFrom;
 use std::hash::{Hash, Hasher};
 
@@ -318,62 +321,70 @@ fn compile(env: CompileEnv<'_>) -> Result<ModuleCacheDataTupleType, CompileError
     let mut traps = PrimaryMap::with_capacity(env.function_body_inputs.len());
     let mut stack_maps = PrimaryMap::with_capacity(env.function_body_inputs.len());
 
-    env.function_body_inputs
+    let inputs = env
+        .function_body_inputs
         .into_iter()
-        .collect::<Vec<(DefinedFuncIndex, &FunctionBodyData<'_>)>>()
-        .par_iter()
-        .map_init(FuncTranslator::new, |func_translator, (i, input)| {
-            let func_index = env.local.func_index(*i);
-            let mut context = Context::new();
-            context.func.name = get_func_name(func_index);
-            context.func.signature = env.local.native_func_signature(func_index).clone();
-            if env.tunables.debug_info {
-                context.func.collect_debug_info();
-            }
+        .collect::<Vec<(DefinedFuncIndex, &FunctionBodyData<'_>)>>();
+
+    let translate_function = |func_translator: &mut FuncTranslator,
+                              (i, input): &(DefinedFuncIndex, &FunctionBodyData<'_>)|
+     -> Result<Context, CompileError> {
+        let func_index = env.local.func_index(*i);
+        let mut context = Context::new();
+        context.func.name = get_func_name(func_index);
+        context.func.signature = env.local.native_func_signature(func_index).clone();
+        if env.tunables.debug_info {
+            context.func.collect_debug_info();
+        }
 
-            let mut func_env = FuncEnvironment::new(isa.frontend_config(), env.local, env.tunables);
-
-            // We use these as constant offsets below in
-            // `stack_limit_from_arguments`, so assert their values here. This
-            // allows the closure below to get coerced to a function pointer, as
-            // needed by `ir::Function`.
-            //
-            // Otherwise our stack limit is specially calculated from the vmctx
-            // argument, where we need to load the `*const VMInterrupts`
-            // pointer, and then from that pointer we need to load the stack
-            // limit itself. Note that manual register allocation is needed here
-            // too due to how late in the process this codegen happens.
-            //
-            // For more information about interrupts and stack checks, see the
-            // top of this file.
-            let vmctx = context
-                .func
-                .create_global_value(ir::GlobalValueData::VMContext);
-            let interrupts_ptr = context.func.create_global_value(ir::GlobalValueData::Load {
-                base: vmctx,
-                offset: i32::try_from(func_env.offsets.vmctx_interrupts())
-                    .unwrap()
-                    .into(),
-                global_type: isa.pointer_type(),
-                readonly: true,
-            });
-            let stack_limit = context.func.create_global_value(ir::GlobalValueData::Load {
-                base: interrupts_ptr,
-                offset: i32::try_from(func_env.offsets.vminterrupts_stack_limit())
-                    .unwrap()
-                    .into(),
-                global_type: isa.pointer_type(),
-                readonly: false,
-            });
-            context.func.stack_limit = Some(stack_limit);
-            func_translator.translate(
-                env.module_translation.0,
-                input.data,
-                input.module_offset,
-                &mut context.func,
-                &mut func_env,
-            )?;
+        let mut func_env = FuncEnvironment::new(isa.frontend_config(), env.local, env.tunables);
+
+        // We use these as constant offsets below in
+        // `stack_limit_from_arguments`, so assert their values here. This
+        // allows the closure below to get coerced to a function pointer, as
+        // needed by `ir::Function`.
+        //
+        // Otherwise our stack limit is specially calculated from the vmctx
+        // argument, where we need to load the `*const VMInterrupts`
+        // pointer, and then from that pointer we need to load the stack
+        // limit itself. Note that manual register allocation is needed here
+        // too due to how late in the process this codegen happens.
+        //
+        // For more information about interrupts and stack checks, see the
+        // top of this file.
+        let vmctx = context
+            .func
+            .create_global_value(ir::GlobalValueData::VMContext);
+        let interrupts_ptr = context.func.create_global_value(ir::GlobalValueData::Load {
+            base: vmctx,
+            offset: i32::try_from(func_env.offsets.vmctx_interrupts())
+                .unwrap()
+                .into(),
+            global_type: isa.pointer_type(),
+            readonly: true,
+        });
+        let stack_limit = context.func.create_global_value(ir::GlobalValueData::Load {
+            base: interrupts_ptr,
+            offset: i32::try_from(func_env.offsets.vminterrupts_stack_limit())
+                .unwrap()
+                .into(),
+            global_type: isa.pointer_type(),
+            readonly: false,
+        });
+        context.func.stack_limit = Some(stack_limit);
+        func_translator.translate(
+            env.module_translation.0,
+            input.data,
+            input.module_offset,
+            &mut context.func,
+            &mut func_env,
+        )?;
+        Ok(context)
+    };
 
+    let compile_function =
+        |(mut context, (i, input)): (Context, &(DefinedFuncIndex, &FunctionBodyData<'_>))| {
+            let func_index = env.local.func_index(*i);
             let mut code_buf: Vec<u8> = Vec::new();
             let mut reloc_sink = RelocSink::new(func_index);
             let mut trap_sink = TrapSink::new();
@@ -416,34 +427,55 @@ fn compile(env: CompileEnv<'_>) -> Result<ModuleCacheDataTupleType, CompileError
                 unwind_info,
                 stack_map_sink.finish(),
             ))
-        })
-        .collect::<Result<Vec<_>, CompileError>>()?
-        .into_iter()
-        .for_each(
-            |(
-                function,
-                func_jt_offsets,
-                relocs,
-                address_transform,
-                ranges,
-                sss,
-                function_traps,
+        };
+
+    let compiled = if env.tunables.inlining {
+        // Inlining needs the Cranelift IR of all of the functions up front.
+        let mut contexts = inputs
+            .par_iter()
+            .map_init(FuncTranslator::new, &translate_function)
+            .collect::<Result<Vec<_>, CompileError>>()?;
+        inline_small_functions(env.local, &mut contexts);
+        contexts
+            .into_par_iter()
+            .zip(inputs.par_iter())
+            .map(&compile_function)
+            .collect::<Result<Vec<_>, CompileError>>()?
+    } else {
+        // Otherwise each function is compiled as soon as it's translated.
+        inputs
+            .par_iter()
+            .map_init(FuncTranslator::new, |func_translator, input| {
+                compile_function((translate_function(func_translator, input)?, input))
+            })
+            .collect::<Result<Vec<_>, CompileError>>()?
+    };
+
+    compiled.into_iter().for_each(
+        |(
+            function,
+            func_jt_offsets,
+            relocs,
+            address_transform,
+            ranges,
+            sss,
+            function_traps,
+            unwind_info,
+            stack_map,
+        )| {
+            functions.push(CompiledFunction {
+                body: function,
+                jt_offsets: func_jt_offsets,
                 unwind_info,
-                stack_map,
-            )| {
-                functions.push(CompiledFunction {
-                    body: function,
-                    jt_offsets: func_jt_offsets,
-                    unwind_info,
-                });
-                relocations.push(relocs);
-                address_transforms.push(address_transform);
-                value_ranges.push(ranges.unwrap_or_default());
-                stack_slots.push(sss);
-                traps.push(function_traps);
-                stack_maps.push(stack_map);
-            },
-        );
+            });
+            relocations.push(relocs);
+            address_transforms.push(address_transform);
+            value_ranges.push(ranges.unwrap_or_default());
+            stack_slots.push(sss);
+            traps.push(function_traps);
+            stack_maps.push(stack_map);
+        },
+    );
 
     // TODO: Reorganize where we create the Vec for the resolved imports.
 
@@ -458,6 +490,64 @@ fn compile(env: CompileEnv<'_>) -> Result<ModuleCacheDataTupleType, CompileError
     ))
 }
 
+/// The number of instructions up to which a function is small enough to be
+/// inlined into its callers.
+const MAX_INLINED_SIZE: usize = 32;
+
+/// Inline the direct calls to small functions, given the Cranelift IR of all
+/// of the defined functions of the module in `contexts`.
+///
+/// Only the calls in the functions as they were translated are inlined, so
+/// each call is inlined at most one level deep and recursion can't blow up the
+/// code. Functions calling themselves are never inlined.
+fn inline_small_functions(local: &ModuleLocal, contexts: &mut [Context]) {
+    let callees = contexts
+        .iter()
+        .map(|context| {
+            let func = &context.func;
+            let size: usize = func
+                .layout
+                .blocks()
+                .map(|block| func.layout.block_insts(block).count())
+                .sum();
+            if size <= MAX_INLINED_SIZE && !calls(func, &func.name) {
+                Some(func.clone())
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    contexts.par_iter_mut().for_each(|context| {
+        let name = context.func.name.clone();
+        cranelift_wasm::inline_calls(&mut context.func, |callee| {
+            let index = match *callee {
+                ExternalName::User {
+                    namespace: 0,
+                    index,
+                } if *callee != name => FuncIndex::from_u32(index),
+                _ => return None,
+            };
+            let defined = local.defined_func_index(index)?;
+            callees[defined.index()].as_ref()
+        });
+    });
+}
+
+/// Returns whether `func` directly calls the function named `name`.
+fn calls(func: &ir::Function, name: &ExternalName) -> bool {
+    func.layout.blocks().any(|block| {
+        func.layout
+            .block_insts(block)
+            .any(|inst| match func.dfg[inst] {
+                ir::InstructionData::Call { func_ref, .. } => {
+                    func.dfg.ext_funcs[func_ref].name == *name
+                }
+                _ => false,
+            })
+    })
+}
+
 #[derive(Hash)]
 struct CompileEnv<'a> {
     local: &'a ModuleLocal,
@@ -1,6 +1,6 @@
 use crate::module::{MemoryPlan, MemoryStyle, ModuleLocal, TableStyle};
 use crate::vmoffsets::VMOffsets;
-use crate::{Tunables, INTERRUPTED, WASM_PAGE_SIZE};
+use crate::{Tunables, INTERRUPTED, TRAP_OUT_OF_FUEL, WASM_PAGE_SIZE};
 use cranelift_codegen::cursor::FuncCursor;
 use cranelift_codegen::ir;
 use cranelift_codegen::ir::condcodes::*;
@@ -9,13 +9,15 @@ use cranelift_codegen::ir::types::*;
 use cranelift_codegen::ir::{AbiParam, ArgumentPurpose, Function, InstBuilder, Signature};
 use cranelift_codegen::isa::{self, TargetFrontendConfig};
 use cranelift_entity::EntityRef;
+use cranelift_frontend::FunctionBuilder;
 use cranelift_wasm::{
-    self, FuncIndex, GlobalIndex, GlobalVariable, MemoryIndex, SignatureIndex, TableElementType,
-    TableIndex, TargetEnvironment, WasmError, WasmResult,
+    self, FuncIndex, FuncTranslationState, GlobalIndex, GlobalVariable, MemoryIndex,
+    SignatureIndex, TableElementType, TableIndex, TargetEnvironment, WasmError, WasmResult,
 };
 #[cfg(feature = "lightbeam")]
 use cranelift_wasm::{DefinedFuncIndex, DefinedGlobalIndex, DefinedMemoryIndex, DefinedTableIndex};
 use std::convert::TryFrom;
+use wasmparser::Operator;
 
 /// Compute an `ir::ExternalName` for a given wasm function index.
 pub fn get_func_name(func_index: FuncIndex) -> ir::ExternalName {
@@ -195,6 +197,10 @@ pub struct FuncEnvironment<'module_environment> {
     pub(crate) offsets: VMOffsets,
 
     tunables: &'module_environment Tunables,
+
+    /// The fuel consumed by the operators translated since it was last added
+    /// to `VMInterrupts::fuel_consumed`.
+    fuel_pending: i64,
 }
 
 impl<'module_environment> FuncEnvironment<'module_environment> {
@@ -219,6 +225,7 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
             builtin_function_signatures,
             offsets: VMOffsets::new(target_config.pointer_bytes(), module),
             tunables,
+            fuel_pending: 0,
         }
     }
 
@@ -406,6 +413,77 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
 
         (base, func_addr)
     }
+
+    /// Returns the fuel that executing `op` consumes.
+    fn fuel_cost(op: &Operator) -> i64 {
+        match op {
+            // Operators which only affect control flow or generate no code
+            // are free, so that the fuel consumed tracks the work done.
+            Operator::Nop
+            | Operator::Drop
+            | Operator::Block { .. }
+            | Operator::Loop { .. }
+            | Operator::Else
+            | Operator::End
+            | Operator::Unreachable
+            | Operator::Return => 0,
+            _ => 1,
+        }
+    }
+
+    /// Returns whether `op` ends the straight-line code whose fuel is counted
+    /// in `fuel_pending`, in which case the fuel must be added to
+    /// `VMInterrupts::fuel_consumed` before translating it. Calls also end it,
+    /// but are handled separately since they check the fuel as well.
+    fn ends_fuel_run(op: &Operator) -> bool {
+        match op {
+            Operator::Block { .. }
+            | Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::Else
+            | Operator::End
+            | Operator::Br { .. }
+            | Operator::BrIf { .. }
+            | Operator::BrTable { .. }
+            | Operator::Return
+            | Operator::Unreachable => true,
+            _ => false,
+        }
+    }
+
+    /// Adds `fuel_pending` to `VMInterrupts::fuel_consumed` and returns the
+    /// new amount of fuel consumed.
+    fn flush_fuel(&mut self, pos: &mut FuncCursor<'_>) -> ir::Value {
+        let vmctx = self.vmctx(&mut pos.func);
+        let pointer_type = self.pointer_type();
+        let base = pos.ins().global_value(pointer_type, vmctx);
+        let offset = i32::try_from(self.offsets.vmctx_interrupts()).unwrap();
+        let interrupts_ptr = pos
+            .ins()
+            .load(pointer_type, ir::MemFlags::trusted(), base, offset);
+        let fuel_offset = i32::from(self.offsets.vminterrupts_fuel_consumed());
+        let consumed = pos
+            .ins()
+            .load(I64, ir::MemFlags::trusted(), interrupts_ptr, fuel_offset);
+        if self.fuel_pending == 0 {
+            return consumed;
+        }
+        let consumed = pos.ins().iadd_imm(consumed, self.fuel_pending);
+        pos.ins().store(
+            ir::MemFlags::trusted(),
+            consumed,
+            interrupts_ptr,
+            fuel_offset,
+        );
+        self.fuel_pending = 0;
+        consumed
+    }
+
+    /// Traps if the fuel `consumed` exceeds the fuel added to the store.
+    fn check_fuel(&mut self, pos: &mut FuncCursor<'_>, consumed: ir::Value) {
+        let out_of_fuel = pos.ins().icmp_imm(IntCC::SignedGreaterThan, consumed, 0);
+        pos.ins().trapnz(out_of_fuel, TRAP_OUT_OF_FUEL);
+    }
 }
 
 // TODO: This is necessary as if Lightbeam used `FuncEnvironment` directly it would cause
@@ -859,7 +937,8 @@ impl<'module_environment> cranelift_wasm::FuncEnvironment for FuncEnvironment<'m
         let table_entry_addr = pos.ins().table_addr(pointer_type, table, callee, 0);
 
         // Dereference table_entry_addr to get the function address.
-        let mem_flags = ir::MemFlags::trusted();
+        let mut mem_flags = ir::MemFlags::trusted();
+        mem_flags.set_table();
         let func_addr = pos.ins().load(
             pointer_type,
             mem_flags,
@@ -885,8 +964,10 @@ impl<'module_environment> cranelift_wasm::FuncEnvironment for FuncEnvironment<'m
                 mem_flags.set_readonly();
                 let caller_sig_id = pos.ins().load(sig_id_type, mem_flags, base, offset);
 
-                // Load the callee ID.
-                let mem_flags = ir::MemFlags::trusted();
+                // Load the callee ID. The table entry may be changed by a table
+                // store, so this isn't read-only.
+                let mut mem_flags = ir::MemFlags::trusted();
+                mem_flags.set_table();
                 let callee_sig_id = pos.ins().load(
                     sig_id_type,
                     mem_flags,
@@ -951,7 +1032,8 @@ impl<'module_environment> cranelift_wasm::FuncEnvironment for FuncEnvironment<'m
         let vmctx = self.vmctx(&mut pos.func);
         let base = pos.ins().global_value(pointer_type, vmctx);
 
-        let mem_flags = ir::MemFlags::trusted();
+        let mut mem_flags = ir::MemFlags::trusted();
+        mem_flags.set_vmctx();
 
         // Load the callee address.
         let body_offset =
@@ -1173,6 +1255,14 @@ impl<'module_environment> cranelift_wasm::FuncEnvironment for FuncEnvironment<'m
     }
 
     fn translate_loop_header(&mut self, mut pos: FuncCursor) -> WasmResult<()> {
+        if self.tunables.consume_fuel {
+            // Check the fuel at the start of each loop so that infinite loops
+            // run out of it. The fuel of the code before the loop has already
+            // been added when translating the `loop` operator.
+            let consumed = self.flush_fuel(&mut pos);
+            self.check_fuel(&mut pos, consumed);
+        }
+
         if !self.tunables.interruptable {
             return Ok(());
         }
@@ -1204,4 +1294,36 @@ impl<'module_environment> cranelift_wasm::FuncEnvironment for FuncEnvironment<'m
         pos.ins().trapnz(cmp, ir::TrapCode::Interrupt);
         Ok(())
     }
+
+    fn before_translate_operator(
+        &mut self,
+        op: &Operator,
+        builder: &mut FunctionBuilder,
+        state: &FuncTranslationState,
+    ) -> WasmResult<()> {
+        // Fuel is counted at compile time for each run of straight-line code,
+        // and added to `VMInterrupts::fuel_consumed` at the end of the run,
+        // checking whether it has run out before calls and at loop headers.
+        // The operator ending a run is counted in it, since the code after it
+        // may be unreachable.
+        if !self.tunables.consume_fuel || !state.reachable() {
+            return Ok(());
+        }
+        self.fuel_pending += Self::fuel_cost(op);
+        let is_call = match op {
+            Operator::Call { .. }
+            | Operator::CallIndirect { .. }
+            | Operator::ReturnCall { .. }
+            | Operator::ReturnCallIndirect { .. } => true,
+            _ => false,
+        };
+        if is_call || (self.fuel_pending != 0 && Self::ends_fuel_run(op)) {
+            let mut pos = builder.cursor();
+            let consumed = self.flush_fuel(&mut pos);
+            if is_call {
+                self.check_fuel(&mut pos, consumed);
+            }
+        }
+        Ok(())
+    }
 }
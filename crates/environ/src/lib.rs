@@ -44,7 +44,7 @@ pub use crate::address_map::{
     ModuleVmctxInfo, ValueLabelsRanges,
 };
 pub use crate::cache::create_new_config as cache_create_new_config;
-pub use crate::cache::CacheConfig;
+pub use crate::cache::{CacheConfig, ModuleCacheData, ModuleCacheDataTupleType};
 pub use crate::compilation::{
     Compilation, CompileError, CompiledFunction, Compiler, Relocation, RelocationTarget,
     Relocations, StackMapInformation, StackMaps, TrapInformation, Traps,
@@ -70,5 +70,9 @@ pub const WASM_PAGE_SIZE: u32 = 0x10000;
 /// The number of pages we can have before we run out of byte index space.
 pub const WASM_MAX_PAGES: u32 = 0x10000;
 
+/// The code of the traps raised when wasm code has consumed all the fuel added
+/// to its store.
+pub const TRAP_OUT_OF_FUEL: ir::TrapCode = ir::TrapCode::User(0);
+
 /// Version number of this crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
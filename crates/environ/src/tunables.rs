@@ -20,6 +20,17 @@ pub struct Tunables {
     /// calls and interrupts are implemented through the `VMInterrupts`
     /// structure, or `InterruptHandle` in the `wasmtime` crate.
     pub interruptable: bool,
+
+    /// Whether or not wasm code consumes fuel as it executes, trapping once it
+    /// has consumed all the fuel added to its store.
+    ///
+    /// Fuel is counted in `VMInterrupts::fuel_consumed`; see
+    /// `FuncEnvironment::before_translate_operator` for how it is consumed.
+    pub consume_fuel: bool,
+
+    /// Whether or not to inline direct calls to small functions when compiling
+    /// with Cranelift.
+    pub inlining: bool,
 }
 
 impl Default for Tunables {
@@ -53,6 +64,8 @@ impl Default for Tunables {
 
             debug_info: false,
             interruptable: false,
+            consume_fuel: false,
+            inlining: false,
         }
     }
 }
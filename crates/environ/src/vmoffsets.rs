@@ -254,6 +254,13 @@ impl VMOffsets {
     pub fn vminterrupts_stack_limit(&self) -> u8 {
         0
     }
+
+    /// Return the offset of the `fuel_consumed` field of `VMInterrupts`
+    pub fn vminterrupts_fuel_consumed(&self) -> u8 {
+        // The field is an `AtomicI64`, which is 8-byte aligned on all
+        // platforms.
+        8
+    }
 }
 
 /// Offsets for `VMCallerCheckedAnyfunc`.
@@ -14,8 +14,8 @@ use wasmtime_environ::isa::{TargetFrontendConfig, TargetIsa};
 use wasmtime_environ::wasm::{DefinedFuncIndex, DefinedMemoryIndex, MemoryIndex, SignatureIndex};
 use wasmtime_environ::{
     CacheConfig, CompileError, CompiledFunction, Compiler as _C, Module, ModuleAddressMap,
-    ModuleMemoryOffset, ModuleTranslation, ModuleVmctxInfo, Relocation, RelocationTarget,
-    Relocations, StackMaps, Traps, Tunables, VMOffsets, ValueLabelsRanges,
+    ModuleCacheDataTupleType, ModuleMemoryOffset, ModuleTranslation, ModuleVmctxInfo, Relocation,
+    RelocationTarget, Relocations, StackMaps, Traps, Tunables, VMOffsets, ValueLabelsRanges,
 };
 use wasmtime_runtime::{InstantiationError, VMFunctionBody, VMTrampoline};
 
@@ -158,23 +158,13 @@ impl Compiler {
         &self.tunables
     }
 
-    /// Compile the given function bodies.
-    pub(crate) fn compile<'data>(
+    /// Compile the given function bodies, without allocating them into
+    /// executable memory.
+    pub fn compile_functions(
         &self,
         translation: &ModuleTranslation,
-        debug_data: Option<DebugInfoData>,
-    ) -> Result<Compilation, SetupError> {
-        let mut code_memory = CodeMemory::new();
-
-        let (
-            compilation,
-            relocations,
-            address_transform,
-            value_ranges,
-            stack_slots,
-            traps,
-            stack_maps,
-        ) = match self.strategy {
+    ) -> Result<ModuleCacheDataTupleType, SetupError> {
+        match self.strategy {
             // For now, interpret `Auto` as `Cranelift` since that's the most stable
             // implementation.
             CompilationStrategy::Auto | CompilationStrategy::Cranelift => {
@@ -193,7 +183,29 @@ impl Compiler {
                 )
             }
         }
-        .map_err(SetupError::Compile)?;
+        .map_err(SetupError::Compile)
+    }
+
+    /// Allocate the given compiled function bodies, as returned by
+    /// `compile_functions` for `translation`, into executable memory, along
+    /// with trampolines for every signature.
+    pub(crate) fn load_functions(
+        &self,
+        translation: &ModuleTranslation,
+        debug_data: Option<DebugInfoData>,
+        functions: ModuleCacheDataTupleType,
+    ) -> Result<Compilation, SetupError> {
+        let mut code_memory = CodeMemory::new();
+
+        let (
+            compilation,
+            relocations,
+            address_transform,
+            value_ranges,
+            stack_slots,
+            traps,
+            stack_maps,
+        ) = functions;
 
         let dwarf_sections = if debug_data.is_some() && !compilation.is_empty() {
             transform_dwarf_data(
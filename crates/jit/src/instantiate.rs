@@ -10,6 +10,7 @@ use crate::link::link_module;
 use crate::resolver::Resolver;
 use std::any::Any;
 use std::collections::HashMap;
+use std::rc::Rc;
 use std::sync::Arc;
 use thiserror::Error;
 use wasmtime_debug::{read_debuginfo, write_debugsections_image, DwarfSection};
@@ -18,13 +19,14 @@ use wasmtime_environ::isa::TargetIsa;
 use wasmtime_environ::wasm::{DefinedFuncIndex, SignatureIndex};
 use wasmtime_environ::{
     CompileError, DataInitializer, DataInitializerLocation, Module, ModuleAddressMap,
-    ModuleEnvironment, ModuleTranslation, StackMaps, Traps,
+    ModuleCacheDataTupleType, ModuleEnvironment, ModuleTranslation, StackMaps, Traps,
 };
 use wasmtime_profiling::ProfilingAgent;
 use wasmtime_runtime::VMInterrupts;
 use wasmtime_runtime::{
-    GdbJitImageRegistration, InstanceHandle, InstantiationError, RuntimeMemoryCreator,
-    SignatureRegistry, StackMapRegistry, VMExternRefActivationsTable, VMFunctionBody, VMTrampoline,
+    GdbJitImageRegistration, InstanceHandle, InstantiationError, ResourceLimiter,
+    RuntimeMemoryCreator, SignatureRegistry, StackMapRegistry, VMExternRefActivationsTable,
+    VMFunctionBody, VMTrampoline,
 };
 
 /// An error condition while setting up a wasm instance, be it validation,
@@ -80,12 +82,46 @@ impl CompiledModule {
         data: &'data [u8],
         profiler: &dyn ProfilingAgent,
     ) -> Result<Self, SetupError> {
-        let environ = ModuleEnvironment::new(compiler.frontend_config(), compiler.tunables());
+        let translation = translate(compiler, data)?;
+        let functions = compiler.compile_functions(&translation)?;
+        Self::from_translation(compiler, data, translation, functions, profiler)
+    }
 
-        let translation = environ
-            .translate(data)
-            .map_err(|error| SetupError::Compile(CompileError::Wasm(error)))?;
+    /// Compile the functions of a data buffer without loading them, so that
+    /// they can be saved and later passed to `CompiledModule::from_precompiled`.
+    pub fn precompile(
+        compiler: &Compiler,
+        data: &[u8],
+    ) -> Result<ModuleCacheDataTupleType, SetupError> {
+        let translation = translate(compiler, data)?;
+        compiler.compile_functions(&translation)
+    }
 
+    /// Create a `CompiledModule` from a data buffer and the functions that
+    /// `CompiledModule::precompile` compiled from it, skipping compilation.
+    ///
+    /// # Unsafety
+    ///
+    /// The `functions` are loaded as machine code as they are, so they must
+    /// have been compiled from `data` by a compiler with the same settings as
+    /// `compiler`.
+    pub unsafe fn from_precompiled(
+        compiler: &Compiler,
+        data: &[u8],
+        functions: ModuleCacheDataTupleType,
+        profiler: &dyn ProfilingAgent,
+    ) -> Result<Self, SetupError> {
+        let translation = translate(compiler, data)?;
+        Self::from_translation(compiler, data, translation, functions, profiler)
+    }
+
+    fn from_translation<'data>(
+        compiler: &Compiler,
+        data: &'data [u8],
+        translation: ModuleTranslation<'data>,
+        functions: ModuleCacheDataTupleType,
+        profiler: &dyn ProfilingAgent,
+    ) -> Result<Self, SetupError> {
         let mut debug_data = None;
         if compiler.tunables().debug_info {
             // TODO Do we want to ignore invalid DWARF data?
@@ -102,7 +138,7 @@ impl CompiledModule {
             traps,
             stack_maps,
             address_transform,
-        } = compiler.compile(&translation, debug_data)?;
+        } = compiler.load_functions(&translation, debug_data, functions)?;
 
         let ModuleTranslation {
             module,
@@ -170,6 +206,7 @@ impl CompiledModule {
         resolver: &mut dyn Resolver,
         signature_registry: &mut SignatureRegistry,
         mem_creator: Option<&dyn RuntimeMemoryCreator>,
+        limiter: Option<Rc<dyn ResourceLimiter>>,
         interrupts: Arc<VMInterrupts>,
         host_state: Box<dyn Any>,
         externref_activations_table: *mut VMExternRefActivationsTable,
@@ -202,6 +239,7 @@ impl CompiledModule {
             trampolines,
             imports,
             mem_creator,
+            limiter,
             signatures.into_boxed_slice(),
             host_state,
             interrupts,
@@ -281,6 +319,16 @@ impl OwnedDataInitializer {
     }
 }
 
+fn translate<'data>(
+    compiler: &Compiler,
+    data: &'data [u8],
+) -> Result<ModuleTranslation<'data>, SetupError> {
+    let environ = ModuleEnvironment::new(compiler.frontend_config(), compiler.tunables());
+    environ
+        .translate(data)
+        .map_err(|error| SetupError::Compile(CompileError::Wasm(error)))
+}
+
 fn create_dbg_image(
     dwarf_sections: Vec<DwarfSection>,
     isa: &dyn TargetIsa,
@@ -21,6 +21,7 @@ use std::any::Any;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::rc::Rc;
 use std::sync::Arc;
 use std::{mem, ptr, slice};
 use thiserror::Error;
@@ -29,7 +30,27 @@ use wasmtime_environ::wasm::{
     DataIndex, DefinedFuncIndex, DefinedGlobalIndex, DefinedMemoryIndex, DefinedTableIndex,
     ElemIndex, FuncIndex, GlobalIndex, GlobalInit, MemoryIndex, SignatureIndex, TableIndex,
 };
-use wasmtime_environ::{ir, DataInitializer, EntityIndex, Module, TableElements, VMOffsets};
+use wasmtime_environ::{
+    ir, DataInitializer, EntityIndex, Module, TableElements, VMOffsets, WASM_PAGE_SIZE,
+};
+
+/// Decides whether the linear memories and tables of instances may grow.
+///
+/// This is consulted for the initial sizes of memories and tables when an
+/// instance is created, and then every time they grow.
+pub trait ResourceLimiter {
+    /// Returns whether a linear memory may grow from `current` to `desired`
+    /// bytes.
+    fn memory_growing(&self, current: usize, desired: usize) -> bool;
+
+    /// Called when a linear memory which `memory_growing` allowed to grow
+    /// from `current` to `desired` bytes stays at `current` bytes after all,
+    /// because growing or creating it failed.
+    fn memory_grow_failed(&self, _current: usize, _desired: usize) {}
+
+    /// Returns whether a table may grow from `current` to `desired` elements.
+    fn table_growing(&self, current: u32, desired: u32) -> bool;
+}
 
 /// A WebAssembly instance.
 ///
@@ -73,6 +94,9 @@ pub(crate) struct Instance {
     /// interrupted.
     pub(crate) interrupts: Arc<VMInterrupts>,
 
+    /// The limiter deciding whether memories and tables may grow, if any.
+    limiter: Option<Rc<dyn ResourceLimiter>>,
+
     /// Additional context used by compiled wasm code. This field is last, and
     /// represents a dynamically-sized array that extends beyond the nominal
     /// end of the struct (similar to a flexible array member).
@@ -393,11 +417,22 @@ impl Instance {
     /// Returns `None` if memory can't be grown by the specified amount
     /// of pages.
     pub(crate) fn memory_grow(&self, memory_index: DefinedMemoryIndex, delta: u32) -> Option<u32> {
-        let result = self
+        let memory = self
             .memories
             .get(memory_index)
-            .unwrap_or_else(|| panic!("no memory for index {}", memory_index.index()))
-            .grow(delta);
+            .unwrap_or_else(|| panic!("no memory for index {}", memory_index.index()));
+        let page_size = WASM_PAGE_SIZE as usize;
+        let current = memory.size() as usize * page_size;
+        let desired = (memory.size() as usize + delta as usize) * page_size;
+        if let Some(limiter) = &self.limiter {
+            if !limiter.memory_growing(current, desired) {
+                return None;
+            }
+        }
+        let result = memory.grow(delta);
+        if let (None, Some(limiter)) = (result, &self.limiter) {
+            limiter.memory_grow_failed(current, desired);
+        }
 
         // Keep current the VMContext pointers used by compiled wasm code.
         self.set_memory(memory_index, self.memories[memory_index].vmmemory());
@@ -471,11 +506,17 @@ impl Instance {
         init_value: TableElement,
     ) -> Option<u32> {
         unsafe {
-            let orig_size = self
+            let table = self
                 .tables
                 .get(table_index)
-                .unwrap_or_else(|| panic!("no table for index {}", table_index.index()))
-                .grow(delta, init_value)?;
+                .unwrap_or_else(|| panic!("no table for index {}", table_index.index()));
+            if let Some(limiter) = &self.limiter {
+                let desired = table.size().checked_add(delta)?;
+                if !limiter.table_growing(table.size(), desired) {
+                    return None;
+                }
+            }
+            let orig_size = table.grow(delta, init_value)?;
 
             // Keep the `VMContext` pointers used by compiled Wasm code up to
             // date.
@@ -820,6 +861,9 @@ impl InstanceHandle {
     /// It is your responsibility to ensure that the given raw
     /// `externref_activations_table` and `stack_map_registry` outlive this
     /// instance.
+    ///
+    /// The `limiter`, if any, must allow the initial sizes of the memories and
+    /// tables of the module, and then decides whether they may grow.
     pub unsafe fn new(
         module: Arc<Module>,
         code: Arc<dyn Any>,
@@ -827,6 +871,7 @@ impl InstanceHandle {
         trampolines: HashMap<VMSharedSignatureIndex, VMTrampoline>,
         imports: Imports,
         mem_creator: Option<&dyn RuntimeMemoryCreator>,
+        limiter: Option<Rc<dyn ResourceLimiter>>,
         vmshared_signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
         host_state: Box<dyn Any>,
         interrupts: Arc<VMInterrupts>,
@@ -836,8 +881,17 @@ impl InstanceHandle {
         debug_assert!(!externref_activations_table.is_null());
         debug_assert!(!stack_map_registry.is_null());
 
+        if let Some(limiter) = &limiter {
+            check_initial_sizes(&module, &**limiter)?;
+        }
         let tables = create_tables(&module);
-        let memories = create_memories(&module, mem_creator.unwrap_or(&DefaultMemoryCreator {}))?;
+        let memories = create_memories(&module, mem_creator.unwrap_or(&DefaultMemoryCreator {}))
+            .map_err(|e| {
+                if let Some(limiter) = &limiter {
+                    release_initial_memory_sizes(&module, &**limiter, usize::max_value());
+                }
+                e
+            })?;
 
         let vmctx_tables = tables
             .values()
@@ -870,6 +924,7 @@ impl InstanceHandle {
                 trampolines,
                 host_state,
                 interrupts,
+                limiter,
                 vmctx: VMContext {},
             };
             let layout = instance.alloc_layout();
@@ -1201,6 +1256,49 @@ fn check_memory_init_bounds(
     Ok(())
 }
 
+/// Checks that `limiter` allows the initial sizes of the memories and tables
+/// defined by `module`.
+fn check_initial_sizes(
+    module: &Module,
+    limiter: &dyn ResourceLimiter,
+) -> Result<(), InstantiationError> {
+    let num_imports = module.local.num_imported_tables;
+    for plan in &module.local.table_plans.values().as_slice()[num_imports..] {
+        if !limiter.table_growing(0, plan.table.minimum) {
+            return Err(InstantiationError::Resource(format!(
+                "table minimum size of {} elements exceeds the limit",
+                plan.table.minimum
+            )));
+        }
+    }
+    for (allowed, bytes) in initial_memory_sizes(module).enumerate() {
+        if !limiter.memory_growing(0, bytes) {
+            release_initial_memory_sizes(module, limiter, allowed);
+            return Err(InstantiationError::Resource(format!(
+                "memory minimum size of {} bytes exceeds the limit",
+                bytes
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Tells `limiter` that the first `count` memories defined by `module`, which
+/// it allowed, weren't created after all.
+fn release_initial_memory_sizes(module: &Module, limiter: &dyn ResourceLimiter, count: usize) {
+    for bytes in initial_memory_sizes(module).take(count) {
+        limiter.memory_grow_failed(0, bytes);
+    }
+}
+
+/// The initial sizes in bytes of the memories defined by `module`.
+fn initial_memory_sizes(module: &Module) -> impl Iterator<Item = usize> + '_ {
+    let num_imports = module.local.num_imported_memories;
+    module.local.memory_plans.values().as_slice()[num_imports..]
+        .iter()
+        .map(|plan| plan.memory.minimum as usize * WASM_PAGE_SIZE as usize)
+}
+
 /// Allocate memory for just the tables of the current module.
 fn create_tables(module: &Module) -> BoxedSlice<DefinedTableIndex, Table> {
     let num_imports = module.local.num_imported_tables;
@@ -3,7 +3,7 @@
 
 use crate::instance::Instance;
 use std::any::Any;
-use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering::SeqCst};
 use std::{ptr, u32};
 use wasmtime_environ::BuiltinFunctionIndex;
 
@@ -618,6 +618,13 @@ pub struct VMInterrupts {
     /// This is used to control both stack overflow as well as interrupting wasm
     /// modules. For more information see `crates/environ/src/cranelift.rs`.
     pub stack_limit: AtomicUsize,
+
+    /// The amount of fuel consumed by wasm code, offset by the fuel added to
+    /// its store so that it starts out negative.
+    ///
+    /// Code compiled to consume fuel traps when this becomes positive. For
+    /// more information see `Config::consume_fuel` in the `wasmtime` crate.
+    pub fuel_consumed: AtomicI64,
 }
 
 impl VMInterrupts {
@@ -632,6 +639,7 @@ impl Default for VMInterrupts {
     fn default() -> VMInterrupts {
         VMInterrupts {
             stack_limit: AtomicUsize::new(usize::max_value()),
+            fuel_consumed: AtomicI64::new(0),
         }
     }
 }
@@ -651,6 +659,10 @@ mod test_vminterrupts {
             offset_of!(VMInterrupts, stack_limit),
             usize::from(offsets.vminterrupts_stack_limit())
         );
+        assert_eq!(
+            offset_of!(VMInterrupts, fuel_consumed),
+            usize::from(offsets.vminterrupts_fuel_consumed())
+        );
     }
 }
 
@@ -1,18 +1,23 @@
 use crate::entry::{Entry, EntryHandle};
+use crate::exit::{SignalAction, SignalExit, SignalHandler};
 use crate::fdpool::FdPool;
 use crate::handle::Handle;
+use crate::journal::{Journal, Journaled};
+use crate::quota::{IoLimits, IoUsage, Quota};
 use crate::sys::osdir::OsDir;
 use crate::sys::stdio::NullDevice;
 use crate::sys::stdio::{Stderr, StderrExt, Stdin, StdinExt, Stdout, StdoutExt};
+use crate::trace::Tracer;
 use crate::virtfs::{VirtualDir, VirtualDirEntry};
 use crate::wasi::types;
 use crate::wasi::{Errno, Result};
 use std::borrow::Borrow;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
-use std::convert::TryFrom;
+use std::convert::{TryFrom, TryInto};
 use std::ffi::{self, CString, OsString};
 use std::fs::File;
+use std::ops::DerefMut;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::{env, io, string};
@@ -131,6 +136,10 @@ pub struct WasiCtxBuilder {
     preopens: Option<Vec<(PathBuf, PendingPreopen)>>,
     args: Option<Vec<PendingCString>>,
     env: Option<HashMap<PendingCString, PendingCString>>,
+    limits: IoLimits,
+    tracer: Option<Tracer>,
+    journal: Option<Rc<Journal>>,
+    signal_handler: Option<SignalHandler>,
 }
 
 impl WasiCtxBuilder {
@@ -147,6 +156,10 @@ impl WasiCtxBuilder {
             preopens: Some(Vec::new()),
             args: Some(Vec::new()),
             env: Some(HashMap::new()),
+            limits: IoLimits::default(),
+            tracer: None,
+            journal: None,
+            signal_handler: None,
         }
     }
 
@@ -284,27 +297,10 @@ impl WasiCtxBuilder {
         dir: VirtualDirEntry,
         guest_path: P,
     ) -> &mut Self {
-        fn populate_directory(virtentry: HashMap<String, VirtualDirEntry>, dir: &mut VirtualDir) {
-            for (path, entry) in virtentry.into_iter() {
-                match entry {
-                    VirtualDirEntry::Directory(dir_entries) => {
-                        let mut subdir = VirtualDir::new(true);
-                        populate_directory(dir_entries, &mut subdir);
-                        dir.add_dir(subdir, path);
-                    }
-                    VirtualDirEntry::File(content) => {
-                        dir.add_file(content, path);
-                    }
-                }
-            }
-        }
-
         let guest_path_owned = guest_path.as_ref().to_owned();
         let preopen = PendingPreopen::new(move || {
             if let VirtualDirEntry::Directory(entries) = dir {
-                let mut dir = VirtualDir::new(true);
-                populate_directory(entries, &mut dir);
-                Ok(Box::new(dir))
+                Ok(Box::new(VirtualDir::from_entries(entries, true)))
             } else {
                 Err(WasiCtxBuilderError::VirtualDirEntryRootNotADirectory(
                     guest_path_owned,
@@ -318,6 +314,73 @@ impl WasiCtxBuilder {
         self
     }
 
+    /// Add a preopened, already constructed `VirtualDir`, such as one mounting an archive with
+    /// `VirtualDir::from_tar`.
+    pub fn preopened_virt_dir<P: AsRef<Path>>(
+        &mut self,
+        dir: VirtualDir,
+        guest_path: P,
+    ) -> &mut Self {
+        let preopen = PendingPreopen::new(move || Ok(Box::new(dir)));
+        self.preopens
+            .as_mut()
+            .unwrap()
+            .push((guest_path.as_ref().to_owned(), preopen));
+        self
+    }
+
+    /// Limit the number of bytes the guest may write to, or allocate in, regular files.
+    ///
+    /// Once the limit is reached, `fd_write`, `fd_pwrite` and `fd_allocate` fail with
+    /// `Errno::Dquot`.
+    pub fn max_bytes_written(&mut self, max: u64) -> &mut Self {
+        self.limits.max_bytes_written = Some(max);
+        self
+    }
+
+    /// Limit the number of files and directories the guest may create.
+    ///
+    /// Once the limit is reached, `path_open` with `O_CREAT` and `path_create_directory`
+    /// fail with `Errno::Dquot`.
+    pub fn max_files_created(&mut self, max: u64) -> &mut Self {
+        self.limits.max_files_created = Some(max);
+        self
+    }
+
+    /// Limit the number of descriptors, including stdio and preopens, that may be open at
+    /// any one time.
+    ///
+    /// Once the limit is reached, `path_open` fails with `Errno::Nfile`.
+    pub fn max_open_fds(&mut self, max: u32) -> &mut Self {
+        self.limits.max_open_fds = Some(max);
+        self
+    }
+
+    /// Trace every hostcall made through the built `WasiCtx` with `tracer`.
+    pub fn trace(&mut self, tracer: Tracer) -> &mut Self {
+        self.tracer = Some(tracer);
+        self
+    }
+
+    /// Record the nondeterministic inputs of the guest to, or replay them from, `journal`.
+    ///
+    /// Only `wasi_snapshot_preview1` hostcalls are journaled.
+    pub fn journal(&mut self, journal: Rc<Journal>) -> &mut Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Decide what `proc_raise` does with each signal using `handler`.
+    ///
+    /// Without a handler, signals get their POSIX default action, see `SignalAction::default_for`.
+    pub fn signal_handler<F>(&mut self, handler: F) -> &mut Self
+    where
+        F: Fn(types::Signal) -> SignalAction + 'static,
+    {
+        self.signal_handler = Some(SignalHandler::new(handler));
+        self
+    }
+
     /// Build a `WasiCtx`, consuming this `WasiCtxBuilder`.
     ///
     /// If any of the arguments or environment variables in this builder cannot be converted into
@@ -393,6 +456,11 @@ impl WasiCtxBuilder {
             args,
             env,
             entries: RefCell::new(entries),
+            quota: Quota::new(self.limits),
+            tracer: self.tracer.take(),
+            journal: self.journal.take(),
+            signal_handler: self.signal_handler.take(),
+            termination: Cell::new(None),
         })
     }
 }
@@ -410,6 +478,10 @@ impl EntryTable {
         }
     }
 
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
     fn contains(&self, fd: &types::Fd) -> bool {
         self.entries.contains_key(fd)
     }
@@ -437,6 +509,11 @@ impl EntryTable {
 
 pub struct WasiCtx {
     entries: RefCell<EntryTable>,
+    pub(crate) quota: Quota,
+    tracer: Option<Tracer>,
+    journal: Option<Rc<Journal>>,
+    signal_handler: Option<SignalHandler>,
+    termination: Cell<Option<SignalExit>>,
     pub(crate) args: Vec<CString>,
     pub(crate) env: Vec<CString>,
 }
@@ -457,6 +534,90 @@ impl WasiCtx {
             .build()
     }
 
+    /// Get a snapshot of the I/O counters accumulated by this `WasiCtx`.
+    ///
+    /// This can be used by embedders to bill guests for the resources they consumed.
+    pub fn io_usage(&self) -> IoUsage {
+        let open_fds = self.entries.borrow().len();
+        self.quota
+            .usage(open_fds.try_into().unwrap_or(u32::max_value()))
+    }
+
+    /// Get the `Tracer` hostcalls made through this `WasiCtx` should be reported to, if any.
+    pub fn tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_ref()
+    }
+
+    /// Get what `proc_raise` should do with `signal`.
+    pub fn signal_action(&self, signal: types::Signal) -> SignalAction {
+        SignalHandler::action(self.signal_handler.as_ref(), signal)
+    }
+
+    /// Take the termination of the guest requested by a signal raised with `proc_raise`, if any.
+    ///
+    /// Unwinding the guest requires runtime-specific logic, so `proc_raise` only records that
+    /// the guest is to be terminated, and fails with ``Errno::Intr``. Runtimes are expected to check
+    /// for it once the hostcall returns, and to trap with the `SignalExit`.
+    pub fn take_termination(&self) -> Option<SignalExit> {
+        self.termination.take()
+    }
+
+    /// Request the termination of the guest; see `WasiCtx::take_termination`.
+    pub(crate) fn terminate(&self, exit: SignalExit) {
+        self.termination.set(Some(exit))
+    }
+
+    /// Run `hostcall` through the journal, if any, returning its result.
+    pub(crate) fn journaled<T: Journaled>(
+        &self,
+        hostcall: &str,
+        f: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        match &self.journal {
+            Some(journal) => journal.value(hostcall, f),
+            None => f(),
+        }
+    }
+
+    /// Run `hostcall`, which fills the guest buffers `bufs` using `read`, through the journal,
+    /// if any, returning the number of bytes read.
+    pub(crate) fn journaled_read<B: DerefMut<Target = [u8]>>(
+        &self,
+        hostcall: &str,
+        bufs: &mut [B],
+        read: impl FnOnce(&mut [B]) -> Result<usize>,
+    ) -> Result<usize> {
+        let journal = match &self.journal {
+            Some(journal) => journal,
+            None => return read(bufs),
+        };
+        let data = journal.value(hostcall, || {
+            let len = read(bufs)?;
+            Ok(Journal::gather(bufs, len))
+        })?;
+        journal.scatter(hostcall, &data, bufs)
+    }
+
+    /// Run `hostcall`, which fills all of the guest buffers `bufs` using `fill`, through the
+    /// journal, if any.
+    pub(crate) fn journaled_fill<B: DerefMut<Target = [u8]>>(
+        &self,
+        hostcall: &str,
+        bufs: &mut [B],
+        fill: impl FnOnce(&mut [B]) -> Result<()>,
+    ) -> Result<()> {
+        let journal = match &self.journal {
+            Some(journal) => journal,
+            None => return fill(bufs),
+        };
+        let data = journal.value(hostcall, || {
+            fill(bufs)?;
+            let len = bufs.iter().map(|buf| buf.len()).sum();
+            Ok(Journal::gather(bufs, len))
+        })?;
+        journal.fill(hostcall, &data, bufs)
+    }
+
     /// Check if `WasiCtx` contains the specified raw WASI `fd`.
     pub(crate) fn contains_entry(&self, fd: types::Fd) -> bool {
         self.entries.borrow().contains(&fd)
@@ -470,12 +631,21 @@ impl WasiCtx {
         }
     }
 
+    /// Check that another descriptor can be opened without exceeding the open descriptors limit.
+    pub(crate) fn check_open(&self) -> Result<()> {
+        self.quota.check_open(self.entries.borrow().len())
+    }
+
     /// Insert the specified `Entry` into the `WasiCtx` object.
     ///
     /// The `Entry` will automatically get another free raw WASI `fd` assigned. Note that
     /// the two subsequent free raw WASI `fd`s do not have to be stored contiguously.
+    ///
+    /// Fails with `Errno::Nfile` if the open descriptors limit has been reached.
     pub(crate) fn insert_entry(&self, entry: Entry) -> Result<types::Fd> {
-        self.entries.borrow_mut().insert(entry).ok_or(Errno::Mfile)
+        let mut entries = self.entries.borrow_mut();
+        self.quota.check_open(entries.len())?;
+        entries.insert(entry).ok_or(Errno::Mfile)
     }
 
     /// Insert the specified `Entry` with the specified raw WASI `fd` key into the `WasiCtx`
@@ -0,0 +1,162 @@
+//! Termination of a guest through `proc_exit` and `proc_raise`.
+//!
+//! Both hostcalls unwind the guest's stack, which requires runtime-specific
+//! logic. Runtimes report such terminations with the error types defined here,
+//! so that embedders can tell them apart from traps; `wasmtime-wasi` unwinds
+//! with a `Trap` carrying them, which `wasmtime_wasi::i32_exit` and
+//! `wasmtime_wasi::exit_status` look for.
+use crate::wasi::types::Signal;
+use std::fmt;
+
+/// Error reported when a guest exits through `proc_exit` with an `i32` status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct I32Exit(pub i32);
+
+impl fmt::Display for I32Exit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Exited with i32 exit status {}", self.0)
+    }
+}
+
+impl std::error::Error for I32Exit {}
+
+/// Error reported when a guest is terminated by a signal raised with `proc_raise`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalExit(pub Signal);
+
+impl SignalExit {
+    /// The conventional number of the signal.
+    ///
+    /// Signal numbers vary between hosts, so the Linux numbering is used
+    /// everywhere to keep exit statuses identical on every host.
+    pub fn signal_number(&self) -> i32 {
+        match self.0 {
+            Signal::None => 0,
+            Signal::Hup => 1,
+            Signal::Int => 2,
+            Signal::Quit => 3,
+            Signal::Ill => 4,
+            Signal::Trap => 5,
+            Signal::Abrt => 6,
+            Signal::Bus => 7,
+            Signal::Fpe => 8,
+            Signal::Kill => 9,
+            Signal::Usr1 => 10,
+            Signal::Segv => 11,
+            Signal::Usr2 => 12,
+            Signal::Pipe => 13,
+            Signal::Alrm => 14,
+            Signal::Term => 15,
+            Signal::Chld => 17,
+            Signal::Cont => 18,
+            Signal::Stop => 19,
+            Signal::Tstp => 20,
+            Signal::Ttin => 21,
+            Signal::Ttou => 22,
+            Signal::Urg => 23,
+            Signal::Xcpu => 24,
+            Signal::Xfsz => 25,
+            Signal::Vtalrm => 26,
+            Signal::Prof => 27,
+            Signal::Winch => 28,
+            Signal::Poll => 29,
+            Signal::Pwr => 30,
+            Signal::Sys => 31,
+        }
+    }
+
+    /// The exit status a shell reports for a native program killed by this
+    /// signal, i.e. `128 + signal_number()`.
+    pub fn exit_status(&self) -> i32 {
+        128 + self.signal_number()
+    }
+}
+
+impl fmt::Display for SignalExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Terminated by signal {}", self.0)
+    }
+}
+
+impl std::error::Error for SignalExit {}
+
+/// What `proc_raise` does with a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalAction {
+    /// Return to the guest as if nothing happened.
+    Ignore,
+    /// Terminate the guest with a `SignalExit`.
+    Terminate,
+}
+
+impl SignalAction {
+    /// The POSIX default action of `signal`.
+    ///
+    /// Stopping a guest isn't supported, so the job control signals are ignored.
+    pub fn default_for(signal: Signal) -> Self {
+        match signal {
+            Signal::None
+            | Signal::Chld
+            | Signal::Cont
+            | Signal::Urg
+            | Signal::Winch
+            | Signal::Stop
+            | Signal::Tstp
+            | Signal::Ttin
+            | Signal::Ttou => Self::Ignore,
+            _ => Self::Terminate,
+        }
+    }
+}
+
+/// Decides what `proc_raise` does with a signal; see `WasiCtxBuilder::signal_handler`.
+pub(crate) struct SignalHandler(Box<dyn Fn(Signal) -> SignalAction>);
+
+impl SignalHandler {
+    pub(crate) fn new<F: Fn(Signal) -> SignalAction + 'static>(handler: F) -> Self {
+        Self(Box::new(handler))
+    }
+
+    /// Get what to do with `signal`, falling back to its default action if there's no handler.
+    pub(crate) fn action(handler: Option<&Self>, signal: Signal) -> SignalAction {
+        match handler {
+            Some(handler) => (handler.0)(signal),
+            None => SignalAction::default_for(signal),
+        }
+    }
+}
+
+impl fmt::Debug for SignalHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignalHandler").finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SignalAction, SignalExit};
+    use crate::wasi::types::Signal;
+
+    #[test]
+    fn exit_status() {
+        assert_eq!(SignalExit(Signal::Abrt).exit_status(), 134);
+        assert_eq!(SignalExit(Signal::Kill).exit_status(), 137);
+        assert_eq!(SignalExit(Signal::Term).exit_status(), 143);
+    }
+
+    #[test]
+    fn default_action() {
+        assert_eq!(
+            SignalAction::default_for(Signal::Int),
+            SignalAction::Terminate
+        );
+        assert_eq!(
+            SignalAction::default_for(Signal::Chld),
+            SignalAction::Ignore
+        );
+        assert_eq!(
+            SignalAction::default_for(Signal::None),
+            SignalAction::Ignore
+        );
+    }
+}
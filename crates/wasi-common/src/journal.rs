@@ -0,0 +1,601 @@
+//! Recording and replaying of the nondeterministic inputs a guest receives.
+//!
+//! A `Journal` in record mode logs the results of every hostcall whose outcome
+//! depends on the host rather than on the guest: clock reads, random bytes,
+//! filesystem operations, data read from files and stdin, and `poll_oneoff`
+//! events. A `Journal` in replay mode serves those results back from the log
+//! without reaching the host at all, so that a run can be reproduced exactly,
+//! even where the files it used are gone. Only the hostcalls which don't leave
+//! the `WasiCtx`, such as `args_get` or `fd_renumber`, run as usual.
+//!
+//! The log is a text file with one hostcall per line:
+//!
+//! ```text
+//! <hostcall> <errno> <payload>
+//! ```
+//!
+//! where `errno` is `0` on success, in which case `payload` encodes the
+//! result, and the raw errno value returned to the guest otherwise.
+use crate::handle::{Handle, HandleRights};
+use crate::wasi::types;
+use crate::wasi::{Errno, Result};
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::ops::{Deref, DerefMut};
+
+/// A hostcall result which can be stored in a `Journal`.
+pub(crate) trait Journaled: Sized {
+    fn encode(&self) -> String;
+    fn decode(payload: &str) -> Option<Self>;
+}
+
+impl Journaled for () {
+    fn encode(&self) -> String {
+        String::new()
+    }
+
+    fn decode(payload: &str) -> Option<Self> {
+        if payload.is_empty() {
+            Some(())
+        } else {
+            None
+        }
+    }
+}
+
+impl Journaled for u64 {
+    fn encode(&self) -> String {
+        self.to_string()
+    }
+
+    fn decode(payload: &str) -> Option<Self> {
+        payload.parse().ok()
+    }
+}
+
+impl Journaled for Vec<u8> {
+    fn encode(&self) -> String {
+        let mut hex = String::with_capacity(self.len() * 2);
+        for byte in self {
+            let _ = write!(hex, "{:02x}", byte);
+        }
+        hex
+    }
+
+    fn decode(payload: &str) -> Option<Self> {
+        if payload.len() % 2 != 0 {
+            return None;
+        }
+        (0..payload.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(payload.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
+}
+
+impl Journaled for types::Fdflags {
+    fn encode(&self) -> String {
+        u16::from(*self).to_string()
+    }
+
+    fn decode(payload: &str) -> Option<Self> {
+        Self::try_from(payload.parse::<u16>().ok()?).ok()
+    }
+}
+
+impl Journaled for types::Filestat {
+    fn encode(&self) -> String {
+        format!(
+            "{}:{}:{}:{}:{}:{}:{}:{}",
+            self.dev,
+            self.ino,
+            u8::from(self.filetype),
+            self.nlink,
+            self.size,
+            self.atim,
+            self.mtim,
+            self.ctim,
+        )
+    }
+
+    fn decode(payload: &str) -> Option<Self> {
+        let mut fields = payload.split(':');
+        let mut next = || fields.next()?.parse::<u64>().ok();
+        let dev = next()?;
+        let ino = next()?;
+        let filetype = types::Filetype::try_from(u8::try_from(next()?).ok()?).ok()?;
+        Some(Self {
+            dev,
+            ino,
+            filetype,
+            nlink: next()?,
+            size: next()?,
+            atim: next()?,
+            mtim: next()?,
+            ctim: next()?,
+        })
+    }
+}
+
+/// The type and rights of a descriptor opened by `path_open`.
+impl Journaled for (types::Filetype, HandleRights) {
+    fn encode(&self) -> String {
+        let (file_type, rights) = self;
+        format!(
+            "{}:{}:{}",
+            u8::from(*file_type),
+            u64::from(rights.base),
+            u64::from(rights.inheriting)
+        )
+    }
+
+    fn decode(payload: &str) -> Option<Self> {
+        let mut fields = payload.split(':');
+        let mut next = || fields.next()?.parse::<u64>().ok();
+        let file_type = types::Filetype::try_from(u8::try_from(next()?).ok()?).ok()?;
+        let base = types::Rights::try_from(next()?).ok()?;
+        let inheriting = types::Rights::try_from(next()?).ok()?;
+        Some((file_type, HandleRights::new(base, inheriting)))
+    }
+}
+
+impl Journaled for Vec<types::Event> {
+    fn encode(&self) -> String {
+        let events: Vec<String> = self
+            .iter()
+            .map(|event| {
+                format!(
+                    "{}:{}:{}:{}:{}",
+                    event.userdata,
+                    u16::from(event.error),
+                    u8::from(event.type_),
+                    event.fd_readwrite.nbytes,
+                    u16::from(event.fd_readwrite.flags),
+                )
+            })
+            .collect();
+        events.join(",")
+    }
+
+    fn decode(payload: &str) -> Option<Self> {
+        if payload.is_empty() {
+            return Some(Vec::new());
+        }
+        payload
+            .split(',')
+            .map(|event| {
+                let mut fields = event.split(':');
+                let mut next = || fields.next()?.parse::<u64>().ok();
+                let userdata = next()?;
+                let error = Errno::try_from(u16::try_from(next()?).ok()?).ok()?;
+                let type_ = types::Eventtype::try_from(u8::try_from(next()?).ok()?).ok()?;
+                let nbytes = next()?;
+                let flags = types::Eventrwflags::try_from(u16::try_from(next()?).ok()?).ok()?;
+                Some(types::Event {
+                    userdata,
+                    error,
+                    type_,
+                    fd_readwrite: types::EventFdReadwrite { nbytes, flags },
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct Record {
+    hostcall: String,
+    errno: u16,
+    payload: String,
+}
+
+enum Mode {
+    Record(RefCell<Box<dyn Write>>),
+    Replay(RefCell<VecDeque<Record>>),
+}
+
+/// A log of the nondeterministic inputs received by a guest, either being
+/// recorded or replayed.
+///
+/// A `Journal` is attached to a context with `WasiCtxBuilder::journal`. The
+/// embedder keeps a reference to it to check, once the guest is done, whether
+/// a replayed run diverged from the recorded one with `Journal::divergence`.
+pub struct Journal {
+    mode: Mode,
+    divergence: RefCell<Option<String>>,
+    position: Cell<usize>,
+}
+
+impl Journal {
+    /// Create a `Journal` recording to `out`.
+    ///
+    /// Records are buffered, so `Journal::flush` should be called once the guest is done, since
+    /// a guest exiting through `proc_exit` typically ends the process without dropping anything.
+    pub fn record<W: Write + 'static>(out: W) -> Self {
+        Self::new(Mode::Record(RefCell::new(Box::new(BufWriter::new(out)))))
+    }
+
+    /// Create a `Journal` replaying the log previously recorded to `input`.
+    pub fn replay<R: BufRead>(input: R) -> io::Result<Self> {
+        let mut records = VecDeque::new();
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+            let mut parts = line.splitn(3, ' ');
+            let hostcall = parts.next().unwrap_or("").to_owned();
+            let errno = parts.next().and_then(|errno| errno.parse().ok());
+            let payload = parts.next().unwrap_or("").to_owned();
+            match errno {
+                Some(errno) if !hostcall.is_empty() => records.push_back(Record {
+                    hostcall,
+                    errno,
+                    payload,
+                }),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("malformed journal entry on line {}", i + 1),
+                    ))
+                }
+            }
+        }
+        Ok(Self::new(Mode::Replay(RefCell::new(records))))
+    }
+
+    fn new(mode: Mode) -> Self {
+        Self {
+            mode,
+            divergence: RefCell::new(None),
+            position: Cell::new(0),
+        }
+    }
+
+    /// Whether this `Journal` serves hostcall results from a log rather than from the host.
+    pub fn is_replaying(&self) -> bool {
+        match self.mode {
+            Mode::Record(_) => false,
+            Mode::Replay(_) => true,
+        }
+    }
+
+    /// Write out the records buffered so far.
+    pub fn flush(&self) -> io::Result<()> {
+        match &self.mode {
+            Mode::Record(out) => out.borrow_mut().flush(),
+            Mode::Replay(_) => Ok(()),
+        }
+    }
+
+    /// Describe how the replayed guest diverged from the recorded call sequence, if it did.
+    ///
+    /// This includes the case of a guest finishing before all recorded hostcalls were replayed,
+    /// so it's meant to be called once the guest has exited.
+    pub fn divergence(&self) -> Option<String> {
+        if let Some(divergence) = self.divergence.borrow().as_ref() {
+            return Some(divergence.clone());
+        }
+        match &self.mode {
+            Mode::Replay(records) => records.borrow().front().map(|record| {
+                format!(
+                    "guest exited before hostcall #{} (`{}`) was replayed",
+                    self.position.get(),
+                    record.hostcall
+                )
+            }),
+            Mode::Record(_) => None,
+        }
+    }
+
+    /// Run `hostcall` through the journal: when recording, `f` is called and its result logged;
+    /// when replaying, the logged result is returned without calling `f`.
+    pub(crate) fn value<T: Journaled>(
+        &self,
+        hostcall: &str,
+        f: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let position = self.position.get();
+        self.position.set(position + 1);
+        match &self.mode {
+            Mode::Record(out) => {
+                let res = f();
+                let line = match &res {
+                    Ok(value) => format!("{} 0 {}", hostcall, value.encode()),
+                    Err(errno) => format!("{} {}", hostcall, u16::from(*errno)),
+                };
+                if let Err(err) = writeln!(out.borrow_mut(), "{}", line) {
+                    log::error!("failed to record hostcall `{}`: {}", hostcall, err);
+                    return Err(Errno::Io);
+                }
+                res
+            }
+            Mode::Replay(records) => {
+                if self.divergence.borrow().is_some() {
+                    return Err(Errno::Notrecoverable);
+                }
+                let record = match records.borrow_mut().pop_front() {
+                    Some(record) if record.hostcall == hostcall => record,
+                    Some(record) => {
+                        return Err(self.diverge(format!(
+                            "hostcall #{} is `{}`, but `{}` was recorded",
+                            position, hostcall, record.hostcall
+                        )))
+                    }
+                    None => {
+                        return Err(self.diverge(format!(
+                            "hostcall #{} (`{}`) was not recorded",
+                            position, hostcall
+                        )))
+                    }
+                };
+                if record.errno != 0 {
+                    return match Errno::try_from(record.errno) {
+                        Ok(errno) => Err(errno),
+                        Err(_) => Err(self.diverge(format!(
+                            "invalid errno {} recorded for hostcall #{}",
+                            record.errno, position
+                        ))),
+                    };
+                }
+                T::decode(&record.payload).ok_or_else(|| {
+                    self.diverge(format!(
+                        "invalid result recorded for hostcall #{} (`{}`)",
+                        position, hostcall
+                    ))
+                })
+            }
+        }
+    }
+
+    /// Concatenate the first `len` bytes held by the guest buffers `bufs`.
+    pub(crate) fn gather<B: Deref<Target = [u8]>>(bufs: &[B], len: usize) -> Vec<u8> {
+        let mut data = Vec::with_capacity(len);
+        for buf in bufs {
+            let remaining = len - data.len();
+            data.extend_from_slice(&buf[..std::cmp::min(buf.len(), remaining)]);
+        }
+        data
+    }
+
+    /// Copy `data` replayed for `hostcall` into the guest buffers `bufs`, returning the number of
+    /// bytes copied.
+    pub(crate) fn scatter<B: DerefMut<Target = [u8]>>(
+        &self,
+        hostcall: &str,
+        data: &[u8],
+        bufs: &mut [B],
+    ) -> Result<usize> {
+        let capacity: usize = bufs.iter().map(|buf| buf.len()).sum();
+        if data.len() > capacity {
+            return Err(self.diverge(format!(
+                "`{}` recorded {} bytes, but the guest provided room for only {}",
+                hostcall,
+                data.len(),
+                capacity
+            )));
+        }
+        let mut rest = data;
+        for buf in bufs.iter_mut() {
+            let len = std::cmp::min(buf.len(), rest.len());
+            buf[..len].copy_from_slice(&rest[..len]);
+            rest = &rest[len..];
+        }
+        Ok(data.len())
+    }
+
+    /// Like `Journal::scatter`, but for hostcalls which always fill the whole of `bufs`, so that
+    /// `data` falling short of it is a divergence too.
+    pub(crate) fn fill<B: DerefMut<Target = [u8]>>(
+        &self,
+        hostcall: &str,
+        data: &[u8],
+        bufs: &mut [B],
+    ) -> Result<()> {
+        let capacity: usize = bufs.iter().map(|buf| buf.len()).sum();
+        if data.len() != capacity {
+            return Err(self.diverge(format!(
+                "`{}` recorded {} bytes, but the guest asked for {}",
+                hostcall,
+                data.len(),
+                capacity
+            )));
+        }
+        self.scatter(hostcall, data, bufs)?;
+        Ok(())
+    }
+
+    /// Flag the replay as diverged, returning the error to hand back to the guest.
+    pub(crate) fn diverge(&self, divergence: String) -> Errno {
+        log::error!("replay diverged: {}", divergence);
+        self.divergence.borrow_mut().get_or_insert(divergence);
+        Errno::Notrecoverable
+    }
+}
+
+/// The handle of a descriptor opened by a replayed `path_open`.
+///
+/// Nothing is opened on the host when replaying, so this only holds what the `WasiCtx` itself
+/// tracks, the type and rights of the descriptor: the hostcalls made on it are served from the
+/// journal without ever reaching the handle.
+#[derive(Clone)]
+pub(crate) struct ReplayedHandle {
+    file_type: types::Filetype,
+    rights: Cell<HandleRights>,
+}
+
+impl ReplayedHandle {
+    pub(crate) fn new(file_type: types::Filetype, rights: HandleRights) -> Self {
+        Self {
+            file_type,
+            rights: Cell::new(rights),
+        }
+    }
+}
+
+impl Handle for ReplayedHandle {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn try_clone(&self) -> io::Result<Box<dyn Handle>> {
+        Ok(Box::new(self.clone()))
+    }
+    fn get_file_type(&self) -> types::Filetype {
+        self.file_type
+    }
+    fn get_rights(&self) -> HandleRights {
+        self.rights.get()
+    }
+    fn set_rights(&self, rights: HandleRights) {
+        self.rights.set(rights)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Journal, Journaled};
+    use crate::handle::HandleRights;
+    use crate::wasi::{types, Errno};
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn record() -> Vec<u8> {
+        let buf = SharedBuf::default();
+        let journal = Journal::record(buf.clone());
+        assert_eq!(journal.value("clock_time_get", || Ok(42u64)), Ok(42));
+        assert_eq!(
+            journal.value("random_get", || Ok(vec![0xde, 0xad])),
+            Ok(vec![0xde, 0xad])
+        );
+        assert_eq!(
+            journal.value::<Vec<u8>>("fd_read", || Err(Errno::Badf)),
+            Err(Errno::Badf)
+        );
+        assert_eq!(journal.divergence(), None);
+        journal.flush().unwrap();
+        buf.0.replace(Vec::new())
+    }
+
+    fn unreachable<T>() -> Result<T, Errno> {
+        panic!("replay must not call the host")
+    }
+
+    #[test]
+    fn replay() {
+        let log = record();
+        let journal = Journal::replay(&log[..]).unwrap();
+        assert!(journal.is_replaying());
+        assert_eq!(journal.value("clock_time_get", unreachable), Ok(42u64));
+        assert_eq!(
+            journal.value("random_get", unreachable),
+            Ok(vec![0xde, 0xad])
+        );
+        assert_eq!(
+            journal.value::<Vec<u8>>("fd_read", unreachable),
+            Err(Errno::Badf)
+        );
+        assert_eq!(journal.divergence(), None);
+    }
+
+    #[test]
+    fn diverge() {
+        let log = record();
+        let journal = Journal::replay(&log[..]).unwrap();
+        assert_eq!(journal.value("clock_time_get", || Ok(0u64)), Ok(42));
+        assert_eq!(
+            journal.value("fd_read", || Ok(Vec::new())),
+            Err(Errno::Notrecoverable)
+        );
+        assert!(journal.divergence().is_some());
+    }
+
+    #[test]
+    fn exited_early() {
+        let log = record();
+        let journal = Journal::replay(&log[..]).unwrap();
+        assert_eq!(journal.value("clock_time_get", || Ok(0u64)), Ok(42));
+        assert!(journal.divergence().is_some());
+    }
+
+    #[test]
+    fn scatter_gather() {
+        let journal = Journal::replay(&b""[..]).unwrap();
+        let mut bufs = vec![vec![0u8; 2], vec![0u8; 3]];
+        assert_eq!(journal.scatter("fd_read", b"abcd", &mut bufs), Ok(4));
+        assert_eq!(bufs, vec![b"ab".to_vec(), b"cd\0".to_vec()]);
+        assert_eq!(Journal::gather(&bufs, 3), b"abc".to_vec());
+        assert_eq!(
+            journal.scatter("fd_read", b"abcdef", &mut bufs),
+            Err(Errno::Notrecoverable)
+        );
+    }
+
+    #[test]
+    fn fill() {
+        let journal = Journal::replay(&b""[..]).unwrap();
+        let mut bufs = vec![vec![0u8; 4]];
+        assert_eq!(journal.fill("random_get", b"abcd", &mut bufs), Ok(()));
+        assert_eq!(bufs, vec![b"abcd".to_vec()]);
+        assert_eq!(
+            journal.fill("random_get", b"ab", &mut bufs),
+            Err(Errno::Notrecoverable)
+        );
+        assert!(journal.divergence().is_some());
+    }
+
+    #[test]
+    fn filestat() {
+        let filestat = types::Filestat {
+            dev: 1,
+            ino: 2,
+            filetype: types::Filetype::RegularFile,
+            nlink: 3,
+            size: 4,
+            atim: 5,
+            mtim: 6,
+            ctim: 7,
+        };
+        assert_eq!(types::Filestat::decode(&filestat.encode()), Some(filestat));
+        assert_eq!(<()>::decode(&().encode()), Some(()));
+    }
+
+    #[test]
+    fn opened() {
+        let rights = HandleRights::new(types::Rights::FD_READ, types::Rights::FD_WRITE);
+        let (file_type, decoded) = <(types::Filetype, HandleRights)>::decode(
+            &(types::Filetype::Directory, rights).encode(),
+        )
+        .unwrap();
+        assert_eq!(file_type, types::Filetype::Directory);
+        assert_eq!(decoded.base(), rights.base());
+        assert_eq!(decoded.inheriting(), rights.inheriting());
+    }
+
+    #[test]
+    fn events() {
+        let events = vec![types::Event {
+            userdata: 7,
+            error: Errno::Success,
+            type_: types::Eventtype::FdRead,
+            fd_readwrite: types::EventFdReadwrite {
+                nbytes: 3,
+                flags: types::Eventrwflags::FD_READWRITE_HANGUP,
+            },
+        }];
+        assert_eq!(Vec::<types::Event>::decode(&events.encode()), Some(events));
+    }
+}
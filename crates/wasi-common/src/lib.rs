@@ -23,22 +23,30 @@
 
 mod ctx;
 mod entry;
+mod exit;
 mod fdpool;
 pub mod fs;
 mod handle;
+mod journal;
 pub mod old;
 mod path;
 mod poll;
+mod quota;
 mod sandboxed_tty_writer;
 pub mod snapshots;
 mod sys;
+mod trace;
 mod virtfs;
 pub mod wasi;
 
 pub use ctx::{WasiCtx, WasiCtxBuilder, WasiCtxBuilderError};
+pub use exit::{I32Exit, SignalAction, SignalExit};
 pub use handle::{Handle, HandleRights};
+pub use journal::Journal;
+pub use quota::{IoLimits, IoUsage};
 pub use sys::osdir::OsDir;
 pub use sys::osfile::OsFile;
 pub use sys::osother::OsOther;
 pub use sys::preopen_dir;
-pub use virtfs::{FileContents, VirtualDirEntry};
+pub use trace::{TraceArg, TraceFormat, Tracer};
+pub use virtfs::{FileContents, VirtualDir, VirtualDirEntry};
@@ -1,7 +1,11 @@
+use crate::exit::{SignalAction, SignalExit, SignalHandler};
 use crate::fdpool::FdPool;
 use crate::old::snapshot_0::entry::Entry;
 use crate::old::snapshot_0::wasi::{self, WasiError, WasiResult};
+use crate::trace::Tracer;
+use crate::wasi::types::Signal;
 use std::borrow::Borrow;
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::ffi::{self, CString, OsString};
 use std::fs::File;
@@ -107,6 +111,8 @@ pub struct WasiCtxBuilder {
     preopens: Option<Vec<(PathBuf, File)>>,
     args: Option<Vec<PendingCString>>,
     env: Option<HashMap<PendingCString, PendingCString>>,
+    tracer: Option<Tracer>,
+    signal_handler: Option<SignalHandler>,
 }
 
 impl WasiCtxBuilder {
@@ -119,6 +125,8 @@ impl WasiCtxBuilder {
             preopens: Some(Vec::new()),
             args: Some(Vec::new()),
             env: Some(HashMap::new()),
+            tracer: None,
+            signal_handler: None,
         }
     }
 
@@ -246,6 +254,23 @@ impl WasiCtxBuilder {
         self
     }
 
+    /// Trace every hostcall made through the built `WasiCtx` with `tracer`.
+    pub fn trace(&mut self, tracer: Tracer) -> &mut Self {
+        self.tracer = Some(tracer);
+        self
+    }
+
+    /// Decide what `proc_raise` does with each signal using `handler`.
+    ///
+    /// Without a handler, signals get their POSIX default action, see `SignalAction::default_for`.
+    pub fn signal_handler<F>(&mut self, handler: F) -> &mut Self
+    where
+        F: Fn(Signal) -> SignalAction + 'static,
+    {
+        self.signal_handler = Some(SignalHandler::new(handler));
+        self
+    }
+
     /// Build a `WasiCtx`, consuming this `WasiCtxBuilder`.
     ///
     /// If any of the arguments or environment variables in this builder cannot be converted into
@@ -321,6 +346,9 @@ impl WasiCtxBuilder {
             env,
             fd_pool,
             entries,
+            tracer: self.tracer.take(),
+            signal_handler: self.signal_handler.take(),
+            termination: Cell::new(None),
         })
     }
 }
@@ -331,6 +359,9 @@ pub struct WasiCtx {
     entries: HashMap<wasi::__wasi_fd_t, Entry>,
     pub(crate) args: Vec<CString>,
     pub(crate) env: Vec<CString>,
+    tracer: Option<Tracer>,
+    signal_handler: Option<SignalHandler>,
+    termination: Cell<Option<SignalExit>>,
 }
 
 impl WasiCtx {
@@ -349,6 +380,30 @@ impl WasiCtx {
             .build()
     }
 
+    /// Get the `Tracer` hostcalls made through this `WasiCtx` should be reported to, if any.
+    pub fn tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_ref()
+    }
+
+    /// Get what `proc_raise` should do with `signal`.
+    pub fn signal_action(&self, signal: Signal) -> SignalAction {
+        SignalHandler::action(self.signal_handler.as_ref(), signal)
+    }
+
+    /// Take the termination of the guest requested by a signal raised with `proc_raise`, if any.
+    ///
+    /// Unwinding the guest requires runtime-specific logic, so `proc_raise` only records that
+    /// the guest is to be terminated, and fails with `EINTR`. Runtimes are expected to check
+    /// for it once the hostcall returns, and to trap with the `SignalExit`.
+    pub fn take_termination(&self) -> Option<SignalExit> {
+        self.termination.take()
+    }
+
+    /// Request the termination of the guest; see `WasiCtx::take_termination`.
+    pub(crate) fn terminate(&self, exit: SignalExit) {
+        self.termination.set(Some(exit))
+    }
+
     /// Check if `WasiCtx` contains the specified raw WASI `fd`.
     pub(crate) unsafe fn contains_entry(&self, fd: wasi::__wasi_fd_t) -> bool {
         self.entries.contains_key(&fd)
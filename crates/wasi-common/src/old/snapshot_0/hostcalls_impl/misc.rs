@@ -5,6 +5,8 @@ use crate::old::snapshot_0::memory::*;
 use crate::old::snapshot_0::sys::hostcalls_impl;
 use crate::old::snapshot_0::wasi::{self, WasiError, WasiResult};
 use crate::old::snapshot_0::wasi32;
+use crate::wasi::types::Signal;
+use crate::{SignalAction, SignalExit};
 use log::{error, trace};
 use std::convert::TryFrom;
 
@@ -343,9 +345,22 @@ pub(crate) struct FdEventData<'a> {
 }
 
 pub(crate) fn proc_raise(
-    _wasi_ctx: &WasiCtx,
+    wasi_ctx: &WasiCtx,
     _memory: &mut [u8],
-    _sig: wasi::__wasi_signal_t,
+    sig: wasi::__wasi_signal_t,
 ) -> WasiResult<()> {
-    unimplemented!("proc_raise")
+    trace!("proc_raise(sig={:?})", sig);
+
+    // Snapshot 0 numbers signals like later snapshots do.
+    let signal = Signal::try_from(sig).map_err(|_| WasiError::EINVAL)?;
+    match wasi_ctx.signal_action(signal) {
+        SignalAction::Ignore => Ok(()),
+        // Like proc_exit, terminating the guest is expected to unwind the
+        // stack, which requires runtime-specific logic, so the runtime is left
+        // to trap once this returns.
+        SignalAction::Terminate => {
+            wasi_ctx.terminate(SignalExit(signal));
+            Err(WasiError::EINTR)
+        }
+    }
 }
@@ -0,0 +1,157 @@
+use crate::wasi::{Errno, Result};
+use std::cell::Cell;
+
+/// Limits on the filesystem resources a `WasiCtx` is allowed to consume.
+///
+/// Every limit defaults to `None`, meaning unlimited. Limits are configured
+/// with the `WasiCtxBuilder::max_*` methods.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IoLimits {
+    /// Maximum number of bytes the guest may write to (or allocate in)
+    /// regular files. Exceeding it yields `Errno::Dquot`.
+    pub max_bytes_written: Option<u64>,
+    /// Maximum number of files and directories the guest may create.
+    /// Exceeding it yields `Errno::Dquot`.
+    pub max_files_created: Option<u64>,
+    /// Maximum number of descriptors, including stdio and preopens, that may
+    /// be open at any one time. Exceeding it yields `Errno::Nfile`.
+    pub max_open_fds: Option<u32>,
+}
+
+/// I/O counters accumulated by a `WasiCtx` over its lifetime.
+///
+/// A snapshot of these can be obtained with `WasiCtx::io_usage`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IoUsage {
+    /// Number of bytes written to, or allocated in, regular files.
+    pub bytes_written: u64,
+    /// Number of files and directories created.
+    pub files_created: u64,
+    /// Number of descriptors currently open.
+    pub open_fds: u32,
+}
+
+/// Bookkeeping for `IoLimits`, shared by all the hostcalls of a `WasiCtx`.
+#[derive(Debug, Default)]
+pub(crate) struct Quota {
+    limits: IoLimits,
+    bytes_written: Cell<u64>,
+    files_created: Cell<u64>,
+}
+
+impl Quota {
+    pub(crate) fn new(limits: IoLimits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// Check that `len` more bytes may be written without exceeding the quota.
+    pub(crate) fn check_write(&self, len: u64) -> Result<()> {
+        let total = self
+            .bytes_written
+            .get()
+            .checked_add(len)
+            .ok_or(Errno::Dquot)?;
+        match self.limits.max_bytes_written {
+            Some(max) if total > max => {
+                log::debug!("     | bytes written quota of {} exceeded", max);
+                Err(Errno::Dquot)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Record `len` bytes as written.
+    pub(crate) fn record_write(&self, len: u64) {
+        self.bytes_written
+            .set(self.bytes_written.get().saturating_add(len));
+    }
+
+    /// Check that one more file or directory may be created.
+    pub(crate) fn check_create(&self) -> Result<()> {
+        match self.limits.max_files_created {
+            Some(max) if self.files_created.get() >= max => {
+                log::debug!("     | files created quota of {} exceeded", max);
+                Err(Errno::Dquot)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Record that a file or directory was created.
+    pub(crate) fn record_create(&self) {
+        self.files_created
+            .set(self.files_created.get().saturating_add(1));
+    }
+
+    /// Check that one more descriptor may be opened given `open_fds` are already open.
+    pub(crate) fn check_open(&self, open_fds: usize) -> Result<()> {
+        match self.limits.max_open_fds {
+            Some(max) if open_fds >= max as usize => {
+                log::debug!("     | open descriptors limit of {} reached", max);
+                Err(Errno::Nfile)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn usage(&self, open_fds: u32) -> IoUsage {
+        IoUsage {
+            bytes_written: self.bytes_written.get(),
+            files_created: self.files_created.get(),
+            open_fds,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{IoLimits, Quota};
+    use crate::wasi::Errno;
+
+    #[test]
+    fn unlimited() {
+        let quota = Quota::new(IoLimits::default());
+        quota.record_write(u64::max_value());
+        assert_eq!(quota.check_write(0), Ok(()));
+        assert_eq!(quota.check_create(), Ok(()));
+        assert_eq!(quota.check_open(usize::max_value()), Ok(()));
+    }
+
+    #[test]
+    fn bytes_written() {
+        let quota = Quota::new(IoLimits {
+            max_bytes_written: Some(10),
+            ..IoLimits::default()
+        });
+        assert_eq!(quota.check_write(10), Ok(()));
+        quota.record_write(6);
+        assert_eq!(quota.check_write(4), Ok(()));
+        assert_eq!(quota.check_write(5), Err(Errno::Dquot));
+        assert_eq!(quota.usage(0).bytes_written, 6);
+    }
+
+    #[test]
+    fn files_created() {
+        let quota = Quota::new(IoLimits {
+            max_files_created: Some(1),
+            ..IoLimits::default()
+        });
+        assert_eq!(quota.check_create(), Ok(()));
+        quota.record_create();
+        assert_eq!(quota.check_create(), Err(Errno::Dquot));
+        assert_eq!(quota.usage(0).files_created, 1);
+    }
+
+    #[test]
+    fn open_fds() {
+        let quota = Quota::new(IoLimits {
+            max_open_fds: Some(4),
+            ..IoLimits::default()
+        });
+        assert_eq!(quota.check_open(3), Ok(()));
+        assert_eq!(quota.check_open(4), Err(Errno::Nfile));
+    }
+}
@@ -1,14 +1,15 @@
 use crate::entry::{Entry, EntryHandle};
 use crate::handle::HandleRights;
+use crate::journal::ReplayedHandle;
 use crate::sys::clock;
 use crate::wasi::wasi_snapshot_preview1::WasiSnapshotPreview1;
 use crate::wasi::{types, AsBytes, Errno, Result};
-use crate::WasiCtx;
 use crate::{path, poll};
+use crate::{SignalAction, SignalExit, WasiCtx};
 use log::{debug, error, trace};
 use std::convert::TryInto;
 use std::io::{self, SeekFrom};
-use wiggle::{GuestPtr, GuestSlice};
+use wiggle::{GuestPtr, GuestSlice, GuestSliceMut};
 
 impl<'a> WasiSnapshotPreview1 for WasiCtx {
     fn args_get<'b>(
@@ -72,7 +73,7 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     }
 
     fn clock_res_get(&self, id: types::Clockid) -> Result<types::Timestamp> {
-        let resolution = clock::res_get(id)?;
+        let resolution = self.journaled("clock_res_get", || clock::res_get(id))?;
         Ok(resolution)
     }
 
@@ -81,7 +82,7 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         id: types::Clockid,
         _precision: types::Timestamp,
     ) -> Result<types::Timestamp> {
-        let time = clock::time_get(id)?;
+        let time = self.journaled("clock_time_get", || clock::time_get(id))?;
         Ok(time)
     }
 
@@ -94,9 +95,8 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     ) -> Result<()> {
         let required_rights = HandleRights::from_base(types::Rights::FD_ADVISE);
         let entry = self.get_entry(fd)?;
-        entry
-            .as_handle(&required_rights)?
-            .advise(advice, offset, len)
+        let handle = entry.as_handle(&required_rights)?;
+        self.journaled("fd_advise", || handle.advise(advice, offset, len))
     }
 
     fn fd_allocate(
@@ -107,7 +107,19 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     ) -> Result<()> {
         let required_rights = HandleRights::from_base(types::Rights::FD_ALLOCATE);
         let entry = self.get_entry(fd)?;
-        entry.as_handle(&required_rights)?.allocate(offset, len)
+        let handle = entry.as_handle(&required_rights)?;
+        self.journaled("fd_allocate", || {
+            // Only the bytes by which the file grows count towards the quota.
+            let size = handle.filestat_get()?.size;
+            let growth = offset
+                .checked_add(len)
+                .ok_or(Errno::TooBig)?
+                .saturating_sub(size);
+            self.quota.check_write(growth)?;
+            handle.allocate(offset, len)?;
+            self.quota.record_write(growth);
+            Ok(())
+        })
     }
 
     fn fd_close(&self, fd: types::Fd) -> Result<()> {
@@ -124,13 +136,14 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     fn fd_datasync(&self, fd: types::Fd) -> Result<()> {
         let required_rights = HandleRights::from_base(types::Rights::FD_DATASYNC);
         let entry = self.get_entry(fd)?;
-        entry.as_handle(&required_rights)?.datasync()
+        let handle = entry.as_handle(&required_rights)?;
+        self.journaled("fd_datasync", || handle.datasync())
     }
 
     fn fd_fdstat_get(&self, fd: types::Fd) -> Result<types::Fdstat> {
         let entry = self.get_entry(fd)?;
         let file = entry.as_handle(&HandleRights::empty())?;
-        let fs_flags = file.fdstat_get()?;
+        let fs_flags = self.journaled("fd_fdstat_get", || file.fdstat_get())?;
         let rights = entry.get_rights();
         let fdstat = types::Fdstat {
             fs_filetype: entry.get_file_type(),
@@ -144,7 +157,8 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     fn fd_fdstat_set_flags(&self, fd: types::Fd, flags: types::Fdflags) -> Result<()> {
         let required_rights = HandleRights::from_base(types::Rights::FD_FDSTAT_SET_FLAGS);
         let entry = self.get_entry(fd)?;
-        entry.as_handle(&required_rights)?.fdstat_set_flags(flags)
+        let handle = entry.as_handle(&required_rights)?;
+        self.journaled("fd_fdstat_set_flags", || handle.fdstat_set_flags(flags))
     }
 
     fn fd_fdstat_set_rights(
@@ -165,7 +179,8 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     fn fd_filestat_get(&self, fd: types::Fd) -> Result<types::Filestat> {
         let required_rights = HandleRights::from_base(types::Rights::FD_FILESTAT_GET);
         let entry = self.get_entry(fd)?;
-        let host_filestat = entry.as_handle(&required_rights)?.filestat_get()?;
+        let handle = entry.as_handle(&required_rights)?;
+        let host_filestat = self.journaled("fd_filestat_get", || handle.filestat_get())?;
         Ok(host_filestat)
     }
 
@@ -176,7 +191,8 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         if size > i64::max_value() as u64 {
             return Err(Errno::TooBig);
         }
-        entry.as_handle(&required_rights)?.filestat_set_size(size)
+        let handle = entry.as_handle(&required_rights)?;
+        self.journaled("fd_filestat_set_size", || handle.filestat_set_size(size))
     }
 
     fn fd_filestat_set_times(
@@ -188,9 +204,10 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     ) -> Result<()> {
         let required_rights = HandleRights::from_base(types::Rights::FD_FILESTAT_SET_TIMES);
         let entry = self.get_entry(fd)?;
-        entry
-            .as_handle(&required_rights)?
-            .filestat_set_times(atim, mtim, fst_flags)
+        let handle = entry.as_handle(&required_rights)?;
+        self.journaled("fd_filestat_set_times", || {
+            handle.filestat_set_times(atim, mtim, fst_flags)
+        })
     }
 
     fn fd_pread(
@@ -199,11 +216,11 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         iovs: &types::IovecArray<'_>,
         offset: types::Filesize,
     ) -> Result<types::Size> {
-        let mut guest_slices: Vec<GuestSlice<'_, u8>> = Vec::new();
+        let mut guest_slices: Vec<GuestSliceMut<'_, u8>> = Vec::new();
         for iov_ptr in iovs.iter() {
             let iov_ptr = iov_ptr?;
             let iov: types::Iovec = iov_ptr.read()?;
-            guest_slices.push(iov.buf.as_array(iov.buf_len).as_slice()?);
+            guest_slices.push(iov.buf.as_array(iov.buf_len).as_slice_mut()?);
         }
 
         let required_rights =
@@ -213,17 +230,15 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
             return Err(Errno::Io);
         }
 
-        let host_nread = {
+        let handle = entry.as_handle(&required_rights)?;
+        let host_nread = self.journaled_read("fd_pread", &mut guest_slices, |guest_slices| {
             let mut buf = guest_slices
                 .iter_mut()
                 .map(|s| io::IoSliceMut::new(&mut *s))
                 .collect::<Vec<io::IoSliceMut<'_>>>();
-            entry
-                .as_handle(&required_rights)?
-                .preadv(&mut buf, offset)?
-                .try_into()?
-        };
-        Ok(host_nread)
+            handle.preadv(&mut buf, offset)
+        })?;
+        Ok(host_nread.try_into()?)
     }
 
     fn fd_prestat_get(&self, fd: types::Fd) -> Result<types::Prestat> {
@@ -290,15 +305,21 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
             return Err(Errno::Io);
         }
 
-        let host_nwritten = {
+        let handle = entry.as_handle(&required_rights)?;
+        let quota_applies = entry.get_file_type() == types::Filetype::RegularFile;
+        let host_nwritten = self.journaled("fd_pwrite", || {
+            if quota_applies {
+                self.quota.check_write(iovec_len(&guest_slices))?;
+            }
             let buf: Vec<io::IoSlice> =
                 guest_slices.iter().map(|s| io::IoSlice::new(&*s)).collect();
-            entry
-                .as_handle(&required_rights)?
-                .pwritev(&buf, offset)?
-                .try_into()?
-        };
-        Ok(host_nwritten)
+            let host_nwritten = handle.pwritev(&buf, offset)? as u64;
+            if quota_applies {
+                self.quota.record_write(host_nwritten);
+            }
+            Ok(host_nwritten)
+        })?;
+        Ok(host_nwritten.try_into()?)
     }
 
     fn fd_read(&self, fd: types::Fd, iovs: &types::IovecArray<'_>) -> Result<types::Size> {
@@ -306,23 +327,21 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         for iov_ptr in iovs.iter() {
             let iov_ptr = iov_ptr?;
             let iov: types::Iovec = iov_ptr.read()?;
-            guest_slices.push(iov.buf.as_array(iov.buf_len).as_slice()?);
+            guest_slices.push(iov.buf.as_array(iov.buf_len).as_slice_mut()?);
         }
 
         let required_rights = HandleRights::from_base(types::Rights::FD_READ);
         let entry = self.get_entry(fd)?;
-        let host_nread = {
+        let handle = entry.as_handle(&required_rights)?;
+        let host_nread = self.journaled_read("fd_read", &mut guest_slices, |guest_slices| {
             let mut slices: Vec<io::IoSliceMut> = guest_slices
                 .iter_mut()
                 .map(|s| io::IoSliceMut::new(&mut *s))
                 .collect();
-            entry
-                .as_handle(&required_rights)?
-                .read_vectored(&mut slices)?
-                .try_into()?
-        };
+            handle.read_vectored(&mut slices)
+        })?;
 
-        Ok(host_nread)
+        Ok(host_nread.try_into()?)
     }
 
     fn fd_readdir(
@@ -335,27 +354,27 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         let required_rights = HandleRights::from_base(types::Rights::FD_READDIR);
         let entry = self.get_entry(fd)?;
 
-        let mut bufused = 0;
-        let mut buf = buf.clone();
-        for pair in entry.as_handle(&required_rights)?.readdir(cookie)? {
-            let (dirent, name) = pair?;
-            let dirent_raw = dirent.as_bytes()?;
-            let dirent_len: types::Size = dirent_raw.len().try_into()?;
-            let name_raw = name.as_bytes();
-            let name_len = name_raw.len().try_into()?;
-            let offset = dirent_len.checked_add(name_len).ok_or(Errno::Overflow)?;
-            if (buf_len - bufused) < offset {
-                break;
-            } else {
-                buf.as_array(dirent_len).copy_from_slice(&dirent_raw)?;
-                buf = buf.add(dirent_len)?;
-                buf.as_array(name_len).copy_from_slice(name_raw)?;
-                buf = buf.add(name_len)?;
-                bufused += offset;
+        let handle = entry.as_handle(&required_rights)?;
+        let mut bufs = [buf.as_array(buf_len).as_slice_mut()?];
+        let bufused = self.journaled_read("fd_readdir", &mut bufs, |bufs| {
+            let buf = &mut *bufs[0];
+            let mut bufused = 0;
+            for pair in handle.readdir(cookie)? {
+                let (dirent, name) = pair?;
+                let dirent_raw = dirent.as_bytes()?;
+                let name_raw = name.as_bytes();
+                let end = bufused + dirent_raw.len() + name_raw.len();
+                if end > buf.len() {
+                    break;
+                }
+                buf[bufused..bufused + dirent_raw.len()].copy_from_slice(&dirent_raw);
+                buf[bufused + dirent_raw.len()..end].copy_from_slice(name_raw);
+                bufused = end;
             }
-        }
+            Ok(bufused)
+        })?;
 
-        Ok(bufused)
+        Ok(bufused.try_into()?)
     }
 
     fn fd_renumber(&self, from: types::Fd, to: types::Fd) -> Result<()> {
@@ -399,22 +418,23 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
             types::Whence::End => SeekFrom::End(offset),
             types::Whence::Set => SeekFrom::Start(offset as u64),
         };
-        let host_newoffset = entry.as_handle(&required_rights)?.seek(pos)?;
+        let handle = entry.as_handle(&required_rights)?;
+        let host_newoffset = self.journaled("fd_seek", || handle.seek(pos))?;
         Ok(host_newoffset)
     }
 
     fn fd_sync(&self, fd: types::Fd) -> Result<()> {
         let required_rights = HandleRights::from_base(types::Rights::FD_SYNC);
         let entry = self.get_entry(fd)?;
-        entry.as_handle(&required_rights)?.sync()
+        let handle = entry.as_handle(&required_rights)?;
+        self.journaled("fd_sync", || handle.sync())
     }
 
     fn fd_tell(&self, fd: types::Fd) -> Result<types::Filesize> {
         let required_rights = HandleRights::from_base(types::Rights::FD_TELL);
         let entry = self.get_entry(fd)?;
-        let host_offset = entry
-            .as_handle(&required_rights)?
-            .seek(SeekFrom::Current(0))?;
+        let handle = entry.as_handle(&required_rights)?;
+        let host_offset = self.journaled("fd_tell", || handle.seek(SeekFrom::Current(0)))?;
         Ok(host_offset)
     }
 
@@ -427,15 +447,21 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         }
         let required_rights = HandleRights::from_base(types::Rights::FD_WRITE);
         let entry = self.get_entry(fd)?;
-        let host_nwritten = {
+        let handle = entry.as_handle(&required_rights)?;
+        let quota_applies = entry.get_file_type() == types::Filetype::RegularFile;
+        let host_nwritten = self.journaled("fd_write", || {
+            if quota_applies {
+                self.quota.check_write(iovec_len(&guest_slices))?;
+            }
             let slices: Vec<io::IoSlice> =
                 guest_slices.iter().map(|s| io::IoSlice::new(&*s)).collect();
-            entry
-                .as_handle(&required_rights)?
-                .write_vectored(&slices)?
-                .try_into()?
-        };
-        Ok(host_nwritten)
+            let host_nwritten = handle.write_vectored(&slices)? as u64;
+            if quota_applies {
+                self.quota.record_write(host_nwritten);
+            }
+            Ok(host_nwritten)
+        })?;
+        Ok(host_nwritten.try_into()?)
     }
 
     fn path_create_directory(&self, dirfd: types::Fd, path: &GuestPtr<'_, str>) -> Result<()> {
@@ -443,14 +469,19 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
             types::Rights::PATH_OPEN | types::Rights::PATH_CREATE_DIRECTORY,
         );
         let entry = self.get_entry(dirfd)?;
-        let (dirfd, path) = path::get(
-            &entry,
-            &required_rights,
-            types::Lookupflags::empty(),
-            path,
-            false,
-        )?;
-        dirfd.create_directory(&path)
+        self.journaled("path_create_directory", || {
+            let (dirfd, path) = path::get(
+                &entry,
+                &required_rights,
+                types::Lookupflags::empty(),
+                path,
+                false,
+            )?;
+            self.quota.check_create()?;
+            dirfd.create_directory(&path)?;
+            self.quota.record_create();
+            Ok(())
+        })
     }
 
     fn path_filestat_get(
@@ -461,9 +492,10 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     ) -> Result<types::Filestat> {
         let required_rights = HandleRights::from_base(types::Rights::PATH_FILESTAT_GET);
         let entry = self.get_entry(dirfd)?;
-        let (dirfd, path) = path::get(&entry, &required_rights, flags, path, false)?;
-        let host_filestat =
-            dirfd.filestat_get_at(&path, flags.contains(&types::Lookupflags::SYMLINK_FOLLOW))?;
+        let host_filestat = self.journaled("path_filestat_get", || {
+            let (dirfd, path) = path::get(&entry, &required_rights, flags, path, false)?;
+            dirfd.filestat_get_at(&path, flags.contains(&types::Lookupflags::SYMLINK_FOLLOW))
+        })?;
         Ok(host_filestat)
     }
 
@@ -478,15 +510,16 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     ) -> Result<()> {
         let required_rights = HandleRights::from_base(types::Rights::PATH_FILESTAT_SET_TIMES);
         let entry = self.get_entry(dirfd)?;
-        let (dirfd, path) = path::get(&entry, &required_rights, flags, path, false)?;
-        dirfd.filestat_set_times_at(
-            &path,
-            atim,
-            mtim,
-            fst_flags,
-            flags.contains(&types::Lookupflags::SYMLINK_FOLLOW),
-        )?;
-        Ok(())
+        self.journaled("path_filestat_set_times", || {
+            let (dirfd, path) = path::get(&entry, &required_rights, flags, path, false)?;
+            dirfd.filestat_set_times_at(
+                &path,
+                atim,
+                mtim,
+                fst_flags,
+                flags.contains(&types::Lookupflags::SYMLINK_FOLLOW),
+            )
+        })
     }
 
     fn path_link(
@@ -497,30 +530,32 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         new_fd: types::Fd,
         new_path: &GuestPtr<'_, str>,
     ) -> Result<()> {
-        let required_rights = HandleRights::from_base(types::Rights::PATH_LINK_SOURCE);
         let old_entry = self.get_entry(old_fd)?;
-        let (old_dirfd, old_path) = path::get(
-            &old_entry,
-            &required_rights,
-            types::Lookupflags::empty(),
-            old_path,
-            false,
-        )?;
-        let required_rights = HandleRights::from_base(types::Rights::PATH_LINK_TARGET);
         let new_entry = self.get_entry(new_fd)?;
-        let (new_dirfd, new_path) = path::get(
-            &new_entry,
-            &required_rights,
-            types::Lookupflags::empty(),
-            new_path,
-            false,
-        )?;
-        old_dirfd.link(
-            &old_path,
-            new_dirfd,
-            &new_path,
-            old_flags.contains(&types::Lookupflags::SYMLINK_FOLLOW),
-        )
+        self.journaled("path_link", || {
+            let required_rights = HandleRights::from_base(types::Rights::PATH_LINK_SOURCE);
+            let (old_dirfd, old_path) = path::get(
+                &old_entry,
+                &required_rights,
+                types::Lookupflags::empty(),
+                old_path,
+                false,
+            )?;
+            let required_rights = HandleRights::from_base(types::Rights::PATH_LINK_TARGET);
+            let (new_dirfd, new_path) = path::get(
+                &new_entry,
+                &required_rights,
+                types::Lookupflags::empty(),
+                new_path,
+                false,
+            )?;
+            old_dirfd.link(
+                &old_path,
+                new_dirfd,
+                &new_path,
+                old_flags.contains(&types::Lookupflags::SYMLINK_FOLLOW),
+            )
+        })
     }
 
     fn path_open(
@@ -540,35 +575,52 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         );
         trace!("     | needed_rights={}", needed_rights);
         let entry = self.get_entry(dirfd)?;
-        let (dirfd, path) = path::get(
-            &entry,
-            &needed_rights,
-            dirflags,
-            path,
-            oflags & types::Oflags::CREAT != types::Oflags::empty(),
-        )?;
-        // which open mode do we need?
-        let read = fs_rights_base & (types::Rights::FD_READ | types::Rights::FD_READDIR)
-            != types::Rights::empty();
-        let write = fs_rights_base
-            & (types::Rights::FD_DATASYNC
-                | types::Rights::FD_WRITE
-                | types::Rights::FD_ALLOCATE
-                | types::Rights::FD_FILESTAT_SET_SIZE)
-            != types::Rights::empty();
-        trace!(
-            "     | calling path_open impl: read={}, write={}",
-            read,
-            write
-        );
-        let fd = dirfd.openat(&path, read, write, oflags, fdflags)?;
-        let entry = Entry::new(EntryHandle::from(fd));
-        // We need to manually deny the rights which are not explicitly requested
-        // because Entry::from will assign maximal consistent rights.
-        let mut rights = entry.get_rights();
-        rights.base &= fs_rights_base;
-        rights.inheriting &= fs_rights_inheriting;
-        entry.set_rights(rights);
+        let mut opened = None;
+        let (file_type, rights) = self.journaled("path_open", || {
+            let creat = oflags & types::Oflags::CREAT != types::Oflags::empty();
+            let (dirfd, path) = path::get(&entry, &needed_rights, dirflags, path, creat)?;
+            // Only charge the files created quota if the file doesn't exist yet.
+            let creates_file =
+                creat && matches!(dirfd.filestat_get_at(&path, false), Err(Errno::Noent));
+            if creates_file {
+                self.quota.check_create()?;
+            }
+            // Check that the new descriptor can be inserted before anything is created on disk.
+            self.check_open()?;
+            // which open mode do we need?
+            let read = fs_rights_base & (types::Rights::FD_READ | types::Rights::FD_READDIR)
+                != types::Rights::empty();
+            let write = fs_rights_base
+                & (types::Rights::FD_DATASYNC
+                    | types::Rights::FD_WRITE
+                    | types::Rights::FD_ALLOCATE
+                    | types::Rights::FD_FILESTAT_SET_SIZE)
+                != types::Rights::empty();
+            trace!(
+                "     | calling path_open impl: read={}, write={}",
+                read,
+                write
+            );
+            let fd = dirfd.openat(&path, read, write, oflags, fdflags)?;
+            if creates_file {
+                self.quota.record_create();
+            }
+            let entry = Entry::new(EntryHandle::from(fd));
+            // We need to manually deny the rights which are not explicitly requested
+            // because Entry::from will assign maximal consistent rights.
+            let mut rights = entry.get_rights();
+            rights.base &= fs_rights_base;
+            rights.inheriting &= fs_rights_inheriting;
+            entry.set_rights(rights);
+            let file_type = entry.get_file_type();
+            opened = Some(entry);
+            Ok((file_type, rights))
+        })?;
+        // A replayed `path_open` doesn't open anything, so the descriptor gets a placeholder
+        // handle, which is never reached as the hostcalls made on it are replayed as well.
+        let entry = opened.unwrap_or_else(|| {
+            Entry::new(EntryHandle::new(ReplayedHandle::new(file_type, rights)))
+        });
         let guest_fd = self.insert_entry(entry)?;
         Ok(guest_fd)
     }
@@ -582,29 +634,33 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     ) -> Result<types::Size> {
         let required_rights = HandleRights::from_base(types::Rights::PATH_READLINK);
         let entry = self.get_entry(dirfd)?;
-        let (dirfd, path) = path::get(
-            &entry,
-            &required_rights,
-            types::Lookupflags::empty(),
-            path,
-            false,
-        )?;
-        let mut slice = buf.as_array(buf_len).as_slice()?;
-        let host_bufused = dirfd.readlink(&path, &mut *slice)?.try_into()?;
-        Ok(host_bufused)
+        let mut bufs = [buf.as_array(buf_len).as_slice_mut()?];
+        let host_bufused = self.journaled_read("path_readlink", &mut bufs, |bufs| {
+            let (dirfd, path) = path::get(
+                &entry,
+                &required_rights,
+                types::Lookupflags::empty(),
+                path,
+                false,
+            )?;
+            dirfd.readlink(&path, &mut *bufs[0])
+        })?;
+        Ok(host_bufused.try_into()?)
     }
 
     fn path_remove_directory(&self, dirfd: types::Fd, path: &GuestPtr<'_, str>) -> Result<()> {
         let required_rights = HandleRights::from_base(types::Rights::PATH_REMOVE_DIRECTORY);
         let entry = self.get_entry(dirfd)?;
-        let (dirfd, path) = path::get(
-            &entry,
-            &required_rights,
-            types::Lookupflags::empty(),
-            path,
-            true,
-        )?;
-        dirfd.remove_directory(&path)
+        self.journaled("path_remove_directory", || {
+            let (dirfd, path) = path::get(
+                &entry,
+                &required_rights,
+                types::Lookupflags::empty(),
+                path,
+                true,
+            )?;
+            dirfd.remove_directory(&path)
+        })
     }
 
     fn path_rename(
@@ -614,25 +670,27 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         new_fd: types::Fd,
         new_path: &GuestPtr<'_, str>,
     ) -> Result<()> {
-        let required_rights = HandleRights::from_base(types::Rights::PATH_RENAME_SOURCE);
-        let entry = self.get_entry(old_fd)?;
-        let (old_dirfd, old_path) = path::get(
-            &entry,
-            &required_rights,
-            types::Lookupflags::empty(),
-            old_path,
-            true,
-        )?;
-        let required_rights = HandleRights::from_base(types::Rights::PATH_RENAME_TARGET);
-        let entry = self.get_entry(new_fd)?;
-        let (new_dirfd, new_path) = path::get(
-            &entry,
-            &required_rights,
-            types::Lookupflags::empty(),
-            new_path,
-            true,
-        )?;
-        old_dirfd.rename(&old_path, new_dirfd, &new_path)
+        let old_entry = self.get_entry(old_fd)?;
+        let new_entry = self.get_entry(new_fd)?;
+        self.journaled("path_rename", || {
+            let required_rights = HandleRights::from_base(types::Rights::PATH_RENAME_SOURCE);
+            let (old_dirfd, old_path) = path::get(
+                &old_entry,
+                &required_rights,
+                types::Lookupflags::empty(),
+                old_path,
+                true,
+            )?;
+            let required_rights = HandleRights::from_base(types::Rights::PATH_RENAME_TARGET);
+            let (new_dirfd, new_path) = path::get(
+                &new_entry,
+                &required_rights,
+                types::Lookupflags::empty(),
+                new_path,
+                true,
+            )?;
+            old_dirfd.rename(&old_path, new_dirfd, &new_path)
+        })
     }
 
     fn path_symlink(
@@ -643,30 +701,33 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     ) -> Result<()> {
         let required_rights = HandleRights::from_base(types::Rights::PATH_SYMLINK);
         let entry = self.get_entry(dirfd)?;
-        let (new_fd, new_path) = path::get(
-            &entry,
-            &required_rights,
-            types::Lookupflags::empty(),
-            new_path,
-            true,
-        )?;
-        let old_path = old_path.as_str()?;
-        trace!("     | old_path='{}'", &*old_path);
-        new_fd.symlink(&old_path, &new_path)
+        self.journaled("path_symlink", || {
+            let (new_fd, new_path) = path::get(
+                &entry,
+                &required_rights,
+                types::Lookupflags::empty(),
+                new_path,
+                true,
+            )?;
+            let old_path = old_path.as_str()?;
+            trace!("     | old_path='{}'", &*old_path);
+            new_fd.symlink(&old_path, &new_path)
+        })
     }
 
     fn path_unlink_file(&self, dirfd: types::Fd, path: &GuestPtr<'_, str>) -> Result<()> {
         let required_rights = HandleRights::from_base(types::Rights::PATH_UNLINK_FILE);
         let entry = self.get_entry(dirfd)?;
-        let (dirfd, path) = path::get(
-            &entry,
-            &required_rights,
-            types::Lookupflags::empty(),
-            path,
-            false,
-        )?;
-        dirfd.unlink_file(&path)?;
-        Ok(())
+        self.journaled("path_unlink_file", || {
+            let (dirfd, path) = path::get(
+                &entry,
+                &required_rights,
+                types::Lookupflags::empty(),
+                path,
+                false,
+            )?;
+            dirfd.unlink_file(&path)
+        })
     }
 
     fn poll_oneoff(
@@ -771,7 +832,10 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         // The underlying implementation should successfully and immediately return
         // if no events have been passed. Such situation may occur if all provided
         // events have been filtered out as errors in the code above.
-        poll::oneoff(timeout, fd_events, &mut events)?;
+        let events = self.journaled("poll_oneoff", move || {
+            poll::oneoff(timeout, fd_events, &mut events)?;
+            Ok(events)
+        })?;
         let nevents = events.len().try_into()?;
 
         let out_events = out.as_array(nevents);
@@ -791,8 +855,17 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         unimplemented!("runtimes are expected to override this implementation")
     }
 
-    fn proc_raise(&self, _sig: types::Signal) -> Result<()> {
-        unimplemented!("proc_raise")
+    fn proc_raise(&self, sig: types::Signal) -> Result<()> {
+        match self.signal_action(sig) {
+            SignalAction::Ignore => Ok(()),
+            // Like proc_exit, terminating the guest is expected to unwind the
+            // stack, which requires runtime-specific logic, so the runtime is
+            // left to trap once this returns.
+            SignalAction::Terminate => {
+                self.terminate(SignalExit(sig));
+                Err(Errno::Intr)
+            }
+        }
     }
 
     fn sched_yield(&self) -> Result<()> {
@@ -801,10 +874,12 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     }
 
     fn random_get(&self, buf: &GuestPtr<u8>, buf_len: types::Size) -> Result<()> {
-        let mut slice = buf.as_array(buf_len).as_slice()?;
-        getrandom::getrandom(&mut *slice).map_err(|err| {
-            error!("getrandom failure: {:?}", err);
-            Errno::Io
+        let mut slices = [buf.as_array(buf_len).as_slice_mut()?];
+        self.journaled_fill("random_get", &mut slices, |slices| {
+            getrandom::getrandom(&mut *slices[0]).map_err(|err| {
+                error!("getrandom failure: {:?}", err);
+                Errno::Io
+            })
         })
     }
 
@@ -830,3 +905,102 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         unimplemented!("sock_shutdown")
     }
 }
+
+/// Total number of bytes described by a list of guest iovecs.
+fn iovec_len(slices: &[GuestSlice<'_, u8>]) -> u64 {
+    slices.iter().map(|s| s.len() as u64).sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::WasiCtxBuilder;
+    use std::fs::File;
+    use std::path::Path;
+    use wiggle_test::HostMemory;
+
+    /// The descriptor of the directory preopened by `ctx`, after stdin, stdout and stderr.
+    const DIR_FD: u32 = 3;
+
+    fn ctx(dir: &Path, configure: impl FnOnce(&mut WasiCtxBuilder)) -> WasiCtx {
+        let mut builder = WasiCtxBuilder::new();
+        builder.preopened_dir(File::open(dir).unwrap(), ".");
+        configure(&mut builder);
+        builder.build().unwrap()
+    }
+
+    fn open(
+        ctx: &WasiCtx,
+        mem: &HostMemory,
+        name: &str,
+        rights: types::Rights,
+    ) -> Result<types::Fd> {
+        let path = GuestPtr::<[u8]>::new(mem, (0, name.len() as u32));
+        path.as_slice_mut()
+            .unwrap()
+            .copy_from_slice(name.as_bytes());
+        ctx.path_open(
+            DIR_FD.into(),
+            types::Lookupflags::empty(),
+            &GuestPtr::new(mem, (0, name.len() as u32)),
+            types::Oflags::CREAT,
+            rights,
+            types::Rights::empty(),
+            types::Fdflags::empty(),
+        )
+    }
+
+    fn write(ctx: &WasiCtx, mem: &HostMemory, fd: types::Fd, data: &[u8]) -> Result<types::Size> {
+        let buf = GuestPtr::<[u8]>::new(mem, (64, data.len() as u32));
+        buf.as_slice_mut().unwrap().copy_from_slice(data);
+        let ciov = types::Ciovec {
+            buf: GuestPtr::new(mem, 64),
+            buf_len: data.len() as u32,
+        };
+        GuestPtr::new(mem, 32).write(ciov).unwrap();
+        ctx.fd_write(fd, &GuestPtr::new(mem, (32, 1)))
+    }
+
+    #[test]
+    fn open_fds_limit_creates_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let mem = HostMemory::new();
+        // stdin, stdout, stderr and the preopened directory are already open.
+        let ctx = ctx(dir.path(), |b| {
+            b.max_open_fds(4);
+        });
+        assert_eq!(
+            open(&ctx, &mem, "file", types::Rights::FD_WRITE),
+            Err(Errno::Nfile)
+        );
+        assert!(!dir.path().join("file").exists());
+        assert_eq!(ctx.io_usage().files_created, 0);
+    }
+
+    #[test]
+    fn rights_are_checked_before_quotas() {
+        let dir = tempfile::tempdir().unwrap();
+        let mem = HostMemory::new();
+        let ctx = ctx(dir.path(), |b| {
+            b.max_bytes_written(0);
+        });
+        let fd = open(&ctx, &mem, "file", types::Rights::FD_READ).unwrap();
+        assert_eq!(write(&ctx, &mem, fd, b"hello"), Err(Errno::Notcapable));
+        let fd = open(&ctx, &mem, "file", types::Rights::FD_WRITE).unwrap();
+        assert_eq!(write(&ctx, &mem, fd, b"hello"), Err(Errno::Dquot));
+    }
+
+    #[test]
+    fn raise_requests_termination() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = ctx(dir.path(), |_| {});
+        assert_eq!(ctx.proc_raise(types::Signal::Chld), Ok(()));
+        assert_eq!(ctx.take_termination(), None);
+        assert_eq!(ctx.proc_raise(types::Signal::Term), Err(Errno::Intr));
+        assert_eq!(
+            ctx.take_termination(),
+            Some(SignalExit(types::Signal::Term))
+        );
+        assert_eq!(ctx.take_termination(), None);
+    }
+}
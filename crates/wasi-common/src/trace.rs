@@ -0,0 +1,369 @@
+//! strace-like tracing of the hostcalls made by a guest.
+//!
+//! Tracing is enabled per context with `WasiCtxBuilder::trace`. The shims
+//! binding WASI to a runtime (such as `wasmtime-wasi`) report every hostcall
+//! to the context's `Tracer` before dispatching it, so that calls which never
+//! return, such as `proc_exit`, are traced too, and then report its result.
+//! The `Tracer` writes one line per event either as human-readable text or as
+//! a JSON object.
+use crate::wasi::types::Errno;
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::fmt::{self, Write as _};
+use std::io::{self, Write};
+
+/// Output format of a `Tracer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// Human-readable lines in the style of strace, such as
+    /// `fd_write(fd=1, iovs=[{buf=0x10, buf_len=14}], nwritten=0x8) ...` for a
+    /// call and `<... fd_write resumed> = 0 (success)` for its result.
+    Text,
+    /// One JSON object per line, with an `"event"` of either `"call"` or
+    /// `"return"`.
+    Json,
+}
+
+/// A decoded argument of a hostcall.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceArg<'a> {
+    /// An integer argument, such as a file descriptor or a length.
+    Int(i64),
+    /// An argument better displayed in hexadecimal, such as a guest pointer.
+    Hex(u64),
+    /// A floating point argument.
+    Float(f64),
+    /// Flags, along with the names of their bits starting from the least significant one.
+    Flags(u64, &'static [&'static str]),
+    /// A string read from guest memory, such as a path.
+    Str(&'a str),
+    /// The buffers of an array of iovecs or ciovecs read from guest memory, as pointer and
+    /// length pairs.
+    Iovecs(Vec<(u32, u32)>),
+    /// An argument which couldn't be read from guest memory.
+    Invalid,
+}
+
+impl<'a> TraceArg<'a> {
+    /// Decode the string at `ptr` of `len` bytes in the guest `memory`.
+    pub fn guest_str(memory: &'a [u8], ptr: u32, len: u32) -> Self {
+        let start = ptr as usize;
+        let bytes = start
+            .checked_add(len as usize)
+            .and_then(|end| memory.get(start..end));
+        match bytes.map(std::str::from_utf8) {
+            Some(Ok(s)) => Self::Str(s),
+            _ => Self::Invalid,
+        }
+    }
+
+    /// Decode the array of `len` iovecs or ciovecs at `ptr` in the guest `memory`.
+    pub fn guest_iovecs(memory: &'a [u8], ptr: u32, len: u32) -> Self {
+        let start = ptr as usize;
+        let bytes = (len as usize)
+            .checked_mul(8)
+            .and_then(|size| start.checked_add(size))
+            .and_then(|end| memory.get(start..end));
+        let bytes = match bytes {
+            Some(bytes) => bytes,
+            None => return Self::Invalid,
+        };
+        let u32_at = |i: usize| {
+            let mut word = [0; 4];
+            word.copy_from_slice(&bytes[i..i + 4]);
+            u32::from_le_bytes(word)
+        };
+        Self::Iovecs(
+            (0..bytes.len())
+                .step_by(8)
+                .map(|i| (u32_at(i), u32_at(i + 4)))
+                .collect(),
+        )
+    }
+}
+
+/// Write the names of the bits set in `bits`, separated by `|`, followed by any unnamed bits in
+/// hexadecimal.
+fn write_flags(f: &mut impl fmt::Write, bits: u64, names: &[&str]) -> fmt::Result {
+    if bits == 0 {
+        return write!(f, "0");
+    }
+    let mut unnamed = bits;
+    let mut sep = "";
+    for (i, name) in names.iter().enumerate().take(64) {
+        if bits & (1 << i) != 0 {
+            write!(f, "{}{}", sep, name)?;
+            unnamed &= !(1 << i);
+            sep = "|";
+        }
+    }
+    if unnamed != 0 {
+        write!(f, "{}{:#x}", sep, unnamed)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for TraceArg<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(i) => write!(f, "{}", i),
+            Self::Hex(i) => write!(f, "{:#x}", i),
+            Self::Float(x) => write!(f, "{}", x),
+            Self::Flags(bits, names) => write_flags(f, *bits, names),
+            Self::Str(s) => write!(f, "{:?}", s),
+            Self::Iovecs(iovecs) => {
+                write!(f, "[")?;
+                for (i, (buf, buf_len)) in iovecs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{{buf={:#x}, buf_len={}}}", buf, buf_len)?;
+                }
+                write!(f, "]")
+            }
+            Self::Invalid => write!(f, "<invalid>"),
+        }
+    }
+}
+
+/// Writes a trace of the hostcalls made through a `WasiCtx`.
+pub struct Tracer {
+    format: TraceFormat,
+    out: RefCell<Box<dyn Write>>,
+}
+
+impl Tracer {
+    /// Create a `Tracer` writing to `out` in the specified `format`.
+    pub fn new<W: Write + 'static>(out: W, format: TraceFormat) -> Self {
+        Self {
+            format,
+            out: RefCell::new(Box::new(out)),
+        }
+    }
+
+    /// Create a `Tracer` writing to the host process' stderr.
+    pub fn stderr(format: TraceFormat) -> Self {
+        Self::new(io::stderr(), format)
+    }
+
+    /// Record a call to `func` of the WASI `module`, before it is dispatched.
+    pub fn call(&self, module: &str, func: &str, args: &[(&str, TraceArg<'_>)]) {
+        self.write(match self.format {
+            TraceFormat::Text => text_call(func, args),
+            TraceFormat::Json => json_call(module, func, args),
+        });
+    }
+
+    /// Record the return of the last call to `func` of the WASI `module`.
+    ///
+    /// `errno` is the raw errno value returned to the guest, if the function returns one.
+    pub fn ret(&self, module: &str, func: &str, errno: Option<i32>) {
+        self.write(match self.format {
+            TraceFormat::Text => text_ret(func, errno),
+            TraceFormat::Json => json_ret(module, func, errno),
+        });
+    }
+
+    fn write(&self, line: String) {
+        // Failing to trace shouldn't fail the guest.
+        if let Err(err) = writeln!(self.out.borrow_mut(), "{}", line) {
+            log::warn!("failed to write hostcall trace: {}", err);
+        }
+    }
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tracer")
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+/// Name of the `errno` value, as spelled in the witx files.
+fn errno_name(errno: i32) -> Option<String> {
+    u16::try_from(errno)
+        .ok()
+        .and_then(|raw| Errno::try_from(raw).ok())
+        .map(|e| format!("{:?}", e).to_lowercase())
+}
+
+fn text_call(func: &str, args: &[(&str, TraceArg<'_>)]) -> String {
+    let mut line = format!("{}(", func);
+    for (i, (name, arg)) in args.iter().enumerate() {
+        if i > 0 {
+            line.push_str(", ");
+        }
+        let _ = write!(line, "{}={}", name, arg);
+    }
+    line.push_str(") ...");
+    line
+}
+
+fn text_ret(func: &str, errno: Option<i32>) -> String {
+    let mut line = format!("<... {} resumed>", func);
+    if let Some(errno) = errno {
+        let _ = write!(line, " = {}", errno);
+        if let Some(name) = errno_name(errno) {
+            let _ = write!(line, " ({})", name);
+        }
+    }
+    line
+}
+
+fn json_call(module: &str, func: &str, args: &[(&str, TraceArg<'_>)]) -> String {
+    let mut line = format!(
+        "{{\"event\":\"call\",\"module\":{},\"func\":{},\"args\":{{",
+        json_str(module),
+        json_str(func)
+    );
+    for (i, (name, arg)) in args.iter().enumerate() {
+        if i > 0 {
+            line.push(',');
+        }
+        let value = match arg {
+            TraceArg::Int(i) => i.to_string(),
+            TraceArg::Float(x) if x.is_finite() => x.to_string(),
+            TraceArg::Str(s) => json_str(s),
+            TraceArg::Iovecs(iovecs) => {
+                let iovecs: Vec<String> = iovecs
+                    .iter()
+                    .map(|(buf, buf_len)| {
+                        format!(
+                            "{{\"buf\":{},\"buf_len\":{}}}",
+                            json_str(&format!("{:#x}", buf)),
+                            buf_len
+                        )
+                    })
+                    .collect();
+                format!("[{}]", iovecs.join(","))
+            }
+            TraceArg::Hex(_) | TraceArg::Float(_) | TraceArg::Flags(..) | TraceArg::Invalid => {
+                json_str(&arg.to_string())
+            }
+        };
+        let _ = write!(line, "{}:{}", json_str(name), value);
+    }
+    line.push_str("}}");
+    line
+}
+
+fn json_ret(module: &str, func: &str, errno: Option<i32>) -> String {
+    let mut line = format!(
+        "{{\"event\":\"return\",\"module\":{},\"func\":{}",
+        json_str(module),
+        json_str(func)
+    );
+    if let Some(errno) = errno {
+        let _ = write!(line, ",\"errno\":{}", errno);
+        if let Some(name) = errno_name(errno) {
+            let _ = write!(line, ",\"errno_name\":{}", json_str(&name));
+        }
+    }
+    line.push('}');
+    line
+}
+
+fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{json_call, json_ret, text_call, text_ret, TraceArg};
+
+    const OFLAGS: &[&str] = &["creat", "directory", "excl", "trunc"];
+
+    #[test]
+    fn text() {
+        let args = [
+            ("fd", TraceArg::Int(3)),
+            ("path", TraceArg::Str("foo/bar")),
+            ("oflags", TraceArg::Flags(5, OFLAGS)),
+            ("opened_fd", TraceArg::Hex(0x10)),
+        ];
+        assert_eq!(
+            text_call("path_open", &args),
+            "path_open(fd=3, path=\"foo/bar\", oflags=creat|excl, opened_fd=0x10) ..."
+        );
+        assert_eq!(
+            text_ret("path_open", Some(8)),
+            "<... path_open resumed> = 8 (badf)"
+        );
+        assert_eq!(text_call("proc_exit", &[]), "proc_exit() ...");
+        assert_eq!(text_ret("proc_exit", None), "<... proc_exit resumed>");
+    }
+
+    #[test]
+    fn json() {
+        let args = [
+            ("fd", TraceArg::Int(3)),
+            ("path", TraceArg::Str("a\"b")),
+            ("oflags", TraceArg::Flags(9, OFLAGS)),
+            ("iovs", TraceArg::Iovecs(vec![(16, 14)])),
+        ];
+        assert_eq!(
+            json_call("wasi_snapshot_preview1", "path_open", &args),
+            "{\"event\":\"call\",\"module\":\"wasi_snapshot_preview1\",\"func\":\"path_open\",\
+             \"args\":{\"fd\":3,\"path\":\"a\\\"b\",\"oflags\":\"creat|trunc\",\
+             \"iovs\":[{\"buf\":\"0x10\",\"buf_len\":14}]}}"
+        );
+        assert_eq!(
+            json_ret("wasi_snapshot_preview1", "path_open", Some(0)),
+            "{\"event\":\"return\",\"module\":\"wasi_snapshot_preview1\",\"func\":\"path_open\",\
+             \"errno\":0,\"errno_name\":\"success\"}"
+        );
+    }
+
+    #[test]
+    fn flags() {
+        assert_eq!(TraceArg::Flags(0, OFLAGS).to_string(), "0");
+        assert_eq!(TraceArg::Flags(2, OFLAGS).to_string(), "directory");
+        assert_eq!(TraceArg::Flags(0x21, OFLAGS).to_string(), "creat|0x20");
+    }
+
+    #[test]
+    fn guest_str() {
+        let memory = b"hello world";
+        assert_eq!(TraceArg::guest_str(memory, 6, 5), TraceArg::Str("world"));
+        assert_eq!(TraceArg::guest_str(memory, 6, 6), TraceArg::Invalid);
+        assert_eq!(
+            TraceArg::guest_str(memory, u32::max_value(), 2),
+            TraceArg::Invalid
+        );
+    }
+
+    #[test]
+    fn guest_iovecs() {
+        let memory = [0, 0, 16, 0, 0, 0, 14, 0, 0, 0, 32, 0, 0, 0, 1, 0, 0, 0];
+        assert_eq!(
+            TraceArg::guest_iovecs(&memory, 2, 2),
+            TraceArg::Iovecs(vec![(16, 14), (32, 1)])
+        );
+        assert_eq!(
+            TraceArg::guest_iovecs(&memory, 2, 2).to_string(),
+            "[{buf=0x10, buf_len=14}, {buf=0x20, buf_len=1}]"
+        );
+        assert_eq!(TraceArg::guest_iovecs(&memory, 4, 2), TraceArg::Invalid);
+        assert_eq!(
+            TraceArg::guest_iovecs(&memory, 0, u32::max_value()),
+            TraceArg::Invalid
+        );
+    }
+}
@@ -7,8 +7,8 @@ use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::convert::TryInto;
 use std::io;
-use std::io::SeekFrom;
-use std::path::{Path, PathBuf};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
 use std::rc::Rc;
 
 /// An entry in a virtual filesystem
@@ -131,6 +131,72 @@ impl VecFileContents {
             content: Vec::new(),
         }
     }
+
+    fn with_content(content: Vec<u8>) -> Self {
+        Self { content }
+    }
+}
+
+/// A seekable source of bytes backing the files of a mounted archive.
+trait ArchiveReader: Read + Seek {}
+
+impl<T: Read + Seek> ArchiveReader for T {}
+
+/// Read-only contents of a file stored uncompressed within an archive.
+///
+/// The contents are not held in memory; each read seeks into the shared archive reader.
+struct ArchiveFileContents {
+    archive: Rc<RefCell<dyn ArchiveReader>>,
+    offset: u64,
+    size: types::Filesize,
+}
+
+impl FileContents for ArchiveFileContents {
+    fn max_size(&self) -> types::Filesize {
+        self.size
+    }
+
+    fn size(&self) -> types::Filesize {
+        self.size
+    }
+
+    fn resize(&mut self, _new_size: types::Filesize) -> Result<()> {
+        Err(Errno::Rofs)
+    }
+
+    fn preadv(&self, iovs: &mut [io::IoSliceMut], offset: types::Filesize) -> Result<usize> {
+        let mut read_total = 0usize;
+        for iov in iovs.iter_mut() {
+            let read = self.pread(iov, offset + read_total as types::Filesize)?;
+            read_total += read;
+            if read < iov.len() {
+                break;
+            }
+        }
+        Ok(read_total)
+    }
+
+    fn pwritev(&mut self, _iovs: &[io::IoSlice], _offset: types::Filesize) -> Result<usize> {
+        Err(Errno::Rofs)
+    }
+
+    fn pread(&self, buf: &mut [u8], offset: types::Filesize) -> Result<usize> {
+        trace!("     | pread(buf.len={}, offset={})", buf.len(), offset);
+        let data_remaining = self.size.saturating_sub(offset);
+        let read_count = std::cmp::min(buf.len() as types::Filesize, data_remaining) as usize;
+        if read_count == 0 {
+            return Ok(0);
+        }
+
+        let mut archive = self.archive.borrow_mut();
+        archive.seek(SeekFrom::Start(self.offset + offset))?;
+        archive.read_exact(&mut buf[..read_count])?;
+        Ok(read_count)
+    }
+
+    fn pwrite(&mut self, _buf: &[u8], _offset: types::Filesize) -> Result<usize> {
+        Err(Errno::Rofs)
+    }
 }
 
 /// An `InMemoryFile` is a shared handle to some underlying data. The relationship is analagous to
@@ -253,7 +319,9 @@ impl Handle for InMemoryFile {
     fn read_vectored(&self, iovs: &mut [io::IoSliceMut]) -> Result<usize> {
         trace!("read_vectored(iovs={:?})", iovs);
         trace!("     | *read_start={:?}", self.cursor.get());
-        self.data.borrow_mut().preadv(iovs, self.cursor.get())
+        let read = self.data.borrow_mut().preadv(iovs, self.cursor.get())?;
+        self.cursor.set(self.cursor.get() + read as types::Filesize);
+        Ok(read)
     }
     fn seek(&self, offset: SeekFrom) -> Result<types::Filesize> {
         let content_len = self.data.borrow().size();
@@ -427,6 +495,81 @@ impl VirtualDir {
         }
     }
 
+    /// Create a `VirtualDir` tree from `VirtualDirEntry::Directory` contents.
+    pub(crate) fn from_entries(entries: HashMap<String, VirtualDirEntry>, writable: bool) -> Self {
+        let mut dir = Self::new(writable);
+        for (path, entry) in entries.into_iter() {
+            match entry {
+                VirtualDirEntry::Directory(dir_entries) => {
+                    dir.add_dir(Self::from_entries(dir_entries, writable), path);
+                }
+                VirtualDirEntry::File(content) => {
+                    dir.add_file(content, path);
+                }
+            }
+        }
+        dir
+    }
+
+    /// Mount a tar archive as a read-only `VirtualDir`.
+    ///
+    /// Only the archive's headers are read up front; file contents are read lazily from
+    /// `archive` when the guest accesses them. Entries other than regular files and directories
+    /// (links, devices, etc.) are skipped. Archive paths which are absolute or contain `..`
+    /// components are rejected, as are files with the same path as a directory.
+    pub fn from_tar<R: Read + Seek + 'static>(archive: R) -> io::Result<Self> {
+        let mut tar = tar::Archive::new(archive);
+        let mut files = Vec::new();
+        let mut dirs = Vec::new();
+        for entry in tar.entries()? {
+            let entry = entry?;
+            let path = entry.path()?.into_owned();
+            match entry.header().entry_type() {
+                tar::EntryType::Regular | tar::EntryType::Continuous => {
+                    files.push((path, entry.raw_file_position(), entry.size()));
+                }
+                tar::EntryType::Directory => dirs.push(path),
+                other => trace!("VirtualDir::from_tar skipping {:?} entry {:?}", other, path),
+            }
+        }
+
+        let archive: Rc<RefCell<dyn ArchiveReader>> = Rc::new(RefCell::new(tar.into_inner()));
+        let mut root = HashMap::new();
+        for path in dirs {
+            let (parent, name) = archive_entry_parent(&mut root, &path)?;
+            if let Some(name) = name {
+                parent
+                    .entry(name)
+                    .or_insert_with(VirtualDirEntry::empty_directory);
+            }
+        }
+        for (path, offset, size) in files {
+            let (parent, name) = archive_entry_parent(&mut root, &path)?;
+            let name = name.ok_or_else(|| invalid_archive_path(&path))?;
+            let contents = ArchiveFileContents {
+                archive: Rc::clone(&archive),
+                offset,
+                size,
+            };
+            if let Some(VirtualDirEntry::Directory(_)) =
+                parent.insert(name, VirtualDirEntry::File(Box::new(contents)))
+            {
+                return Err(invalid_archive_path(&path));
+            }
+        }
+
+        Ok(Self::from_entries(root, false))
+    }
+
+    /// Copy the tree of the host directory at `path` into a writable, in-memory `VirtualDir`.
+    ///
+    /// The contents of its files are read up front, and changes made by the guest are never
+    /// written back to the host. Entries other than regular files and directories (symlinks,
+    /// devices, etc.) are skipped, so the guest can't reach outside of the copied tree.
+    pub fn snapshot<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::from_entries(snapshot_entries(path.as_ref())?, true))
+    }
+
     #[allow(dead_code)]
     pub fn with_dir<P: AsRef<Path>>(mut self, dir: Self, path: P) -> Self {
         self.add_dir(dir, path);
@@ -458,6 +601,75 @@ impl VirtualDir {
     }
 }
 
+/// Read the entries of the host directory at `path`, recursively, for `VirtualDir::snapshot`.
+fn snapshot_entries(path: &Path) -> io::Result<HashMap<String, VirtualDirEntry>> {
+    let mut entries = HashMap::new();
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name().into_string().map_err(|name| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("non-UTF-8 file name: {:?}", name),
+            )
+        })?;
+        // `DirEntry::file_type` doesn't follow symlinks.
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            let dir = snapshot_entries(&entry.path())?;
+            entries.insert(name, VirtualDirEntry::Directory(dir));
+        } else if file_type.is_file() {
+            let content = VecFileContents::with_content(std::fs::read(entry.path())?);
+            entries.insert(name, VirtualDirEntry::File(Box::new(content)));
+        } else {
+            trace!("VirtualDir::snapshot skipping {:?}", entry.path());
+        }
+    }
+    Ok(entries)
+}
+
+/// Walk (and create as needed) the directories leading to an archive entry at `path`, returning
+/// the entries of its parent directory along with the entry's own name.
+///
+/// The name is `None` if `path` refers to the archive root itself.
+fn archive_entry_parent<'a>(
+    root: &'a mut HashMap<String, VirtualDirEntry>,
+    path: &Path,
+) -> io::Result<(&'a mut HashMap<String, VirtualDirEntry>, Option<String>)> {
+    let mut names = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(name) => {
+                let name = name.to_str().ok_or_else(|| invalid_archive_path(path))?;
+                names.push(name.to_owned());
+            }
+            Component::Prefix(_) | Component::RootDir | Component::ParentDir => {
+                return Err(invalid_archive_path(path));
+            }
+        }
+    }
+
+    let name = names.pop();
+    let mut dir = root;
+    for name in names {
+        let entry = dir
+            .entry(name)
+            .or_insert_with(VirtualDirEntry::empty_directory);
+        dir = match entry {
+            VirtualDirEntry::Directory(entries) => entries,
+            VirtualDirEntry::File(_) => return Err(invalid_archive_path(path)),
+        };
+    }
+    Ok((dir, name))
+}
+
+fn invalid_archive_path(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid path in archive: {}", path.display()),
+    )
+}
+
 impl MovableFile for VirtualDir {
     fn set_parent(&self, new_parent: Option<Box<dyn Handle>>) {
         *self.parent.borrow_mut() = new_parent;
@@ -718,6 +930,10 @@ impl Handle for VirtualDir {
         Err(Errno::Notdir)
     }
     fn remove_directory(&self, path: &str) -> Result<()> {
+        if !self.writable {
+            return Err(Errno::Acces);
+        }
+
         let trimmed_path = path.trim_end_matches('/');
         let mut entries = self.entries.borrow_mut();
         match entries.entry(Path::new(trimmed_path).to_path_buf()) {
@@ -767,6 +983,10 @@ impl Handle for VirtualDir {
             return Err(Errno::Isdir);
         }
 
+        if !self.writable {
+            return Err(Errno::Acces);
+        }
+
         let mut entries = self.entries.borrow_mut();
         match entries.entry(Path::new(trimmed_path).to_path_buf()) {
             Entry::Occupied(e) => {
@@ -799,3 +1019,145 @@ impl Handle for VirtualDir {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    fn archive(entries: &[(&str, Option<&[u8]>)]) -> Cursor<Vec<u8>> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, contents) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(0o755);
+            match contents {
+                Some(contents) => {
+                    header.set_entry_type(tar::EntryType::Regular);
+                    header.set_size(contents.len() as u64);
+                    builder.append_data(&mut header, path, *contents).unwrap();
+                }
+                None => {
+                    header.set_entry_type(tar::EntryType::Directory);
+                    header.set_size(0);
+                    builder.append_data(&mut header, path, io::empty()).unwrap();
+                }
+            }
+        }
+        Cursor::new(builder.into_inner().unwrap())
+    }
+
+    /// Read the file at `path`, whose components are looked up one at a time like `path::get`
+    /// does.
+    fn read(dir: &VirtualDir, path: &str) -> Result<Vec<u8>> {
+        let mut file = dir.try_clone()?;
+        for name in path.split('/') {
+            file = file.openat(
+                name,
+                true,
+                false,
+                types::Oflags::empty(),
+                types::Fdflags::empty(),
+            )?;
+        }
+        let mut buf = vec![0; file.filestat_get()?.size as usize];
+        let read = file.preadv(&mut [io::IoSliceMut::new(&mut buf)], 0)?;
+        assert_eq!(read, buf.len());
+        Ok(buf)
+    }
+
+    #[test]
+    fn read_vectored_advances_cursor() {
+        let file = InMemoryFile::memory_backed();
+        file.write_vectored(&[io::IoSlice::new(b"hello")]).unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut buf = [0; 3];
+        assert_eq!(
+            file.read_vectored(&mut [io::IoSliceMut::new(&mut buf)]),
+            Ok(3)
+        );
+        assert_eq!(&buf, b"hel");
+        assert_eq!(
+            file.read_vectored(&mut [io::IoSliceMut::new(&mut buf)]),
+            Ok(2)
+        );
+        assert_eq!(&buf[..2], b"lo");
+        assert_eq!(
+            file.read_vectored(&mut [io::IoSliceMut::new(&mut buf)]),
+            Ok(0)
+        );
+    }
+
+    #[test]
+    fn from_tar() {
+        let dir = VirtualDir::from_tar(archive(&[
+            ("a", None),
+            ("a/b.txt", Some(b"hello")),
+            ("c/d.txt", Some(b"world")),
+        ]))
+        .unwrap();
+        assert_eq!(read(&dir, "a/b.txt"), Ok(b"hello".to_vec()));
+        assert_eq!(read(&dir, "c/d.txt"), Ok(b"world".to_vec()));
+        assert_eq!(read(&dir, "e.txt"), Err(Errno::Noent));
+    }
+
+    #[test]
+    fn from_tar_is_read_only() {
+        let dir = VirtualDir::from_tar(archive(&[("a.txt", Some(b"hello"))])).unwrap();
+        let open = |oflags| {
+            dir.openat("b.txt", true, true, oflags, types::Fdflags::empty())
+                .map(|_| ())
+        };
+        assert_eq!(open(types::Oflags::CREAT), Err(Errno::Acces));
+        assert_eq!(dir.create_directory("c"), Err(Errno::Acces));
+        assert_eq!(dir.unlink_file("a.txt"), Err(Errno::Acces));
+        assert_eq!(read(&dir, "a.txt"), Ok(b"hello".to_vec()));
+    }
+
+    #[test]
+    fn from_tar_directories_are_read_only() {
+        let dir = VirtualDir::from_tar(archive(&[("a", None)])).unwrap();
+        assert_eq!(dir.remove_directory("a"), Err(Errno::Acces));
+    }
+
+    #[test]
+    fn from_tar_rejects_file_over_directory() {
+        let err = VirtualDir::from_tar(archive(&[("a", None), ("a", Some(b"hello"))]))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = VirtualDir::from_tar(archive(&[("a/b.txt", Some(b"")), ("a", Some(b""))]))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn snapshot() {
+        let host = tempfile::tempdir().unwrap();
+        std::fs::create_dir(host.path().join("a")).unwrap();
+        std::fs::write(host.path().join("a/b.txt"), b"hello").unwrap();
+        std::fs::write(host.path().join("c.txt"), b"world").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("/", host.path().join("d")).unwrap();
+
+        let dir = VirtualDir::snapshot(host.path()).unwrap();
+        assert_eq!(read(&dir, "a/b.txt"), Ok(b"hello".to_vec()));
+        assert_eq!(read(&dir, "c.txt"), Ok(b"world".to_vec()));
+        assert_eq!(read(&dir, "d/etc"), Err(Errno::Noent));
+
+        // Changes made to the snapshot stay in memory.
+        dir.unlink_file("c.txt").unwrap();
+        dir.create_directory("e").unwrap();
+        assert_eq!(read(&dir, "c.txt"), Err(Errno::Noent));
+        assert!(host.path().join("c.txt").exists());
+        assert!(!host.path().join("e").exists());
+    }
+
+    #[test]
+    fn from_tar_rejects_escaping_paths() {
+        let err = VirtualDir::from_tar(archive(&[("../a.txt", Some(b"hello"))]))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
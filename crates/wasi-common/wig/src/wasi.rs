@@ -34,6 +34,15 @@ pub fn define_struct(args: TokenStream) -> TokenStream {
     let mut ctor_externs = Vec::new();
     let mut ctor_fields = Vec::new();
     let mut linker_add = Vec::new();
+    // Snapshot 0 numbers signals like later snapshots do.
+    let raise = quote! {
+        match std::convert::TryFrom::try_from(sig) {
+            Ok(sig) => unsafe {
+                hostcalls::proc_raise(&mut my_cx.borrow_mut(), &mut [], sig).into()
+            },
+            Err(_) => wasi_common::old::snapshot_0::wasi::__WASI_ERRNO_INVAL.into(),
+        }
+    };
 
     for module in doc.modules() {
         let module_name = module.name.as_str();
@@ -46,13 +55,8 @@ pub fn define_struct(args: TokenStream) -> TokenStream {
             linker_add.push(quote! {
                 linker.define(#module_name, #name, self.#name_ident.clone())?;
             });
-            // `proc_exit` is special; it's essentially an unwinding primitive,
-            // so we implement it in the runtime rather than use the implementation
-            // in wasi-common.
-            if name == "proc_exit" {
-                ctor_externs.push(quote! {
-                    let #name_ident = wasmtime::Func::wrap(store, crate::wasi_proc_exit);
-                });
+            if let Some(shim) = runtime_shim(module_name, func, raise.clone()) {
+                ctor_externs.push(shim);
                 continue;
             }
 
@@ -177,7 +181,7 @@ pub fn define_struct(args: TokenStream) -> TokenStream {
                         witx::IntRepr::U16 => {
                             returns.push(quote! { types::I32 });
                             ret_ty = quote! { i32 };
-                            cvt_ret = quote! { .into() }
+                            cvt_ret = quote! { .into() };
                         }
                         other => panic!("unsupported ret enum repr {:?}", other),
                     },
@@ -196,6 +200,8 @@ pub fn define_struct(args: TokenStream) -> TokenStream {
             }
 
             let format_str = format!("{}({})", name, formats.join(", "));
+            let (trace_call, trace_ret) =
+                trace_shim(module_name, func, quote! { memory.data_unchecked() });
             ctor_externs.push(quote! {
                 let my_cx = cx.clone();
                 let #name_ident = wasmtime::Func::wrap(
@@ -214,11 +220,14 @@ pub fn define_struct(args: TokenStream) -> TokenStream {
                                     #handle_early_error
                                 }
                             };
-                            hostcalls::#name_ident(
+                            #trace_call
+                            let ret: #ret_ty = hostcalls::#name_ident(
                                 &mut my_cx.borrow_mut(),
                                 memory.data_unchecked_mut(),
                                 #(#hostcall_args),*
-                            ) #cvt_ret
+                            ) #cvt_ret;
+                            #trace_ret
+                            ret
                         }
                     }
                 );
@@ -288,6 +297,16 @@ pub fn define_struct_for_wiggle(args: TokenStream) -> TokenStream {
     let mut ctor_externs = Vec::new();
     let mut ctor_fields = Vec::new();
     let mut linker_add = Vec::new();
+    let raise = quote! {
+        match <wasi_common::wasi::types::Signal as std::convert::TryFrom<i32>>::try_from(sig) {
+            Ok(signal) => {
+                use wasi_common::wasi::wasi_snapshot_preview1::WasiSnapshotPreview1;
+                let ret = my_cx.borrow().proc_raise(signal);
+                ret.err().unwrap_or(wasi_common::wasi::Errno::Success).into()
+            }
+            Err(_) => wasi_common::wasi::Errno::Inval.into(),
+        }
+    };
 
     for module in doc.modules() {
         let module_name = module.name.as_str();
@@ -301,13 +320,8 @@ pub fn define_struct_for_wiggle(args: TokenStream) -> TokenStream {
             linker_add.push(quote! {
                 linker.define(#module_name, #name, self.#name_ident.clone())?;
             });
-            // `proc_exit` is special; it's essentially an unwinding primitive,
-            // so we implement it in the runtime rather than use the implementation
-            // in wasi-common.
-            if name == "proc_exit" {
-                ctor_externs.push(quote! {
-                    let #name_ident = wasmtime::Func::wrap(store, crate::wasi_proc_exit);
-                });
+            if let Some(shim) = runtime_shim(module_name, func, raise.clone()) {
+                ctor_externs.push(shim);
                 continue;
             }
 
@@ -432,7 +446,7 @@ pub fn define_struct_for_wiggle(args: TokenStream) -> TokenStream {
                         witx::IntRepr::U16 => {
                             returns.push(quote! { types::I32 });
                             ret_ty = quote! { i32 };
-                            cvt_ret = quote! { .into() }
+                            cvt_ret = quote! { .into() };
                         }
                         other => panic!("unsupported ret enum repr {:?}", other),
                     },
@@ -451,6 +465,8 @@ pub fn define_struct_for_wiggle(args: TokenStream) -> TokenStream {
             }
 
             let format_str = format!("{}({})", name, formats.join(", "));
+            let (trace_call, trace_ret) =
+                trace_shim(module_name, func, quote! { mem.mem.data_unchecked() });
             ctor_externs.push(quote! {
                 let my_cx = cx.clone();
                 let #name_ident = wasmtime::Func::wrap(
@@ -476,11 +492,14 @@ pub fn define_struct_for_wiggle(args: TokenStream) -> TokenStream {
                             // root of each function invocation is correct.
                             let bc = wiggle::BorrowChecker::new();
                             let mem = WasiMemory { mem, bc };
-                            wasi_common::wasi::#module_id::#name_ident(
+                            #trace_call
+                            let ret: #ret_ty = wasi_common::wasi::#module_id::#name_ident(
                                 &mut my_cx.borrow_mut(),
                                 &mem,
                                 #(#hostcall_args),*
-                            ) #cvt_ret
+                            ) #cvt_ret;
+                            #trace_ret
+                            ret
                         }
                     }
                 );
@@ -498,7 +517,7 @@ pub fn define_struct_for_wiggle(args: TokenStream) -> TokenStream {
         }
 
         unsafe impl wiggle::GuestMemory for WasiMemory {
-            fn base(&self) -> (*mut u8, u32) {
+            fn base(&self) -> (*mut u8, u64) {
                 (self.mem.data_ptr(), self.mem.data_size() as _)
             }
             fn borrow_checker(&self) -> &wiggle::BorrowChecker {
@@ -552,3 +571,160 @@ pub fn define_struct_for_wiggle(args: TokenStream) -> TokenStream {
         }
     }
 }
+
+/// The shim of `func` of `module` if it is implemented in the runtime rather than in
+/// wasi-common, which is the case of the functions that unwind the guest.
+///
+/// `raise` is the call of the `proc_raise` implementation of wasi-common with
+/// the `sig` argument, returning the errno for the guest.
+fn runtime_shim(
+    module: &str,
+    func: &witx::InterfaceFunc,
+    raise: TokenStream,
+) -> Option<TokenStream> {
+    let name_ident = Ident::new(func.name.as_str(), Span::call_site());
+    // These functions only take integers, so their trace doesn't read memory.
+    let (trace_call, trace_ret) = trace_shim(module, func, quote! { &[] });
+    match func.name.as_str() {
+        // `proc_exit` is special; it's essentially an unwinding primitive,
+        // so we implement it in the runtime rather than use the implementation
+        // in wasi-common.
+        "proc_exit" => Some(quote! {
+            let my_cx = cx.clone();
+            let #name_ident = wasmtime::Func::wrap(
+                store,
+                move |rval: i32| -> Result<(), wasmtime::Trap> {
+                    #trace_call
+                    crate::wasi_proc_exit(rval)
+                },
+            );
+        }),
+        // `proc_raise` may terminate the program, which unwinds like
+        // `proc_exit`, so the runtime traps when wasi-common requests it.
+        "proc_raise" => Some(quote! {
+            let my_cx = cx.clone();
+            let #name_ident = wasmtime::Func::wrap(
+                store,
+                move |sig: i32| -> Result<i32, wasmtime::Trap> {
+                    log::trace!("proc_raise(sig={})", sig);
+                    #trace_call
+                    let errno: i32 = #raise;
+                    let ret = crate::wasi_proc_raise(errno, my_cx.borrow().take_termination())?;
+                    #trace_ret
+                    Ok(ret)
+                },
+            );
+        }),
+        _ => None,
+    }
+}
+
+/// The code of the shim of `func` of `module` reporting the call to the
+/// context's `Tracer` before dispatching it, and the code reporting its result,
+/// `ret`, after it returns.
+///
+/// Arguments are decoded for the trace; the strings and iovecs they point to
+/// are read from the guest memory, the slice `data`.
+fn trace_shim(
+    module: &str,
+    func: &witx::InterfaceFunc,
+    data: TokenStream,
+) -> (TokenStream, TokenStream) {
+    let name = func.name.as_str();
+    let mut args = Vec::new();
+    let mut reads_memory = false;
+    for param in func.params.iter() {
+        let arg = utils::param_name(param);
+        let arg_str = arg.to_string();
+        let len = format_ident!("{}_len", arg);
+        // The argument is passed to the shim as an `i64` if `is_64`, and as an
+        // `i32` otherwise.
+        let int = |is_64: bool| {
+            if is_64 {
+                quote! { wasi_common::TraceArg::Int(#arg as i64) }
+            } else {
+                quote! { wasi_common::TraceArg::Int(#arg as u32 as i64) }
+            }
+        };
+        let value = match &*param.tref.type_() {
+            witx::Type::Int(i) => int(matches!(i.repr, witx::IntRepr::U64)),
+            witx::Type::Enum(e) => int(matches!(e.repr, witx::IntRepr::U64)),
+            witx::Type::Flags(f) => {
+                let bits = if matches!(f.repr, witx::IntRepr::U64) {
+                    quote! { #arg as u64 }
+                } else {
+                    quote! { #arg as u32 as u64 }
+                };
+                let names = f.flags.iter().map(|flag| flag.name.as_str());
+                quote! { wasi_common::TraceArg::Flags(#bits, &[#(#names),*]) }
+            }
+            witx::Type::Builtin(witx::BuiltinType::S64)
+            | witx::Type::Builtin(witx::BuiltinType::U64) => int(true),
+            witx::Type::Builtin(witx::BuiltinType::F32)
+            | witx::Type::Builtin(witx::BuiltinType::F64) => {
+                quote! { wasi_common::TraceArg::Float(#arg as f64) }
+            }
+            witx::Type::Builtin(witx::BuiltinType::String) => {
+                reads_memory = true;
+                quote! { wasi_common::TraceArg::guest_str(data, #arg as u32, #len as u32) }
+            }
+            witx::Type::Builtin(_) | witx::Type::Handle(_) => int(false),
+            witx::Type::Array(elem) if is_iovec(elem) => {
+                reads_memory = true;
+                quote! { wasi_common::TraceArg::guest_iovecs(data, #arg as u32, #len as u32) }
+            }
+            witx::Type::Array(_) => {
+                let len_str = len.to_string();
+                args.push(quote! { (#arg_str, wasi_common::TraceArg::Hex(#arg as u32 as u64)) });
+                args.push(quote! { (#len_str, wasi_common::TraceArg::Int(#len as u32 as i64)) });
+                continue;
+            }
+            witx::Type::ConstPointer(_) | witx::Type::Pointer(_) => {
+                quote! { wasi_common::TraceArg::Hex(#arg as u32 as u64) }
+            }
+            witx::Type::Struct(_) | witx::Type::Union(_) => panic!("unsupported argument type"),
+        };
+        args.push(quote! { (#arg_str, #value) });
+    }
+    // All results but the first are returned through out-pointers.
+    for result in func.results.iter().skip(1) {
+        let arg = format_ident!("{}", result.name.as_str());
+        let arg_str = arg.to_string();
+        args.push(quote! { (#arg_str, wasi_common::TraceArg::Hex(#arg as u32 as u64)) });
+    }
+
+    let data = if reads_memory {
+        quote! { let data = #data; }
+    } else {
+        quote! {}
+    };
+    let errno = if func.results.is_empty() {
+        quote! { None }
+    } else {
+        quote! { Some(ret) }
+    };
+    let call = quote! {
+        if let Some(tracer) = my_cx.borrow().tracer() {
+            #data
+            tracer.call(#module, #name, &[#(#args),*]);
+        }
+    };
+    let ret = quote! {
+        if let Some(tracer) = my_cx.borrow().tracer() {
+            tracer.ret(#module, #name, #errno);
+        }
+    };
+    (call, ret)
+}
+
+/// Whether `tref` is an `iovec` or a `ciovec`: a buffer's pointer followed by
+/// its length.
+fn is_iovec(tref: &witx::TypeRef) -> bool {
+    match &*tref.type_() {
+        witx::Type::Struct(s) => {
+            let names: Vec<&str> = s.members.iter().map(|m| m.name.as_str()).collect();
+            names == ["buf", "buf_len"]
+        }
+        _ => false,
+    }
+}
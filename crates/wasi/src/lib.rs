@@ -2,7 +2,7 @@ use wasmtime::Trap;
 
 pub mod old;
 
-pub use wasi_common::{WasiCtx, WasiCtxBuilder};
+pub use wasi_common::{I32Exit, SignalExit, WasiCtx, WasiCtxBuilder};
 
 // Defines a `struct Wasi` with member fields and appropriate APIs for dealing
 // with all the various WASI exports.
@@ -15,16 +15,43 @@ pub fn is_wasi_module(name: &str) -> bool {
     name.starts_with("wasi")
 }
 
+/// Get the exit status the guest requested if `trap` was caused by `proc_exit`
+/// or by a signal terminating it, as a native process would report it.
+pub fn exit_status(trap: &Trap) -> Option<i32> {
+    i32_exit(trap).map(|exit| exit.0).or_else(|| {
+        trap.downcast_ref::<SignalExit>()
+            .map(SignalExit::exit_status)
+    })
+}
+
+/// Get the `I32Exit` of the guest if `trap` was caused by `proc_exit`.
+pub fn i32_exit(trap: &Trap) -> Option<I32Exit> {
+    trap.downcast_ref::<I32Exit>().copied()
+}
+
+fn exit_trap(error: impl std::error::Error + Send + Sync + 'static) -> Trap {
+    Trap::from(Box::new(error) as Box<dyn std::error::Error + Send + Sync>)
+}
+
 /// Implement the WASI `proc_exit` function. This function is implemented here
 /// instead of in wasi-common so that we can use the runtime to perform an
 /// unwind rather than exiting the host process.
 fn wasi_proc_exit(status: i32) -> Result<(), Trap> {
     // Check that the status is within WASI's range.
     if status >= 0 && status < 126 {
-        Err(Trap::i32_exit(status))
+        Err(exit_trap(I32Exit(status)))
     } else {
         Err(Trap::new(
             "exit with invalid exit status outside of [0..126)",
         ))
     }
 }
+
+/// Finish the WASI `proc_raise` function once wasi-common returned `errno`,
+/// unwinding the guest like `proc_exit` if the signal terminated it.
+fn wasi_proc_raise(errno: i32, termination: Option<SignalExit>) -> Result<i32, Trap> {
+    match termination {
+        Some(exit) => Err(exit_trap(exit)),
+        None => Ok(errno),
+    }
+}
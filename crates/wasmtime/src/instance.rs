@@ -41,18 +41,23 @@ fn instantiate(
         );
     }
 
+    store.check_module_instance()?;
+
     let mut resolver = SimpleResolver { imports };
     let config = store.engine().config();
     let instance = unsafe {
-        let instance = compiled_module.instantiate(
-            &mut resolver,
-            &mut store.signatures_mut(),
-            config.memory_creator.as_ref().map(|a| a as _),
-            store.interrupts().clone(),
-            host,
-            &*store.externref_activations_table() as *const _ as *mut _,
-            &*store.stack_map_registry() as *const _ as *mut _,
-        )?;
+        let instance = store.with_limit_errors(|| {
+            compiled_module.instantiate(
+                &mut resolver,
+                &mut store.signatures_mut(),
+                config.memory_creator.as_ref().map(|a| a as _),
+                store.limiter(),
+                store.interrupts().clone(),
+                host,
+                &*store.externref_activations_table() as *const _ as *mut _,
+                &*store.stack_map_registry() as *const _ as *mut _,
+            )
+        })?;
 
         // After we've created the `InstanceHandle` we still need to run
         // initialization to set up data/elements/etc. We do this after adding
@@ -100,6 +105,9 @@ fn instantiate(
         }
     }
 
+    // Only successful instantiations count towards the store's limits.
+    store.add_module_instance();
+
     Ok(instance)
 }
 
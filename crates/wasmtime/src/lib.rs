@@ -14,8 +14,10 @@ mod externals;
 mod frame_info;
 mod func;
 mod instance;
+mod limits;
 mod linker;
 mod module;
+mod precompiled;
 mod r#ref;
 mod runtime;
 mod trampoline;
@@ -27,11 +29,12 @@ pub use crate::externals::*;
 pub use crate::frame_info::FrameInfo;
 pub use crate::func::*;
 pub use crate::instance::Instance;
+pub use crate::limits::{ResourceLimit, StoreLimits};
 pub use crate::linker::*;
 pub use crate::module::Module;
 pub use crate::r#ref::ExternRef;
 pub use crate::runtime::*;
-pub use crate::trap::Trap;
+pub use crate::trap::{Trap, TrapCode};
 pub use crate::types::*;
 pub use crate::values::*;
 
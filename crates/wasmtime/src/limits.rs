@@ -0,0 +1,203 @@
+use anyhow::{Context as _, Result};
+use std::cell::Cell;
+use std::fmt;
+use wasmtime_runtime::ResourceLimiter;
+
+/// Limits on the resources used by the instances of a [`Store`].
+///
+/// The limits are given to [`Store::new_with_limits`], and then apply to all
+/// the instances, memories and tables created in the store, including the ones
+/// created with [`Memory::new`] and [`Table::new`].
+///
+/// Growing a memory or a table beyond its limit fails the same way as growing
+/// it beyond its maximum size: `memory.grow` returns -1, and [`Memory::grow`]
+/// returns an error. The memory limit applies to the total size of all the
+/// memories of the store, and the table limit to each table. Creating a memory
+/// or table whose minimum size exceeds its limit, or more instances than
+/// allowed, is an instantiation error. In all cases [`Store::exceeded_limit`]
+/// then returns the limit that was hit.
+///
+/// # Example
+///
+/// ```
+/// # use wasmtime::*;
+/// # fn main() -> anyhow::Result<()> {
+/// let store = Store::new_with_limits(
+///     &Engine::default(),
+///     StoreLimits::new().memory_size(1 << 16).instances(1),
+/// );
+/// let module = Module::new(store.engine(), r#"
+///     (module
+///         (memory (export "memory") 1)
+///         (func (export "grow") (result i32)
+///             (memory.grow (i32.const 1))))
+/// "#)?;
+/// let instance = Instance::new(&store, &module, &[])?;
+/// let grow = instance.get_func("grow").unwrap().get0::<i32>()?;
+/// assert_eq!(grow()?, -1);
+/// assert_eq!(store.exceeded_limit(), Some(ResourceLimit::MemorySize));
+///
+/// assert!(Instance::new(&store, &module, &[]).is_err());
+/// assert_eq!(store.exceeded_limit(), Some(ResourceLimit::Instances));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Store`]: crate::Store
+/// [`Store::new_with_limits`]: crate::Store::new_with_limits
+/// [`Store::exceeded_limit`]: crate::Store::exceeded_limit
+/// [`Memory::new`]: crate::Memory::new
+/// [`Memory::grow`]: crate::Memory::grow
+/// [`Table::new`]: crate::Table::new
+#[derive(Clone, Debug, Default)]
+pub struct StoreLimits {
+    memory_size: Option<usize>,
+    table_elements: Option<u32>,
+    instances: Option<usize>,
+}
+
+impl StoreLimits {
+    /// Creates limits which don't limit anything.
+    pub fn new() -> StoreLimits {
+        StoreLimits::default()
+    }
+
+    /// Limits the total size of the linear memories of the store to `bytes`.
+    pub fn memory_size(&mut self, bytes: usize) -> &mut Self {
+        self.memory_size = Some(bytes);
+        self
+    }
+
+    /// Limits the number of elements of each table to `elements`.
+    pub fn table_elements(&mut self, elements: u32) -> &mut Self {
+        self.table_elements = Some(elements);
+        self
+    }
+
+    /// Limits the number of instances of modules created in the store to
+    /// `instances`.
+    ///
+    /// Only successful instantiations count towards this limit, and the
+    /// instances created for host-defined functions, memories, tables and
+    /// globals don't.
+    pub fn instances(&mut self, instances: usize) -> &mut Self {
+        self.instances = Some(instances);
+        self
+    }
+}
+
+/// A limit set by [`StoreLimits`].
+///
+/// Errors caused by exceeding a limit, such as failing to instantiate a module
+/// whose memory is too large, can be downcast to the limit.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ResourceLimit {
+    /// The total size of the linear memories, set by
+    /// [`StoreLimits::memory_size`].
+    MemorySize,
+    /// The number of elements of a table, set by
+    /// [`StoreLimits::table_elements`].
+    TableElements,
+    /// The number of instances, set by [`StoreLimits::instances`].
+    Instances,
+}
+
+impl fmt::Display for ResourceLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResourceLimit::MemorySize => write!(f, "memory size limit exceeded"),
+            ResourceLimit::TableElements => write!(f, "table elements limit exceeded"),
+            ResourceLimit::Instances => write!(f, "instances limit exceeded"),
+        }
+    }
+}
+
+impl std::error::Error for ResourceLimit {}
+
+/// Enforces the `StoreLimits` of a store, remembering the first limit hit.
+pub(crate) struct Limiter {
+    limits: StoreLimits,
+    instances: Cell<usize>,
+    /// The total size in bytes of the linear memories of the store.
+    memory_size: Cell<usize>,
+    exceeded: Cell<Option<ResourceLimit>>,
+    /// The last limit hit since `take_denied` was called.
+    denied: Cell<Option<ResourceLimit>>,
+}
+
+impl Limiter {
+    pub(crate) fn new(limits: &StoreLimits) -> Limiter {
+        Limiter {
+            limits: limits.clone(),
+            instances: Cell::new(0),
+            memory_size: Cell::new(0),
+            exceeded: Cell::new(None),
+            denied: Cell::new(None),
+        }
+    }
+
+    /// Returns the first limit that was hit, if any.
+    pub(crate) fn exceeded(&self) -> Option<ResourceLimit> {
+        self.exceeded.get()
+    }
+
+    /// Returns the last limit hit since this was last called, if any.
+    pub(crate) fn take_denied(&self) -> Option<ResourceLimit> {
+        self.denied.take()
+    }
+
+    /// Fails if another module instance would be one too many.
+    pub(crate) fn check_instance(&self) -> Result<()> {
+        if let Some(max) = self.limits.instances {
+            if self.instances.get() >= max {
+                self.exceed(ResourceLimit::Instances);
+                return Err(ResourceLimit::Instances)
+                    .with_context(|| format!("instance limit of {} exceeded", max));
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts a new module instance.
+    pub(crate) fn add_instance(&self) {
+        self.instances.set(self.instances.get() + 1);
+    }
+
+    fn exceed(&self, limit: ResourceLimit) {
+        self.denied.set(Some(limit));
+        if self.exceeded.get().is_none() {
+            self.exceeded.set(Some(limit));
+        }
+    }
+}
+
+impl ResourceLimiter for Limiter {
+    fn memory_growing(&self, current: usize, desired: usize) -> bool {
+        let total = self.memory_size.get() - current + desired;
+        match self.limits.memory_size {
+            Some(max) if total > max => {
+                self.exceed(ResourceLimit::MemorySize);
+                false
+            }
+            _ => {
+                self.memory_size.set(total);
+                true
+            }
+        }
+    }
+
+    fn memory_grow_failed(&self, current: usize, desired: usize) {
+        self.memory_size
+            .set(self.memory_size.get() - desired + current);
+    }
+
+    fn table_growing(&self, _current: u32, desired: u32) -> bool {
+        match self.limits.table_elements {
+            Some(max) if desired > max => {
+                self.exceed(ResourceLimit::TableElements);
+                false
+            }
+            _ => true,
+        }
+    }
+}
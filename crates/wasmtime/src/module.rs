@@ -1,4 +1,5 @@
 use crate::frame_info::GlobalFrameInfoRegistration;
+use crate::precompiled;
 use crate::runtime::Engine;
 use crate::types::{EntityType, ExportType, ExternType, ImportType};
 use anyhow::{Error, Result};
@@ -117,6 +118,9 @@ impl Module {
     ///   configuration of `enging`
     /// * If the `wat` feature is enabled and the input is text, then it may be
     ///   rejected if it fails to parse.
+    /// * The `engine` was configured with a
+    ///   [`Config::target`](crate::Config::target) other than the host, so it
+    ///   can't execute the code it compiles.
     ///
     /// The error returned should contain full information about why module
     /// creation failed if one is returned.
@@ -301,13 +305,79 @@ impl Module {
     }
 
     unsafe fn compile(engine: &Engine, binary: &[u8]) -> Result<Self> {
+        engine.check_host_target()?;
         let compiled = CompiledModule::new(engine.compiler(), binary, &*engine.config().profiler)?;
+        Ok(Module::from_compiled(engine, compiled))
+    }
 
-        Ok(Module {
+    /// Returns whether `bytes` look like a module precompiled by
+    /// [`Engine::precompile_module`], rather than a WebAssembly binary or
+    /// text module.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let engine = Engine::default();
+    /// let precompiled = engine.precompile_module(b"(module)")?;
+    /// assert!(Module::is_precompiled(&precompiled));
+    /// assert!(!Module::is_precompiled(b"(module)"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn is_precompiled(bytes: &[u8]) -> bool {
+        precompiled::is_precompiled(bytes)
+    }
+
+    /// Creates a new WebAssembly `Module` from a module previously compiled by
+    /// [`Engine::precompile_module`], without compiling it again.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if `engine` was configured with a
+    /// [`Config::target`](crate::Config::target) other than the host, if
+    /// `bytes` aren't a precompiled module, or if they were precompiled by a
+    /// different version of Wasmtime or by an engine whose code generation
+    /// settings differ from those of `engine`, such as its target, Cranelift
+    /// flags, or enabled WebAssembly features. The code may use fewer CPU
+    /// features than `engine` has, but not ones it lacks.
+    ///
+    /// # Unsafety
+    ///
+    /// The machine code in `bytes` is loaded as it is, without validation, so
+    /// this function is only safe to call on the trusted output of
+    /// [`Engine::precompile_module`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let engine = Engine::default();
+    /// let precompiled = engine.precompile_module(b"(module)")?;
+    /// let module = unsafe { Module::deserialize(&engine, &precompiled)? };
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn deserialize(engine: &Engine, bytes: &[u8]) -> Result<Module> {
+        engine.check_host_target()?;
+        let (wasm, functions) = precompiled::deserialize(engine, bytes)?;
+        let compiled = CompiledModule::from_precompiled(
+            engine.compiler(),
+            &wasm,
+            functions,
+            &*engine.config().profiler,
+        )?;
+        Ok(Module::from_compiled(engine, compiled))
+    }
+
+    fn from_compiled(engine: &Engine, compiled: CompiledModule) -> Module {
+        Module {
             engine: engine.clone(),
             compiled: Arc::new(compiled),
             frame_info_registration: Arc::new(Mutex::new(None)),
-        })
+        }
     }
 
     pub(crate) fn compiled_module(&self) -> &CompiledModule {
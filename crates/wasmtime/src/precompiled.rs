@@ -0,0 +1,258 @@
+//! The format of the modules produced by [`Engine::precompile_module`] and
+//! loaded by [`Module::deserialize`](crate::Module::deserialize).
+
+use crate::Engine;
+use anyhow::{bail, Context as _, Result};
+use serde::{Deserialize, Serialize};
+use wasmtime_environ::{ModuleCacheData, ModuleCacheDataTupleType};
+
+/// The bytes every precompiled module starts with, which start neither a wasm
+/// binary nor a text module.
+const MAGIC: &[u8] = b"\x7fwasmtime-precompiled\0";
+
+#[derive(Serialize, Deserialize)]
+struct Precompiled {
+    version: String,
+    settings: Settings,
+    wasm: Vec<u8>,
+    functions: ModuleCacheData,
+}
+
+/// The settings of an engine that affect the code it generates.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Settings {
+    target: String,
+    shared_flags: String,
+    /// The ISA-specific flags, such as the CPU features the code may use.
+    isa_flags: Vec<(String, String)>,
+    strategy: String,
+    static_memory_bound: u32,
+    static_memory_offset_guard_size: u64,
+    dynamic_memory_offset_guard_size: u64,
+    debug_info: bool,
+    interruptable: bool,
+    consume_fuel: bool,
+    wasm_threads: bool,
+    wasm_reference_types: bool,
+    wasm_bulk_memory: bool,
+    wasm_simd: bool,
+    wasm_multi_value: bool,
+}
+
+impl Settings {
+    fn new(engine: &Engine) -> Settings {
+        let config = engine.config();
+        let isa = engine.compiler().isa();
+        let tunables = &config.tunables;
+        let features = &config.validating_config.operator_config;
+        Settings {
+            target: isa.triple().to_string(),
+            shared_flags: isa.flags().to_string(),
+            isa_flags: isa.isa_flags(),
+            strategy: format!("{:?}", config.strategy),
+            static_memory_bound: tunables.static_memory_bound,
+            static_memory_offset_guard_size: tunables.static_memory_offset_guard_size,
+            dynamic_memory_offset_guard_size: tunables.dynamic_memory_offset_guard_size,
+            debug_info: tunables.debug_info,
+            interruptable: tunables.interruptable,
+            consume_fuel: tunables.consume_fuel,
+            wasm_threads: features.enable_threads,
+            wasm_reference_types: features.enable_reference_types,
+            wasm_bulk_memory: features.enable_bulk_memory,
+            wasm_simd: features.enable_simd,
+            wasm_multi_value: features.enable_multi_value,
+        }
+    }
+
+    /// Checks that code compiled with these settings can be loaded into an
+    /// engine with the `engine` settings.
+    fn check_compatible(&self, engine: &Settings) -> Result<()> {
+        macro_rules! check {
+            ($($field:ident)*) => {$(
+                if self.$field != engine.$field {
+                    bail!(
+                        "module was precompiled with `{}` set to {:?}, \
+                         but the engine has it set to {:?}",
+                        stringify!($field),
+                        self.$field,
+                        engine.$field,
+                    );
+                }
+            )*};
+        }
+        check!(
+            target
+            shared_flags
+            strategy
+            static_memory_bound
+            static_memory_offset_guard_size
+            dynamic_memory_offset_guard_size
+            debug_info
+            interruptable
+            consume_fuel
+            wasm_threads
+            wasm_reference_types
+            wasm_bulk_memory
+            wasm_simd
+            wasm_multi_value
+        );
+
+        if self.isa_flags.is_empty() {
+            bail!(
+                "module was precompiled for `{}`, whose target flags can't be checked",
+                self.target
+            );
+        }
+        // The code may only use CPU features that the engine's target has too,
+        // but needn't use all of them, so that modules compiled for the
+        // baseline of a target run on any machine of that target.
+        for (name, value) in self.isa_flags.iter() {
+            let engine_value = engine
+                .isa_flags
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.as_str());
+            let compatible = match (value.as_str(), engine_value) {
+                ("false", Some(_)) => true,
+                (value, Some(engine_value)) => value == engine_value,
+                (_, None) => false,
+            };
+            if !compatible {
+                bail!(
+                    "module was precompiled with the target flag `{}` set to {}, \
+                     but the engine has it set to {}",
+                    name,
+                    value,
+                    engine_value.unwrap_or("nothing"),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that the target flags of `engine`, such as the CPU features its code
+/// may use, can be recorded in precompiled modules. Backends which don't
+/// report their flags produce code that can't be checked when it's loaded.
+pub(crate) fn check_isa_flags(engine: &Engine) -> Result<()> {
+    let isa = engine.compiler().isa();
+    if isa.isa_flags().is_empty() {
+        bail!(
+            "cannot precompile modules for `{}`, whose target flags can't be checked",
+            isa.triple()
+        );
+    }
+    Ok(())
+}
+
+/// Returns whether `bytes` start like a precompiled module.
+pub(crate) fn is_precompiled(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Serializes the `functions` compiled by `engine` from the wasm binary `wasm`.
+pub(crate) fn serialize(
+    engine: &Engine,
+    wasm: &[u8],
+    functions: ModuleCacheDataTupleType,
+) -> Result<Vec<u8>> {
+    let precompiled = Precompiled {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        settings: Settings::new(engine),
+        wasm: wasm.to_vec(),
+        functions: ModuleCacheData::from_tuple(functions),
+    };
+    let mut bytes = MAGIC.to_vec();
+    bincode::serialize_into(&mut bytes, &precompiled)?;
+    Ok(bytes)
+}
+
+/// Deserializes a precompiled module, returning its wasm binary and compiled
+/// functions if `engine` can load them.
+pub(crate) fn deserialize(
+    engine: &Engine,
+    bytes: &[u8],
+) -> Result<(Vec<u8>, ModuleCacheDataTupleType)> {
+    if !is_precompiled(bytes) {
+        bail!("bytes are not a precompiled module");
+    }
+    let precompiled: Precompiled = bincode::deserialize(&bytes[MAGIC.len()..])
+        .context("failed to deserialize precompiled module")?;
+    if precompiled.version != env!("CARGO_PKG_VERSION") {
+        bail!(
+            "module was precompiled by Wasmtime {}, but this is Wasmtime {}",
+            precompiled.version,
+            env!("CARGO_PKG_VERSION"),
+        );
+    }
+    precompiled
+        .settings
+        .check_compatible(&Settings::new(engine))?;
+    Ok((precompiled.wasm, precompiled.functions.into_tuple()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Module};
+
+    #[test]
+    #[cfg_attr(target_arch = "aarch64", ignore)] // precompiling needs target flags
+    fn round_trip() -> Result<()> {
+        let engine = Engine::default();
+        let bytes = engine.precompile_module(b"(module (func (export \"f\")))")?;
+        assert!(is_precompiled(&bytes));
+        let module = unsafe { Module::deserialize(&engine, &bytes)? };
+        assert!(module.get_export("f").is_some());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "aarch64", ignore)] // precompiling needs target flags
+    fn mismatched_settings() -> Result<()> {
+        let bytes = Engine::default().precompile_module(b"(module)")?;
+
+        let mut config = Config::new();
+        config.interruptable(true);
+        let engine = Engine::new(&config);
+        let err = unsafe { Module::deserialize(&engine, &bytes) }
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("`interruptable`"), "{}", err);
+
+        let err = unsafe { Module::deserialize(&engine, b"(module)") }
+            .err()
+            .unwrap();
+        assert!(
+            err.to_string().contains("not a precompiled module"),
+            "{}",
+            err
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn cpu_features_subset() -> Result<()> {
+        let mut baseline = Config::new();
+        let mut sse41 = Config::new();
+        unsafe {
+            baseline.cranelift_cpu_feature("has_sse41", false)?;
+            sse41.cranelift_cpu_feature("has_sse41", true)?;
+        }
+        let baseline = Engine::new(&baseline);
+        let sse41 = Engine::new(&sse41);
+
+        // Code which doesn't use a feature loads where it is available.
+        let bytes = baseline.precompile_module(b"(module (func (export \"f\")))")?;
+        unsafe { Module::deserialize(&sse41, &bytes)? };
+
+        // Code which may use a feature doesn't load where it isn't.
+        let bytes = sse41.precompile_module(b"(module (func (export \"f\")))")?;
+        let err = unsafe { Module::deserialize(&baseline, &bytes) }
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("`has_sse41`"), "{}", err);
+        Ok(())
+    }
+}
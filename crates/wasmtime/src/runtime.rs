@@ -1,10 +1,12 @@
 use crate::externals::MemoryCreator;
+use crate::limits::{Limiter, ResourceLimit, StoreLimits};
+use crate::precompiled;
 use crate::r#ref::ExternRef;
 use crate::trampoline::{MemoryCreatorProxy, StoreInstanceHandle};
 use crate::Module;
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use std::any::Any;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::cmp;
 use std::collections::HashMap;
 use std::convert::TryFrom;
@@ -12,15 +14,18 @@ use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::path::Path;
 use std::rc::{Rc, Weak};
+use std::str::FromStr;
+use std::sync::atomic::Ordering::SeqCst;
 use std::sync::Arc;
+use target_lexicon::Triple;
 use wasmparser::{OperatorValidatorConfig, ValidatingParserConfig};
 use wasmtime_environ::settings::{self, Configurable, SetError};
 use wasmtime_environ::{ir, isa, isa::TargetIsa, wasm, CacheConfig, Tunables};
-use wasmtime_jit::{native, CompilationStrategy, Compiler};
+use wasmtime_jit::{native, CompilationStrategy, CompiledModule, Compiler};
 use wasmtime_profiling::{JitDumpAgent, NullProfilerAgent, ProfilingAgent, VTuneAgent};
 use wasmtime_runtime::{
-    debug_builtins, InstanceHandle, RuntimeMemoryCreator, SignalHandler, SignatureRegistry,
-    StackMapRegistry, VMExternRef, VMExternRefActivationsTable, VMInterrupts,
+    debug_builtins, InstanceHandle, ResourceLimiter, RuntimeMemoryCreator, SignalHandler,
+    SignatureRegistry, StackMapRegistry, VMExternRef, VMExternRefActivationsTable, VMInterrupts,
     VMSharedSignatureIndex,
 };
 
@@ -125,6 +130,27 @@ impl Config {
         self
     }
 
+    /// Configures whether executing WebAssembly code consumes fuel, trapping
+    /// once it has consumed all the fuel added to its store.
+    ///
+    /// Fuel limits how long wasm code may execute, deterministically, unlike
+    /// interrupts. Executing most wasm instructions consumes one unit of fuel,
+    /// while instructions which only affect control flow, such as `block` and
+    /// `end`, are free. Code is checked for running out of fuel before calls
+    /// and at the start of loops, so it may run slightly past its fuel before
+    /// trapping.
+    ///
+    /// Stores start out with no fuel, which is added with
+    /// [`Store::add_fuel`]. Running out of fuel raises a trap whose
+    /// [`Trap::trap_code`](crate::Trap::trap_code) is
+    /// [`TrapCode::OutOfFuel`](crate::TrapCode::OutOfFuel).
+    ///
+    /// By default this option is `false`.
+    pub fn consume_fuel(&mut self, enable: bool) -> &mut Self {
+        self.tunables.consume_fuel = enable;
+        self
+    }
+
     /// Configures the maximum amount of native stack space available to
     /// executing WebAssembly code.
     ///
@@ -365,6 +391,50 @@ impl Config {
         self
     }
 
+    /// Configures whether Cranelift should harden bounds checks against
+    /// Spectre attacks.
+    ///
+    /// When enabled, the address of a linear memory or table access which is
+    /// guarded by an explicit bounds check is additionally clamped with a
+    /// conditional move, so that even if the processor mispredicts the bounds
+    /// check the access can't reach outside of the memory or table. This
+    /// matters when mutually distrusting modules run in the same process.
+    ///
+    /// Memory accesses that rely on guard pages rather than explicit bounds
+    /// checks, such as those of static memories on 64-bit hosts (see
+    /// [`Config::static_memory_maximum_size`]), aren't affected.
+    ///
+    /// The conditional move is only guaranteed on x86-64 and AArch64. Other
+    /// targets fall back to a plain `select`, which may be compiled to a
+    /// branch and then offers no protection.
+    ///
+    /// The default value for this is `false`.
+    pub fn cranelift_spectre_mitigations(&mut self, enable: bool) -> &mut Self {
+        let val = if enable { "true" } else { "false" };
+        self.flags
+            .set("enable_bounds_check_spectre_mitigation", val)
+            .expect("should be valid flag");
+        self
+    }
+
+    /// Configures whether Cranelift inlines direct calls to small functions
+    /// into their callers.
+    ///
+    /// Calling a function takes saving and restoring registers and setting up
+    /// a stack frame, which can cost more than the function itself, such as
+    /// for the getters and wrappers commonly emitted by compilers to
+    /// WebAssembly. With inlining, calls to functions defined in the same
+    /// module which are small enough are replaced by a copy of the function's
+    /// body, at the cost of compiling the module taking longer. Calls in the
+    /// copied bodies aren't inlined again. Traps raised by inlined code are
+    /// reported at the call, without a frame for the inlined function.
+    ///
+    /// The default value for this is `false`.
+    pub fn cranelift_inlining(&mut self, enable: bool) -> &mut Self {
+        self.tunables.inlining = enable;
+        self
+    }
+
     /// Allows settings another Cranelift flag defined by a flag name and value. This allows
     /// fine-tuning of Cranelift settings.
     ///
@@ -391,6 +461,47 @@ impl Config {
         Ok(self)
     }
 
+    /// Configures the target for which this configuration's engine compiles
+    /// code, given as a target triple such as `"aarch64-unknown-linux-gnu"`.
+    ///
+    /// By default code is compiled for the host, using the CPU features it
+    /// supports. Setting a target allows cross-compiling modules with
+    /// [`Engine::precompile_module`], but an engine whose target isn't the
+    /// host can't create modules to execute them. The CPU features of the
+    /// target are reset to its baseline, and may be enabled with
+    /// [`Config::cranelift_cpu_feature`].
+    ///
+    /// Targets other than the host are only supported if the `cross-compile`
+    /// feature of this crate is enabled.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if `target` isn't a valid target triple, or if
+    /// Cranelift doesn't support compiling for it in this build.
+    pub fn target(&mut self, target: &str) -> Result<&mut Self> {
+        let triple = Triple::from_str(target).map_err(|e| anyhow!(e))?;
+        self.isa_flags = native::lookup(triple)?;
+        Ok(self)
+    }
+
+    /// Enables or disables a CPU feature of the target, for example
+    /// `has_avx2` on x86_64, allowing Cranelift to use the instructions it
+    /// provides.
+    ///
+    /// # Unsafety
+    ///
+    /// This is unsafe because code using a CPU feature which the processor
+    /// executing it doesn't support will fault with illegal instructions.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the target has no such feature.
+    pub unsafe fn cranelift_cpu_feature(&mut self, name: &str, enable: bool) -> Result<&mut Self> {
+        let val = if enable { "true" } else { "false" };
+        self.isa_flags.set(name, val)?;
+        Ok(self)
+    }
+
     /// Loads cache configuration specified at `path`.
     ///
     /// This method will read the file specified by `path` on the filesystem and
@@ -768,7 +879,10 @@ impl Engine {
         &self.inner.config
     }
 
-    pub(crate) fn compiler(&self) -> &Compiler {
+    /// Returns the compiler of this engine, with the target ISA and the
+    /// tunables built from its configuration, for tools inspecting the code it
+    /// generates.
+    pub fn compiler(&self) -> &Compiler {
         &self.inner.compiler
     }
 
@@ -776,6 +890,50 @@ impl Engine {
     pub fn same(a: &Engine, b: &Engine) -> bool {
         Arc::ptr_eq(&a.inner, &b.inner)
     }
+
+    /// Returns an error if the code compiled by this engine can't be executed
+    /// on the host, because it was configured with another [`Config::target`].
+    pub(crate) fn check_host_target(&self) -> Result<()> {
+        let target = self.compiler().isa().triple();
+        let host = Triple::host();
+        if target.architecture != host.architecture
+            || target.operating_system != host.operating_system
+            || target.pointer_width() != host.pointer_width()
+            || target.endianness() != host.endianness()
+        {
+            bail!(
+                "cannot create modules for target `{}` on a `{}` host, \
+                 they can only be precompiled with `Engine::precompile_module`",
+                target,
+                host
+            );
+        }
+        Ok(())
+    }
+
+    /// Compiles a WebAssembly module ahead of time, returning an artifact which
+    /// [`Module::deserialize`] can load without compiling it again.
+    ///
+    /// The `bytes` provided may be in the same formats as those of
+    /// [`Module::new`], and are validated and compiled according to the
+    /// configuration of this engine. The artifact can only be loaded by the
+    /// same version of Wasmtime, with an engine whose code generation settings
+    /// are the same as this one's.
+    ///
+    /// # Errors
+    ///
+    /// This function fails for the same reasons as [`Module::new`], and if the
+    /// backend for the target doesn't report the CPU features its code may
+    /// use, such as the aarch64 one, as the artifact couldn't be checked when
+    /// it's loaded.
+    pub fn precompile_module(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        #[cfg(feature = "wat")]
+        let bytes = wat::parse_bytes(bytes)?;
+        Module::validate(self, &bytes)?;
+        precompiled::check_isa_flags(self)?;
+        let functions = CompiledModule::precompile(self.compiler(), &bytes)?;
+        precompiled::serialize(self, &bytes, functions)
+    }
 }
 
 impl Default for Engine {
@@ -818,6 +976,8 @@ pub(crate) struct StoreInner {
     host_info: RefCell<HashMap<HostInfoKey, Rc<RefCell<dyn Any>>>>,
     externref_activations_table: Rc<VMExternRefActivationsTable>,
     stack_map_registry: Rc<StackMapRegistry>,
+    limiter: Option<Rc<Limiter>>,
+    fuel_added: Cell<u64>,
 }
 
 struct HostInfoKey(VMExternRef);
@@ -842,6 +1002,18 @@ impl Hash for HostInfoKey {
 impl Store {
     /// Creates a new store to be associated with the given [`Engine`].
     pub fn new(engine: &Engine) -> Store {
+        Store::new_with_limiter(engine, None)
+    }
+
+    /// Creates a new store to be associated with the given [`Engine`], whose
+    /// instances, memories and tables are limited by `limits`.
+    ///
+    /// For more information see [`StoreLimits`].
+    pub fn new_with_limits(engine: &Engine, limits: &StoreLimits) -> Store {
+        Store::new_with_limiter(engine, Some(Rc::new(Limiter::new(limits))))
+    }
+
+    fn new_with_limiter(engine: &Engine, limiter: Option<Rc<Limiter>>) -> Store {
         // Ensure that wasmtime_runtime's signal handlers are configured. Note
         // that at the `Store` level it means we should perform this
         // once-per-thread. Platforms like Unix, however, only require this
@@ -860,6 +1032,8 @@ impl Store {
                 host_info: RefCell::new(HashMap::new()),
                 externref_activations_table: Rc::new(VMExternRefActivationsTable::new()),
                 stack_map_registry: Rc::new(StackMapRegistry::default()),
+                limiter,
+                fuel_added: Cell::new(0),
             }),
         }
     }
@@ -882,6 +1056,53 @@ impl Store {
             .map(|x| x as _)
     }
 
+    pub(crate) fn limiter(&self) -> Option<Rc<dyn ResourceLimiter>> {
+        self.inner.limiter.clone().map(|l| l as _)
+    }
+
+    /// Fails if the store's limits don't allow another instance of a module.
+    pub(crate) fn check_module_instance(&self) -> Result<()> {
+        match &self.inner.limiter {
+            Some(limiter) => limiter.check_instance(),
+            None => Ok(()),
+        }
+    }
+
+    /// Runs `f`, which creates memories or tables, and attributes its error to
+    /// the limit of the store it exceeded, if any, so that the error can be
+    /// downcast to that [`ResourceLimit`].
+    pub(crate) fn with_limit_errors<T, E>(&self, f: impl FnOnce() -> Result<T, E>) -> Result<T>
+    where
+        E: Into<anyhow::Error>,
+    {
+        let limiter = match &self.inner.limiter {
+            Some(limiter) => limiter,
+            None => return f().map_err(Into::into),
+        };
+        limiter.take_denied();
+        f().map_err(|e| {
+            let e = e.into();
+            match limiter.take_denied() {
+                Some(limit) => anyhow::Error::new(limit).context(e.to_string()),
+                None => e,
+            }
+        })
+    }
+
+    /// Counts a new instance of a module, which was created successfully,
+    /// towards the store's limits.
+    pub(crate) fn add_module_instance(&self) {
+        if let Some(limiter) = &self.inner.limiter {
+            limiter.add_instance();
+        }
+    }
+
+    /// Returns the first of the limits of this store that was exceeded, if it
+    /// was created with [`Store::new_with_limits`] and any was.
+    pub fn exceeded_limit(&self) -> Option<ResourceLimit> {
+        self.inner.limiter.as_ref().and_then(|l| l.exceeded())
+    }
+
     pub(crate) fn lookup_signature(&self, sig_index: VMSharedSignatureIndex) -> wasm::WasmFuncType {
         self.inner
             .signatures
@@ -1121,6 +1342,35 @@ impl Store {
         }
     }
 
+    /// Adds `fuel` to the fuel available to the wasm code executing in this
+    /// store.
+    ///
+    /// Returns an error if fuel consumption isn't enabled with
+    /// [`Config::consume_fuel`].
+    pub fn add_fuel(&self, fuel: u64) -> Result<()> {
+        if !self.engine().config().tunables.consume_fuel {
+            bail!("fuel is not enabled for this `Store`");
+        }
+        let fuel = i64::try_from(fuel).unwrap_or(i64::max_value());
+        let consumed = &self.interrupts().fuel_consumed;
+        let previous = consumed.load(SeqCst);
+        consumed.store(previous.saturating_sub(fuel), SeqCst);
+        let added = self.inner.fuel_added.get();
+        self.inner.fuel_added.set(added.saturating_add(fuel as u64));
+        Ok(())
+    }
+
+    /// Returns the fuel consumed by the wasm code executed in this store, or
+    /// `None` if fuel consumption isn't enabled with [`Config::consume_fuel`].
+    pub fn fuel_consumed(&self) -> Option<u64> {
+        if !self.engine().config().tunables.consume_fuel {
+            return None;
+        }
+        let consumed = self.interrupts().fuel_consumed.load(SeqCst);
+        let total = i128::from(self.inner.fuel_added.get()) + i128::from(consumed);
+        Some(u64::try_from(total.max(0)).unwrap_or(u64::max_value()))
+    }
+
     pub(crate) fn externref_activations_table(&self) -> &Rc<VMExternRefActivationsTable> {
         &self.inner.externref_activations_table
     }
@@ -36,19 +36,22 @@ pub(crate) fn create_handle(
         .collect::<PrimaryMap<_, _>>();
 
     unsafe {
-        let handle = InstanceHandle::new(
-            Arc::new(module),
-            Arc::new(()),
-            finished_functions.into_boxed_slice(),
-            trampolines,
-            imports,
-            store.memory_creator(),
-            signatures.into_boxed_slice(),
-            state,
-            store.interrupts().clone(),
-            &*store.externref_activations_table() as *const _ as *mut _,
-            &*store.stack_map_registry() as *const _ as *mut _,
-        )?;
+        let handle = store.with_limit_errors(|| {
+            InstanceHandle::new(
+                Arc::new(module),
+                Arc::new(()),
+                finished_functions.into_boxed_slice(),
+                trampolines,
+                imports,
+                store.memory_creator(),
+                store.limiter(),
+                signatures.into_boxed_slice(),
+                state,
+                store.interrupts().clone(),
+                &*store.externref_activations_table() as *const _ as *mut _,
+                &*store.stack_map_registry() as *const _ as *mut _,
+            )
+        })?;
         Ok(store.add_instance(handle))
     }
 }
@@ -3,7 +3,7 @@ use crate::FrameInfo;
 use backtrace::Backtrace;
 use std::fmt;
 use std::sync::Arc;
-use wasmtime_environ::ir::TrapCode;
+use wasmtime_environ::ir;
 
 /// A struct representing an aborted instruction execution, with a message
 /// indicating the cause.
@@ -35,8 +35,41 @@ impl fmt::Display for TrapReason {
     }
 }
 
+/// The kind of a trap raised by WebAssembly code, as returned by
+/// [`Trap::trap_code`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TrapCode {
+    /// The call stack was exhausted, as limited by
+    /// [`Config::max_wasm_stack`](crate::Config::max_wasm_stack).
+    StackOverflow,
+    /// A memory access was out of bounds.
+    MemoryOutOfBounds,
+    /// A table access was out of bounds.
+    TableOutOfBounds,
+    /// An indirect call was made to a null table element.
+    IndirectCallToNull,
+    /// An indirect call was made with the wrong function signature.
+    BadSignature,
+    /// An integer arithmetic operation overflowed.
+    IntegerOverflow,
+    /// An integer was divided by zero.
+    IntegerDivisionByZero,
+    /// A float couldn't be converted to an integer.
+    BadConversionToInteger,
+    /// An `unreachable` instruction was executed.
+    UnreachableCodeReached,
+    /// Execution was interrupted with an
+    /// [`InterruptHandle`](crate::InterruptHandle).
+    Interrupt,
+    /// All the fuel added to the store was consumed, as enabled by
+    /// [`Config::consume_fuel`](crate::Config::consume_fuel).
+    OutOfFuel,
+}
+
 struct TrapInner {
     reason: TrapReason,
+    code: Option<TrapCode>,
     wasm_trace: Vec<FrameInfo>,
     native_trace: Backtrace,
 }
@@ -55,7 +88,7 @@ impl Trap {
     pub fn new<I: Into<String>>(message: I) -> Self {
         let info = FRAME_INFO.read().unwrap();
         let reason = TrapReason::Message(message.into());
-        Trap::new_with_trace(&info, None, reason, Backtrace::new_unresolved())
+        Trap::new_with_trace(&info, None, reason, None, Backtrace::new_unresolved())
     }
 
     /// Creates a new `Trap` representing an explicit program exit with a classic `i32`
@@ -64,6 +97,7 @@ impl Trap {
         Trap {
             inner: Arc::new(TrapInner {
                 reason: TrapReason::I32Exit(status),
+                code: None,
                 wasm_trace: Vec::new(),
                 native_trace: Backtrace::from(Vec::new()),
             }),
@@ -82,9 +116,9 @@ impl Trap {
                 let mut code = info
                     .lookup_trap_info(pc)
                     .map(|info| info.trap_code)
-                    .unwrap_or(TrapCode::StackOverflow);
-                if maybe_interrupted && code == TrapCode::StackOverflow {
-                    code = TrapCode::Interrupt;
+                    .unwrap_or(ir::TrapCode::StackOverflow);
+                if maybe_interrupted && code == ir::TrapCode::StackOverflow {
+                    code = ir::TrapCode::Interrupt;
                 }
                 Trap::new_wasm(&info, Some(pc), code, backtrace)
             }
@@ -94,7 +128,7 @@ impl Trap {
             } => Trap::new_wasm(&info, None, trap_code, backtrace),
             wasmtime_runtime::Trap::OOM { backtrace } => {
                 let reason = TrapReason::Message("out of memory".to_string());
-                Trap::new_with_trace(&info, None, reason, backtrace)
+                Trap::new_with_trace(&info, None, reason, None, backtrace)
             }
         }
     }
@@ -102,31 +136,39 @@ impl Trap {
     fn new_wasm(
         info: &GlobalFrameInfo,
         trap_pc: Option<usize>,
-        code: TrapCode,
+        code: ir::TrapCode,
         backtrace: Backtrace,
     ) -> Self {
         use wasmtime_environ::ir::TrapCode::*;
-        let desc = match code {
-            StackOverflow => "call stack exhausted",
-            HeapOutOfBounds => "out of bounds memory access",
-            TableOutOfBounds => "undefined element: out of bounds table access",
-            IndirectCallToNull => "uninitialized element",
-            BadSignature => "indirect call type mismatch",
-            IntegerOverflow => "integer overflow",
-            IntegerDivisionByZero => "integer divide by zero",
-            BadConversionToInteger => "invalid conversion to integer",
-            UnreachableCodeReached => "unreachable",
-            Interrupt => "interrupt",
+        let (code, desc) = match code {
+            StackOverflow => (TrapCode::StackOverflow, "call stack exhausted"),
+            HeapOutOfBounds => (TrapCode::MemoryOutOfBounds, "out of bounds memory access"),
+            TableOutOfBounds => (
+                TrapCode::TableOutOfBounds,
+                "undefined element: out of bounds table access",
+            ),
+            IndirectCallToNull => (TrapCode::IndirectCallToNull, "uninitialized element"),
+            BadSignature => (TrapCode::BadSignature, "indirect call type mismatch"),
+            IntegerOverflow => (TrapCode::IntegerOverflow, "integer overflow"),
+            IntegerDivisionByZero => (TrapCode::IntegerDivisionByZero, "integer divide by zero"),
+            BadConversionToInteger => (
+                TrapCode::BadConversionToInteger,
+                "invalid conversion to integer",
+            ),
+            UnreachableCodeReached => (TrapCode::UnreachableCodeReached, "unreachable"),
+            Interrupt => (TrapCode::Interrupt, "interrupt"),
+            wasmtime_environ::TRAP_OUT_OF_FUEL => (TrapCode::OutOfFuel, "all fuel consumed"),
             User(_) => unreachable!(),
         };
         let msg = TrapReason::Message(format!("wasm trap: {}", desc));
-        Trap::new_with_trace(info, trap_pc, msg, backtrace)
+        Trap::new_with_trace(info, trap_pc, msg, Some(code), backtrace)
     }
 
     fn new_with_trace(
         info: &GlobalFrameInfo,
         trap_pc: Option<usize>,
         reason: TrapReason,
+        code: Option<TrapCode>,
         native_trace: Backtrace,
     ) -> Self {
         let mut wasm_trace = Vec::new();
@@ -153,6 +195,7 @@ impl Trap {
         Trap {
             inner: Arc::new(TrapInner {
                 reason,
+                code,
                 wasm_trace,
                 native_trace,
             }),
@@ -168,6 +211,21 @@ impl Trap {
         }
     }
 
+    /// Returns the kind of the trap, if it was raised by WebAssembly code
+    /// rather than created by the host.
+    pub fn trap_code(&self) -> Option<TrapCode> {
+        self.inner.code
+    }
+
+    /// If the trap was created from a host error of type `E`, such as an error
+    /// returned by a host function, return a reference to it.
+    pub fn downcast_ref<E: std::error::Error + 'static>(&self) -> Option<&E> {
+        match &self.inner.reason {
+            TrapReason::Error(e) => e.downcast_ref(),
+            TrapReason::I32Exit(_) | TrapReason::Message(_) => None,
+        }
+    }
+
     /// Returns a list of function frames in WebAssembly code that led to this
     /// trap happening.
     pub fn trace(&self) -> &[FrameInfo] {
@@ -232,7 +290,7 @@ impl From<Box<dyn std::error::Error + Send + Sync>> for Trap {
         } else {
             let info = FRAME_INFO.read().unwrap();
             let reason = TrapReason::Error(e.into());
-            Trap::new_with_trace(&info, None, reason, Backtrace::new_unresolved())
+            Trap::new_with_trace(&info, None, reason, None, Backtrace::new_unresolved())
         }
     }
 }
@@ -34,6 +34,8 @@ pub struct WastContext {
     /// Wast files have a concept of a "current" module, which is the most
     /// recently defined.
     current: Option<Instance>,
+    /// The module the current instance is an instance of.
+    current_module: Option<Module>,
     // FIXME(#1479) this is only needed to retain correct trap information after
     // we've dropped previous `Instance` values.
     modules: Vec<Module>,
@@ -65,12 +67,47 @@ impl WastContext {
         linker.allow_shadowing(true);
         Self {
             current: None,
+            current_module: None,
             linker,
             store,
             modules: Vec::new(),
         }
     }
 
+    /// Returns the store that modules are instantiated in.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Returns the linker holding the registered instances.
+    pub fn linker(&self) -> &Linker {
+        &self.linker
+    }
+
+    /// Returns the most recently defined instance, which actions without a
+    /// module name are performed on.
+    pub fn current_instance(&self) -> Option<&Instance> {
+        self.current.as_ref()
+    }
+
+    /// Returns the module the current instance is an instance of.
+    pub fn current_module(&self) -> Option<&Module> {
+        self.current_module.as_ref()
+    }
+
+    /// Instantiates `module`, making it the current instance and registering
+    /// it as `name` if given.
+    pub fn define_module(&mut self, name: Option<&str>, module: &Module) -> Result<Instance> {
+        self.modules.push(module.clone());
+        let instance = self.linker.instantiate(module)?;
+        if let Some(name) = name {
+            self.linker.instance(name, &instance)?;
+        }
+        self.current = Some(instance.clone());
+        self.current_module = Some(module.clone());
+        Ok(instance)
+    }
+
     fn get_export(&self, module: Option<&str>, name: &str) -> Result<Extern> {
         match module {
             Some(module) => self.linker.get_one_by_name(module, name),
@@ -134,11 +171,15 @@ impl WastContext {
             self.linker.instance(name, &instance)?;
         }
         self.current = Some(instance);
+        self.current_module = self.modules.last().cloned();
         Ok(())
     }
 
     /// Register an instance to make it available for performing actions.
-    fn register(&mut self, name: Option<&str>, as_name: &str) -> Result<()> {
+    ///
+    /// The instance registered as `name`, or the current instance if `name`
+    /// is `None`, is registered as `as_name`.
+    pub fn register(&mut self, name: Option<&str>, as_name: &str) -> Result<()> {
         match name {
             Some(name) => self.linker.alias(name, as_name),
             None => {
@@ -233,6 +274,29 @@ impl WastContext {
         Ok(())
     }
 
+    /// Run the directives of a wast script given as text, such as
+    /// `(invoke "f" (i32.const 1))`, returning the results of the last one
+    /// if it's an invocation.
+    ///
+    /// Unlike in scripts, traps raised by invocations are returned as errors.
+    pub fn run_directives(&mut self, wast: &str) -> Result<Vec<Val>> {
+        let buf = ParseBuffer::new(wast)?;
+        let ast = parser::parse::<wast::Wast>(&buf)?;
+        let mut results = Vec::new();
+        for directive in ast.directives {
+            results = match directive {
+                wast::WastDirective::Invoke(invoke) => {
+                    self.perform_invoke(invoke)?.into_result()?
+                }
+                directive => {
+                    self.run_directive(directive)?;
+                    Vec::new()
+                }
+            };
+        }
+        Ok(results)
+    }
+
     fn run_directive(&mut self, directive: wast::WastDirective) -> Result<()> {
         use wast::WastDirective::*;
 
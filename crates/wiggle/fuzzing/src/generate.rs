@@ -0,0 +1,382 @@
+//! Generating shims for arbitrary witx documents.
+
+use arbitrary::Arbitrary;
+use quote::{format_ident, quote};
+use std::fmt::Write;
+use wiggle_generate::{AsyncConf, ErrorTransform, Names, PointerWidthConf};
+
+/// Maximum number of variants, flags, fields, etc. in a generated type.
+const MAX_MEMBERS: usize = 16;
+
+/// A witx document: a list of type definitions, each of which may refer to
+/// the previous ones, and modules of functions using them.
+#[derive(Arbitrary, Clone, Debug)]
+pub struct WitxDocument {
+    types: Vec<TypeDef>,
+    modules: Vec<ModuleDef>,
+}
+
+/// A witx module with the given functions.
+#[derive(Arbitrary, Clone, Debug)]
+pub struct ModuleDef {
+    funcs: Vec<FuncDef>,
+}
+
+/// A witx function.
+#[derive(Arbitrary, Clone, Debug)]
+pub struct FuncDef {
+    /// Types of the parameters.
+    params: Vec<TypeRef>,
+    /// Types of the results after the leading `$errno`, or `None` if the
+    /// function has no results at all. Types the generator doesn't support as
+    /// results are skipped.
+    results: Option<Vec<TypeRef>>,
+}
+
+/// A witx type definition.
+#[derive(Arbitrary, Clone, Debug)]
+pub enum TypeDef {
+    /// An enum with `1 + variants % MAX_MEMBERS` variants.
+    Enum {
+        /// Representation of the enum.
+        repr: IntRepr,
+        /// Number of variants.
+        variants: u8,
+    },
+    /// Flags with `1 + flags % MAX_MEMBERS` members.
+    Flags {
+        /// Representation of the flags.
+        repr: IntRepr,
+        /// Number of flags.
+        flags: u8,
+    },
+    /// An integer with named constants.
+    Int {
+        /// Representation of the integer.
+        repr: IntRepr,
+        /// Values of the constants.
+        consts: Vec<u64>,
+    },
+    /// A handle.
+    Handle,
+    /// A struct with the given fields.
+    Struct(Vec<TypeRef>),
+    /// A union, along with its tag enum, with the given variants, which may be
+    /// empty.
+    Union(Vec<Option<TypeRef>>),
+    /// An array.
+    Array(TypeRef),
+    /// A mutable pointer.
+    Pointer(TypeRef),
+    /// A const pointer.
+    ConstPointer(TypeRef),
+}
+
+/// Representation of an enum, flags, or int.
+#[derive(Arbitrary, Clone, Copy, Debug)]
+#[allow(missing_docs)]
+pub enum IntRepr {
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+/// A reference to a type from a definition.
+#[derive(Arbitrary, Clone, Debug)]
+pub enum TypeRef {
+    /// A builtin type.
+    Builtin(Builtin),
+    /// A previous type definition, modulo the number of previous definitions.
+    Named(u8),
+}
+
+/// A witx builtin type.
+#[derive(Arbitrary, Clone, Copy, Debug)]
+#[allow(missing_docs)]
+pub enum Builtin {
+    U8,
+    U16,
+    U32,
+    U64,
+    S8,
+    S16,
+    S32,
+    S64,
+    F32,
+    F64,
+    Char8,
+    USize,
+    String,
+}
+
+impl IntRepr {
+    fn as_witx(self) -> &'static str {
+        match self {
+            IntRepr::U8 => "u8",
+            IntRepr::U16 => "u16",
+            IntRepr::U32 => "u32",
+            IntRepr::U64 => "u64",
+        }
+    }
+}
+
+impl Builtin {
+    fn as_witx(self) -> &'static str {
+        match self {
+            Builtin::U8 => "u8",
+            Builtin::U16 => "u16",
+            Builtin::U32 => "u32",
+            Builtin::U64 => "u64",
+            Builtin::S8 => "s8",
+            Builtin::S16 => "s16",
+            Builtin::S32 => "s32",
+            Builtin::S64 => "s64",
+            Builtin::F32 => "f32",
+            Builtin::F64 => "f64",
+            Builtin::Char8 => "char8",
+            Builtin::USize => "usize",
+            Builtin::String => "string",
+        }
+    }
+}
+
+impl TypeRef {
+    /// Render this reference from the definition of the type at `index`.
+    fn as_witx(&self, index: usize) -> String {
+        match self {
+            TypeRef::Builtin(b) => b.as_witx().to_string(),
+            TypeRef::Named(_) if index == 0 => "u32".to_string(),
+            TypeRef::Named(n) => format!("$t{}", usize::from(*n) % index),
+        }
+    }
+}
+
+impl Builtin {
+    /// Whether the generator supports results of this type.
+    fn can_be_result(self) -> bool {
+        match self {
+            Builtin::String => false,
+            _ => true,
+        }
+    }
+}
+
+impl TypeDef {
+    /// Whether the generator supports results of this type.
+    fn can_be_result(&self) -> bool {
+        match self {
+            TypeDef::Array(_) | TypeDef::Pointer(_) | TypeDef::ConstPointer(_) => false,
+            _ => true,
+        }
+    }
+}
+
+impl WitxDocument {
+    /// Whether the generator supports results of type `ty`, referred to from a
+    /// function.
+    fn can_be_result(&self, ty: &TypeRef) -> bool {
+        match ty {
+            TypeRef::Builtin(b) => b.can_be_result(),
+            TypeRef::Named(_) if self.types.is_empty() => true,
+            TypeRef::Named(n) => self.types[usize::from(*n) % self.types.len()].can_be_result(),
+        }
+    }
+
+    /// Render a function named `name`.
+    fn func_to_witx(&self, name: &str, func: &FuncDef) -> String {
+        let index = self.types.len();
+        let mut witx = format!("  (@interface func (export \"{}\")", name);
+        for (p, ty) in func.params.iter().take(MAX_MEMBERS).enumerate() {
+            let _ = write!(witx, " (param $p{} {})", p, ty.as_witx(index));
+        }
+        if let Some(results) = &func.results {
+            witx.push_str(" (result $error $errno)");
+            let results = results.iter().filter(|ty| self.can_be_result(ty));
+            for (r, ty) in results.take(MAX_MEMBERS).enumerate() {
+                let _ = write!(witx, " (result $r{} {})", r, ty.as_witx(index));
+            }
+        }
+        witx.push(')');
+        witx
+    }
+
+    /// Render the document as witx. Besides the arbitrary modules, it has a
+    /// `$types` module with a function taking, and if possible returning, each
+    /// type.
+    pub fn to_witx(&self) -> String {
+        let mut doc = String::new();
+        let mut funcs = String::new();
+        doc.push_str("(typename $errno (enum u32 $ok $guest_error))\n");
+        for (i, ty) in self.types.iter().enumerate() {
+            let def = match ty {
+                TypeDef::Enum { repr, variants } => {
+                    let variants = (0..=usize::from(*variants) % MAX_MEMBERS)
+                        .map(|v| format!(" $v{}", v))
+                        .collect::<String>();
+                    format!("(enum {}{})", repr.as_witx(), variants)
+                }
+                TypeDef::Flags { repr, flags } => {
+                    let flags = (0..=usize::from(*flags) % MAX_MEMBERS)
+                        .map(|f| format!(" $f{}", f))
+                        .collect::<String>();
+                    format!("(flags {}{})", repr.as_witx(), flags)
+                }
+                TypeDef::Int { repr, consts } => {
+                    let consts = consts
+                        .iter()
+                        .take(MAX_MEMBERS)
+                        .enumerate()
+                        .map(|(c, value)| format!(" (const $c{} {})", c, value))
+                        .collect::<String>();
+                    format!("(int {}{})", repr.as_witx(), consts)
+                }
+                TypeDef::Handle => "(handle)".to_string(),
+                TypeDef::Struct(fields) => {
+                    let fields = fields
+                        .iter()
+                        .take(MAX_MEMBERS)
+                        .enumerate()
+                        .map(|(f, ty)| format!(" (field $f{} {})", f, ty.as_witx(i)))
+                        .collect::<String>();
+                    format!("(struct{})", fields)
+                }
+                TypeDef::Union(variants) => {
+                    let variants = &variants[..variants.len().min(MAX_MEMBERS)];
+                    let tag = (0..variants.len())
+                        .map(|v| format!(" $v{}", v))
+                        .collect::<String>();
+                    let _ = writeln!(doc, "(typename $t{}_tag (enum u8{}))", i, tag);
+                    let variants = variants
+                        .iter()
+                        .enumerate()
+                        .map(|(v, ty)| match ty {
+                            Some(ty) => format!(" (field $v{} {})", v, ty.as_witx(i)),
+                            None => format!(" (empty $v{})", v),
+                        })
+                        .collect::<String>();
+                    format!("(union $t{}_tag{})", i, variants)
+                }
+                TypeDef::Array(ty) => format!("(array {})", ty.as_witx(i)),
+                TypeDef::Pointer(ty) => format!("(@witx pointer {})", ty.as_witx(i)),
+                TypeDef::ConstPointer(ty) => format!("(@witx const_pointer {})", ty.as_witx(i)),
+            };
+            let _ = writeln!(doc, "(typename $t{} {})", i, def);
+
+            let result = if ty.can_be_result() {
+                format!(" (result $r $t{})", i)
+            } else {
+                String::new()
+            };
+            let _ = writeln!(
+                funcs,
+                "  (@interface func (export \"f{}\") (param $p $t{}) (result $error $errno){})",
+                i, i, result
+            );
+        }
+        let _ = writeln!(doc, "(module $types\n{})", funcs);
+        for (m, module) in self.modules.iter().take(MAX_MEMBERS).enumerate() {
+            let funcs = module
+                .funcs
+                .iter()
+                .take(MAX_MEMBERS)
+                .enumerate()
+                .map(|(f, func)| self.func_to_witx(&format!("f{}", f), func))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let _ = writeln!(doc, "(module $m{}\n{})", m, funcs);
+        }
+        doc
+    }
+}
+
+/// Generate the shims for `doc`, checking that they are valid Rust code.
+///
+/// Documents rejected by the witx validator are ignored.
+pub fn generate(doc: &WitxDocument) {
+    let doc = match witx::parse(&doc.to_witx()) {
+        Ok(doc) => doc,
+        Err(_) => return,
+    };
+    let names = Names::new(&format_ident!("Ctx"), quote!(wiggle));
+    let code = wiggle_generate::generate(
+        &doc,
+        &names,
+        &ErrorTransform::empty(),
+        &AsyncConf::default(),
+        PointerWidthConf::default(),
+    );
+    if let Err(e) = syn::parse2::<syn::File>(code.clone()) {
+        panic!("generated invalid code ({}):\n{}", e, code);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn every_type() {
+        let builtin = TypeRef::Builtin(Builtin::S64);
+        let doc = WitxDocument {
+            types: vec![
+                TypeDef::Enum {
+                    repr: IntRepr::U8,
+                    variants: 3,
+                },
+                TypeDef::Flags {
+                    repr: IntRepr::U16,
+                    flags: 4,
+                },
+                TypeDef::Int {
+                    repr: IntRepr::U64,
+                    consts: vec![0, 42],
+                },
+                TypeDef::Handle,
+                TypeDef::Struct(vec![builtin.clone(), TypeRef::Named(0)]),
+                TypeDef::Union(vec![Some(TypeRef::Named(4)), None]),
+                TypeDef::Array(TypeRef::Named(1)),
+                TypeDef::Pointer(TypeRef::Named(5)),
+                TypeDef::ConstPointer(builtin),
+            ],
+            modules: vec![],
+        };
+        witx::parse(&doc.to_witx()).expect("valid witx");
+        generate(&doc);
+    }
+
+    #[test]
+    fn functions_and_modules() {
+        let doc = WitxDocument {
+            types: vec![
+                TypeDef::Struct(vec![TypeRef::Builtin(Builtin::U32)]),
+                TypeDef::Array(TypeRef::Builtin(Builtin::U8)),
+            ],
+            modules: vec![
+                ModuleDef {
+                    funcs: vec![
+                        FuncDef {
+                            params: vec![],
+                            results: None,
+                        },
+                        FuncDef {
+                            params: vec![
+                                TypeRef::Named(0),
+                                TypeRef::Named(1),
+                                TypeRef::Builtin(Builtin::String),
+                            ],
+                            results: Some(vec![
+                                TypeRef::Named(0),
+                                TypeRef::Named(1),
+                                TypeRef::Builtin(Builtin::F64),
+                            ]),
+                        },
+                    ],
+                },
+                ModuleDef { funcs: vec![] },
+            ],
+        };
+        witx::parse(&doc.to_witx()).expect("valid witx");
+        generate(&doc);
+    }
+}
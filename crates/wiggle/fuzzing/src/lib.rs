@@ -0,0 +1,19 @@
+//! Utilities for fuzzing wiggle.
+//!
+//! The actual fuzz targets are defined in `wasmtime/fuzz/fuzz_targets/wiggle_*`.
+//! This crate just has generators and oracles for fuzzing:
+//!
+//! * [`generate`] builds witx documents out of arbitrary types, functions and
+//!   modules, and checks that `wiggle_generate` turns them into valid Rust
+//!   code.
+//!
+//! * [`shims`] calls hostcall shims generated for a witx document covering
+//!   every kind of type with arbitrary arguments and guest memory contents,
+//!   and checks that invalid inputs are reported as a `GuestError` rather than
+//!   a panic.
+
+#![deny(missing_debug_implementations)]
+#![deny(missing_docs)]
+
+pub mod generate;
+pub mod shims;
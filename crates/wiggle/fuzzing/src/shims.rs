@@ -0,0 +1,246 @@
+//! Calling generated shims with arbitrary arguments and guest memory.
+
+use arbitrary::Arbitrary;
+use std::cell::{RefCell, UnsafeCell};
+use std::convert::TryFrom;
+use wiggle::{BorrowChecker, GuestError, GuestErrorType, GuestMemory, GuestPtr};
+
+// The generated modules are kept private, as they aren't documented.
+mod generated {
+    use super::Ctx;
+
+    wiggle::from_witx!({
+        witx: ["shims.witx"],
+        ctx: Ctx,
+    });
+}
+use generated::{shims, types};
+
+/// The largest guest memory given to the shims.
+const MAX_MEMORY_SIZE: usize = 1 << 16;
+
+/// The context of the shims, which records the `GuestError`s it's given.
+#[derive(Debug, Default)]
+pub struct Ctx {
+    guest_errors: RefCell<Vec<GuestError>>,
+}
+
+impl GuestErrorType for types::Errno {
+    fn success() -> Self {
+        types::Errno::Ok
+    }
+}
+
+impl types::GuestErrorConversion for Ctx {
+    fn into_errno(&self, e: GuestError) -> types::Errno {
+        self.guest_errors.borrow_mut().push(e);
+        types::Errno::GuestError
+    }
+}
+
+impl Ctx {
+    fn check<T>(&self, result: Result<T, GuestError>) -> Result<T, types::Errno> {
+        result.map_err(|e| types::GuestErrorConversion::into_errno(self, e))
+    }
+}
+
+impl shims::Shims for Ctx {
+    fn enum_arg(&self, c: types::Color) -> Result<types::Color, types::Errno> {
+        Ok(c)
+    }
+
+    fn flags_arg(
+        &self,
+        p: types::Perms,
+        by_ptr: &GuestPtr<types::Perms>,
+    ) -> Result<types::Perms, types::Errno> {
+        Ok(p | self.check(by_ptr.read())?)
+    }
+
+    fn int_arg(&self, c: types::Cookie) -> Result<types::Cookie, types::Errno> {
+        Ok(c)
+    }
+
+    fn handle_arg(&self, fd: types::Fd) -> Result<types::Fd, types::Errno> {
+        Ok(fd)
+    }
+
+    fn struct_arg(&self, p: &types::Point) -> Result<types::Point, types::Errno> {
+        Ok(p.clone())
+    }
+
+    fn union_arg(&self, s: &types::Shape<'_>) -> Result<types::Color, types::Errno> {
+        match s {
+            types::Shape::Red(_) => Ok(types::Color::Red),
+            types::Shape::Green(p) => {
+                self.check(p.read())?;
+                Ok(types::Color::Green)
+            }
+            types::Shape::Blue => Ok(types::Color::Blue),
+        }
+    }
+
+    fn string_arg(&self, s: &GuestPtr<'_, str>) -> Result<u32, types::Errno> {
+        Ok(self.check(s.as_str())?.len() as u32)
+    }
+
+    fn iovecs_arg(&self, iovs: &types::IovecArray<'_>) -> Result<u32, types::Errno> {
+        // Hold every slice at once, so that overlapping iovecs are shared.
+        let mut slices = Vec::new();
+        for iov in iovs.iter() {
+            let iov = self.check(self.check(iov)?.read())?;
+            slices.push(self.check(iov.buf.as_array(iov.buf_len).as_slice())?);
+        }
+        Ok(slices.iter().map(|s| s.len() as u32).sum())
+    }
+}
+
+/// Guest memory initialized with arbitrary contents.
+struct FuzzMemory {
+    // `u64`s so that the memory is aligned like a wasm memory would be.
+    buf: UnsafeCell<Vec<u64>>,
+    len: usize,
+    bc: BorrowChecker,
+}
+
+impl FuzzMemory {
+    fn new(contents: &[u8]) -> Self {
+        let len = contents.len().min(MAX_MEMORY_SIZE);
+        let mut buf = vec![0u64; (len + 7) / 8];
+        for (i, byte) in contents[..len].iter().enumerate() {
+            buf[i / 8] |= u64::from(*byte) << (8 * (i % 8));
+        }
+        FuzzMemory {
+            buf: UnsafeCell::new(buf),
+            len,
+            // SAFETY: this is the only `BorrowChecker` for this memory.
+            bc: unsafe { BorrowChecker::new() },
+        }
+    }
+}
+
+unsafe impl GuestMemory for FuzzMemory {
+    fn base(&self) -> (*mut u8, u64) {
+        unsafe { ((*self.buf.get()).as_mut_ptr() as *mut u8, self.len as u64) }
+    }
+    fn borrow_checker(&self) -> &BorrowChecker {
+        &self.bc
+    }
+}
+
+/// A shim to call.
+#[derive(Arbitrary, Clone, Copy, Debug)]
+#[allow(missing_docs)]
+pub enum Shim {
+    EnumArg,
+    FlagsArg,
+    IntArg,
+    HandleArg,
+    StructArg,
+    UnionArg,
+    StringArg,
+    IovecsArg,
+}
+
+/// A call of a shim, with its ABI-level arguments, in an arbitrary guest
+/// memory.
+#[derive(Arbitrary, Clone, Debug)]
+pub struct ShimCall {
+    shim: Shim,
+    args: [u64; 3],
+    memory: Vec<u8>,
+}
+
+/// Call a shim, checking that it returns an error value if and only if it was
+/// given a `GuestError`, and that every borrow of guest memory was released.
+///
+/// Then check that reading every type at the pointers given by the arguments
+/// either fails with a `GuestError` or decodes the bytes in guest memory.
+pub fn call_shim(call: &ShimCall) {
+    let ctx = Ctx::default();
+    let mem = FuzzMemory::new(&call.memory);
+    let [a, b, c] = call.args;
+    let (a32, b32, c32) = (a as i32, b as i32, c as i32);
+
+    let errno = match call.shim {
+        Shim::EnumArg => shims::enum_arg(&ctx, &mem, a32, b32),
+        Shim::FlagsArg => shims::flags_arg(&ctx, &mem, a32, b32, c32),
+        Shim::IntArg => shims::int_arg(&ctx, &mem, a as i64, b32),
+        Shim::HandleArg => shims::handle_arg(&ctx, &mem, a32, b32),
+        Shim::StructArg => shims::struct_arg(&ctx, &mem, a32, b32),
+        Shim::UnionArg => shims::union_arg(&ctx, &mem, a32, b32),
+        Shim::StringArg => shims::string_arg(&ctx, &mem, a32, b32, c32),
+        Shim::IovecsArg => shims::iovecs_arg(&ctx, &mem, a32, b32, c32),
+    };
+    let errno = types::Errno::try_from(errno).expect("shims return valid error values");
+    let guest_errors = ctx.guest_errors.borrow();
+    match errno {
+        types::Errno::Ok => assert!(guest_errors.is_empty()),
+        types::Errno::GuestError => assert_eq!(guest_errors.len(), 1),
+    }
+    assert!(!mem.borrow_checker().has_outstanding_borrows());
+
+    for &ptr in call.args.iter() {
+        let ptr = u64::from(ptr as u32);
+        let byte = GuestPtr::<u8>::new(&mem, ptr).read().ok();
+
+        if let Ok(color) = GuestPtr::<types::Color>::new(&mem, ptr).read() {
+            assert_eq!(Some(u8::from(color)), byte);
+        } else if let Some(byte) = byte {
+            assert!(byte > 2, "valid enum value {} rejected", byte);
+        }
+        if let Ok(perms) = GuestPtr::<types::Perms>::new(&mem, ptr).read() {
+            assert_eq!(u16::from(perms) & !u16::from(types::Perms::all()), 0);
+        }
+        match GuestPtr::<types::Shape>::new(&mem, ptr).read() {
+            Ok(shape) => {
+                let tag = match shape {
+                    types::Shape::Red(_) => types::Color::Red,
+                    types::Shape::Green(_) => types::Color::Green,
+                    types::Shape::Blue => types::Color::Blue,
+                };
+                assert_eq!(Some(u8::from(tag)), byte);
+            }
+            Err(GuestError::InvalidEnumValue(_)) => {
+                assert!(byte.map_or(false, |b| b > 2));
+            }
+            Err(_) => {}
+        }
+        let _ = GuestPtr::<types::Cookie>::new(&mem, ptr).read();
+        let _ = GuestPtr::<types::Fd>::new(&mem, ptr).read();
+        let _ = GuestPtr::<types::Point>::new(&mem, ptr).read();
+        let _ = GuestPtr::<types::Iovec>::new(&mem, ptr).read();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn valid_and_invalid_calls() {
+        // A string "hi" at 8, and an iovec pointing at it at 16.
+        let mut memory = vec![0; 32];
+        memory[8..10].copy_from_slice(b"hi");
+        memory[16] = 8;
+        memory[20] = 2;
+        for &shim in &[
+            Shim::EnumArg,
+            Shim::FlagsArg,
+            Shim::IntArg,
+            Shim::HandleArg,
+            Shim::StructArg,
+            Shim::UnionArg,
+            Shim::StringArg,
+            Shim::IovecsArg,
+        ] {
+            for &args in &[[0, 24, 28], [8, 2, 24], [16, 1, 24], [3, 4096, 1 << 40]] {
+                call_shim(&ShimCall {
+                    shim,
+                    args,
+                    memory: memory.clone(),
+                });
+            }
+        }
+    }
+}
@@ -18,6 +18,8 @@ pub struct Config {
     pub witx: WitxConf,
     pub ctx: CtxConf,
     pub errors: ErrorConf,
+    pub async_: AsyncConf,
+    pub pointer_width: PointerWidthConf,
 }
 
 #[derive(Debug, Clone)]
@@ -25,6 +27,8 @@ pub enum ConfigField {
     Witx(WitxConf),
     Ctx(CtxConf),
     Error(ErrorConf),
+    Async(AsyncConf),
+    PointerWidth(PointerWidthConf),
 }
 
 mod kw {
@@ -32,6 +36,8 @@ mod kw {
     syn::custom_keyword!(witx_literal);
     syn::custom_keyword!(ctx);
     syn::custom_keyword!(errors);
+    syn::custom_keyword!(target);
+    syn::custom_keyword!(pointer_width);
 }
 
 impl Parse for ConfigField {
@@ -53,6 +59,14 @@ impl Parse for ConfigField {
             input.parse::<kw::errors>()?;
             input.parse::<Token![:]>()?;
             Ok(ConfigField::Error(input.parse()?))
+        } else if lookahead.peek(Token![async]) {
+            input.parse::<Token![async]>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Async(input.parse()?))
+        } else if lookahead.peek(kw::pointer_width) {
+            input.parse::<kw::pointer_width>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::PointerWidth(input.parse()?))
         } else {
             Err(lookahead.error())
         }
@@ -64,6 +78,8 @@ impl Config {
         let mut witx = None;
         let mut ctx = None;
         let mut errors = None;
+        let mut async_ = None;
+        let mut pointer_width = None;
         for f in fields {
             match f {
                 ConfigField::Witx(c) => {
@@ -84,6 +100,18 @@ impl Config {
                     }
                     errors = Some(c);
                 }
+                ConfigField::Async(c) => {
+                    if async_.is_some() {
+                        return Err(Error::new(err_loc, "duplicate `async` field"));
+                    }
+                    async_ = Some(c);
+                }
+                ConfigField::PointerWidth(c) => {
+                    if pointer_width.is_some() {
+                        return Err(Error::new(err_loc, "duplicate `pointer_width` field"));
+                    }
+                    pointer_width = Some(c);
+                }
             }
         }
         Ok(Config {
@@ -94,6 +122,8 @@ impl Config {
                 .take()
                 .ok_or_else(|| Error::new(err_loc, "`ctx` field required"))?,
             errors: errors.take().unwrap_or_default(),
+            async_: async_.take().unwrap_or_default(),
+            pointer_width: pointer_width.take().unwrap_or_default(),
         })
     }
 
@@ -117,6 +147,170 @@ impl Parse for Config {
     }
 }
 
+/// Configuration of the `wasmtime_integration!` macro.
+#[derive(Debug, Clone)]
+pub struct WasmtimeConfig {
+    pub witx: WitxConf,
+    pub ctx: CtxConf,
+    /// Path to the module containing the output of the corresponding `from_witx!`
+    /// invocation. Defaults to `self`.
+    pub target: syn::Path,
+    pub pointer_width: PointerWidthConf,
+}
+
+#[derive(Debug, Clone)]
+pub enum WasmtimeConfigField {
+    Witx(WitxConf),
+    Ctx(CtxConf),
+    Target(syn::Path),
+    PointerWidth(PointerWidthConf),
+}
+
+impl Parse for WasmtimeConfigField {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lookahead = input.lookahead1();
+        if lookahead.peek(kw::target) {
+            input.parse::<kw::target>()?;
+            input.parse::<Token![:]>()?;
+            Ok(WasmtimeConfigField::Target(input.parse()?))
+        } else if lookahead.peek(kw::witx)
+            || lookahead.peek(kw::witx_literal)
+            || lookahead.peek(kw::ctx)
+            || lookahead.peek(kw::pointer_width)
+        {
+            match input.parse::<ConfigField>()? {
+                ConfigField::Witx(c) => Ok(WasmtimeConfigField::Witx(c)),
+                ConfigField::Ctx(c) => Ok(WasmtimeConfigField::Ctx(c)),
+                ConfigField::PointerWidth(c) => Ok(WasmtimeConfigField::PointerWidth(c)),
+                ConfigField::Error(_) | ConfigField::Async(_) => unreachable!(),
+            }
+        } else {
+            Err(lookahead.error())
+        }
+    }
+}
+
+impl WasmtimeConfig {
+    pub fn build(fields: impl Iterator<Item = WasmtimeConfigField>, err_loc: Span) -> Result<Self> {
+        let mut witx = None;
+        let mut ctx = None;
+        let mut target = None;
+        let mut pointer_width = None;
+        for f in fields {
+            match f {
+                WasmtimeConfigField::Witx(c) => {
+                    if witx.is_some() {
+                        return Err(Error::new(err_loc, "duplicate `witx` field"));
+                    }
+                    witx = Some(c);
+                }
+                WasmtimeConfigField::Ctx(c) => {
+                    if ctx.is_some() {
+                        return Err(Error::new(err_loc, "duplicate `ctx` field"));
+                    }
+                    ctx = Some(c);
+                }
+                WasmtimeConfigField::Target(c) => {
+                    if target.is_some() {
+                        return Err(Error::new(err_loc, "duplicate `target` field"));
+                    }
+                    target = Some(c);
+                }
+                WasmtimeConfigField::PointerWidth(c) => {
+                    if pointer_width.is_some() {
+                        return Err(Error::new(err_loc, "duplicate `pointer_width` field"));
+                    }
+                    pointer_width = Some(c);
+                }
+            }
+        }
+        Ok(WasmtimeConfig {
+            witx: witx
+                .take()
+                .ok_or_else(|| Error::new(err_loc, "`witx` field required"))?,
+            ctx: ctx
+                .take()
+                .ok_or_else(|| Error::new(err_loc, "`ctx` field required"))?,
+            target: target.take().unwrap_or_else(|| syn::parse_quote!(self)),
+            pointer_width: pointer_width.take().unwrap_or_default(),
+        })
+    }
+
+    /// Load the `witx` document for the configuration.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the paths given in the `witx` field were not valid documents.
+    pub fn load_document(&self) -> witx::Document {
+        self.witx.load_document()
+    }
+}
+
+impl Parse for WasmtimeConfig {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let contents;
+        let _lbrace = braced!(contents in input);
+        let fields: Punctuated<WasmtimeConfigField, Token![,]> =
+            contents.parse_terminated(WasmtimeConfigField::parse)?;
+        Ok(WasmtimeConfig::build(fields.into_iter(), input.span())?)
+    }
+}
+
+/// Configuration of the `guest_from_witx!` macro.
+#[derive(Debug, Clone)]
+pub struct GuestConfig {
+    pub witx: WitxConf,
+}
+
+impl GuestConfig {
+    pub fn build(fields: impl Iterator<Item = ConfigField>, err_loc: Span) -> Result<Self> {
+        let mut witx = None;
+        for f in fields {
+            match f {
+                ConfigField::Witx(c) => {
+                    if witx.is_some() {
+                        return Err(Error::new(err_loc, "duplicate `witx` field"));
+                    }
+                    witx = Some(c);
+                }
+                ConfigField::Ctx(_)
+                | ConfigField::Error(_)
+                | ConfigField::Async(_)
+                | ConfigField::PointerWidth(_) => {
+                    return Err(Error::new(
+                        err_loc,
+                        "only the `witx` field applies to guest bindings",
+                    ));
+                }
+            }
+        }
+        Ok(GuestConfig {
+            witx: witx
+                .take()
+                .ok_or_else(|| Error::new(err_loc, "`witx` field required"))?,
+        })
+    }
+
+    /// Load the `witx` document for the configuration.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the paths given in the `witx` field were not valid documents.
+    pub fn load_document(&self) -> witx::Document {
+        self.witx.load_document()
+    }
+}
+
+impl Parse for GuestConfig {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let contents;
+        let _lbrace = braced!(contents in input);
+        let fields: Punctuated<ConfigField, Token![,]> =
+            contents.parse_terminated(ConfigField::parse)?;
+        Ok(GuestConfig::build(fields.into_iter(), input.span())?)
+    }
+}
+
 /// The witx document(s) that will be loaded from a [`Config`](struct.Config.html).
 ///
 /// A witx interface definition can be provided either as a collection of relative paths to
@@ -302,3 +496,186 @@ impl Parse for ErrorConfField {
         })
     }
 }
+
+/// The width of the pointers of the guest memory: `32` for wasm32 guests, the
+/// default, or `64` for guests whose memory is indexed by `i64`.
+///
+/// Pointers passed to the generated functions have the corresponding ABI type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerWidthConf {
+    U32,
+    U64,
+}
+
+impl PointerWidthConf {
+    /// The ABI type of pointers of this width.
+    pub fn atom_type(self) -> witx::AtomType {
+        match self {
+            PointerWidthConf::U32 => witx::AtomType::I32,
+            PointerWidthConf::U64 => witx::AtomType::I64,
+        }
+    }
+}
+
+impl Default for PointerWidthConf {
+    fn default() -> Self {
+        PointerWidthConf::U32
+    }
+}
+
+impl Parse for PointerWidthConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let width = input.parse::<syn::LitInt>()?;
+        match width.base10_parse::<u8>() {
+            Ok(32) => Ok(PointerWidthConf::U32),
+            Ok(64) => Ok(PointerWidthConf::U64),
+            _ => Err(Error::new(width.span(), "`pointer_width` must be 32 or 64")),
+        }
+    }
+}
+
+/// The functions generated with `async` trait methods and shims.
+///
+/// Either `*`, making every function `async`, or a map from module names to
+/// either `*` or a list of function names, e.g.
+/// `{ my_module::{read, write}, other_module::* }`.
+#[derive(Debug, Clone)]
+pub enum AsyncConf {
+    /// Every function of every module is `async`.
+    All,
+    /// Only the listed functions are `async`.
+    Modules(Vec<(Ident, AsyncFunctions)>),
+}
+
+/// The `async` functions of a module, see [`AsyncConf`](enum.AsyncConf.html).
+#[derive(Debug, Clone)]
+pub enum AsyncFunctions {
+    /// Every function of the module is `async`.
+    All,
+    /// Only the functions with these names are `async`.
+    Some(Vec<Ident>),
+}
+
+impl AsyncConf {
+    /// Whether the function `func` of `module`, both named as in the witx
+    /// document, is `async`.
+    pub fn is_async(&self, module: &str, func: &str) -> bool {
+        match self {
+            AsyncConf::All => true,
+            AsyncConf::Modules(modules) => match modules.iter().find(|(m, _)| m == module) {
+                Some((_, AsyncFunctions::All)) => true,
+                Some((_, AsyncFunctions::Some(funcs))) => funcs.iter().any(|f| f == func),
+                None => false,
+            },
+        }
+    }
+
+    /// Check that every module and function named is defined in `doc`, so that
+    /// a typo doesn't silently leave a function synchronous.
+    pub fn validate(&self, doc: &witx::Document) -> Result<()> {
+        let modules = match self {
+            AsyncConf::All => return Ok(()),
+            AsyncConf::Modules(modules) => modules,
+        };
+        for (module, functions) in modules {
+            let m = doc
+                .module(&witx::Id::new(module.to_string()))
+                .ok_or_else(|| {
+                    Error::new(
+                        module.span(),
+                        format!("no module `{}` in the witx document", module),
+                    )
+                })?;
+            if let AsyncFunctions::Some(funcs) = functions {
+                for func in funcs {
+                    if m.func(&witx::Id::new(func.to_string())).is_none() {
+                        return Err(Error::new(
+                            func.span(),
+                            format!("no function `{}` in module `{}`", func, module),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for AsyncConf {
+    fn default() -> Self {
+        AsyncConf::Modules(Vec::new())
+    }
+}
+
+impl Parse for AsyncConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(Token![*]) {
+            input.parse::<Token![*]>()?;
+            return Ok(AsyncConf::All);
+        }
+        let content;
+        let _ = braced!(content in input);
+        let items: Punctuated<AsyncConfField, Token![,]> =
+            content.parse_terminated(Parse::parse)?;
+        let mut m: Vec<(Ident, AsyncFunctions)> = Vec::new();
+        for i in items {
+            if m.iter().any(|(module, _)| *module == i.module) {
+                return Err(Error::new(
+                    i.module.span(),
+                    format!("duplicate `async` functions for module {}", i.module),
+                ));
+            }
+            m.push((i.module, i.functions));
+        }
+        Ok(AsyncConf::Modules(m))
+    }
+}
+
+struct AsyncConfField {
+    module: Ident,
+    functions: AsyncFunctions,
+}
+
+impl Parse for AsyncConfField {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let module = input.parse::<Ident>()?;
+        input.parse::<Token![::]>()?;
+        let functions = if input.peek(Token![*]) {
+            input.parse::<Token![*]>()?;
+            AsyncFunctions::All
+        } else {
+            let content;
+            let _ = braced!(content in input);
+            let funcs: Punctuated<Ident, Token![,]> = content.parse_terminated(Ident::parse)?;
+            AsyncFunctions::Some(funcs.into_iter().collect())
+        };
+        Ok(AsyncConfField { module, functions })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AsyncConf;
+
+    fn validate(conf: &str) -> Result<(), String> {
+        let doc = witx::load(&[concat!(env!("CARGO_MANIFEST_DIR"), "/../tests/atoms.witx")])
+            .expect("loading atoms.witx");
+        let conf: AsyncConf = syn::parse_str(conf).expect("parsing async conf");
+        conf.validate(&doc).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn async_names_are_validated() {
+        assert_eq!(validate("*"), Ok(()));
+        assert_eq!(validate("{ atoms::* }"), Ok(()));
+        assert_eq!(validate("{ atoms::{double_int_return_float} }"), Ok(()));
+        assert_eq!(
+            validate("{ atom::* }"),
+            Err("no module `atom` in the witx document".to_string())
+        );
+        assert_eq!(
+            validate("{ atoms::{double_int_return_flaot} }"),
+            Err("no function `double_int_return_flaot` in module `atoms`".to_string())
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use proc_macro2::TokenStream;
 use quote::quote;
 
+use crate::config::{AsyncConf, PointerWidthConf};
 use crate::error_transform::ErrorTransform;
 use crate::lifetimes::anon_lifetime;
 use crate::module_trait::passed_by_reference;
@@ -12,6 +13,8 @@ pub fn define_func(
     module: &witx::Module,
     func: &witx::InterfaceFunc,
     errxform: &ErrorTransform,
+    async_: &AsyncConf,
+    pointer_width: PointerWidthConf,
 ) -> TokenStream {
     let funcname = func.name.as_str();
 
@@ -22,7 +25,7 @@ pub fn define_func(
 
     let params = coretype.args.iter().map(|arg| {
         let name = names.func_core_arg(arg);
-        let atom = names.atom_type(arg.repr());
+        let atom = abi_arg_type(names, arg, pointer_width);
         quote!(#name : #atom)
     });
 
@@ -91,7 +94,7 @@ pub fn define_func(
     let marshal_args = func
         .params
         .iter()
-        .map(|p| marshal_arg(names, p, error_handling(p.name.as_str())));
+        .map(|p| marshal_arg(names, p, error_handling(p.name.as_str()), pointer_width));
     let trait_args = func.params.iter().map(|param| {
         let name = names.func_param(&param.name);
         if passed_by_reference(&*param.tref.type_()) {
@@ -149,7 +152,7 @@ pub fn define_func(
         .results
         .iter()
         .skip(1)
-        .map(|result| marshal_result(names, result, &error_handling));
+        .map(|result| marshal_result(names, result, &error_handling, pointer_width));
     let marshal_rets_pre = marshal_rets.clone().map(|(pre, _post)| pre);
     let marshal_rets_post = marshal_rets.map(|(_pre, post)| post);
 
@@ -171,19 +174,35 @@ pub fn define_func(
     let mod_name = &module.name.as_str();
     let func_name = &func.name.as_str();
 
-    quote!(pub fn #ident(#abi_args) -> #abi_ret {
+    let (asyncness, trait_call) = if async_.is_async(mod_name, func_name) {
+        // The span must not stay entered while the future is suspended.
+        let call = quote! {
+            {
+                drop(_enter);
+                let result = #trait_name::#ident(ctx, #(#trait_args),*).await;
+                _enter = _span.enter();
+                result
+            }
+        };
+        (quote!(async), call)
+    } else {
+        (quote!(), quote!(#trait_name::#ident(ctx, #(#trait_args),*)))
+    };
+
+    quote!(pub #asyncness fn #ident(#abi_args) -> #abi_ret {
         let _span = #rt::tracing::span!(
             #rt::tracing::Level::TRACE,
             "wiggle abi",
             module = #mod_name,
             function = #func_name
         );
-        let _enter = _span.enter();
+        #[allow(unused_mut)]
+        let mut _enter = _span.enter();
 
         #(#marshal_args)*
         #(#marshal_rets_pre)*
         #log_marshalled_args
-        let #trait_bindings  = match #trait_name::#ident(ctx, #(#trait_args),*) {
+        let #trait_bindings  = match #trait_call {
             Ok(#trait_bindings) => { #trait_rets },
             Err(e) => { #ret_err },
         };
@@ -192,10 +211,41 @@ pub fn define_func(
     })
 }
 
+/// The ABI type of the core argument `arg`, where pointers are `pointer_width`
+/// wide.
+pub(crate) fn abi_arg_type(
+    names: &Names,
+    arg: &witx::CoreParamType,
+    pointer_width: PointerWidthConf,
+) -> TokenStream {
+    let is_pointer = match arg.signifies {
+        witx::CoreParamSignifies::PointerTo => true,
+        witx::CoreParamSignifies::Value(_) => match &*arg.param.tref.type_() {
+            witx::Type::Pointer(_) | witx::Type::ConstPointer(_) => true,
+            _ => false,
+        },
+        witx::CoreParamSignifies::LengthOf => false,
+    };
+    if is_pointer {
+        names.atom_type(pointer_width.atom_type())
+    } else {
+        names.atom_type(arg.repr())
+    }
+}
+
+/// Converts the ABI-level pointer `arg` to a `GuestPtr` offset.
+fn guest_offset(arg: &proc_macro2::Ident, pointer_width: PointerWidthConf) -> TokenStream {
+    match pointer_width {
+        PointerWidthConf::U32 => quote!(#arg as u32 as u64),
+        PointerWidthConf::U64 => quote!(#arg as u64),
+    }
+}
+
 fn marshal_arg(
     names: &Names,
     param: &witx::InterfaceFuncParam,
     error_handling: TokenStream,
+    pointer_width: PointerWidthConf,
 ) -> TokenStream {
     let rt = names.runtime_mod();
     let tref = &param.tref;
@@ -219,9 +269,10 @@ fn marshal_arg(
     let read_conversion = {
         let pointee_type = names.type_ref(tref, anon_lifetime());
         let arg_name = names.func_ptr_binding(&param.name);
+        let offset = guest_offset(&arg_name, pointer_width);
         let name = names.func_param(&param.name);
         quote! {
-            let #name = match #rt::GuestPtr::<#pointee_type>::new(memory, #arg_name as u32).read() {
+            let #name = match #rt::GuestPtr::<#pointee_type>::new(memory, #offset).read() {
                 Ok(r) => r,
                 Err(e) => {
                     #error_handling
@@ -265,17 +316,19 @@ fn marshal_arg(
                 let lifetime = anon_lifetime();
                 let ptr_name = names.func_ptr_binding(&param.name);
                 let len_name = names.func_len_binding(&param.name);
+                let offset = guest_offset(&ptr_name, pointer_width);
                 let name = names.func_param(&param.name);
                 quote! {
-                    let #name = #rt::GuestPtr::<#lifetime, str>::new(memory, (#ptr_name as u32, #len_name as u32));
+                    let #name = #rt::GuestPtr::<#lifetime, str>::new(memory, (#offset, #len_name as u32));
                 }
             }
         },
         witx::Type::Pointer(pointee) | witx::Type::ConstPointer(pointee) => {
             let pointee_type = names.type_ref(pointee, anon_lifetime());
             let name = names.func_param(&param.name);
+            let offset = guest_offset(&name, pointer_width);
             quote! {
-                let #name = #rt::GuestPtr::<#pointee_type>::new(memory, #name as u32);
+                let #name = #rt::GuestPtr::<#pointee_type>::new(memory, #offset);
             }
         }
         witx::Type::Struct(_) => read_conversion,
@@ -283,9 +336,10 @@ fn marshal_arg(
             let pointee_type = names.type_ref(arr, anon_lifetime());
             let ptr_name = names.func_ptr_binding(&param.name);
             let len_name = names.func_len_binding(&param.name);
+            let offset = guest_offset(&ptr_name, pointer_width);
             let name = names.func_param(&param.name);
             quote! {
-                let #name = #rt::GuestPtr::<[#pointee_type]>::new(memory, (#ptr_name as u32, #len_name as u32));
+                let #name = #rt::GuestPtr::<[#pointee_type]>::new(memory, (#offset, #len_name as u32));
             }
         }
         witx::Type::Union(_u) => read_conversion,
@@ -301,6 +355,7 @@ fn marshal_result<F>(
     names: &Names,
     result: &witx::InterfaceFuncParam,
     error_handling: F,
+    pointer_width: PointerWidthConf,
 ) -> (TokenStream, TokenStream)
 where
     F: Fn(&str) -> TokenStream,
@@ -313,8 +368,9 @@ where
         // core type is given func_ptr_binding name.
         let ptr_name = names.func_ptr_binding(&result.name);
         let ptr_err_handling = error_handling(&format!("{}:result_ptr_mut", result.name.as_str()));
+        let offset = guest_offset(&ptr_name, pointer_width);
         let pre = quote! {
-            let #ptr_name = #rt::GuestPtr::<#pointee_type>::new(memory, #ptr_name as u32);
+            let #ptr_name = #rt::GuestPtr::<#pointee_type>::new(memory, #offset);
         };
         // trait binding returns func_param name.
         let val_name = names.func_param(&result.name);
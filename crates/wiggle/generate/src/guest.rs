@@ -0,0 +1,225 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::lifetimes::anon_lifetime;
+use crate::names::Names;
+use crate::types::{define_datatype, int_repr_tokens};
+
+/// Generate the guest side of a witx document: the same `types` module as
+/// `generate`, and for each witx module a Rust module containing the raw
+/// `extern "C"` imports in a `raw` submodule, along with safe wrappers.
+pub fn generate_guest(doc: &witx::Document, names: &Names) -> TokenStream {
+    let types = doc.typenames().map(|t| define_datatype(&names, &t));
+
+    let modules = doc.modules().map(|module| {
+        let modname = names.module(&module.name);
+        let module_name = module.name.as_str();
+        let imports = module.funcs().map(|f| define_import(names, &f));
+        let wrappers = module.funcs().filter_map(|f| define_wrapper(names, &f));
+        quote!(
+            pub mod #modname {
+                #[allow(unused_imports)]
+                use super::types::*;
+
+                pub mod raw {
+                    #[link(wasm_import_module = #module_name)]
+                    extern "C" {
+                        #(#imports)*
+                    }
+                }
+
+                #(#wrappers)*
+            }
+        )
+    });
+
+    quote!(
+        pub mod types {
+            #(#types)*
+        }
+        #(#modules)*
+    )
+}
+
+fn define_import(names: &Names, func: &witx::InterfaceFunc) -> TokenStream {
+    let ident = names.func(&func.name);
+    let func_name = func.name.as_str();
+    let coretype = func.core_type();
+
+    let params = coretype.args.iter().map(|arg| {
+        let name = names.func_core_arg(arg);
+        let atom = names.atom_type(arg.repr());
+        quote!(#name: #atom)
+    });
+    let ret = match &coretype.ret {
+        Some(ret) => match ret.signifies {
+            witx::CoreParamSignifies::Value(atom) => {
+                let atom = names.atom_type(atom);
+                quote!(-> #atom)
+            }
+            _ => unreachable!("ret should always be passed by value"),
+        },
+        None if func.noreturn => quote!(-> !),
+        None => quote!(),
+    };
+
+    quote! {
+        #[link_name = #func_name]
+        pub fn #ident(#(#params),*) #ret;
+    }
+}
+
+/// Whether values of `ty` can be passed across the ABI as they are.
+fn is_value(ty: &witx::Type) -> bool {
+    match ty {
+        witx::Type::Enum(_) | witx::Type::Flags(_) | witx::Type::Int(_) | witx::Type::Handle(_) => {
+            true
+        }
+        witx::Type::Builtin(b) => !matches!(b, witx::BuiltinType::String),
+        witx::Type::Struct(_)
+        | witx::Type::Union(_)
+        | witx::Type::Pointer(_)
+        | witx::Type::ConstPointer(_)
+        | witx::Type::Array(_) => false,
+    }
+}
+
+/// The type results of `ty` are written as by the host, and how to convert
+/// that representation, named `raw`, to `ty`. Every bit pattern of the
+/// representation is valid, so that a value written by the host can be read
+/// before it is validated.
+fn result_repr(names: &Names, tref: &witx::TypeRef) -> (TokenStream, TokenStream) {
+    let rt = names.runtime_mod();
+    let typename = names.type_ref(tref, anon_lifetime());
+    let try_from = quote! {
+        #typename::try_from(raw).map_err(#rt::GuestCallError::InvalidResult)?
+    };
+    match &*tref.type_() {
+        witx::Type::Enum(e) => (int_repr_tokens(e.repr), try_from),
+        witx::Type::Flags(f) => (int_repr_tokens(f.repr), try_from),
+        witx::Type::Int(i) => (int_repr_tokens(i.repr), try_from),
+        witx::Type::Handle(_) => (quote!(u32), quote!(#typename::from(raw))),
+        witx::Type::Builtin(_) => (typename, quote!(raw)),
+        _ => unreachable!("only values are results of wrappers"),
+    }
+}
+
+/// Define a safe wrapper around the import of `func`, taking idiomatic Rust
+/// types and returning `Result<($return_types), GuestCallError<$error_type>>`.
+///
+/// Wrappers are only defined for functions whose params are values, strings or
+/// arrays of values, whose results are values and whose first result, if any,
+/// is an enum error type. Other functions are only available as raw imports.
+///
+/// Results are read as their integer representation and validated, so that
+/// an invalid value returned by the host is an error rather than undefined
+/// behavior.
+fn define_wrapper(names: &Names, func: &witx::InterfaceFunc) -> Option<TokenStream> {
+    let rt = names.runtime_mod();
+    let ident = names.func(&func.name);
+
+    let mut params = Vec::new();
+    let mut abi_args = Vec::new();
+    for param in func.params.iter() {
+        let name = names.func_param(&param.name);
+        let tref = &param.tref;
+        match &*tref.type_() {
+            witx::Type::Builtin(witx::BuiltinType::String) => {
+                params.push(quote!(#name: &str));
+                abi_args.push(quote!(#name.as_ptr() as i32));
+                abi_args.push(quote!(#name.len() as i32));
+            }
+            witx::Type::Array(elem) if is_value(&*elem.type_()) => {
+                let elem_type = names.type_ref(elem, anon_lifetime());
+                params.push(quote!(#name: &[#elem_type]));
+                abi_args.push(quote!(#name.as_ptr() as i32));
+                abi_args.push(quote!(#name.len() as i32));
+            }
+            ty if is_value(ty) => {
+                let typename = names.type_ref(tref, anon_lifetime());
+                let atom = match ty.passed_by() {
+                    witx::TypePassedBy::Value(atom) => names.atom_type(atom),
+                    _ => unreachable!("values are passed by value"),
+                };
+                params.push(quote!(#name: #typename));
+                if let witx::Type::Builtin(_) = ty {
+                    abi_args.push(quote!(#name as #atom));
+                } else {
+                    abi_args.push(quote!(#atom::from(#name)));
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    if func.noreturn {
+        return Some(quote! {
+            pub fn #ident(#(#params),*) -> ! {
+                unsafe { raw::#ident(#(#abi_args),*) }
+            }
+        });
+    }
+
+    let (err, results) = match func.results.split_first() {
+        Some((err, results)) => (err, results),
+        None => {
+            return Some(quote! {
+                pub fn #ident(#(#params),*) {
+                    unsafe { raw::#ident(#(#abi_args),*) }
+                }
+            })
+        }
+    };
+    let err_repr = match &*err.tref.type_() {
+        witx::Type::Enum(e) => int_repr_tokens(e.repr),
+        _ => return None,
+    };
+    let err_type = names.type_ref(&err.tref, anon_lifetime());
+
+    let mut result_names = Vec::new();
+    let mut result_types = Vec::new();
+    let mut result_reprs = Vec::new();
+    let mut result_conversions = Vec::new();
+    for result in results {
+        if !is_value(&*result.tref.type_()) {
+            return None;
+        }
+        let name = names.func_param(&result.name);
+        abi_args.push(quote!(#name.as_mut_ptr() as i32));
+        result_types.push(names.type_ref(&result.tref, anon_lifetime()));
+        let (repr, conversion) = result_repr(names, &result.tref);
+        result_reprs.push(repr);
+        result_conversions.push(conversion);
+        result_names.push(name);
+    }
+    let (ok_type, ok_value) = match result_names.len() {
+        1 => (quote!(#(#result_types)*), quote!(#(#result_names)*)),
+        _ => (quote!((#(#result_types),*)), quote!((#(#result_names),*))),
+    };
+
+    Some(quote! {
+        pub fn #ident(#(#params),*) -> Result<#ok_type, #rt::GuestCallError<#err_type>> {
+            use ::std::convert::TryFrom;
+            #(
+                let mut #result_names = ::std::mem::MaybeUninit::<#result_reprs>::uninit();
+            )*
+            let err = unsafe { raw::#ident(#(#abi_args),*) };
+            // By convention, the first variant of an error enum signifies success.
+            if err != 0 {
+                let err = #err_repr::try_from(err)
+                    .map_err(#rt::GuestError::from)
+                    .and_then(#err_type::try_from)
+                    .map_err(#rt::GuestCallError::InvalidResult)?;
+                return Err(#rt::GuestCallError::Host(err));
+            }
+            #(
+                // The host initialized every result since it returned
+                // successfully, and every bit pattern of its representation
+                // is valid.
+                let raw = unsafe { #result_names.assume_init() };
+                let #result_names = #result_conversions;
+            )*
+            Ok(#ok_value)
+        }
+    })
+}
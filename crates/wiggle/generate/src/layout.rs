@@ -0,0 +1,188 @@
+//! Layout of witx types in guest memory.
+//!
+//! `witx::Layout` lays types out for 32-bit guest memories only. These
+//! functions follow the same rules for any `PointerWidthConf`, where pointers,
+//! strings and arrays grow with the width of guest pointers.
+
+use crate::config::PointerWidthConf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeAlign {
+    pub size: u32,
+    pub align: u32,
+}
+
+/// Layout of a struct: the offset of each member and of the whole struct.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructLayout {
+    pub member_offsets: Vec<u32>,
+    pub size_align: SizeAlign,
+}
+
+/// Layout of a union: the offset of its contents after the tag, and of the
+/// whole union.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnionLayout {
+    pub contents_offset: u32,
+    pub size_align: SizeAlign,
+}
+
+pub fn size_align(tref: &witx::TypeRef, width: PointerWidthConf) -> SizeAlign {
+    type_size_align(&*tref.type_(), width)
+}
+
+fn type_size_align(ty: &witx::Type, width: PointerWidthConf) -> SizeAlign {
+    let pointer = match width {
+        PointerWidthConf::U32 => 4,
+        PointerWidthConf::U64 => 8,
+    };
+    let prim = |size| SizeAlign { size, align: size };
+    match ty {
+        witx::Type::Builtin(b) => match b {
+            witx::BuiltinType::U8 | witx::BuiltinType::S8 | witx::BuiltinType::Char8 => prim(1),
+            witx::BuiltinType::U16 | witx::BuiltinType::S16 => prim(2),
+            witx::BuiltinType::U32
+            | witx::BuiltinType::S32
+            | witx::BuiltinType::F32
+            | witx::BuiltinType::USize => prim(4),
+            witx::BuiltinType::U64 | witx::BuiltinType::S64 | witx::BuiltinType::F64 => prim(8),
+            // A pointer followed by a 32-bit length.
+            witx::BuiltinType::String => slice_size_align(pointer),
+        },
+        witx::Type::Pointer(_) | witx::Type::ConstPointer(_) => prim(pointer),
+        witx::Type::Array(_) => slice_size_align(pointer),
+        witx::Type::Enum(e) => prim(int_repr_size(e.repr)),
+        witx::Type::Flags(f) => prim(int_repr_size(f.repr)),
+        witx::Type::Int(i) => prim(int_repr_size(i.repr)),
+        witx::Type::Handle(_) => prim(4),
+        witx::Type::Struct(s) => struct_layout(s, width).size_align,
+        witx::Type::Union(u) => union_layout(u, width).size_align,
+    }
+}
+
+pub fn struct_layout(s: &witx::StructDatatype, width: PointerWidthConf) -> StructLayout {
+    let mut member_offsets = Vec::with_capacity(s.members.len());
+    let mut offset = 0;
+    let mut align = 1;
+    for m in s.members.iter() {
+        let sa = size_align(&m.tref, width);
+        offset = align_to(offset, sa.align);
+        member_offsets.push(offset);
+        offset += sa.size;
+        align = align.max(sa.align);
+    }
+    StructLayout {
+        member_offsets,
+        size_align: SizeAlign {
+            size: align_to(offset, align),
+            align,
+        },
+    }
+}
+
+pub fn union_layout(u: &witx::UnionDatatype, width: PointerWidthConf) -> UnionLayout {
+    let tag = type_size_align(&*u.tag.tref.type_(), width);
+    let variants = u
+        .variants
+        .iter()
+        .filter_map(|v| v.tref.as_ref().map(|tref| size_align(tref, width)))
+        .collect::<Vec<_>>();
+    let contents_size = variants.iter().map(|sa| sa.size).max().unwrap_or(0);
+    let contents_align = variants.iter().map(|sa| sa.align).max().unwrap_or(1);
+    let contents_offset = align_to(tag.size, contents_align);
+    let align = tag.align.max(contents_align);
+    UnionLayout {
+        contents_offset,
+        size_align: SizeAlign {
+            size: align_to(contents_offset + contents_size, align),
+            align,
+        },
+    }
+}
+
+fn slice_size_align(pointer: u32) -> SizeAlign {
+    SizeAlign {
+        size: align_to(pointer + 4, pointer),
+        align: pointer,
+    }
+}
+
+fn int_repr_size(repr: witx::IntRepr) -> u32 {
+    match repr {
+        witx::IntRepr::U8 => 1,
+        witx::IntRepr::U16 => 2,
+        witx::IntRepr::U32 => 4,
+        witx::IntRepr::U64 => 8,
+    }
+}
+
+fn align_to(offset: u32, align: u32) -> u32 {
+    (offset + align - 1) / align * align
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use witx::Layout;
+
+    fn load(name: &str) -> witx::Document {
+        witx::load(&[format!("{}/../tests/{}", env!("CARGO_MANIFEST_DIR"), name)])
+            .expect("loading witx")
+    }
+
+    #[test]
+    fn matches_witx_for_32_bit_pointers() {
+        for doc in &[load("structs.witx"), load("union.witx")] {
+            for t in doc.typenames() {
+                let tref = witx::TypeRef::Name(t.clone());
+                let witx_sa = tref.mem_size_align();
+                let sa = size_align(&tref, PointerWidthConf::U32);
+                assert_eq!(
+                    sa.size as usize,
+                    witx_sa.size,
+                    "size of {}",
+                    t.name.as_str()
+                );
+                assert_eq!(
+                    sa.align as usize,
+                    witx_sa.align,
+                    "align of {}",
+                    t.name.as_str()
+                );
+                match &*t.tref.type_() {
+                    witx::Type::Struct(s) => {
+                        let offsets = s
+                            .member_layout()
+                            .iter()
+                            .map(|ml| ml.offset as u32)
+                            .collect::<Vec<_>>();
+                        let layout = struct_layout(s, PointerWidthConf::U32);
+                        assert_eq!(layout.member_offsets, offsets);
+                    }
+                    witx::Type::Union(u) => {
+                        let layout = union_layout(u, PointerWidthConf::U32);
+                        assert_eq!(
+                            layout.contents_offset as usize,
+                            u.union_layout().contents_offset
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pointers_grow_with_64_bit_memories() {
+        let doc = load("structs.witx");
+        let pair = doc
+            .typename(&witx::Id::new("pair_int_ptrs"))
+            .expect("pair_int_ptrs is defined");
+        let layout = match &*pair.tref.type_() {
+            witx::Type::Struct(s) => struct_layout(s, PointerWidthConf::U64),
+            _ => panic!("pair_int_ptrs is a struct"),
+        };
+        assert_eq!(layout.member_offsets, vec![0, 8]);
+        assert_eq!(layout.size_align, SizeAlign { size: 16, align: 8 });
+    }
+}
@@ -1,24 +1,37 @@
 pub mod config;
 mod error_transform;
 mod funcs;
+mod guest;
+mod layout;
 mod lifetimes;
 mod module_trait;
 mod names;
 mod types;
+mod wasmtime;
 
 use proc_macro2::TokenStream;
 use quote::quote;
 
 use lifetimes::anon_lifetime;
 
-pub use config::Config;
+pub use config::{
+    AsyncConf, AsyncFunctions, Config, GuestConfig, PointerWidthConf, WasmtimeConfig,
+};
 pub use error_transform::{ErrorTransform, UserErrorType};
 pub use funcs::define_func;
+pub use guest::generate_guest;
 pub use module_trait::define_module_trait;
 pub use names::Names;
 pub use types::define_datatype;
+pub use wasmtime::generate_wasmtime;
 
-pub fn generate(doc: &witx::Document, names: &Names, errs: &ErrorTransform) -> TokenStream {
+pub fn generate(
+    doc: &witx::Document,
+    names: &Names,
+    errs: &ErrorTransform,
+    async_: &AsyncConf,
+    pointer_width: PointerWidthConf,
+) -> TokenStream {
     // TODO at some point config should grow more ability to configure name
     // overrides.
     let rt = names.runtime_mod();
@@ -51,8 +64,8 @@ pub fn generate(doc: &witx::Document, names: &Names, errs: &ErrorTransform) -> T
         let modname = names.module(&module.name);
         let fs = module
             .funcs()
-            .map(|f| define_func(&names, &module, &f, &errs));
-        let modtrait = define_module_trait(&names, &module, &errs);
+            .map(|f| define_func(&names, &module, &f, &errs, async_, pointer_width));
+        let modtrait = define_module_trait(&names, &module, &errs, async_);
         let ctx_type = names.ctx_type();
         quote!(
             pub mod #modname {
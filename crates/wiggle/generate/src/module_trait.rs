@@ -1,6 +1,7 @@
 use proc_macro2::TokenStream;
 use quote::quote;
 
+use crate::config::AsyncConf;
 use crate::error_transform::ErrorTransform;
 use crate::lifetimes::{anon_lifetime, LifetimeExt};
 use crate::names::Names;
@@ -21,8 +22,15 @@ pub fn passed_by_reference(ty: &witx::Type) -> bool {
     }
 }
 
-pub fn define_module_trait(names: &Names, m: &Module, errxform: &ErrorTransform) -> TokenStream {
+pub fn define_module_trait(
+    names: &Names,
+    m: &Module,
+    errxform: &ErrorTransform,
+    async_: &AsyncConf,
+) -> TokenStream {
+    let rt = names.runtime_mod();
     let traitname = names.trait_name(&m.name);
+    let is_async = |f: &witx::InterfaceFunc| async_.is_async(m.name.as_str(), f.name.as_str());
     let traitmethods = m.funcs().map(|f| {
         // Check if we're returning an entity anotated with a lifetime,
         // in which case, we'll need to annotate the function itself, and
@@ -66,13 +74,22 @@ pub fn define_module_trait(names: &Names, m: &Module, errxform: &ErrorTransform)
             })
             .unwrap_or(quote!(()));
 
+        let asyncness = if is_async(&f) { quote!(async) } else { quote!() };
         if is_anonymous {
-            quote!(fn #funcname(&self, #(#args),*) -> Result<(#(#rets),*), #err>;)
+            quote!(#asyncness fn #funcname(&self, #(#args),*) -> Result<(#(#rets),*), #err>;)
         } else {
-            quote!(fn #funcname<#lifetime>(&self, #(#args),*) -> Result<(#(#rets),*), #err>;)
+            quote!(#asyncness fn #funcname<#lifetime>(&self, #(#args),*) -> Result<(#(#rets),*), #err>;)
         }
     });
+    // Async trait methods are boxed by `async_trait`. Guest pointers aren't
+    // `Send`, so neither are the futures.
+    let attr = if m.funcs().any(|f| is_async(&f)) {
+        quote!(#[#rt::async_trait(?Send)])
+    } else {
+        quote!()
+    };
     quote! {
+        #attr
         pub trait #traitname {
             #(#traitmethods)*
         }
@@ -74,7 +74,7 @@ fn define_witx_array(names: &Names, name: &witx::Id, arr_raw: &witx::TypeRef) ->
     quote!(pub type #ident<'a> = #rt::GuestPtr<'a, [#pointee_type]>;)
 }
 
-fn int_repr_tokens(int_repr: witx::IntRepr) -> TokenStream {
+pub(crate) fn int_repr_tokens(int_repr: witx::IntRepr) -> TokenStream {
     match int_repr {
         witx::IntRepr::U8 => quote!(u8),
         witx::IntRepr::U16 => quote!(u16),
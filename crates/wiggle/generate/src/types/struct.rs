@@ -1,3 +1,5 @@
+use crate::config::PointerWidthConf;
+use crate::layout::struct_layout;
 use crate::lifetimes::{anon_lifetime, LifetimeExt};
 use crate::names::Names;
 
@@ -12,8 +14,28 @@ pub(super) fn define_struct(
 ) -> TokenStream {
     let rt = names.runtime_mod();
     let ident = names.type_(name);
-    let size = s.mem_size_align().size as u32;
-    let align = s.mem_size_align().align as usize;
+    let layout32 = struct_layout(s, PointerWidthConf::U32);
+    let layout64 = struct_layout(s, PointerWidthConf::U64);
+    let (size32, size64) = (layout32.size_align.size, layout64.size_align.size);
+    let (align32, align64) = (
+        layout32.size_align.align as usize,
+        layout64.size_align.align as usize,
+    );
+    // Members are at different offsets in 32- and 64-bit memories if the
+    // struct contains pointers.
+    let member_offsets = layout32
+        .member_offsets
+        .iter()
+        .zip(&layout64.member_offsets)
+        .map(|(offset32, offset64)| {
+            quote! {
+                match location.mem().pointer_width() {
+                    #rt::PointerWidth::U32 => #offset32,
+                    #rt::PointerWidth::U64 => #offset64,
+                }
+            }
+        })
+        .collect::<Vec<_>>();
 
     let member_names = s.members.iter().map(|m| names.struct_member(&m.name));
     let member_decls = s.members.iter().map(|m| {
@@ -32,11 +54,10 @@ pub(super) fn define_struct(
         quote!(pub #name: #type_)
     });
 
-    let member_reads = s.member_layout().into_iter().map(|ml| {
-        let name = names.struct_member(&ml.member.name);
-        let offset = ml.offset as u32;
+    let member_reads = s.members.iter().zip(&member_offsets).map(|(m, offset)| {
+        let name = names.struct_member(&m.name);
         let location = quote!(location.cast::<u8>().add(#offset)?.cast());
-        match &ml.member.tref {
+        match &m.tref {
             witx::TypeRef::Name(nt) => {
                 let type_ = names.type_(&nt.name);
                 quote! {
@@ -61,9 +82,8 @@ pub(super) fn define_struct(
         }
     });
 
-    let member_writes = s.member_layout().into_iter().map(|ml| {
-        let name = names.struct_member(&ml.member.name);
-        let offset = ml.offset as u32;
+    let member_writes = s.members.iter().zip(&member_offsets).map(|(m, offset)| {
+        let name = names.struct_member(&m.name);
         quote! {
             #rt::GuestType::write(
                 &location.cast::<u8>().add(#offset)?.cast(),
@@ -112,11 +132,25 @@ pub(super) fn define_struct(
 
         impl<'a> #rt::GuestType<'a> for #ident #struct_lifetime {
             fn guest_size() -> u32 {
-                #size
+                #size32
             }
 
             fn guest_align() -> usize {
-                #align
+                #align32
+            }
+
+            fn guest_size_for(width: #rt::PointerWidth) -> u32 {
+                match width {
+                    #rt::PointerWidth::U32 => #size32,
+                    #rt::PointerWidth::U64 => #size64,
+                }
+            }
+
+            fn guest_align_for(width: #rt::PointerWidth) -> usize {
+                match width {
+                    #rt::PointerWidth::U32 => #align32,
+                    #rt::PointerWidth::U64 => #align64,
+                }
             }
 
             fn read(location: &#rt::GuestPtr<'a, Self>) -> Result<Self, #rt::GuestError> {
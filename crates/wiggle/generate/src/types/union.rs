@@ -1,17 +1,28 @@
+use crate::config::PointerWidthConf;
+use crate::layout::union_layout;
 use crate::lifetimes::LifetimeExt;
 use crate::names::Names;
 
 use proc_macro2::TokenStream;
 use quote::quote;
-use witx::Layout;
 
 pub(super) fn define_union(names: &Names, name: &witx::Id, u: &witx::UnionDatatype) -> TokenStream {
     let rt = names.runtime_mod();
     let ident = names.type_(name);
-    let size = u.mem_size_align().size as u32;
-    let align = u.mem_size_align().align as usize;
-    let ulayout = u.union_layout();
-    let contents_offset = ulayout.contents_offset as u32;
+    let layout32 = union_layout(u, PointerWidthConf::U32);
+    let layout64 = union_layout(u, PointerWidthConf::U64);
+    let (size32, size64) = (layout32.size_align.size, layout64.size_align.size);
+    let (align32, align64) = (
+        layout32.size_align.align as usize,
+        layout64.size_align.align as usize,
+    );
+    let (offset32, offset64) = (layout32.contents_offset, layout64.contents_offset);
+    let contents_offset = quote! {
+        match location.mem().pointer_width() {
+            #rt::PointerWidth::U32 => #offset32,
+            #rt::PointerWidth::U64 => #offset64,
+        }
+    };
 
     let lifetime = quote!('a);
 
@@ -80,11 +91,25 @@ pub(super) fn define_union(names: &Names, name: &witx::Id, u: &witx::UnionDataty
 
         impl<'a> #rt::GuestType<'a> for #ident #enum_lifetime {
             fn guest_size() -> u32 {
-                #size
+                #size32
             }
 
             fn guest_align() -> usize {
-                #align
+                #align32
+            }
+
+            fn guest_size_for(width: #rt::PointerWidth) -> u32 {
+                match width {
+                    #rt::PointerWidth::U32 => #size32,
+                    #rt::PointerWidth::U64 => #size64,
+                }
+            }
+
+            fn guest_align_for(width: #rt::PointerWidth) -> usize {
+                match width {
+                    #rt::PointerWidth::U32 => #align32,
+                    #rt::PointerWidth::U64 => #align64,
+                }
             }
 
             fn read(location: &#rt::GuestPtr<'a, Self>)
@@ -0,0 +1,117 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::config::PointerWidthConf;
+use crate::funcs::abi_arg_type;
+use crate::names::Names;
+
+/// Generate an `add_to_linker` function defining every function of `doc` in a
+/// `wasmtime::Linker`, forwarding calls to the functions generated by `from_witx!`
+/// in the `target` module, for guests whose pointers are `pointer_width` wide.
+pub fn generate_wasmtime(
+    doc: &witx::Document,
+    names: &Names,
+    target: &syn::Path,
+    pointer_width: PointerWidthConf,
+) -> TokenStream {
+    let rt = names.runtime_mod();
+    let ctx_type = names.ctx_type();
+    let guest_pointer_width = match pointer_width {
+        PointerWidthConf::U32 => quote!(#rt::PointerWidth::U32),
+        PointerWidthConf::U64 => quote!(#rt::PointerWidth::U64),
+    };
+
+    let funcs = doc.modules().flat_map(|module| {
+        let module_name = module.name.as_str().to_owned();
+        let modname = names.module(&module.name);
+        module
+            .funcs()
+            .map(|func| {
+                define_wasmtime_func(names, target, &module_name, &modname, &func, pointer_width)
+            })
+            .collect::<Vec<_>>()
+    });
+
+    quote! {
+        /// Define every function of the witx document in `linker`, with calls
+        /// dispatched to `ctx`.
+        ///
+        /// Calls trap if the calling instance doesn't export its memory as `memory`.
+        pub fn add_to_linker(
+            linker: &mut wasmtime::Linker,
+            ctx: std::rc::Rc<#ctx_type>,
+        ) -> anyhow::Result<()> {
+            /// Lightweight `wasmtime::Memory` wrapper implementing `GuestMemory`.
+            struct WasmtimeGuestMemory {
+                mem: wasmtime::Memory,
+                bc: #rt::BorrowChecker,
+            }
+
+            unsafe impl #rt::GuestMemory for WasmtimeGuestMemory {
+                fn base(&self) -> (*mut u8, u64) {
+                    (self.mem.data_ptr(), self.mem.data_size() as _)
+                }
+                fn pointer_width(&self) -> #rt::PointerWidth {
+                    #guest_pointer_width
+                }
+                fn borrow_checker(&self) -> &#rt::BorrowChecker {
+                    &self.bc
+                }
+            }
+
+            #(#funcs)*
+            Ok(())
+        }
+    }
+}
+
+fn define_wasmtime_func(
+    names: &Names,
+    target: &syn::Path,
+    module_name: &str,
+    modname: &proc_macro2::Ident,
+    func: &witx::InterfaceFunc,
+    pointer_width: PointerWidthConf,
+) -> TokenStream {
+    let rt = names.runtime_mod();
+    let func_name = func.name.as_str();
+    let ident = names.func(&func.name);
+    let coretype = func.core_type();
+
+    let arg_names = coretype
+        .args
+        .iter()
+        .map(|arg| names.func_core_arg(arg))
+        .collect::<Vec<_>>();
+    let arg_decls = coretype.args.iter().zip(&arg_names).map(|(arg, name)| {
+        let atom = abi_arg_type(names, arg, pointer_width);
+        quote!(#name: #atom)
+    });
+    let ret = match &coretype.ret {
+        Some(ret) => match ret.signifies {
+            witx::CoreParamSignifies::Value(atom) => names.atom_type(atom),
+            _ => unreachable!("ret should always be passed by value"),
+        },
+        None => quote!(()),
+    };
+
+    quote! {
+        {
+            let ctx = ctx.clone();
+            linker.func(
+                #module_name,
+                #func_name,
+                move |caller: wasmtime::Caller<'_> #(, #arg_decls)*| -> Result<#ret, wasmtime::Trap> {
+                    let mem = match caller.get_export("memory") {
+                        Some(wasmtime::Extern::Memory(m)) => m,
+                        _ => return Err(wasmtime::Trap::new("missing required memory export")),
+                    };
+                    // The generated functions never re-enter the guest, so
+                    // a fresh borrow checker per call is correct.
+                    let mem = WasmtimeGuestMemory { mem, bc: #rt::BorrowChecker::new() };
+                    Ok(#target::#modname::#ident(&*ctx, &mem #(, #arg_names)*))
+                },
+            )?;
+        }
+    }
+}
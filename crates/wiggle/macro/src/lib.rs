@@ -30,6 +30,18 @@ use syn::parse_macro_input;
 ///   CARGO_MANIFEST_DIR of the crate where the macro is invoked.
 /// * `ctx` takes a type name. This type must implement all of the module
 ///    traits
+/// * `async` is optional, and makes some functions `async`: both their
+///   module trait method and their abi-level function. It takes either `*`,
+///   for all functions, or a map from module names to either `*` or a list
+///   of function names, e.g. `async: { arrays::{reduce_excuses} }`, which
+///   must all be defined in the witx document. Module traits with `async`
+///   methods must be implemented with `#[wiggle::async_trait(?Send)]`, which
+///   requires the `async` feature of `wiggle`.
+/// * `pointer_width` is optional, and is the width of the guest's pointers:
+///   `32`, the default, or `64` for guests whose memory is indexed by `i64`.
+///   Pointers passed to the abi-level functions are `i32` or `i64`
+///   accordingly. Pointers stored in guest memory are as wide as the
+///   `GuestMemory::pointer_width` of the memory passed to those functions.
 ///
 /// ## Example
 ///
@@ -96,12 +108,21 @@ pub fn from_witx(args: TokenStream) -> TokenStream {
     );
 
     let doc = config.load_document();
+    if let Err(err) = config.async_.validate(&doc) {
+        return err.to_compile_error().into();
+    }
     let names = wiggle_generate::Names::new(&config.ctx.name, quote!(wiggle));
 
     let error_transform = wiggle_generate::ErrorTransform::new(&config.errors, &doc)
         .expect("validating error transform");
 
-    let code = wiggle_generate::generate(&doc, &names, &error_transform);
+    let code = wiggle_generate::generate(
+        &doc,
+        &names,
+        &error_transform,
+        &config.async_,
+        config.pointer_width,
+    );
     let metadata = if cfg!(feature = "wiggle_metadata") {
         wiggle_generate::generate_metadata(&doc, &names)
     } else {
@@ -110,3 +131,104 @@ pub fn from_witx(args: TokenStream) -> TokenStream {
 
     TokenStream::from(quote! { #code #metadata })
 }
+
+/// Generates a `pub fn add_to_linker(linker: &mut wasmtime::Linker, ctx: Rc<Ctx>)`
+/// function defining every function of a witx document in a `wasmtime::Linker`.
+///
+/// Each definition obtains the guest memory from the calling instance's `memory`
+/// export, wraps it in a `GuestMemory`, and forwards the call to the function
+/// generated by `from_witx!`. A missing `memory` export raises a trap.
+///
+/// Arguments are provided using Rust struct value syntax.
+///
+/// * `witx` (or `witx_literal`) and `ctx` are the same as in the corresponding
+///   `from_witx!` invocation. The `ctx` type must not have lifetime parameters.
+/// * `target` is an optional path to the module where `from_witx!` was invoked.
+///   It defaults to `self`, i.e. the module invoking `wasmtime_integration!`.
+/// * `pointer_width` is the same as in the corresponding `from_witx!`
+///   invocation.
+///
+/// The crate invoking this macro must depend on `wasmtime` and `anyhow`.
+/// Functions made `async` in `from_witx!` aren't supported.
+///
+/// ## Example
+///
+/// ```ignore
+/// wiggle::from_witx!({
+///     witx: ["my_api.witx"],
+///     ctx: MyCtx,
+/// });
+///
+/// wiggle::wasmtime_integration!({
+///     witx: ["my_api.witx"],
+///     ctx: MyCtx,
+/// });
+///
+/// let mut linker = wasmtime::Linker::new(&store);
+/// add_to_linker(&mut linker, Rc::new(MyCtx::new()))?;
+/// ```
+#[proc_macro]
+pub fn wasmtime_integration(args: TokenStream) -> TokenStream {
+    let mut config = parse_macro_input!(args as wiggle_generate::WasmtimeConfig);
+    config.witx.make_paths_relative_to(
+        std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR env var"),
+    );
+
+    let doc = config.load_document();
+    let names = wiggle_generate::Names::new(&config.ctx.name, quote!(wiggle));
+
+    TokenStream::from(wiggle_generate::generate_wasmtime(
+        &doc,
+        &names,
+        &config.target,
+        config.pointer_width,
+    ))
+}
+
+/// Generates guest-side bindings to the functions of a witx document, for use
+/// by Rust programs compiled to WebAssembly.
+///
+/// This macro expands to the same `types` module as `from_witx!`, and for
+/// each `module` defined in the witx document, a Rust module containing:
+///
+/// * A `raw` module of `extern "C"` functions, imported from the witx module
+///   with `#[link(wasm_import_module)]`, taking and returning ABI-level values.
+///
+/// * A safe wrapper for each function whose params are values, strings
+///   (`&str`) or arrays of values (`&[T]`), whose results are values, and
+///   whose first result, if any, is an enum error type. Wrappers return
+///   `Result<($return_types), wiggle::GuestCallError<$error_type>>`, where an
+///   error value of `0` signifies success. Errors and results returned by the
+///   host which aren't valid for their type are reported as
+///   `GuestCallError::InvalidResult`. Other functions are only available in
+///   `raw`.
+///
+/// Arguments are provided using Rust struct value syntax.
+///
+/// * `witx` (or `witx_literal`) is the same as in `from_witx!`.
+///
+/// The crate invoking this macro must depend on `wiggle`, and must be compiled
+/// to a `wasm32` target for the imports to be linked.
+///
+/// ## Example
+///
+/// ```ignore
+/// wiggle::guest_from_witx!({
+///     witx: ["my_api.witx"],
+/// });
+///
+/// let total_bytes = my_api::hello_string("hello")?;
+/// ```
+#[proc_macro]
+pub fn guest_from_witx(args: TokenStream) -> TokenStream {
+    let mut config = parse_macro_input!(args as wiggle_generate::GuestConfig);
+    config.witx.make_paths_relative_to(
+        std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR env var"),
+    );
+
+    let doc = config.load_document();
+    // The guest side has no context type, which only the host side refers to.
+    let names = wiggle_generate::Names::new(&quote::format_ident!("GuestCtx"), quote!(wiggle));
+
+    TokenStream::from(wiggle_generate::generate_guest(&doc, &names))
+}
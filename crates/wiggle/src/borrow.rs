@@ -13,9 +13,11 @@ pub struct BorrowChecker {
 impl BorrowChecker {
     /// A `BorrowChecker` manages run-time validation of borrows from a `GuestMemory`. It keeps
     /// track of regions of guest memory which are possible to alias with Rust references (via the
-    /// `GuestSlice` and `GuestStr` structs, which implement `std::ops::Deref` and
-    /// `std::ops::DerefMut`. It also enforces that `GuestPtr::read` and `GuestPtr::write` do not
-    /// access memory with an outstanding borrow.
+    /// `GuestSlice`, `GuestSliceMut`, `GuestStr` and `GuestStrMut` structs, which implement
+    /// `std::ops::Deref` and, for the mutable ones, `std::ops::DerefMut`). A region may be
+    /// borrowed by any number of shared borrows, or by a single mutable borrow. It also enforces
+    /// that `GuestPtr::read` does not access memory with an outstanding mutable borrow, and that
+    /// `GuestPtr::write` does not access memory with any outstanding borrow.
     /// The safety of this mechanism depends on creating exactly one `BorrowChecker` per
     /// WebAssembly memory. There must be no other reads or writes of WebAssembly the memory by
     /// either Rust or WebAssembly code while there are any outstanding borrows, as given by
@@ -32,26 +34,35 @@ impl BorrowChecker {
         self.bc.borrow().has_outstanding_borrows()
     }
 
-    pub(crate) fn borrow(&self, r: Region) -> Result<BorrowHandle, GuestError> {
-        self.bc.borrow_mut().borrow(r)
+    pub(crate) fn shared_borrow(&self, r: Region) -> Result<BorrowHandle, GuestError> {
+        self.bc.borrow_mut().shared_borrow(r)
+    }
+    pub(crate) fn mut_borrow(&self, r: Region) -> Result<BorrowHandle, GuestError> {
+        self.bc.borrow_mut().mut_borrow(r)
     }
     pub(crate) fn unborrow(&self, h: BorrowHandle) {
         self.bc.borrow_mut().unborrow(h)
     }
+    /// Whether `r` overlaps any borrow, i.e. whether it can't be written.
     pub(crate) fn is_borrowed(&self, r: Region) -> bool {
         self.bc.borrow().is_borrowed(r)
     }
+    /// Whether `r` overlaps a mutable borrow, i.e. whether it can't be read.
+    pub(crate) fn is_mut_borrowed(&self, r: Region) -> bool {
+        self.bc.borrow().is_mut_borrowed(r)
+    }
 }
 
 #[derive(Debug)]
 /// This is a pretty naive way to account for borrows. This datastructure
 /// could be made a lot more efficient with some effort.
 struct InnerBorrowChecker {
-    /// Map from handle to region borrowed. A HashMap is probably not ideal
-    /// for this but it works. It would be more efficient if we could
-    /// check `is_borrowed` without an O(n) iteration, by organizing borrows
-    /// by an ordering of Region.
-    borrows: HashMap<BorrowHandle, Region>,
+    /// Maps from handle to region borrowed, for shared and mutable borrows
+    /// respectively. A HashMap is probably not ideal for this but it works.
+    /// It would be more efficient if we could check `is_borrowed` without an
+    /// O(n) iteration, by organizing borrows by an ordering of Region.
+    shared_borrows: HashMap<BorrowHandle, Region>,
+    mut_borrows: HashMap<BorrowHandle, Region>,
     /// Handle to give out for the next borrow. This is the bare minimum of
     /// bookkeeping of free handles, and in a pathological case we could run
     /// out, hence [`GuestError::BorrowCheckerOutOfHandles`]
@@ -61,22 +72,27 @@ struct InnerBorrowChecker {
 impl InnerBorrowChecker {
     fn new() -> Self {
         InnerBorrowChecker {
-            borrows: HashMap::new(),
+            shared_borrows: HashMap::new(),
+            mut_borrows: HashMap::new(),
             next_handle: BorrowHandle(0),
         }
     }
 
     fn has_outstanding_borrows(&self) -> bool {
-        !self.borrows.is_empty()
+        !(self.shared_borrows.is_empty() && self.mut_borrows.is_empty())
     }
 
     fn is_borrowed(&self, r: Region) -> bool {
-        !self.borrows.values().all(|b| !b.overlaps(r))
+        self.is_mut_borrowed(r) || self.shared_borrows.values().any(|b| b.overlaps(r))
+    }
+
+    fn is_mut_borrowed(&self, r: Region) -> bool {
+        self.mut_borrows.values().any(|b| b.overlaps(r))
     }
 
     fn new_handle(&mut self) -> Result<BorrowHandle, GuestError> {
         // Reset handles to 0 if all handles have been returned.
-        if self.borrows.is_empty() {
+        if !self.has_outstanding_borrows() {
             self.next_handle = BorrowHandle(0);
         }
         let h = self.next_handle;
@@ -92,17 +108,29 @@ impl InnerBorrowChecker {
         Ok(h)
     }
 
-    fn borrow(&mut self, r: Region) -> Result<BorrowHandle, GuestError> {
+    fn shared_borrow(&mut self, r: Region) -> Result<BorrowHandle, GuestError> {
+        if self.is_mut_borrowed(r) {
+            return Err(GuestError::PtrBorrowed(r));
+        }
+        let h = self.new_handle()?;
+        self.shared_borrows.insert(h, r);
+        Ok(h)
+    }
+
+    fn mut_borrow(&mut self, r: Region) -> Result<BorrowHandle, GuestError> {
         if self.is_borrowed(r) {
             return Err(GuestError::PtrBorrowed(r));
         }
         let h = self.new_handle()?;
-        self.borrows.insert(h, r);
+        self.mut_borrows.insert(h, r);
         Ok(h)
     }
 
     fn unborrow(&mut self, h: BorrowHandle) {
-        let _ = self.borrows.remove(&h);
+        // Handles are unique across both maps.
+        if self.shared_borrows.remove(&h).is_none() {
+            let _ = self.mut_borrows.remove(&h);
+        }
     }
 }
 
@@ -115,15 +143,15 @@ mod test {
         let r1 = Region::new(0, 10);
         let r2 = Region::new(10, 10);
         assert!(!r1.overlaps(r2));
-        bs.borrow(r1).expect("can borrow r1");
-        bs.borrow(r2).expect("can borrow r2");
+        bs.mut_borrow(r1).expect("can borrow r1");
+        bs.mut_borrow(r2).expect("can borrow r2");
 
         let mut bs = InnerBorrowChecker::new();
         let r1 = Region::new(10, 10);
         let r2 = Region::new(0, 10);
         assert!(!r1.overlaps(r2));
-        bs.borrow(r1).expect("can borrow r1");
-        bs.borrow(r2).expect("can borrow r2");
+        bs.mut_borrow(r1).expect("can borrow r1");
+        bs.mut_borrow(r2).expect("can borrow r2");
     }
 
     #[test]
@@ -132,29 +160,29 @@ mod test {
         let r1 = Region::new(0, 10);
         let r2 = Region::new(9, 10);
         assert!(r1.overlaps(r2));
-        bs.borrow(r1).expect("can borrow r1");
-        assert!(bs.borrow(r2).is_err(), "cant borrow r2");
+        bs.mut_borrow(r1).expect("can borrow r1");
+        assert!(bs.mut_borrow(r2).is_err(), "cant borrow r2");
 
         let mut bs = InnerBorrowChecker::new();
         let r1 = Region::new(0, 10);
         let r2 = Region::new(2, 5);
         assert!(r1.overlaps(r2));
-        bs.borrow(r1).expect("can borrow r1");
-        assert!(bs.borrow(r2).is_err(), "cant borrow r2");
+        bs.mut_borrow(r1).expect("can borrow r1");
+        assert!(bs.mut_borrow(r2).is_err(), "cant borrow r2");
 
         let mut bs = InnerBorrowChecker::new();
         let r1 = Region::new(9, 10);
         let r2 = Region::new(0, 10);
         assert!(r1.overlaps(r2));
-        bs.borrow(r1).expect("can borrow r1");
-        assert!(bs.borrow(r2).is_err(), "cant borrow r2");
+        bs.mut_borrow(r1).expect("can borrow r1");
+        assert!(bs.mut_borrow(r2).is_err(), "cant borrow r2");
 
         let mut bs = InnerBorrowChecker::new();
         let r1 = Region::new(2, 5);
         let r2 = Region::new(0, 10);
         assert!(r1.overlaps(r2));
-        bs.borrow(r1).expect("can borrow r1");
-        assert!(bs.borrow(r2).is_err(), "cant borrow r2");
+        bs.mut_borrow(r1).expect("can borrow r1");
+        assert!(bs.mut_borrow(r2).is_err(), "cant borrow r2");
 
         let mut bs = InnerBorrowChecker::new();
         let r1 = Region::new(2, 5);
@@ -162,10 +190,10 @@ mod test {
         let r3 = Region::new(15, 5);
         let r4 = Region::new(0, 10);
         assert!(r1.overlaps(r4));
-        bs.borrow(r1).expect("can borrow r1");
-        bs.borrow(r2).expect("can borrow r2");
-        bs.borrow(r3).expect("can borrow r3");
-        assert!(bs.borrow(r4).is_err(), "cant borrow r4");
+        bs.mut_borrow(r1).expect("can borrow r1");
+        bs.mut_borrow(r2).expect("can borrow r2");
+        bs.mut_borrow(r3).expect("can borrow r3");
+        assert!(bs.mut_borrow(r4).is_err(), "cant borrow r4");
     }
 
     #[test]
@@ -175,11 +203,11 @@ mod test {
         let r2 = Region::new(10, 10);
         assert!(!r1.overlaps(r2));
         assert_eq!(bs.has_outstanding_borrows(), false, "start with no borrows");
-        let h1 = bs.borrow(r1).expect("can borrow r1");
+        let h1 = bs.mut_borrow(r1).expect("can borrow r1");
         assert_eq!(bs.has_outstanding_borrows(), true, "h1 is outstanding");
-        let h2 = bs.borrow(r2).expect("can borrow r2");
+        let h2 = bs.mut_borrow(r2).expect("can borrow r2");
 
-        assert!(bs.borrow(r2).is_err(), "can't borrow r2 twice");
+        assert!(bs.mut_borrow(r2).is_err(), "can't borrow r2 twice");
         bs.unborrow(h2);
         assert_eq!(
             bs.has_outstanding_borrows(),
@@ -190,7 +218,7 @@ mod test {
         assert_eq!(bs.has_outstanding_borrows(), false, "no remaining borrows");
 
         let _h3 = bs
-            .borrow(r2)
+            .mut_borrow(r2)
             .expect("can borrow r2 again now that its been unborrowed");
     }
 }
@@ -38,3 +38,15 @@ pub enum GuestError {
     #[error("Int conversion error: {0:?}")]
     TryFromIntError(#[from] ::std::num::TryFromIntError),
 }
+
+/// The error of a wrapper function generated by `guest_from_witx!`.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum GuestCallError<E: std::fmt::Debug + std::fmt::Display> {
+    /// The host returned this error value.
+    #[error("{0}")]
+    Host(E),
+    /// The host returned a value, as an error or a result, that isn't valid
+    /// for its type.
+    #[error("Invalid value returned by the host")]
+    InvalidResult(#[source] GuestError),
+}
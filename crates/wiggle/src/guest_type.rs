@@ -1,4 +1,5 @@
-use crate::{region::Region, GuestError, GuestPtr};
+use crate::{region::Region, GuestError, GuestPtr, PointerWidth};
+use std::convert::TryFrom;
 use std::mem;
 
 /// A trait for types which are used to report errors. Each type used in the
@@ -19,13 +20,31 @@ pub trait GuestErrorType {
 /// the host representation of a type, if necessary. It also allows for
 /// validation when reading/writing.
 pub trait GuestType<'a>: Sized {
-    /// Returns the size, in bytes, of this type in the guest memory.
+    /// Returns the size, in bytes, of this type in a 32-bit guest memory.
     fn guest_size() -> u32;
 
     /// Returns the required alignment of this type, in bytes, for both guest
-    /// and host memory.
+    /// and host memory, in a 32-bit guest memory.
     fn guest_align() -> usize;
 
+    /// Returns the size, in bytes, of this type in a guest memory whose
+    /// pointers are `width` wide.
+    ///
+    /// Only types containing pointers need to override this, the default is
+    /// `guest_size`.
+    fn guest_size_for(_width: PointerWidth) -> u32 {
+        Self::guest_size()
+    }
+
+    /// Returns the required alignment of this type, in bytes, in a guest
+    /// memory whose pointers are `width` wide.
+    ///
+    /// Only types containing pointers need to override this, the default is
+    /// `guest_align`.
+    fn guest_align_for(_width: PointerWidth) -> usize {
+        Self::guest_align()
+    }
+
     /// Reads this value from the provided `ptr`.
     ///
     /// Must internally perform any safety checks necessary and is allowed to
@@ -75,7 +94,7 @@ macro_rules! primitives {
                 // should be able to safely ready the pointer just after we
                 // validated it, returning it along here.
                 let offset = ptr.offset();
-                let size = Self::guest_size();
+                let size = Self::guest_size().into();
                 let host_ptr = ptr.mem().validate_size_align(
                     offset,
                     Self::guest_align(),
@@ -85,7 +104,7 @@ macro_rules! primitives {
                     start: offset,
                     len: size,
                 };
-                if ptr.borrow_checker().is_borrowed(region) {
+                if ptr.borrow_checker().is_mut_borrowed(region) {
                     return Err(GuestError::PtrBorrowed(region));
                 }
                 Ok(unsafe { *host_ptr.cast::<Self>() })
@@ -94,7 +113,7 @@ macro_rules! primitives {
             #[inline]
             fn write(ptr: &GuestPtr<'_, Self>, val: Self) -> Result<(), GuestError> {
                 let offset = ptr.offset();
-                let size = Self::guest_size();
+                let size = Self::guest_size().into();
                 let host_ptr = ptr.mem().validate_size_align(
                     offset,
                     Self::guest_align(),
@@ -134,22 +153,43 @@ primitives! {
     f32 f64
 }
 
-// Support pointers-to-pointers where pointers are always 32-bits in wasm land
+// Support pointers-to-pointers, where pointers stored in memory are as wide as
+// the memory's own pointers.
 impl<'a, T> GuestType<'a> for GuestPtr<'a, T> {
     fn guest_size() -> u32 {
-        u32::guest_size()
+        Self::guest_size_for(PointerWidth::U32)
     }
 
     fn guest_align() -> usize {
-        u32::guest_align()
+        Self::guest_align_for(PointerWidth::U32)
+    }
+
+    fn guest_size_for(width: PointerWidth) -> u32 {
+        match width {
+            PointerWidth::U32 => u32::guest_size(),
+            PointerWidth::U64 => u64::guest_size(),
+        }
+    }
+
+    fn guest_align_for(width: PointerWidth) -> usize {
+        match width {
+            PointerWidth::U32 => u32::guest_align(),
+            PointerWidth::U64 => u64::guest_align(),
+        }
     }
 
     fn read(ptr: &GuestPtr<'a, Self>) -> Result<Self, GuestError> {
-        let offset = ptr.cast::<u32>().read()?;
+        let offset = match ptr.mem().pointer_width() {
+            PointerWidth::U32 => ptr.cast::<u32>().read()?.into(),
+            PointerWidth::U64 => ptr.cast::<u64>().read()?,
+        };
         Ok(GuestPtr::new(ptr.mem(), offset))
     }
 
     fn write(ptr: &GuestPtr<'_, Self>, val: Self) -> Result<(), GuestError> {
-        ptr.cast::<u32>().write(val.offset())
+        match ptr.mem().pointer_width() {
+            PointerWidth::U32 => ptr.cast::<u32>().write(u32::try_from(val.offset())?),
+            PointerWidth::U64 => ptr.cast::<u64>().write(val.offset()),
+        }
     }
 }
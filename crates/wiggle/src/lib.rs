@@ -6,7 +6,9 @@ use std::slice;
 use std::str;
 use std::sync::Arc;
 
-pub use wiggle_macro::from_witx;
+#[cfg(feature = "async")]
+pub use async_trait::async_trait;
+pub use wiggle_macro::{from_witx, guest_from_witx, wasmtime_integration};
 
 #[cfg(feature = "wiggle_metadata")]
 pub use witx;
@@ -20,7 +22,7 @@ pub extern crate tracing;
 
 pub use borrow::BorrowChecker;
 use borrow::BorrowHandle;
-pub use error::GuestError;
+pub use error::{GuestCallError, GuestError};
 pub use guest_type::{GuestErrorType, GuestType, GuestTypeTransparent};
 pub use region::Region;
 
@@ -61,18 +63,20 @@ pub use region::Region;
 /// See the safety guarantees of [`BorrowChecker`], which asserts that exactly
 /// one `BorrowChecker` may be constructed for each WebAssembly memory.
 ///
-/// The [`GuestMemory::as_slice`] or [`GuestPtr::as_str`] will return smart
-/// pointers [`GuestSlice`] and [`GuestStr`]. These types, which implement
-/// [`std::ops::Deref`] and [`std::ops::DerefMut`], provide mutable references
-/// into the memory region given by a `GuestMemory`.
+/// The [`GuestPtr::as_slice`] or [`GuestPtr::as_str`] methods will return
+/// smart pointers [`GuestSlice`] and [`GuestStr`]. These types, which
+/// implement [`std::ops::Deref`], provide shared references into the memory
+/// region given by a `GuestMemory`. Similarly, [`GuestPtr::as_slice_mut`] and
+/// [`GuestPtr::as_str_mut`] return [`GuestSliceMut`] and [`GuestStrMut`],
+/// which also implement [`std::ops::DerefMut`] to provide mutable references.
 ///
 /// These smart pointers are dynamically borrow-checked by the `BorrowChecker`
-/// given by [`GuestMemory::borrow_checker()`]. While a `GuestSlice`
-/// or a `GuestStr` are live, the [`BorrowChecker::has_outstanding_borrows()`]
-/// method will always return `true`. If you need to re-enter the guest or
-/// otherwise read or write to the contents of a WebAssembly memory, all
-/// `GuestSlice`s and `GuestStr`s for the memory must be dropped, at which
-/// point `BorrowChecker::has_outstanding_borrows()` will return `false`.
+/// given by [`GuestMemory::borrow_checker()`]. While any of them is live, the
+/// [`BorrowChecker::has_outstanding_borrows()`] method will always return
+/// `true`. If you need to re-enter the guest or otherwise read or write to the
+/// contents of a WebAssembly memory, all of these smart pointers for the memory
+/// must be dropped, at which point `BorrowChecker::has_outstanding_borrows()`
+/// will return `false`.
 pub unsafe trait GuestMemory {
     /// Returns the base allocation of this guest memory, located in host
     /// memory.
@@ -84,7 +88,17 @@ pub unsafe trait GuestMemory {
     /// Note that there are safety guarantees about this method that
     /// implementations must uphold, and for more details see the
     /// [`GuestMemory`] documentation.
-    fn base(&self) -> (*mut u8, u32);
+    fn base(&self) -> (*mut u8, u64);
+
+    /// Returns the width of the guest's pointers.
+    ///
+    /// This defaults to 32 bits. Implementations for 64-bit memories should
+    /// return `PointerWidth::U64`, allowing pointer arithmetic beyond 4 GiB.
+    /// Pointers stored in guest memory, such as the fields of witx structs, are
+    /// laid out with this width too.
+    fn pointer_width(&self) -> PointerWidth {
+        PointerWidth::U32
+    }
 
     /// Gives a reference to the [`BorrowChecker`] used to keep track of each
     /// outstanding borrow of the memory region. [`BorrowChecker::new`] safety
@@ -112,28 +126,27 @@ pub unsafe trait GuestMemory {
     /// returned pointer.
     fn validate_size_align(
         &self,
-        offset: u32,
+        offset: u64,
         align: usize,
-        len: u32,
+        len: u64,
     ) -> Result<*mut u8, GuestError> {
         let (base_ptr, base_len) = self.base();
         let region = Region { start: offset, len };
 
-        // Figure out our pointer to the start of memory
-        let start = match (base_ptr as usize).checked_add(offset as usize) {
-            Some(ptr) => ptr,
-            None => return Err(GuestError::PtrOverflow),
-        };
-        // and use that to figure out the end pointer
-        let end = match start.checked_add(len as usize) {
-            Some(ptr) => ptr,
+        // Figure out the guest-relative end of the region, in the guest's
+        // 64-bit address space so that it can't overflow on 32-bit hosts
+        let end = match offset.checked_add(len) {
+            Some(end) => end,
             None => return Err(GuestError::PtrOverflow),
         };
-        // and then verify that our end doesn't reach past the end of our memory
-        if end > (base_ptr as usize) + (base_len as usize) {
+        // and verify that it doesn't reach past the end of our memory.
+        if end > base_len {
             return Err(GuestError::PtrOutOfBounds(region));
         }
-        // and finally verify that the alignment is correct
+        // The region is within the host allocation of the memory, so the
+        // host pointer to its start can't overflow.
+        let start = (base_ptr as usize) + (offset as usize);
+        // Finally verify that the alignment is correct
         if start % align != 0 {
             return Err(GuestError::PtrNotAligned(region, align as u32));
         }
@@ -142,8 +155,8 @@ pub unsafe trait GuestMemory {
 
     /// Convenience method for creating a `GuestPtr` at a particular offset.
     ///
-    /// Note that `T` can be almost any type, and typically `offset` is a `u32`.
-    /// The exception is slices and strings, in which case `offset` is a `(u32,
+    /// Note that `T` can be almost any type, and typically `offset` is a `u64`.
+    /// The exception is slices and strings, in which case `offset` is a `(u64,
     /// u32)` of `(offset, length)`.
     fn ptr<'a, T>(&'a self, offset: T::Pointer) -> GuestPtr<'a, T>
     where
@@ -157,50 +170,84 @@ pub unsafe trait GuestMemory {
 // Forwarding trait implementations to the original type
 
 unsafe impl<'a, T: ?Sized + GuestMemory> GuestMemory for &'a T {
-    fn base(&self) -> (*mut u8, u32) {
+    fn base(&self) -> (*mut u8, u64) {
         T::base(self)
     }
+    fn pointer_width(&self) -> PointerWidth {
+        T::pointer_width(self)
+    }
     fn borrow_checker(&self) -> &BorrowChecker {
         T::borrow_checker(self)
     }
 }
 
 unsafe impl<'a, T: ?Sized + GuestMemory> GuestMemory for &'a mut T {
-    fn base(&self) -> (*mut u8, u32) {
+    fn base(&self) -> (*mut u8, u64) {
         T::base(self)
     }
+    fn pointer_width(&self) -> PointerWidth {
+        T::pointer_width(self)
+    }
     fn borrow_checker(&self) -> &BorrowChecker {
         T::borrow_checker(self)
     }
 }
 
 unsafe impl<T: ?Sized + GuestMemory> GuestMemory for Box<T> {
-    fn base(&self) -> (*mut u8, u32) {
+    fn base(&self) -> (*mut u8, u64) {
         T::base(self)
     }
+    fn pointer_width(&self) -> PointerWidth {
+        T::pointer_width(self)
+    }
     fn borrow_checker(&self) -> &BorrowChecker {
         T::borrow_checker(self)
     }
 }
 
 unsafe impl<T: ?Sized + GuestMemory> GuestMemory for Rc<T> {
-    fn base(&self) -> (*mut u8, u32) {
+    fn base(&self) -> (*mut u8, u64) {
         T::base(self)
     }
+    fn pointer_width(&self) -> PointerWidth {
+        T::pointer_width(self)
+    }
     fn borrow_checker(&self) -> &BorrowChecker {
         T::borrow_checker(self)
     }
 }
 
 unsafe impl<T: ?Sized + GuestMemory> GuestMemory for Arc<T> {
-    fn base(&self) -> (*mut u8, u32) {
+    fn base(&self) -> (*mut u8, u64) {
         T::base(self)
     }
+    fn pointer_width(&self) -> PointerWidth {
+        T::pointer_width(self)
+    }
     fn borrow_checker(&self) -> &BorrowChecker {
         T::borrow_checker(self)
     }
 }
 
+/// The width of the pointers of a guest, see [`GuestMemory::pointer_width`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PointerWidth {
+    /// 32-bit pointers, as used by wasm32 guests.
+    U32,
+    /// 64-bit pointers, as used by guests with a 64-bit memory.
+    U64,
+}
+
+impl PointerWidth {
+    /// Returns the largest offset a pointer of this width can hold.
+    pub fn max_offset(self) -> u64 {
+        match self {
+            PointerWidth::U32 => u32::max_value().into(),
+            PointerWidth::U64 => u64::max_value(),
+        }
+    }
+}
+
 /// A *guest* pointer into host memory.
 ///
 /// This type represents a pointer from the guest that points into host memory.
@@ -237,8 +284,9 @@ unsafe impl<T: ?Sized + GuestMemory> GuestMemory for Arc<T> {
 /// construct a `T` from a `U`.
 ///
 /// For example `GuestPtr<GuestPtr<T>>` is a valid type, but this is actually
-/// more equivalent to `GuestPtr<u32>` because guest pointers are always
-/// 32-bits. That being said you can create a `GuestPtr<T>` from a `u32`.
+/// more equivalent to `GuestPtr<u32>` (or `GuestPtr<u64>` in a 64-bit memory)
+/// because guest pointers stored in memory are as wide as the memory's
+/// pointers. That being said you can create a `GuestPtr<T>` from a `u64`.
 ///
 /// Additionally `GuestPtr<MyEnum>` will actually delegate, typically, to and
 /// implementation which loads the underlying data as `GuestPtr<u8>` (or
@@ -259,8 +307,8 @@ impl<'a, T: ?Sized + Pointee> GuestPtr<'a, T> {
     /// Creates a new `GuestPtr` from the given `mem` and `pointer` values.
     ///
     /// Note that for sized types like `u32`, `GuestPtr<T>`, etc, the `pointer`
-    /// vlue is a `u32` offset into guest memory. For slices and strings,
-    /// `pointer` is a `(u32, u32)` offset/length pair.
+    /// vlue is a `u64` offset into guest memory. For slices and strings,
+    /// `pointer` is a `(u64, u32)` offset/length pair.
     pub fn new(mem: &'a (dyn GuestMemory + 'a), pointer: T::Pointer) -> GuestPtr<'a, T> {
         GuestPtr {
             mem,
@@ -271,8 +319,8 @@ impl<'a, T: ?Sized + Pointee> GuestPtr<'a, T> {
 
     /// Returns the offset of this pointer in guest memory.
     ///
-    /// Note that for sized types this returns a `u32`, but for slices and
-    /// strings it returns a `(u32, u32)` pointer/length pair.
+    /// Note that for sized types this returns a `u64`, but for slices and
+    /// strings it returns a `(u64, u32)` pointer/length pair.
     pub fn offset(&self) -> T::Pointer {
         self.pointer
     }
@@ -295,7 +343,7 @@ impl<'a, T: ?Sized + Pointee> GuestPtr<'a, T> {
     /// etc of the returned pointer.
     pub fn cast<U>(&self) -> GuestPtr<'a, U>
     where
-        T: Pointee<Pointer = u32>,
+        T: Pointee<Pointer = u64>,
     {
         GuestPtr::new(self.mem, self.pointer)
     }
@@ -352,14 +400,16 @@ impl<'a, T: ?Sized + Pointee> GuestPtr<'a, T> {
     ///
     /// This will either return the resulting pointer or `Err` if the pointer
     /// arithmetic calculation would overflow around the end of the address
-    /// space.
+    /// space, whose size is given by [`GuestMemory::pointer_width`].
     pub fn add(&self, amt: u32) -> Result<GuestPtr<'a, T>, GuestError>
     where
-        T: GuestType<'a> + Pointee<Pointer = u32>,
+        T: GuestType<'a> + Pointee<Pointer = u64>,
     {
-        let offset = amt
-            .checked_mul(T::guest_size())
-            .and_then(|o| self.pointer.checked_add(o));
+        // The product of two `u32`s always fits in a `u64`.
+        let offset = self
+            .pointer
+            .checked_add(u64::from(amt) * u64::from(T::guest_size_for(self.mem.pointer_width())))
+            .filter(|o| *o <= self.mem.pointer_width().max_offset());
         let offset = match offset {
             Some(o) => o,
             None => return Err(GuestError::PtrOverflow),
@@ -371,7 +421,7 @@ impl<'a, T: ?Sized + Pointee> GuestPtr<'a, T> {
     /// base.
     pub fn as_array(&self, elems: u32) -> GuestPtr<'a, [T]>
     where
-        T: GuestType<'a> + Pointee<Pointer = u32>,
+        T: GuestType<'a> + Pointee<Pointer = u64>,
     {
         GuestPtr::new(self.mem, (self.pointer, elems))
     }
@@ -382,7 +432,7 @@ impl<'a, T> GuestPtr<'a, [T]> {
     /// array.
     ///
     /// This is similar to `<[T]>::as_ptr()`
-    pub fn offset_base(&self) -> u32 {
+    pub fn offset_base(&self) -> u64 {
         self.pointer.0
     }
 
@@ -407,46 +457,127 @@ impl<'a, T> GuestPtr<'a, [T]> {
 
     /// Attempts to create a [`GuestSlice<'_, T>`] from this pointer, performing
     /// bounds checks and type validation. The `GuestSlice` is a smart pointer
-    /// that can be used as a `&[T]` or a `&mut [T]` via the `Deref` and `DerefMut`
-    /// traits. The region of memory backing the slice will be marked as borrowed
-    /// by the [`BorrowChecker`] until the `GuestSlice` is dropped.
+    /// that can be used as a `&[T]` via the `Deref` trait. The region of memory
+    /// backing the slice will be marked as shared-borrowed by the
+    /// [`BorrowChecker`] until the `GuestSlice` is dropped: any number of
+    /// `GuestSlice`s may view the same region, and it can still be read with
+    /// [`GuestPtr::read`], but not mutably borrowed or written.
     ///
     /// This function will return a `GuestSlice` into host memory if all checks
-    /// succeed (valid utf-8, valid pointers, memory is not borrowed, etc). If
-    /// any checks fail then `GuestError` will be returned.
+    /// succeed (valid pointers, valid values, memory is not mutably borrowed,
+    /// etc). If any checks fail then `GuestError` will be returned.
     pub fn as_slice(&self) -> Result<GuestSlice<'a, T>, GuestError>
     where
         T: GuestTypeTransparent<'a>,
     {
-        let len = match self.pointer.1.checked_mul(T::guest_size()) {
-            Some(l) => l,
-            None => return Err(GuestError::PtrOverflow),
-        };
-        let ptr =
-            self.mem
-                .validate_size_align(self.pointer.0, T::guest_align(), len)? as *mut T;
+        let (ptr, region) = self.validate_slice()?;
+        let borrow = self.mem.borrow_checker().shared_borrow(region)?;
 
-        let borrow = self.mem.borrow_checker().borrow(Region {
-            start: self.pointer.0,
-            len,
-        })?;
+        // SAFETY: iff there are no overlapping mutable borrows it is valid to
+        // construct a &[T]
+        let ptr = unsafe { slice::from_raw_parts(ptr, self.pointer.1 as usize) };
 
-        // Validate all elements in slice.
-        // SAFETY: ptr has been validated by self.mem.validate_size_align
-        for offs in 0..self.pointer.1 {
-            T::validate(unsafe { ptr.add(offs as usize) })?;
-        }
+        Ok(GuestSlice {
+            ptr,
+            bc: self.mem.borrow_checker(),
+            borrow,
+        })
+    }
+
+    /// Attempts to create a [`GuestSliceMut<'_, T>`] from this pointer,
+    /// performing bounds checks and type validation. The `GuestSliceMut` is a
+    /// smart pointer that can be used as a `&[T]` or a `&mut [T]` via the
+    /// `Deref` and `DerefMut` traits. The region of memory backing the slice
+    /// will be marked as mutably borrowed by the [`BorrowChecker`] until the
+    /// `GuestSliceMut` is dropped, so it can't be accessed by other means in
+    /// the meantime.
+    ///
+    /// This function will return a `GuestSliceMut` into host memory if all
+    /// checks succeed (valid pointers, valid values, memory is not borrowed,
+    /// etc). If any checks fail then `GuestError` will be returned.
+    pub fn as_slice_mut(&self) -> Result<GuestSliceMut<'a, T>, GuestError>
+    where
+        T: GuestTypeTransparent<'a>,
+    {
+        let (ptr, region) = self.validate_slice()?;
+        let borrow = self.mem.borrow_checker().mut_borrow(region)?;
 
-        // SAFETY: iff there are no overlapping borrows it is valid to construct a &mut [T]
+        // SAFETY: iff there are no overlapping borrows it is valid to
+        // construct a &mut [T]
         let ptr = unsafe { slice::from_raw_parts_mut(ptr, self.pointer.1 as usize) };
 
-        Ok(GuestSlice {
+        Ok(GuestSliceMut {
             ptr,
             bc: self.mem.borrow_checker(),
             borrow,
         })
     }
 
+    /// Attempts to create a [`GuestCow<'_, T>`] from this pointer, which
+    /// starts out as a shared view of guest memory like [`GuestPtr::as_slice`]
+    /// and is only copied to the host if it's modified through
+    /// [`GuestCow::to_mut`].
+    ///
+    /// This is useful to process guest data which only occasionally needs to
+    /// be modified, without ever writing the modifications back to the guest.
+    pub fn as_cow(&self) -> Result<GuestCow<'a, T>, GuestError>
+    where
+        T: GuestTypeTransparent<'a> + Copy,
+    {
+        Ok(GuestCow::Borrowed(self.as_slice()?))
+    }
+
+    /// Creates a `&mut [T]` from this pointer, performing bounds checks and
+    /// type validation but bypassing the [`BorrowChecker`] entirely.
+    ///
+    /// This is a fast path for trusted, single-threaded contexts where the
+    /// bookkeeping of [`GuestPtr::as_slice_mut`] is measurable, such as
+    /// hostcalls copying large buffers.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that for the lifetime `'a` of the returned
+    /// slice, no other reference to the same region of guest memory exists,
+    /// no other `GuestSlice`, `GuestStr`, etc. borrowing it is created, the
+    /// region isn't accessed by `GuestPtr::read` or `GuestPtr::write`, and
+    /// the guest doesn't run.
+    pub unsafe fn as_unchecked_slice_mut(&self) -> Result<&'a mut [T], GuestError>
+    where
+        T: GuestTypeTransparent<'a>,
+    {
+        let (ptr, _region) = self.validate_slice()?;
+        Ok(slice::from_raw_parts_mut(ptr, self.pointer.1 as usize))
+    }
+
+    /// Bounds checks and validates the values of the slice, returning its host
+    /// pointer and guest region.
+    fn validate_slice(&self) -> Result<(*mut T, Region), GuestError>
+    where
+        T: GuestTypeTransparent<'a>,
+    {
+        let width = self.mem.pointer_width();
+        // The product of two `u32`s always fits in a `u64`.
+        let len = u64::from(self.pointer.1) * u64::from(T::guest_size_for(width));
+        let ptr = self
+            .mem
+            .validate_size_align(self.pointer.0, T::guest_align_for(width), len)?
+            as *mut T;
+
+        // Validate all elements in slice.
+        // SAFETY: ptr has been validated by self.mem.validate_size_align
+        for offs in 0..self.pointer.1 {
+            T::validate(unsafe { ptr.add(offs as usize) })?;
+        }
+
+        Ok((
+            ptr,
+            Region {
+                start: self.pointer.0,
+                len,
+            },
+        ))
+    }
+
     /// Copies the data pointed to by `slice` into this guest region.
     ///
     /// This method is a *safe* method to copy data from the host to the guest.
@@ -464,7 +595,7 @@ impl<'a, T> GuestPtr<'a, [T]> {
         T: GuestTypeTransparent<'a> + Copy,
     {
         // bounds check ...
-        let mut self_slice = self.as_slice()?;
+        let mut self_slice = self.as_slice_mut()?;
         // ... length check ...
         if self_slice.len() != slice.len() {
             return Err(GuestError::SliceLengthsDiffer);
@@ -484,7 +615,7 @@ impl<'a, T> GuestPtr<'a, [T]> {
 impl<'a> GuestPtr<'a, str> {
     /// For strings, returns the relative pointer to the base of the string
     /// allocation.
-    pub fn offset_base(&self) -> u32 {
+    pub fn offset_base(&self) -> u64 {
         self.pointer.0
     }
 
@@ -501,9 +632,9 @@ impl<'a> GuestPtr<'a, str> {
 
     /// Attempts to create a [`GuestStr<'_>`] from this pointer, performing
     /// bounds checks and utf-8 checks. The resulting `GuestStr` can be used
-    /// as a `&str` or `&mut str` via the `Deref` and `DerefMut` traits. The
-    /// region of memory backing the `str` will be marked as borrowed by the
-    /// [`BorrowChecker`] until the `GuestStr` is dropped.
+    /// as a `&str` via the `Deref` trait. The region of memory backing the
+    /// `str` will be marked as shared-borrowed by the [`BorrowChecker`] until
+    /// the `GuestStr` is dropped.
     ///
     /// This function will return `GuestStr` into host memory if all checks
     /// succeed (valid utf-8, valid pointers, etc). If any checks fail then
@@ -511,11 +642,44 @@ impl<'a> GuestPtr<'a, str> {
     pub fn as_str(&self) -> Result<GuestStr<'a>, GuestError> {
         let ptr = self
             .mem
-            .validate_size_align(self.pointer.0, 1, self.pointer.1)?;
+            .validate_size_align(self.pointer.0, 1, self.pointer.1.into())?;
+
+        let borrow = self.mem.borrow_checker().shared_borrow(Region {
+            start: self.pointer.0,
+            len: self.pointer.1.into(),
+        })?;
+        let guest_str = GuestStr {
+            // SAFETY: iff there are no overlapping mutable borrows it is ok to
+            // construct a &[u8]. Creating the `GuestStr` now releases the
+            // borrow if the utf-8 validation fails.
+            ptr: unsafe { slice::from_raw_parts(ptr, self.pointer.1 as usize) },
+            bc: self.mem.borrow_checker(),
+            borrow,
+        };
+        // Validate that contents are utf-8:
+        match str::from_utf8(guest_str.ptr) {
+            Ok(_) => Ok(guest_str),
+            Err(e) => Err(GuestError::InvalidUtf8(e)),
+        }
+    }
+
+    /// Attempts to create a [`GuestStrMut<'_>`] from this pointer, performing
+    /// bounds checks and utf-8 checks. The resulting `GuestStrMut` can be used
+    /// as a `&str` or `&mut str` via the `Deref` and `DerefMut` traits. The
+    /// region of memory backing the `str` will be marked as mutably borrowed
+    /// by the [`BorrowChecker`] until the `GuestStrMut` is dropped.
+    ///
+    /// This function will return `GuestStrMut` into host memory if all checks
+    /// succeed (valid utf-8, valid pointers, etc). If any checks fail then
+    /// `GuestError` will be returned.
+    pub fn as_str_mut(&self) -> Result<GuestStrMut<'a>, GuestError> {
+        let ptr = self
+            .mem
+            .validate_size_align(self.pointer.0, 1, self.pointer.1.into())?;
 
-        let borrow = self.mem.borrow_checker().borrow(Region {
+        let borrow = self.mem.borrow_checker().mut_borrow(Region {
             start: self.pointer.0,
-            len: self.pointer.1,
+            len: self.pointer.1.into(),
         })?;
 
         // SAFETY: iff there are no overlapping borrows it is ok to construct
@@ -523,12 +687,15 @@ impl<'a> GuestPtr<'a, str> {
         let ptr = unsafe { slice::from_raw_parts_mut(ptr, self.pointer.1 as usize) };
         // Validate that contents are utf-8:
         match str::from_utf8_mut(ptr) {
-            Ok(ptr) => Ok(GuestStr {
+            Ok(ptr) => Ok(GuestStrMut {
                 ptr,
                 bc: self.mem.borrow_checker(),
                 borrow,
             }),
-            Err(e) => Err(GuestError::InvalidUtf8(e)),
+            Err(e) => {
+                self.mem.borrow_checker().unborrow(borrow);
+                Err(GuestError::InvalidUtf8(e))
+            }
         }
     }
 }
@@ -547,29 +714,117 @@ impl<T: ?Sized + Pointee> fmt::Debug for GuestPtr<'_, T> {
     }
 }
 
+/// A smart pointer to a shared slice in guest memory.
+/// Usable as a `&'a [T]` via [`std::ops::Deref`].
+pub struct GuestSlice<'a, T> {
+    ptr: &'a [T],
+    bc: &'a BorrowChecker,
+    borrow: BorrowHandle,
+}
+
+impl<'a, T> std::ops::Deref for GuestSlice<'a, T> {
+    type Target = [T];
+    fn deref(&self) -> &Self::Target {
+        self.ptr
+    }
+}
+
+impl<'a, T> Drop for GuestSlice<'a, T> {
+    fn drop(&mut self) {
+        self.bc.unborrow(self.borrow)
+    }
+}
+
 /// A smart pointer to a mutable slice in guest memory.
 /// Usable as a `&'a [T]` via [`std::ops::Deref`] and as a `&'a mut [T]` via
 /// [`std::ops::DerefMut`].
-pub struct GuestSlice<'a, T> {
+pub struct GuestSliceMut<'a, T> {
     ptr: &'a mut [T],
     bc: &'a BorrowChecker,
     borrow: BorrowHandle,
 }
 
-impl<'a, T> std::ops::Deref for GuestSlice<'a, T> {
+impl<'a, T> std::ops::Deref for GuestSliceMut<'a, T> {
     type Target = [T];
     fn deref(&self) -> &Self::Target {
         self.ptr
     }
 }
 
-impl<'a, T> std::ops::DerefMut for GuestSlice<'a, T> {
+impl<'a, T> std::ops::DerefMut for GuestSliceMut<'a, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.ptr
     }
 }
 
-impl<'a, T> Drop for GuestSlice<'a, T> {
+impl<'a, T> Drop for GuestSliceMut<'a, T> {
+    fn drop(&mut self) {
+        self.bc.unborrow(self.borrow)
+    }
+}
+
+/// A clone-on-write smart pointer to a slice in guest memory, created by
+/// [`GuestPtr::as_cow`].
+/// Usable as a `&[T]` via [`std::ops::Deref`]. It borrows guest memory like a
+/// [`GuestSlice`] until it's modified through [`GuestCow::to_mut`], which
+/// copies it to the host.
+pub enum GuestCow<'a, T> {
+    /// A shared view of guest memory.
+    Borrowed(GuestSlice<'a, T>),
+    /// A copy of guest memory, owned by the host.
+    Owned(Vec<T>),
+}
+
+impl<'a, T: Copy> GuestCow<'a, T> {
+    /// Gets a mutable reference to the data, copying it to the host and
+    /// releasing the borrow of guest memory if it's still borrowed.
+    pub fn to_mut(&mut self) -> &mut Vec<T> {
+        if let GuestCow::Borrowed(slice) = self {
+            *self = GuestCow::Owned(slice.to_vec());
+        }
+        match self {
+            GuestCow::Owned(vec) => vec,
+            GuestCow::Borrowed(_) => unreachable!(),
+        }
+    }
+
+    /// Extracts the owned data, copying it to the host if it's still borrowed.
+    pub fn into_owned(self) -> Vec<T> {
+        match self {
+            GuestCow::Borrowed(slice) => slice.to_vec(),
+            GuestCow::Owned(vec) => vec,
+        }
+    }
+}
+
+impl<'a, T> std::ops::Deref for GuestCow<'a, T> {
+    type Target = [T];
+    fn deref(&self) -> &Self::Target {
+        match self {
+            GuestCow::Borrowed(slice) => slice,
+            GuestCow::Owned(vec) => vec,
+        }
+    }
+}
+
+/// A smart pointer to a shared `str` in guest memory.
+/// Usable as a `&'a str` via [`std::ops::Deref`].
+pub struct GuestStr<'a> {
+    ptr: &'a [u8],
+    bc: &'a BorrowChecker,
+    borrow: BorrowHandle,
+}
+
+impl<'a> std::ops::Deref for GuestStr<'a> {
+    type Target = str;
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: the contents were validated as utf-8 when the `GuestStr` was
+        // created, and can't be mutated while it's borrowed.
+        unsafe { str::from_utf8_unchecked(self.ptr) }
+    }
+}
+
+impl<'a> Drop for GuestStr<'a> {
     fn drop(&mut self) {
         self.bc.unborrow(self.borrow)
     }
@@ -578,26 +833,26 @@ impl<'a, T> Drop for GuestSlice<'a, T> {
 /// A smart pointer to a mutable `str` in guest memory.
 /// Usable as a `&'a str` via [`std::ops::Deref`] and as a `&'a mut str` via
 /// [`std::ops::DerefMut`].
-pub struct GuestStr<'a> {
+pub struct GuestStrMut<'a> {
     ptr: &'a mut str,
     bc: &'a BorrowChecker,
     borrow: BorrowHandle,
 }
 
-impl<'a> std::ops::Deref for GuestStr<'a> {
+impl<'a> std::ops::Deref for GuestStrMut<'a> {
     type Target = str;
     fn deref(&self) -> &Self::Target {
         self.ptr
     }
 }
 
-impl<'a> std::ops::DerefMut for GuestStr<'a> {
+impl<'a> std::ops::DerefMut for GuestStrMut<'a> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.ptr
     }
 }
 
-impl<'a> Drop for GuestStr<'a> {
+impl<'a> Drop for GuestStrMut<'a> {
     fn drop(&mut self) {
         self.bc.unborrow(self.borrow)
     }
@@ -622,21 +877,21 @@ pub trait Pointee: private::Sealed {
 }
 
 impl<T> Pointee for T {
-    type Pointer = u32;
+    type Pointer = u64;
     fn debug(pointer: Self::Pointer, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "*guest {:#x}", pointer)
     }
 }
 
 impl<T> Pointee for [T] {
-    type Pointer = (u32, u32);
+    type Pointer = (u64, u32);
     fn debug(pointer: Self::Pointer, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "*guest {:#x}/{}", pointer.0, pointer.1)
     }
 }
 
 impl Pointee for str {
-    type Pointer = (u32, u32);
+    type Pointer = (u64, u32);
     fn debug(pointer: Self::Pointer, f: &mut fmt::Formatter) -> fmt::Result {
         <[u8]>::debug(pointer, f)
     }
@@ -1,12 +1,17 @@
+use crate::GuestError;
+
 /// Represents a contiguous region in memory.
+///
+/// Offsets are 64-bit so that regions of both 32- and 64-bit guest memories
+/// can be represented.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Region {
-    pub start: u32,
-    pub len: u32,
+    pub start: u64,
+    pub len: u64,
 }
 
 impl Region {
-    pub fn new(start: u32, len: u32) -> Self {
+    pub fn new(start: u64, len: u64) -> Self {
         Self { start, len }
     }
 
@@ -17,11 +22,13 @@ impl Region {
             return false;
         }
 
-        let self_start = self.start as u64;
-        let self_end = self_start + (self.len - 1) as u64;
+        // Computed in a wider type so that regions ending at the very end of
+        // the 64-bit address space don't overflow.
+        let self_start = self.start as u128;
+        let self_end = self_start + (self.len - 1) as u128;
 
-        let rhs_start = rhs.start as u64;
-        let rhs_end = rhs_start + (rhs.len - 1) as u64;
+        let rhs_start = rhs.start as u128;
+        let rhs_end = rhs_start + (rhs.len - 1) as u128;
 
         if self_start <= rhs_start {
             self_end >= rhs_start
@@ -30,12 +37,12 @@ impl Region {
         }
     }
 
-    pub fn extend(&self, times: u32) -> Self {
-        let len = self.len * times;
-        Self {
+    pub fn extend(&self, times: u64) -> Result<Self, GuestError> {
+        let len = self.len.checked_mul(times).ok_or(GuestError::PtrOverflow)?;
+        Ok(Self {
             start: self.start,
             len,
-        }
+        })
     }
 }
 
@@ -83,4 +90,22 @@ mod test {
         let r2 = Region::new(0, 10);
         assert!(r1.overlaps(r2));
     }
+
+    #[test]
+    fn end_of_address_space() {
+        let r1 = Region::new(u64::max_value() - 9, 10);
+        let r2 = Region::new(u64::max_value(), 1);
+        assert!(r1.overlaps(r2));
+
+        let r1 = Region::new(u64::max_value() - 9, 10);
+        let r2 = Region::new(0, u64::max_value() - 9);
+        assert!(!r1.overlaps(r2));
+    }
+
+    #[test]
+    fn extend_overflow() {
+        let r = Region::new(8, 1 << 32);
+        assert_eq!(r.extend(4), Ok(Region::new(8, 1 << 34)));
+        assert_eq!(r.extend(1 << 32), Err(GuestError::PtrOverflow));
+    }
 }
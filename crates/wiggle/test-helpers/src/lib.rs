@@ -113,10 +113,10 @@ impl HostMemory {
 }
 
 unsafe impl GuestMemory for HostMemory {
-    fn base(&self) -> (*mut u8, u32) {
+    fn base(&self) -> (*mut u8, u64) {
         unsafe {
             let ptr = self.buffer.cell.get();
-            ((*ptr).as_mut_ptr(), (*ptr).len() as u32)
+            ((*ptr).as_mut_ptr(), (*ptr).len() as u64)
         }
     }
     fn borrow_checker(&self) -> &BorrowChecker {
@@ -81,7 +81,7 @@ impl ReduceExcusesExcercise {
         // Populate memory with pointers to generated Excuse values
         for (&excuse, ptr) in self.excuse_values.iter().zip(self.excuse_ptr_locs.iter()) {
             host_memory
-                .ptr(ptr.ptr)
+                .ptr(ptr.ptr.into())
                 .write(excuse)
                 .expect("deref ptr mut to Excuse value");
         }
@@ -89,10 +89,10 @@ impl ReduceExcusesExcercise {
         // Populate the array with pointers to generated Excuse values
         {
             let array: GuestPtr<'_, [GuestPtr<types::Excuse>]> =
-                host_memory.ptr((self.array_ptr_loc.ptr, self.excuse_ptr_locs.len() as u32));
+                host_memory.ptr((self.array_ptr_loc.ptr.into(), self.excuse_ptr_locs.len() as u32));
             for (slot, ptr) in array.iter().zip(&self.excuse_ptr_locs) {
                 let slot = slot.expect("array should be in bounds");
-                slot.write(host_memory.ptr(ptr.ptr))
+                slot.write(host_memory.ptr(ptr.ptr.into()))
                     .expect("should succeed in writing array");
             }
         }
@@ -112,7 +112,7 @@ impl ReduceExcusesExcercise {
             .last()
             .expect("generated vec of excuses should be non-empty");
         let given: types::Excuse = host_memory
-            .ptr(self.return_ptr_loc.ptr)
+            .ptr(self.return_ptr_loc.ptr.into())
             .read()
             .expect("deref ptr to returned value");
         assert_eq!(expected, given, "reduce excuses return val");
@@ -168,12 +168,12 @@ impl PopulateExcusesExcercise {
 
         // Populate array with valid pointers to Excuse type in memory
         let ptr = host_memory.ptr::<[GuestPtr<'_, types::Excuse>]>((
-            self.array_ptr_loc.ptr,
+            self.array_ptr_loc.ptr.into(),
             self.elements.len() as u32,
         ));
         for (ptr, val) in ptr.iter().zip(&self.elements) {
             ptr.expect("should be valid pointer")
-                .write(host_memory.ptr(val.ptr))
+                .write(host_memory.ptr(val.ptr.into()))
                 .expect("failed to write value");
         }
 
@@ -186,7 +186,7 @@ impl PopulateExcusesExcercise {
         assert_eq!(res, types::Errno::Ok.into(), "populate excuses errno");
 
         let arr: GuestPtr<'_, [GuestPtr<'_, types::Excuse>]> =
-            host_memory.ptr((self.array_ptr_loc.ptr, self.elements.len() as u32));
+            host_memory.ptr((self.array_ptr_loc.ptr.into(), self.elements.len() as u32));
         for el in arr.iter() {
             let ptr_to_ptr = el
                 .expect("valid ptr to ptr")
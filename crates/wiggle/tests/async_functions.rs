@@ -0,0 +1,90 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use wiggle::GuestMemory;
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx: ["tests/atoms.witx"],
+    ctx: WasiCtx,
+    async: { atoms::{double_int_return_float} },
+});
+
+impl_errno!(types::Errno, types::GuestErrorConversion);
+
+#[wiggle::async_trait(?Send)]
+impl<'a> atoms::Atoms for WasiCtx<'a> {
+    fn int_float_args(&self, _an_int: u32, _an_float: f32) -> Result<(), types::Errno> {
+        Ok(())
+    }
+    async fn double_int_return_float(
+        &self,
+        an_int: u32,
+    ) -> Result<types::AliasToFloat, types::Errno> {
+        Yield(false).await;
+        Ok((an_int as f32) * 2.0)
+    }
+}
+
+/// A future which is pending once before completing.
+struct Yield(bool);
+
+impl Future for Yield {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+/// Run `future`, which must only be woken while being polled, to completion.
+fn run<F: Future>(future: F) -> F::Output {
+    fn noop_raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker {
+            noop_raw_waker()
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn async_function() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    let ptr = 8;
+
+    let future = atoms::double_int_return_float(&ctx, &host_memory, 21, ptr);
+    let e = run(future);
+    assert_eq!(e, types::Errno::Ok.into(), "double_int_return_float error");
+
+    let result = host_memory
+        .ptr::<types::AliasToFloat>(ptr as u64)
+        .read()
+        .expect("deref ptr to returned value");
+    assert_eq!(result, 42.0);
+}
+
+#[test]
+fn sync_function() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+
+    // Functions not listed in `async` are unaffected.
+    let e = atoms::int_float_args(&ctx, &host_memory, 1, 2.0);
+    assert_eq!(e, types::Errno::Ok.into(), "int_float_args error");
+}
@@ -69,7 +69,7 @@ impl DoubleIntExercise {
         );
 
         let return_val = host_memory
-            .ptr::<types::AliasToFloat>(self.return_loc.ptr)
+            .ptr::<types::AliasToFloat>(self.return_loc.ptr.into())
             .read()
             .expect("failed to read return");
         assert_eq!(e, types::Errno::Ok.into(), "errno");
@@ -0,0 +1,67 @@
+use wiggle::{GuestError, GuestMemory, GuestPtr};
+use wiggle_test::HostMemory;
+
+#[test]
+fn shared_slices() {
+    let host_memory = HostMemory::new();
+    let a: GuestPtr<[u8]> = host_memory.ptr((0, 8));
+    let b: GuestPtr<[u8]> = host_memory.ptr((4, 8));
+    a.copy_from_slice(&[1; 8]).expect("write a");
+
+    let a_slice = a.as_slice().expect("share a");
+    let b_slice = b.as_slice().expect("share overlapping b");
+    assert_eq!(&a_slice[4..], &b_slice[..4]);
+    assert_eq!(host_memory.ptr::<u8>(5).read(), Ok(1), "shared can be read");
+    assert!(
+        host_memory.ptr::<u8>(5).write(2).is_err(),
+        "shared can't be written"
+    );
+    assert!(matches!(
+        b.as_slice_mut().err(),
+        Some(GuestError::PtrBorrowed(_))
+    ));
+    drop(a_slice);
+    drop(b_slice);
+
+    let mut b_slice = b.as_slice_mut().expect("mutably borrow b");
+    assert!(a.as_slice().is_err(), "can't share mutably borrowed a");
+    assert!(host_memory.ptr::<u8>(5).read().is_err(), "can't read b");
+    b_slice[1] = 2;
+    drop(b_slice);
+    assert_eq!(host_memory.ptr::<u8>(5).read(), Ok(2));
+    assert!(!host_memory.borrow_checker().has_outstanding_borrows());
+}
+
+#[test]
+fn cow() {
+    let host_memory = HostMemory::new();
+    let ptr: GuestPtr<[u8]> = host_memory.ptr((16, 4));
+    ptr.copy_from_slice(&[1, 2, 3, 4]).expect("write ptr");
+
+    let mut cow = ptr.as_cow().expect("borrow ptr");
+    assert_eq!(&*cow, &[1, 2, 3, 4]);
+    assert!(host_memory.borrow_checker().has_outstanding_borrows());
+    cow.to_mut().push(5);
+    assert!(
+        !host_memory.borrow_checker().has_outstanding_borrows(),
+        "copying releases the borrow"
+    );
+    assert_eq!(&*cow, &[1, 2, 3, 4, 5]);
+
+    let slice = ptr.as_slice().expect("guest memory is untouched");
+    assert_eq!(&*slice, &[1, 2, 3, 4]);
+}
+
+#[test]
+fn unchecked_slice() {
+    let host_memory = HostMemory::new();
+    let ptr: GuestPtr<[u8]> = host_memory.ptr((32, 4));
+    // SAFETY: nothing else accesses this region while the slice is alive.
+    let slice = unsafe { ptr.as_unchecked_slice_mut() }.expect("in bounds");
+    slice.copy_from_slice(&[4, 3, 2, 1]);
+    assert!(!host_memory.borrow_checker().has_outstanding_borrows());
+    assert_eq!(host_memory.ptr::<u8>(32).read(), Ok(4));
+
+    let ptr: GuestPtr<[u8]> = host_memory.ptr((4096, 1));
+    assert!(unsafe { ptr.as_unchecked_slice_mut() }.is_err());
+}
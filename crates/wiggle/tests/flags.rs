@@ -68,7 +68,7 @@ impl ConfigureCarExercise {
 
         // Populate input ptr
         host_memory
-            .ptr(self.other_config_by_ptr.ptr)
+            .ptr(self.other_config_by_ptr.ptr.into())
             .write(self.other_config)
             .expect("deref ptr mut to CarConfig");
 
@@ -82,7 +82,7 @@ impl ConfigureCarExercise {
         assert_eq!(res, types::Errno::Ok.into(), "configure car errno");
 
         let res_config = host_memory
-            .ptr::<types::CarConfig>(self.return_ptr_loc.ptr)
+            .ptr::<types::CarConfig>(self.return_ptr_loc.ptr.into())
             .read()
             .expect("deref to CarConfig value");
 
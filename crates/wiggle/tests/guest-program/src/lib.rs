@@ -0,0 +1,21 @@
+//! A WebAssembly guest calling the `colors` module through the bindings
+//! generated by `guest_from_witx!`, driven by wiggle's `guest_round_trip` test.
+
+use std::convert::TryFrom;
+use wiggle::GuestCallError;
+
+wiggle::guest_from_witx!({
+    witx: ["../colors.witx"],
+});
+
+/// Calls `colors::next_color`, returning the next color, `100 + errno` if the
+/// host returned an error, or `-1` if it returned an invalid error or result.
+#[no_mangle]
+pub extern "C" fn call_next_color(color: i32) -> i32 {
+    let color = types::Color::try_from(color).expect("valid color");
+    match colors::next_color(color) {
+        Ok(next) => i32::from(next),
+        Err(GuestCallError::Host(errno)) => 100 + i32::from(errno),
+        Err(GuestCallError::InvalidResult(_)) => -1,
+    }
+}
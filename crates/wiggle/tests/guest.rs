@@ -0,0 +1,40 @@
+use std::convert::TryFrom;
+
+mod atoms_guest {
+    wiggle::guest_from_witx!({
+        witx: ["tests/atoms.witx"],
+    });
+}
+
+mod strings_guest {
+    wiggle::guest_from_witx!({
+        witx: ["tests/strings.witx"],
+    });
+}
+
+// The imports are only linked inside a WebAssembly guest, so the bindings are
+// type checked here without ever being called.
+#[allow(dead_code)]
+fn bindings_signatures() {
+    use atoms_guest::{atoms, types::Errno};
+    use strings_guest::strings;
+    use wiggle::GuestCallError;
+
+    let _: unsafe extern "C" fn(i32, f32) -> i32 = atoms::raw::int_float_args;
+    let _: fn(u32, f32) -> Result<(), GuestCallError<Errno>> = atoms::int_float_args;
+    let _: fn(u32) -> Result<f32, GuestCallError<Errno>> = atoms::double_int_return_float;
+    let _: unsafe extern "C" fn(i32, i32, i32) -> i32 = strings::raw::hello_string;
+    let _: fn(&str) -> Result<u32, GuestCallError<strings_guest::types::Errno>> =
+        strings::hello_string;
+    let _: fn(&str, &str, &str) -> Result<u32, GuestCallError<strings_guest::types::Errno>> =
+        strings::multi_string;
+}
+
+#[test]
+fn guest_types_match_abi() {
+    use atoms_guest::types::Errno;
+    assert_eq!(i32::from(Errno::Ok), 0);
+    assert_eq!(i32::from(Errno::PicketLine), 4);
+    assert_eq!(Errno::try_from(2i32), Ok(Errno::DontWantTo));
+    assert!(Errno::try_from(5i32).is_err());
+}
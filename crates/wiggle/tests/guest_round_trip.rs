@@ -0,0 +1,74 @@
+//! Runs a guest using the bindings of `guest_from_witx!` against a host which
+//! writes raw values to its memory, including values invalid for their type.
+//!
+//! Requires the `wasm32-unknown-unknown` target to build the guest.
+
+use std::cell::Cell;
+use std::path::Path;
+use std::process::Command;
+use std::rc::Rc;
+use wasmtime::{Caller, Extern, Linker, Module, Store, Trap};
+
+fn build_guest() -> Vec<u8> {
+    let target_dir = std::env::temp_dir().join("wiggle-guest-program");
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = Command::new(cargo)
+        .args(&["build", "--release", "--target=wasm32-unknown-unknown"])
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/guest-program"))
+        .status()
+        .expect("running cargo");
+    assert!(status.success(), "building the guest program failed");
+    std::fs::read(target_dir.join("wasm32-unknown-unknown/release/wiggle_guest_program.wasm"))
+        .expect("reading the guest program")
+}
+
+#[test]
+fn invalid_results_are_errors() -> anyhow::Result<()> {
+    let store = Store::default();
+    let module = Module::new(store.engine(), build_guest())?;
+
+    // The errno and the byte written as the next color by the host.
+    let reply = Rc::new(Cell::new((0, 0u8)));
+    let mut linker = Linker::new(&store);
+    let host_reply = reply.clone();
+    linker.func(
+        "colors",
+        "next_color",
+        move |caller: Caller<'_>, _color: i32, next_ptr: i32| -> Result<i32, Trap> {
+            let mem = match caller.get_export("memory") {
+                Some(Extern::Memory(mem)) => mem,
+                _ => return Err(Trap::new("missing memory export")),
+            };
+            let (errno, next) = host_reply.get();
+            unsafe {
+                mem.data_unchecked_mut()[next_ptr as usize] = next;
+            }
+            Ok(errno)
+        },
+    )?;
+    let instance = linker.instantiate(&module)?;
+    let call_next_color = instance
+        .get_func("call_next_color")
+        .expect("call_next_color export")
+        .get1::<i32, i32>()?;
+
+    // Green is followed by blue.
+    reply.set((0, 2));
+    assert_eq!(call_next_color(1)?, 2);
+    // An error returned by the host, `invalid_arg`.
+    reply.set((1, 0));
+    assert_eq!(call_next_color(1)?, 101);
+    // An out-of-range discriminant for the result...
+    reply.set((0, 3));
+    assert_eq!(call_next_color(1)?, -1);
+    reply.set((0, 0xff));
+    assert_eq!(call_next_color(1)?, -1);
+    // ...and for the error.
+    reply.set((5, 0));
+    assert_eq!(call_next_color(1)?, -1);
+    reply.set((-1, 0));
+    assert_eq!(call_next_color(1)?, -1);
+    Ok(())
+}
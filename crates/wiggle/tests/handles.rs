@@ -40,7 +40,7 @@ impl HandleExercise {
         assert_eq!(e, types::Errno::Ok.into(), "fd_create error");
 
         let h_got: u32 = host_memory
-            .ptr(self.return_loc.ptr)
+            .ptr(self.return_loc.ptr.into())
             .read()
             .expect("return ref_mut");
 
@@ -56,7 +56,7 @@ impl CookieCutterExercise {
         assert_eq!(res, types::Errno::Ok.into(), "cookie cutter errno");
 
         let is_cookie_start = host_memory
-            .ptr::<types::Bool>(self.return_ptr_loc.ptr)
+            .ptr::<types::Bool>(self.return_ptr_loc.ptr.into())
             .read()
             .expect("deref to Bool value");
 
@@ -0,0 +1,96 @@
+use wiggle::{BorrowChecker, GuestError, GuestMemory, GuestPtr, PointerWidth};
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx_literal: "
+(typename $errno (enum u32 $ok $invalid_arg))
+(typename $pair_int_ptrs
+  (struct
+    (field $first (@witx const_pointer s32))
+    (field $second (@witx const_pointer s32))))
+(module $pointers
+  (@interface func (export \"sum_of_pair_of_ptrs\")
+    (param $an_pair $pair_int_ptrs)
+    (result $error $errno)
+    (result $sum s64)))
+",
+    ctx: WasiCtx,
+    pointer_width: 64,
+});
+
+impl_errno!(types::Errno, types::GuestErrorConversion);
+
+impl<'a> pointers::Pointers for WasiCtx<'a> {
+    fn sum_of_pair_of_ptrs(&self, an_pair: &types::PairIntPtrs) -> Result<i64, types::Errno> {
+        let first = an_pair.first.read().expect("reading first");
+        let second = an_pair.second.read().expect("reading second");
+        Ok(first as i64 + second as i64)
+    }
+}
+
+/// A `HostMemory` pretending to be a 64-bit memory.
+struct HostMemory64(HostMemory);
+
+unsafe impl GuestMemory for HostMemory64 {
+    fn base(&self) -> (*mut u8, u64) {
+        self.0.base()
+    }
+    fn pointer_width(&self) -> PointerWidth {
+        PointerWidth::U64
+    }
+    fn borrow_checker(&self) -> &BorrowChecker {
+        self.0.borrow_checker()
+    }
+}
+
+#[test]
+fn add_past_4gib() {
+    let mem32 = HostMemory::new();
+    let ptr: GuestPtr<u32> = mem32.ptr(u64::from(u32::max_value()) - 3);
+    assert_eq!(ptr.add(1).map(|p| p.offset()), Err(GuestError::PtrOverflow));
+
+    let mem64 = HostMemory64(HostMemory::new());
+    let ptr: GuestPtr<u32> = mem64.ptr(u64::from(u32::max_value()) - 3);
+    assert_eq!(
+        ptr.add(1).map(|p| p.offset()),
+        Ok(u64::from(u32::max_value()) + 1)
+    );
+    let ptr: GuestPtr<u32> = mem64.ptr(u64::max_value() - 3);
+    assert_eq!(ptr.add(1).map(|p| p.offset()), Err(GuestError::PtrOverflow));
+}
+
+#[test]
+fn out_of_bounds_past_4gib() {
+    let mem64 = HostMemory64(HostMemory::new());
+    let ptr: GuestPtr<u8> = mem64.ptr(1 << 32);
+    match ptr.read() {
+        Err(GuestError::PtrOutOfBounds(region)) => {
+            assert_eq!(region.start, 1 << 32);
+            assert_eq!(region.len, 1);
+        }
+        other => panic!("expected out of bounds error, got {:?}", other),
+    }
+    let slice: GuestPtr<[u8]> = mem64.ptr((u64::max_value(), 2));
+    assert_eq!(slice.as_slice().err(), Some(GuestError::PtrOverflow));
+}
+
+#[test]
+fn shims_take_64_bit_pointers() {
+    let ctx = WasiCtx::new();
+    let mem64 = HostMemory64(HostMemory::new());
+    mem64.ptr::<i32>(0).write(10).expect("writing first");
+    mem64.ptr::<i32>(4).write(20).expect("writing second");
+    // The struct's pointers are 8 bytes each in a 64-bit memory.
+    mem64
+        .ptr::<u64>(16)
+        .write(0)
+        .expect("writing an_pair.first");
+    mem64
+        .ptr::<u64>(24)
+        .write(4)
+        .expect("writing an_pair.second");
+
+    let errno = pointers::sum_of_pair_of_ptrs(&ctx, &mem64, 16i64, 32i64);
+    assert_eq!(errno, types::Errno::Ok.into());
+    assert_eq!(mem64.ptr::<i64>(32).read(), Ok(30));
+}
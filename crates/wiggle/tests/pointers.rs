@@ -130,22 +130,22 @@ impl PointersAndEnumsExercise {
         let host_memory = HostMemory::new();
 
         host_memory
-            .ptr(self.input2_loc.ptr)
+            .ptr(self.input2_loc.ptr.into())
             .write(self.input2)
             .expect("input2 ref_mut");
 
         host_memory
-            .ptr(self.input3_loc.ptr)
+            .ptr(self.input3_loc.ptr.into())
             .write(self.input3)
             .expect("input3 ref_mut");
 
         host_memory
-            .ptr(self.input4_loc.ptr)
+            .ptr(self.input4_loc.ptr.into())
             .write(self.input4)
             .expect("input4 ref_mut");
 
         host_memory
-            .ptr(self.input4_ptr_loc.ptr)
+            .ptr(self.input4_ptr_loc.ptr.into())
             .write(self.input4_loc.ptr)
             .expect("input4 ptr ref_mut");
 
@@ -161,7 +161,7 @@ impl PointersAndEnumsExercise {
 
         // Implementation of pointers_and_enums writes input3 to the input2_loc:
         let written_to_input2_loc: i32 = host_memory
-            .ptr(self.input2_loc.ptr)
+            .ptr(self.input2_loc.ptr.into())
             .read()
             .expect("input2 ref");
 
@@ -173,7 +173,7 @@ impl PointersAndEnumsExercise {
 
         // Implementation of pointers_and_enums writes input2_loc to input4_ptr_loc:
         let written_to_input4_ptr: u32 = host_memory
-            .ptr(self.input4_ptr_loc.ptr)
+            .ptr(self.input4_ptr_loc.ptr.into())
             .read()
             .expect("input4_ptr_loc ref");
 
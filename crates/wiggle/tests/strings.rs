@@ -71,7 +71,7 @@ impl HelloStringExercise {
         let host_memory = HostMemory::new();
 
         // Populate string in guest's memory
-        let ptr = host_memory.ptr::<str>((self.string_ptr_loc.ptr, self.test_word.len() as u32));
+        let ptr = host_memory.ptr::<str>((self.string_ptr_loc.ptr.into(), self.test_word.len() as u32));
         for (slot, byte) in ptr.as_bytes().iter().zip(self.test_word.bytes()) {
             slot.expect("should be valid pointer")
                 .write(byte)
@@ -88,7 +88,7 @@ impl HelloStringExercise {
         assert_eq!(res, types::Errno::Ok.into(), "hello string errno");
 
         let given = host_memory
-            .ptr::<u32>(self.return_ptr_loc.ptr)
+            .ptr::<u32>(self.return_ptr_loc.ptr.into())
             .read()
             .expect("deref ptr to return value");
         assert_eq!(self.test_word.len() as u32, given);
@@ -177,7 +177,7 @@ impl MultiStringExercise {
         let host_memory = HostMemory::new();
 
         let write_string = |val: &str, loc: MemArea| {
-            let ptr = host_memory.ptr::<str>((loc.ptr, val.len() as u32));
+            let ptr = host_memory.ptr::<str>((loc.ptr.into(), val.len() as u32));
             for (slot, byte) in ptr.as_bytes().iter().zip(val.bytes()) {
                 slot.expect("should be valid pointer")
                     .write(byte)
@@ -203,7 +203,7 @@ impl MultiStringExercise {
         assert_eq!(res, types::Errno::Ok.into(), "multi string errno");
 
         let given = host_memory
-            .ptr::<u32>(self.return_ptr_loc.ptr)
+            .ptr::<u32>(self.return_ptr_loc.ptr.into())
             .read()
             .expect("deref ptr to return value");
         assert_eq!((self.a.len() + self.b.len() + self.c.len()) as u32, given);
@@ -85,11 +85,11 @@ impl SumOfPairExercise {
         let host_memory = HostMemory::new();
 
         host_memory
-            .ptr(self.input_loc.ptr)
+            .ptr(self.input_loc.ptr.into())
             .write(self.input.first)
             .expect("input ref_mut");
         host_memory
-            .ptr(self.input_loc.ptr + 4)
+            .ptr((self.input_loc.ptr + 4).into())
             .write(self.input.second)
             .expect("input ref_mut");
         let sum_err = structs::sum_of_pair(
@@ -102,7 +102,7 @@ impl SumOfPairExercise {
         assert_eq!(sum_err, types::Errno::Ok.into(), "sum errno");
 
         let return_val: i64 = host_memory
-            .ptr(self.return_loc.ptr)
+            .ptr(self.return_loc.ptr.into())
             .read()
             .expect("return ref");
 
@@ -173,20 +173,20 @@ impl SumPairPtrsExercise {
         let host_memory = HostMemory::new();
 
         host_memory
-            .ptr(self.input_first_loc.ptr)
+            .ptr(self.input_first_loc.ptr.into())
             .write(self.input_first)
             .expect("input_first ref");
         host_memory
-            .ptr(self.input_second_loc.ptr)
+            .ptr(self.input_second_loc.ptr.into())
             .write(self.input_second)
             .expect("input_second ref");
 
         host_memory
-            .ptr(self.input_struct_loc.ptr)
+            .ptr(self.input_struct_loc.ptr.into())
             .write(self.input_first_loc.ptr)
             .expect("input_struct ref");
         host_memory
-            .ptr(self.input_struct_loc.ptr + 4)
+            .ptr((self.input_struct_loc.ptr + 4).into())
             .write(self.input_second_loc.ptr)
             .expect("input_struct ref");
 
@@ -200,7 +200,7 @@ impl SumPairPtrsExercise {
         assert_eq!(res, types::Errno::Ok.into(), "sum of pair of ptrs errno");
 
         let doubled: i64 = host_memory
-            .ptr(self.return_loc.ptr)
+            .ptr(self.return_loc.ptr.into())
             .read()
             .expect("return ref");
 
@@ -257,15 +257,15 @@ impl SumIntAndPtrExercise {
         let host_memory = HostMemory::new();
 
         host_memory
-            .ptr(self.input_first_loc.ptr)
+            .ptr(self.input_first_loc.ptr.into())
             .write(self.input_first)
             .expect("input_first ref");
         host_memory
-            .ptr(self.input_struct_loc.ptr)
+            .ptr(self.input_struct_loc.ptr.into())
             .write(self.input_first_loc.ptr)
             .expect("input_struct ref");
         host_memory
-            .ptr(self.input_struct_loc.ptr + 4)
+            .ptr((self.input_struct_loc.ptr + 4).into())
             .write(self.input_second)
             .expect("input_struct ref");
 
@@ -279,7 +279,7 @@ impl SumIntAndPtrExercise {
         assert_eq!(res, types::Errno::Ok.into(), "sum of int and ptr errno");
 
         let doubled: i64 = host_memory
-            .ptr(self.return_loc.ptr)
+            .ptr(self.return_loc.ptr.into())
             .read()
             .expect("return ref");
 
@@ -318,7 +318,7 @@ impl ReturnPairInts {
         assert_eq!(err, types::Errno::Ok.into(), "return struct errno");
 
         let return_struct: types::PairInts = host_memory
-            .ptr(self.return_loc.ptr)
+            .ptr(self.return_loc.ptr.into())
             .read()
             .expect("return ref");
 
@@ -379,11 +379,11 @@ impl ReturnPairPtrsExercise {
         let host_memory = HostMemory::new();
 
         host_memory
-            .ptr(self.input_first_loc.ptr)
+            .ptr(self.input_first_loc.ptr.into())
             .write(self.input_first)
             .expect("input_first ref");
         host_memory
-            .ptr(self.input_second_loc.ptr)
+            .ptr(self.input_second_loc.ptr.into())
             .write(self.input_second)
             .expect("input_second ref");
 
@@ -398,7 +398,7 @@ impl ReturnPairPtrsExercise {
         assert_eq!(res, types::Errno::Ok.into(), "return pair of ptrs errno");
 
         let ptr_pair_int_ptrs: types::PairIntPtrs<'_> = host_memory
-            .ptr(self.return_loc.ptr)
+            .ptr(self.return_loc.ptr.into())
             .read()
             .expect("failed to read return location");
         let ret_first_ptr = ptr_pair_int_ptrs.first;
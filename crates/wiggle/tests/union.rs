@@ -110,18 +110,18 @@ impl GetTagExercise {
 
         let discriminant: u8 = reason_tag(&self.input).into();
         host_memory
-            .ptr(self.input_loc.ptr)
+            .ptr(self.input_loc.ptr.into())
             .write(discriminant)
             .expect("input discriminant ptr");
         match self.input {
             types::Reason::DogAte(f) => {
                 host_memory
-                    .ptr(self.input_loc.ptr + 4)
+                    .ptr((self.input_loc.ptr + 4).into())
                     .write(f)
                     .expect("input contents ref_mut");
             }
             types::Reason::Traffic(v) => host_memory
-                .ptr(self.input_loc.ptr + 4)
+                .ptr((self.input_loc.ptr + 4).into())
                 .write(v)
                 .expect("input contents ref_mut"),
             types::Reason::Sleeping => {} // Do nothing
@@ -136,7 +136,7 @@ impl GetTagExercise {
         assert_eq!(e, types::Errno::Ok.into(), "get_tag errno");
 
         let return_val: types::Excuse = host_memory
-            .ptr(self.return_loc.ptr)
+            .ptr(self.return_loc.ptr.into())
             .read()
             .expect("return ref");
 
@@ -187,24 +187,24 @@ impl ReasonMultExercise {
 
         let discriminant: u8 = reason_tag(&self.input).into();
         host_memory
-            .ptr(self.input_loc.ptr)
+            .ptr(self.input_loc.ptr.into())
             .write(discriminant)
             .expect("input discriminant ref_mut");
         host_memory
-            .ptr(self.input_loc.ptr + 4)
+            .ptr((self.input_loc.ptr + 4).into())
             .write(self.input_pointee_loc.ptr)
             .expect("input pointer ref_mut");
 
         match self.input {
             types::Reason::DogAte(f) => {
                 host_memory
-                    .ptr(self.input_pointee_loc.ptr)
+                    .ptr(self.input_pointee_loc.ptr.into())
                     .write(f)
                     .expect("input contents ref_mut");
             }
             types::Reason::Traffic(v) => {
                 host_memory
-                    .ptr(self.input_pointee_loc.ptr)
+                    .ptr(self.input_pointee_loc.ptr.into())
                     .write(v)
                     .expect("input contents ref_mut");
             }
@@ -222,7 +222,7 @@ impl ReasonMultExercise {
         match self.input {
             types::Reason::DogAte(f) => {
                 let f_result: f32 = host_memory
-                    .ptr(self.input_pointee_loc.ptr)
+                    .ptr(self.input_pointee_loc.ptr.into())
                     .read()
                     .expect("input contents ref_mut");
                 assert_eq!(
@@ -233,7 +233,7 @@ impl ReasonMultExercise {
             }
             types::Reason::Traffic(v) => {
                 let v_result: i32 = host_memory
-                    .ptr(self.input_pointee_loc.ptr)
+                    .ptr(self.input_pointee_loc.ptr.into())
                     .read()
                     .expect("input contents ref_mut");
                 assert_eq!(
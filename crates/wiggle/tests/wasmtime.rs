@@ -0,0 +1,93 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use wasmtime::{Linker, Module, Store, Trap};
+
+wiggle::from_witx!({
+    witx: ["tests/atoms.witx"],
+    ctx: Ctx,
+});
+
+wiggle::wasmtime_integration!({
+    witx: ["tests/atoms.witx"],
+    ctx: Ctx,
+});
+
+pub struct Ctx {
+    last_int: Cell<u32>,
+}
+
+impl wiggle::GuestErrorType for types::Errno {
+    fn success() -> Self {
+        types::Errno::Ok
+    }
+}
+
+impl types::GuestErrorConversion for Ctx {
+    fn into_errno(&self, _e: wiggle::GuestError) -> types::Errno {
+        types::Errno::InvalidArg
+    }
+}
+
+impl atoms::Atoms for Ctx {
+    fn int_float_args(&self, an_int: u32, _an_float: f32) -> Result<(), types::Errno> {
+        self.last_int.set(an_int);
+        Ok(())
+    }
+    fn double_int_return_float(&self, an_int: u32) -> Result<types::AliasToFloat, types::Errno> {
+        Ok((an_int as f32) * 2.0)
+    }
+}
+
+fn linker(store: &Store) -> anyhow::Result<(Linker, Rc<Ctx>)> {
+    let ctx = Rc::new(Ctx {
+        last_int: Cell::new(0),
+    });
+    let mut linker = Linker::new(store);
+    add_to_linker(&mut linker, ctx.clone())?;
+    Ok((linker, ctx))
+}
+
+#[test]
+fn calls_through_linker() -> anyhow::Result<()> {
+    let store = Store::default();
+    let (linker, ctx) = linker(&store)?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "atoms" "int_float_args" (func $args (param i32 f32) (result i32)))
+                (import "atoms" "double_int_return_float"
+                    (func $double (param i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (func (export "run") (result f32)
+                    (drop (call $args (i32.const 7) (f32.const 1.5)))
+                    (drop (call $double (i32.const 21) (i32.const 8)))
+                    (f32.load (i32.const 8))))
+        "#,
+    )?;
+    let instance = linker.instantiate(&module)?;
+    let run = instance.get_func("run").unwrap().get0::<f32>()?;
+    assert_eq!(run()?, 42.0);
+    assert_eq!(ctx.last_int.get(), 7);
+    Ok(())
+}
+
+#[test]
+fn traps_without_memory() -> anyhow::Result<()> {
+    let store = Store::default();
+    let (linker, _ctx) = linker(&store)?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "atoms" "int_float_args" (func $args (param i32 f32) (result i32)))
+                (func (export "run") (result i32)
+                    (call $args (i32.const 7) (f32.const 1.5))))
+        "#,
+    )?;
+    let instance = linker.instantiate(&module)?;
+    let run = instance.get_func("run").unwrap().get0::<i32>()?;
+    let trap: Trap = run().unwrap_err();
+    assert!(trap.to_string().contains("missing required memory export"));
+    Ok(())
+}
@@ -0,0 +1,8 @@
+#![no_main]
+
+use cranelift_fuzzgen::{run_differential, TestCase};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|testcase: TestCase| {
+    run_differential(&testcase);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wiggle_fuzzing::generate::{generate, WitxDocument};
+
+fuzz_target!(|doc: WitxDocument| {
+    generate(&doc);
+});
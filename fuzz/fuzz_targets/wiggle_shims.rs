@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wiggle_fuzzing::shims::{call_shim, ShimCall};
+
+fuzz_target!(|call: ShimCall| {
+    call_shim(&call);
+});
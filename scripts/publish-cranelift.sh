@@ -24,6 +24,7 @@ for crate in \
     peepmatic \
     codegen/shared \
     codegen/meta \
+    codegen/lower-meta \
     codegen \
     frontend \
     native \
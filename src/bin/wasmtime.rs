@@ -6,7 +6,8 @@
 use anyhow::Result;
 use structopt::{clap::AppSettings, clap::ErrorKind, StructOpt};
 use wasmtime_cli::commands::{
-    ConfigCommand, RunCommand, WasmToObjCommand, WastCommand, WASM2OBJ_AFTER_HELP,
+    CompileCommand, ConfigCommand, ExploreCommand, ReplCommand, RunCommand, WasmToObjCommand,
+    WastCommand, COMPILE_AFTER_HELP, EXPLORE_AFTER_HELP, REPL_AFTER_HELP, WASM2OBJ_AFTER_HELP,
 };
 
 /// Wasmtime WebAssembly Runtime
@@ -36,8 +37,17 @@ use wasmtime_cli::commands::{
 )]
 enum WasmtimeApp {
     // !!! IMPORTANT: if subcommands are added or removed, update `parse_module` in `src/commands/run.rs`. !!!
+    /// Precompiles a WebAssembly module for `wasmtime run`
+    #[structopt(after_help = COMPILE_AFTER_HELP)]
+    Compile(CompileCommand),
     /// Controls Wasmtime configuration settings
     Config(ConfigCommand),
+    /// Explores the machine code generated for a WebAssembly module
+    #[structopt(after_help = EXPLORE_AFTER_HELP)]
+    Explore(ExploreCommand),
+    /// Interactively instantiates WebAssembly modules and calls their exports
+    #[structopt(after_help = REPL_AFTER_HELP)]
+    Repl(ReplCommand),
     /// Runs a WebAssembly module
     Run(RunCommand),
     /// Translates a WebAssembly module to native object file
@@ -51,7 +61,10 @@ impl WasmtimeApp {
     /// Executes the command.
     pub fn execute(&self) -> Result<()> {
         match self {
+            Self::Compile(c) => c.execute(),
             Self::Config(c) => c.execute(),
+            Self::Explore(c) => c.execute(),
+            Self::Repl(c) => c.execute(),
             Self::Run(c) => c.execute(),
             Self::WasmToObj(c) => c.execute(),
             Self::Wast(c) => c.execute(),
@@ -1,8 +1,11 @@
 //! The module for the Wasmtime CLI commands.
 
+mod compile;
 mod config;
+mod explore;
+mod repl;
 mod run;
 mod wasm2obj;
 mod wast;
 
-pub use self::{config::*, run::*, wasm2obj::*, wast::*};
+pub use self::{compile::*, config::*, explore::*, repl::*, run::*, wasm2obj::*, wast::*};
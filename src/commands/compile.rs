@@ -0,0 +1,82 @@
+//! The module that implements the `wasmtime compile` command.
+
+use crate::{init_file_per_thread_logger, parse_target, CommonOptions};
+use anyhow::{Context as _, Result};
+use std::fs;
+use std::path::PathBuf;
+use structopt::{clap::AppSettings, StructOpt};
+use target_lexicon::Triple;
+use wasmtime::Engine;
+
+/// The after help text for the `compile` command.
+pub const COMPILE_AFTER_HELP: &str =
+    "The precompiled module can be run with `wasmtime run` given the same\n\
+     compilation options, without compiling it again.";
+
+/// Precompiles a WebAssembly module for `wasmtime run`
+#[derive(StructOpt)]
+#[structopt(
+    name = "compile",
+    version = env!("CARGO_PKG_VERSION"),
+    setting = AppSettings::ColoredHelp,
+    after_help = COMPILE_AFTER_HELP,
+)]
+pub struct CompileCommand {
+    #[structopt(flatten)]
+    common: CommonOptions,
+
+    /// Generate code which can be interrupted, as required by
+    /// `wasmtime run --wasm-timeout`
+    #[structopt(long)]
+    interruptable: bool,
+
+    /// The target triple to compile for; defaults to the host triple and the
+    /// CPU features it supports. Modules compiled for other targets can only
+    /// be run on them
+    #[structopt(long, value_name = "TARGET", parse(try_from_str = parse_target))]
+    target: Option<Triple>,
+
+    /// The path of the WebAssembly module to compile
+    #[structopt(index = 1, value_name = "MODULE_PATH", parse(from_os_str))]
+    module: PathBuf,
+
+    /// The path of the output precompiled module; defaults to the module path
+    /// with a `.cwasm` extension
+    #[structopt(short = "o", long, value_name = "OUTPUT_PATH", parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+impl CompileCommand {
+    /// Executes the command.
+    pub fn execute(&self) -> Result<()> {
+        if self.common.log_to_files {
+            let prefix = "compile.dbg.";
+            init_file_per_thread_logger(prefix);
+        } else {
+            pretty_env_logger::init();
+        }
+
+        let mut config = self.common.config(self.target.as_ref())?;
+        config.interruptable(self.interruptable);
+        let engine = Engine::new(&config);
+
+        let input = fs::read(&self.module)
+            .with_context(|| format!("failed to read module `{}`", self.module.display()))?;
+        let output = engine
+            .precompile_module(&input)
+            .with_context(|| format!("failed to compile `{}`", self.module.display()))?;
+
+        let output_path = match &self.output {
+            Some(path) => path.clone(),
+            None => self.module.with_extension("cwasm"),
+        };
+        fs::write(&output_path, output).with_context(|| {
+            format!(
+                "failed to write precompiled module `{}`",
+                output_path.display()
+            )
+        })?;
+
+        Ok(())
+    }
+}
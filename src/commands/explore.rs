@@ -0,0 +1,565 @@
+//! The module that implements the `wasmtime explore` command.
+
+use crate::{init_file_per_thread_logger, parse_target, pick_compilation_strategy, CommonOptions};
+use anyhow::{bail, Context as _, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use structopt::{clap::AppSettings, StructOpt};
+use target_lexicon::Triple;
+use wasmtime::{Engine, Strategy};
+use wasmtime_environ::{
+    entity::EntityRef, isa::TargetIsa, wasm::FuncIndex, CacheConfig, Compiler as _, Cranelift,
+    EntityIndex, FunctionAddressMap, FunctionBodyData, ModuleEnvironment,
+};
+use wasmtime_jit::Compiler;
+
+/// The after help text for the `explore` command.
+pub const EXPLORE_AFTER_HELP: &str =
+    "Each WebAssembly instruction is followed by the machine code generated\n\
+     for it. Instructions which generated no machine code of their own are\n\
+     listed without any.";
+
+/// Explores the machine code generated for a WebAssembly module
+#[derive(StructOpt)]
+#[structopt(
+    name = "explore",
+    version = env!("CARGO_PKG_VERSION"),
+    setting = AppSettings::ColoredHelp,
+    after_help = EXPLORE_AFTER_HELP,
+)]
+pub struct ExploreCommand {
+    #[structopt(flatten)]
+    common: CommonOptions,
+
+    /// Generate code which can be interrupted, as `wasmtime compile
+    /// --interruptable` does
+    #[structopt(long)]
+    interruptable: bool,
+
+    /// The target triple to compile for; defaults to the host triple
+    #[structopt(long, value_name = "TARGET", parse(try_from_str = parse_target))]
+    target: Option<Triple>,
+
+    /// The path of the WebAssembly module to explore
+    #[structopt(index = 1, value_name = "MODULE_PATH", parse(from_os_str))]
+    module: PathBuf,
+
+    /// The path of an HTML report to write; the report is printed to stdout
+    /// as text otherwise
+    #[structopt(short = "o", long, value_name = "OUTPUT_PATH", parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+impl ExploreCommand {
+    /// Executes the command.
+    pub fn execute(&self) -> Result<()> {
+        if self.common.log_to_files {
+            let prefix = "explore.dbg.";
+            init_file_per_thread_logger(prefix);
+        } else {
+            pretty_env_logger::init();
+        }
+
+        match pick_compilation_strategy(self.common.cranelift, self.common.lightbeam)? {
+            Strategy::Auto | Strategy::Cranelift => {}
+            other => bail!("exploring is not supported with the {:?} strategy", other),
+        }
+        let cache_config = if self.common.disable_cache {
+            CacheConfig::new_cache_disabled()
+        } else {
+            CacheConfig::from_file(self.common.config.as_deref())?
+        };
+        // Compile with the same settings as `wasmtime compile`, to explore the
+        // code it would generate.
+        let mut config = self.common.config(self.target.as_ref())?;
+        config.interruptable(self.interruptable);
+        let engine = Engine::new(&config);
+
+        #[cfg(not(feature = "disas"))]
+        eprintln!(
+            "note: machine code is shown as bytes, since Wasmtime was built \
+             without the `disas` feature to disassemble it"
+        );
+
+        let wasm = wat::parse_file(&self.module).context("failed to parse module")?;
+        let functions = explore(&wasm, engine.compiler(), &cache_config)?;
+
+        match &self.output {
+            Some(path) => fs::write(path, html_report(&self.module, &functions))
+                .with_context(|| format!("failed to write report `{}`", path.display()))?,
+            None => print!("{}", text_report(&functions)),
+        }
+
+        Ok(())
+    }
+}
+
+/// A function defined by the module, with its instructions.
+struct Function {
+    index: FuncIndex,
+    name: Option<String>,
+    groups: Vec<Group>,
+}
+
+/// A WebAssembly instruction and the machine instructions generated for it.
+struct Group {
+    /// The offset of the WebAssembly instruction in the module and its text,
+    /// if the machine instructions can be attributed to one.
+    wasm: Option<(usize, String)>,
+    machine: Vec<Instruction>,
+}
+
+/// A machine instruction.
+struct Instruction {
+    offset: usize,
+    bytes: Vec<u8>,
+    text: String,
+}
+
+/// Compiles the module `wasm` with the ISA and tunables of `compiler` and
+/// interleaves the WebAssembly instructions of each function with its machine
+/// instructions.
+fn explore(wasm: &[u8], compiler: &Compiler, cache_config: &CacheConfig) -> Result<Vec<Function>> {
+    let isa = compiler.isa();
+    let translation = ModuleEnvironment::new(isa.frontend_config(), compiler.tunables())
+        .translate(wasm)
+        .context("failed to translate module")?;
+    let text = wasmprinter::print_bytes(wasm).context("failed to print module")?;
+    let instructions = function_instructions(&text);
+    let (compilation, _relocations, address_maps, ..) =
+        Cranelift::compile_module(&translation, isa, cache_config)
+            .context("failed to compile module")?;
+
+    let module = &translation.module;
+    let mut functions = Vec::new();
+    for (defined, body) in translation.function_body_inputs.iter() {
+        let index = module.local.func_index(defined);
+        let name = module.func_names.get(&index).cloned().or_else(|| {
+            module
+                .exports
+                .iter()
+                .find(|(_, e)| **e == EntityIndex::Function(index))
+                .map(|(name, _)| name.clone())
+        });
+        let text = instructions
+            .get(defined.index())
+            .map_or(&[][..], |i| &i[..]);
+        let operators = read_operators(body, text)
+            .with_context(|| format!("failed to read function {}", index.index()))?;
+        let code = &compilation.get(defined).body;
+        let machine = disassemble(isa, code, &address_maps[defined])?;
+        functions.push(Function {
+            index,
+            name,
+            groups: group(operators, machine, &address_maps[defined]),
+        });
+    }
+    Ok(functions)
+}
+
+/// Splits a module printed by `wasmprinter` into the instructions of each
+/// function it defines, in order. Every instruction is printed on a line of
+/// its own, except for the `end` of the function body, which is printed as
+/// the parenthesis closing the function after its last instruction.
+fn function_instructions(text: &str) -> Vec<Vec<String>> {
+    let mut functions: Vec<Vec<String>> = Vec::new();
+    let mut in_function = false;
+    for line in text.lines() {
+        let indent = line.len() - line.trim_start().len();
+        let line = line.trim();
+        // Module fields, such as functions, are indented by two spaces and
+        // their contents by more.
+        if indent <= 2 {
+            in_function = line.starts_with("(func");
+            if in_function {
+                functions.push(Vec::new());
+            }
+        } else if in_function && !line.starts_with("(local") {
+            functions.last_mut().unwrap().push(line.to_string());
+        }
+    }
+    for instructions in functions.iter_mut() {
+        if let Some(last) = instructions.last_mut() {
+            last.pop();
+            if last.is_empty() {
+                instructions.pop();
+            }
+        }
+    }
+    functions
+}
+
+/// Reads the offsets of the operators of a function body in the module, and
+/// pairs them with their `text` printed by `function_instructions`.
+fn read_operators(body: &FunctionBodyData, text: &[String]) -> Result<Vec<(usize, String)>> {
+    let body = wasmparser::FunctionBody::new(body.module_offset, body.data);
+    let mut reader = body.get_operators_reader()?;
+    let mut offsets = Vec::new();
+    while !reader.eof() {
+        let (_operator, offset) = reader.read_with_offset()?;
+        offsets.push(offset);
+    }
+    // The last operator is the `end` of the function body.
+    if offsets.len() != text.len() + 1 {
+        bail!(
+            "printed {} instructions for {} operators",
+            text.len(),
+            offsets.len()
+        );
+    }
+    Ok(offsets
+        .into_iter()
+        .zip(text.iter().cloned().chain(Some("end".to_string())))
+        .collect())
+}
+
+/// Groups the machine instructions of a function by the WebAssembly
+/// instruction they were generated for, in code order.
+///
+/// WebAssembly instructions which generated no machine code of their own are
+/// placed in empty groups before the next instruction that did, so that the
+/// groups read like the function body where the code isn't reordered.
+fn group(
+    operators: Vec<(usize, String)>,
+    machine: Vec<Instruction>,
+    address_map: &FunctionAddressMap,
+) -> Vec<Group> {
+    let mut groups: Vec<Group> = Vec::new();
+    let mut current = None;
+    let mut next_operator = 0;
+    for instruction in machine {
+        let srcloc = address_map
+            .instructions
+            .iter()
+            .find(|m| {
+                m.code_offset <= instruction.offset
+                    && instruction.offset < m.code_offset + m.code_len
+            })
+            .map(|m| m.srcloc)
+            .filter(|srcloc| !srcloc.is_default())
+            .map(|srcloc| srcloc.bits() as usize);
+        if srcloc != current || groups.is_empty() {
+            current = srcloc;
+            let wasm = srcloc.and_then(|offset| {
+                while next_operator < operators.len() && operators[next_operator].0 < offset {
+                    groups.push(Group {
+                        wasm: Some(operators[next_operator].clone()),
+                        machine: Vec::new(),
+                    });
+                    next_operator += 1;
+                }
+                if next_operator < operators.len() && operators[next_operator].0 == offset {
+                    next_operator += 1;
+                }
+                operators.iter().find(|(o, _)| *o == offset).cloned()
+            });
+            groups.push(Group {
+                wasm,
+                machine: Vec::new(),
+            });
+        }
+        groups.last_mut().unwrap().machine.push(instruction);
+    }
+    groups.extend(operators[next_operator..].iter().map(|op| Group {
+        wasm: Some(op.clone()),
+        machine: Vec::new(),
+    }));
+    groups
+}
+
+#[cfg(feature = "disas")]
+fn disassemble(
+    isa: &dyn TargetIsa,
+    code: &[u8],
+    _address_map: &FunctionAddressMap,
+) -> Result<Vec<Instruction>> {
+    use capstone::prelude::*;
+    use target_lexicon::Architecture;
+
+    let cs = match isa.triple().architecture {
+        Architecture::I386 | Architecture::I586 | Architecture::I686 => Capstone::new()
+            .x86()
+            .mode(arch::x86::ArchMode::Mode32)
+            .build(),
+        Architecture::X86_64 => Capstone::new()
+            .x86()
+            .mode(arch::x86::ArchMode::Mode64)
+            .build(),
+        Architecture::Aarch64 { .. } => {
+            let mut cs = Capstone::new()
+                .arm64()
+                .mode(arch::arm64::ArchMode::Arm)
+                .build()
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            // Keep going past the constants inlined in the code.
+            cs.set_skipdata(true)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            Ok(cs)
+        }
+        other => bail!("no disassembler for {}", other),
+    }
+    .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let instructions = cs
+        .disasm_all(code, 0)
+        .map_err(|e| anyhow::anyhow!("failed to disassemble: {}", e))?;
+    Ok(instructions
+        .iter()
+        .map(|i| Instruction {
+            offset: i.address() as usize,
+            bytes: i.bytes().to_vec(),
+            text: match (i.mnemonic(), i.op_str()) {
+                (Some(mnemonic), Some(ops)) if !ops.is_empty() => {
+                    format!("{} {}", mnemonic, ops)
+                }
+                (Some(mnemonic), _) => mnemonic.to_string(),
+                _ => String::new(),
+            },
+        })
+        .collect())
+}
+
+/// Without a disassembler, splits the code at the boundaries given by the
+/// address map and shows the bytes of each part.
+#[cfg(not(feature = "disas"))]
+fn disassemble(
+    _isa: &dyn TargetIsa,
+    code: &[u8],
+    address_map: &FunctionAddressMap,
+) -> Result<Vec<Instruction>> {
+    let mut boundaries = vec![0, code.len()];
+    for m in address_map.instructions.iter() {
+        boundaries.push(m.code_offset.min(code.len()));
+        boundaries.push((m.code_offset + m.code_len).min(code.len()));
+    }
+    boundaries.sort();
+    boundaries.dedup();
+    Ok(boundaries
+        .windows(2)
+        .map(|w| Instruction {
+            offset: w[0],
+            bytes: code[w[0]..w[1]].to_vec(),
+            text: String::new(),
+        })
+        .collect())
+}
+
+fn function_title(function: &Function) -> String {
+    match &function.name {
+        Some(name) => format!("function {} ({})", function.index.index(), name),
+        None => format!("function {}", function.index.index()),
+    }
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    let mut s = String::new();
+    for (i, b) in bytes.iter().enumerate() {
+        if i > 0 {
+            s.push(' ');
+        }
+        write!(s, "{:02x}", b).unwrap();
+    }
+    s
+}
+
+fn text_report(functions: &[Function]) -> String {
+    let mut report = String::new();
+    for function in functions {
+        writeln!(report, "{}:", function_title(function)).unwrap();
+        for group in function.groups.iter() {
+            match &group.wasm {
+                Some((offset, text)) => writeln!(report, "  {:#08x}: {}", offset, text).unwrap(),
+                None => writeln!(report, "  ;; no wasm instruction").unwrap(),
+            }
+            for i in group.machine.iter() {
+                writeln!(
+                    report,
+                    "      {:6x}:  {:30} {}",
+                    i.offset,
+                    hex_bytes(&i.bytes),
+                    i.text
+                )
+                .unwrap();
+            }
+        }
+        writeln!(report).unwrap();
+    }
+    report
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn html_report(module: &std::path::Path, functions: &[Function]) -> String {
+    let title = escape_html(&module.display().to_string());
+    let mut report = String::new();
+    writeln!(
+        report,
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>{}</title>\n\
+         <style>\n\
+         body {{ font-family: monospace; }}\n\
+         table {{ border-collapse: collapse; margin-bottom: 2em; }}\n\
+         td {{ vertical-align: top; padding: 0.2em 1em; border-top: 1px solid #ddd; }}\n\
+         td.offset, td.bytes {{ color: #888; }}\n\
+         </style>\n\
+         </head>\n\
+         <body>\n\
+         <h1>{}</h1>",
+        title, title
+    )
+    .unwrap();
+    for function in functions {
+        writeln!(
+            report,
+            "<h2>{}</h2>\n<table>",
+            escape_html(&function_title(function))
+        )
+        .unwrap();
+        for group in function.groups.iter() {
+            let (offset, wasm) = match &group.wasm {
+                Some((offset, text)) => (format!("{:#x}", offset), escape_html(text)),
+                None => (String::new(), String::new()),
+            };
+            let mut offsets = Vec::new();
+            let mut bytes = Vec::new();
+            let mut texts = Vec::new();
+            for i in group.machine.iter() {
+                offsets.push(format!("{:x}", i.offset));
+                bytes.push(hex_bytes(&i.bytes));
+                texts.push(escape_html(&i.text));
+            }
+            writeln!(
+                report,
+                "<tr><td class=\"offset\">{}</td><td>{}</td>\
+                 <td class=\"offset\">{}</td><td class=\"bytes\">{}</td><td>{}</td></tr>",
+                offset,
+                wasm,
+                offsets.join("<br>"),
+                bytes.join("<br>"),
+                texts.join("<br>"),
+            )
+            .unwrap();
+        }
+        writeln!(report, "</table>").unwrap();
+    }
+    writeln!(report, "</body>\n</html>").unwrap();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmtime_environ::{ir::SourceLoc, InstructionAddressMap};
+
+    #[test]
+    fn split_function_instructions() {
+        let text = "\
+(module
+  (type (;0;) (func (param i32) (result i32)))
+  (type (;1;) (func))
+  (func (;0;) (type 0) (param i32) (result i32)
+    (local i32)
+    local.get 0
+    block (result i32)  ;; label = @1
+      i32.const 1
+    end
+    i32.add)
+  (func (;1;) (type 1))
+  (func (;2;) (type 0) (param i32) (result i32)
+    local.get 0)
+  (export \"f\" (func 0)))
+";
+        assert_eq!(
+            function_instructions(text),
+            vec![
+                vec![
+                    "local.get 0",
+                    "block (result i32)  ;; label = @1",
+                    "i32.const 1",
+                    "end",
+                    "i32.add",
+                ],
+                vec![],
+                vec!["local.get 0"],
+            ]
+        );
+    }
+
+    fn instruction(offset: usize) -> Instruction {
+        Instruction {
+            offset,
+            bytes: vec![0x90; 4],
+            text: String::new(),
+        }
+    }
+
+    fn mapping(srcloc: Option<u32>, code_offset: usize) -> InstructionAddressMap {
+        InstructionAddressMap {
+            srcloc: srcloc.map_or_else(SourceLoc::default, SourceLoc::new),
+            code_offset,
+            code_len: 4,
+        }
+    }
+
+    #[test]
+    fn group_by_address_map() {
+        let operators = vec![
+            (10, "i32.const 1".to_string()),
+            (12, "i32.const 2".to_string()),
+            (14, "i32.add".to_string()),
+            (15, "drop".to_string()),
+            (16, "end".to_string()),
+        ];
+        let address_map = FunctionAddressMap {
+            instructions: vec![
+                mapping(None, 0),
+                mapping(Some(12), 4),
+                mapping(Some(14), 8),
+                mapping(Some(14), 12),
+            ],
+            start_srcloc: SourceLoc::default(),
+            end_srcloc: SourceLoc::default(),
+            body_offset: 0,
+            body_len: 16,
+        };
+        let machine = (0..4).map(|i| instruction(i * 4)).collect();
+        let groups = group(operators, machine, &address_map)
+            .into_iter()
+            .map(|g| {
+                let wasm = g.wasm.map(|(offset, _)| offset);
+                let machine = g.machine.iter().map(|i| i.offset).collect::<Vec<_>>();
+                (wasm, machine)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            vec![
+                // The prologue isn't attributed to any instruction.
+                (None, vec![0]),
+                // Instructions without code of their own get empty groups.
+                (Some(10), vec![]),
+                (Some(12), vec![4]),
+                (Some(14), vec![8, 12]),
+                // Trailing instructions without code are kept.
+                (Some(15), vec![]),
+                (Some(16), vec![]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,454 @@
+//! The module that implements the `wasmtime repl` command.
+
+use super::run::vals;
+use crate::{init_file_per_thread_logger, CommonOptions};
+use anyhow::{anyhow, bail, Context as _, Result};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use structopt::{clap::AppSettings, StructOpt};
+use wasmtime::{Engine, Extern, ExternType, Module, Mutability, Store, Val};
+use wasmtime_wast::WastContext;
+
+/// The after help text for the `repl` command.
+pub const REPL_AFTER_HELP: &str =
+    "Type `:help` in the REPL for the list of its commands. Lines starting\n\
+     with `(` are run as wast directives, such as `(module ...)` or\n\
+     `(invoke \"f\" (i32.const 1))`.";
+
+/// The help text printed by the `:help` REPL command.
+const COMMANDS_HELP: &str = "\
+:load PATH [NAME]        load a module from a .wasm or .wat file, registering it as NAME
+:register NAME           register the current instance as NAME
+:imports                 list the imports of the current module
+:exports [NAME]          list the exports of the current instance, or of the instance NAME
+:call FUNC [ARGS...]     call a function with arguments in the text format
+:get GLOBAL              print the value of a global
+:set GLOBAL VALUE        set the value of a mutable global
+:memory MEMORY [OFFSET [LENGTH]]
+                         print the size of a memory, or dump LENGTH bytes at OFFSET
+:help                    print this help
+:quit                    exit the REPL
+(...)                    run a wast directive, e.g. (invoke \"f\" (i32.const 1))
+
+Exports of registered instances are named `NAME.EXPORT`.";
+
+/// The number of bytes dumped by `:memory` when no length is given.
+const DEFAULT_DUMP_LENGTH: usize = 256;
+
+/// Interactively instantiates WebAssembly modules and calls their exports
+#[derive(StructOpt)]
+#[structopt(
+    name = "repl",
+    version = env!("CARGO_PKG_VERSION"),
+    setting = AppSettings::ColoredHelp,
+    after_help = REPL_AFTER_HELP,
+)]
+pub struct ReplCommand {
+    #[structopt(flatten)]
+    common: CommonOptions,
+
+    /// The paths of WebAssembly modules to load on startup, in order
+    #[structopt(value_name = "MODULE_PATH", parse(from_os_str))]
+    modules: Vec<PathBuf>,
+}
+
+impl ReplCommand {
+    /// Executes the command.
+    pub fn execute(&self) -> Result<()> {
+        if self.common.log_to_files {
+            let prefix = "repl.dbg.";
+            init_file_per_thread_logger(prefix);
+        } else {
+            pretty_env_logger::init();
+        }
+
+        let config = self.common.config(None)?;
+        let store = Store::new(&Engine::new(&config));
+        let mut repl = Repl {
+            context: WastContext::new(store),
+        };
+        for path in self.modules.iter() {
+            repl.load(path, None)?;
+        }
+
+        let interactive = atty::is(atty::Stream::Stdin);
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        let mut input = String::new();
+        loop {
+            if interactive {
+                print!("{}", if input.is_empty() { "> " } else { "... " });
+                io::stdout().flush()?;
+            }
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => break,
+            };
+            input.push_str(&line);
+            input.push('\n');
+            if !is_complete(&input) {
+                continue;
+            }
+            let command = input.trim().to_string();
+            input.clear();
+            match repl.run(&command) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) => eprintln!("error: {:?}", e),
+            }
+        }
+        if !input.trim().is_empty() {
+            bail!("unexpected end of input in `{}`", input.trim());
+        }
+
+        Ok(())
+    }
+}
+
+/// The state of the REPL between commands.
+struct Repl {
+    context: WastContext,
+}
+
+impl Repl {
+    /// Runs a command, returning whether the REPL should go on.
+    fn run(&mut self, command: &str) -> Result<bool> {
+        if command.is_empty() || command.starts_with(";;") {
+            return Ok(true);
+        }
+        if command.starts_with('(') {
+            let results = self.context.run_directives(command)?;
+            print_results(&results);
+            return Ok(true);
+        }
+
+        let args = split_args(command)?;
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match args.as_slice() {
+            [":load", path] => self.load(Path::new(path), None)?,
+            [":load", path, name] => self.load(Path::new(path), Some(*name))?,
+            [":register", name] => self.context.register(None, name)?,
+            [":imports"] => self.imports()?,
+            [":exports"] => self.exports(None)?,
+            [":exports", name] => self.exports(Some(*name))?,
+            [":call", func, args @ ..] => self.call(func, args)?,
+            [":get", global] => self.get(global)?,
+            [":set", global, value] => self.set(global, value)?,
+            [":memory", memory] => self.memory(memory, None, None)?,
+            [":memory", memory, offset] => self.memory(memory, Some(*offset), None)?,
+            [":memory", memory, offset, length] => {
+                self.memory(memory, Some(*offset), Some(*length))?
+            }
+            [":help"] => println!("{}", COMMANDS_HELP),
+            [":quit"] => return Ok(false),
+            [command, ..] if command.starts_with(':') => {
+                bail!("invalid `{}` command; type `:help` for help", command)
+            }
+            _ => bail!("invalid command; type `:help` for help"),
+        }
+        Ok(true)
+    }
+
+    fn load(&mut self, path: &Path, name: Option<&str>) -> Result<()> {
+        let module = Module::from_file(self.context.store().engine(), path)
+            .with_context(|| format!("failed to load module `{}`", path.display()))?;
+        self.context
+            .define_module(name, &module)
+            .with_context(|| format!("failed to instantiate `{}`", path.display()))?;
+        Ok(())
+    }
+
+    fn imports(&self) -> Result<()> {
+        let module = self
+            .context
+            .current_module()
+            .ok_or_else(|| anyhow!("no module has been loaded"))?;
+        for import in module.imports() {
+            println!(
+                "{}.{}: {}",
+                import.module(),
+                import.name(),
+                describe(&import.ty())
+            );
+        }
+        Ok(())
+    }
+
+    fn exports(&self, name: Option<&str>) -> Result<()> {
+        match name {
+            Some(name) => {
+                let mut found = false;
+                for (module, field, item) in self.context.linker().iter() {
+                    if module == name {
+                        println!("{}: {}", field, describe(&item.ty()));
+                        found = true;
+                    }
+                }
+                if !found {
+                    bail!("no instance registered as `{}`", name);
+                }
+            }
+            None => {
+                let instance = self
+                    .context
+                    .current_instance()
+                    .ok_or_else(|| anyhow!("no module has been loaded"))?;
+                for export in instance.exports() {
+                    println!("{}: {}", export.name(), describe(&export.ty()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds an export of the current instance, or of a registered instance
+    /// if `name` is of the form `NAME.EXPORT`.
+    fn lookup(&self, name: &str) -> Result<Extern> {
+        if let Some(export) = self
+            .context
+            .current_instance()
+            .and_then(|instance| instance.get_export(name))
+        {
+            return Ok(export);
+        }
+        let parts: Vec<&str> = name.splitn(2, '.').collect();
+        if let [module, field] = parts.as_slice() {
+            if let Ok(export) = self.context.linker().get_one_by_name(module, field) {
+                return Ok(export);
+            }
+        }
+        bail!("no export named `{}`", name)
+    }
+
+    fn call(&self, name: &str, args: &[&str]) -> Result<()> {
+        let func = self
+            .lookup(name)?
+            .into_func()
+            .ok_or_else(|| anyhow!("`{}` is not a function", name))?;
+        let ty = func.ty();
+        if args.len() != ty.params().len() {
+            bail!(
+                "`{}` takes {} arguments, but {} were given",
+                name,
+                ty.params().len(),
+                args.len()
+            );
+        }
+        let args = ty
+            .params()
+            .iter()
+            .zip(args)
+            .enumerate()
+            .map(|(i, (ty, arg))| {
+                vals::parse(ty, arg).with_context(|| format!("invalid argument {}", i))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let results = func.call(&args)?;
+        print_results(&results);
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<()> {
+        let global = self
+            .lookup(name)?
+            .into_global()
+            .ok_or_else(|| anyhow!("`{}` is not a global", name))?;
+        println!("{}", vals::to_string(&global.get()));
+        Ok(())
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        let global = self
+            .lookup(name)?
+            .into_global()
+            .ok_or_else(|| anyhow!("`{}` is not a global", name))?;
+        let value = vals::parse(&global.val_type(), value)?;
+        global.set(value)
+    }
+
+    fn memory(&self, name: &str, offset: Option<&str>, length: Option<&str>) -> Result<()> {
+        let memory = self
+            .lookup(name)?
+            .into_memory()
+            .ok_or_else(|| anyhow!("`{}` is not a memory", name))?;
+        let offset = match offset {
+            Some(offset) => parse_usize(offset)?,
+            None => {
+                println!("{} pages ({} bytes)", memory.size(), memory.data_size());
+                return Ok(());
+            }
+        };
+        let length = match length {
+            Some(length) => parse_usize(length)?,
+            None => DEFAULT_DUMP_LENGTH.min(memory.data_size().saturating_sub(offset)),
+        };
+        // Safe since the data isn't kept across calls into wasm code, which
+        // could grow the memory.
+        let data = unsafe { memory.data_unchecked() };
+        let bytes = offset
+            .checked_add(length)
+            .and_then(|end| data.get(offset..end))
+            .ok_or_else(|| {
+                anyhow!(
+                    "range of {} bytes at {:#x} is out of bounds of a memory of {} bytes",
+                    length,
+                    offset,
+                    data.len()
+                )
+            })?;
+        print!("{}", hexdump(offset, bytes));
+        Ok(())
+    }
+}
+
+fn print_results(results: &[Val]) {
+    for result in results {
+        println!("{}", vals::to_string(result));
+    }
+}
+
+/// Describes the type of an import or export in the syntax of the text format.
+fn describe(ty: &ExternType) -> String {
+    let limits = |min: u32, max: Option<u32>| match max {
+        Some(max) => format!("{} {}", min, max),
+        None => min.to_string(),
+    };
+    match ty {
+        ExternType::Func(ty) => {
+            let mut s = "func".to_string();
+            if !ty.params().is_empty() {
+                s.push_str(" (param");
+                for param in ty.params() {
+                    s.push_str(&format!(" {}", param));
+                }
+                s.push(')');
+            }
+            if !ty.results().is_empty() {
+                s.push_str(" (result");
+                for result in ty.results() {
+                    s.push_str(&format!(" {}", result));
+                }
+                s.push(')');
+            }
+            s
+        }
+        ExternType::Global(ty) => match ty.mutability() {
+            Mutability::Const => format!("global {}", ty.content()),
+            Mutability::Var => format!("global (mut {})", ty.content()),
+        },
+        ExternType::Table(ty) => format!(
+            "table {} {}",
+            limits(ty.limits().min(), ty.limits().max()),
+            ty.element()
+        ),
+        ExternType::Memory(ty) => {
+            format!("memory {}", limits(ty.limits().min(), ty.limits().max()))
+        }
+    }
+}
+
+/// Formats `bytes` found at `offset` as lines of 16 bytes in hexadecimal,
+/// followed by their printable ASCII characters.
+fn hexdump(offset: usize, bytes: &[u8]) -> String {
+    let mut s = String::new();
+    for (i, line) in bytes.chunks(16).enumerate() {
+        s.push_str(&format!("{:08x}:", offset + i * 16));
+        for j in 0..16 {
+            match line.get(j) {
+                Some(byte) => s.push_str(&format!(" {:02x}", byte)),
+                None => s.push_str("   "),
+            }
+        }
+        s.push_str("  ");
+        for byte in line {
+            if byte.is_ascii_graphic() || *byte == b' ' {
+                s.push(*byte as char);
+            } else {
+                s.push('.');
+            }
+        }
+        s.push('\n');
+    }
+    s
+}
+
+fn parse_usize(s: &str) -> Result<usize> {
+    let n = if s.starts_with("0x") {
+        usize::from_str_radix(&s[2..], 16)
+    } else {
+        s.parse()
+    };
+    n.with_context(|| format!("invalid number `{}`", s))
+}
+
+/// Splits a command into its arguments, separated by whitespace unless they
+/// are in double quotes.
+fn split_args(command: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut chars = command.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut arg = String::new();
+        if c == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => arg.extend(chars.next()),
+                    Some(c) => arg.push(c),
+                    None => bail!("unterminated string in `{}`", command),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                arg.push(c);
+                chars.next();
+            }
+        }
+        args.push(arg);
+    }
+    Ok(args)
+}
+
+/// Returns whether `input` is a complete command, rather than the first lines
+/// of a wast directive whose parentheses aren't closed yet.
+fn is_complete(input: &str) -> bool {
+    if !input.trim_start().starts_with('(') {
+        return true;
+    }
+    let mut depth = 0i32;
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            chars.next();
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            ';' if chars.as_str().starts_with(';') => {
+                // Skip a line comment.
+                for c in &mut chars {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    depth <= 0
+}
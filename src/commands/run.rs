@@ -2,23 +2,35 @@
 
 use crate::{init_file_per_thread_logger, CommonOptions};
 use anyhow::{bail, Context as _, Result};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::rc::Rc;
 use std::thread;
 use std::time::Duration;
 use std::{
     ffi::{OsStr, OsString},
-    fs::File,
-    path::{Component, PathBuf},
+    fs::{self, File},
+    io::BufReader,
+    path::{Component, Path, PathBuf},
     process,
 };
 use structopt::{clap::AppSettings, StructOpt};
-use wasi_common::{preopen_dir, WasiCtxBuilder};
-use wasmtime::{Engine, Func, Linker, Module, Store, Trap, Val, ValType};
+use wasi_common::{
+    preopen_dir, I32Exit, Journal, OsFile, SignalExit, TraceFormat, Tracer, VirtualDir,
+    WasiCtxBuilder,
+};
+use wasmtime::{
+    Engine, Func, FuncType, Linker, Module, ResourceLimit, Store, StoreLimits, Trap, TrapCode, Val,
+};
 use wasmtime_wasi::Wasi;
 
+pub(super) mod vals;
+
 fn parse_module(s: &OsStr) -> Result<PathBuf, OsString> {
     // Do not accept wasmtime subcommand names as the module name
     match s.to_str() {
-        Some("help") | Some("config") | Some("run") | Some("wasm2obj") | Some("wast") => {
+        Some("help") | Some("compile") | Some("config") | Some("explore") | Some("repl")
+        | Some("run") | Some("wasm2obj") | Some("wast") => {
             Err("module name cannot be the same as a subcommand".into())
         }
         _ => Ok(s.into()),
@@ -41,6 +53,27 @@ fn parse_map_dirs(s: &str) -> Result<(String, String)> {
     Ok((parts[0].into(), parts[1].into()))
 }
 
+/// Returns whether the host side of a mapped directory is a tar archive to
+/// mount rather than a directory, which is the case if it ends in `.tar`.
+fn is_archive(host: &Path) -> bool {
+    host.extension().map_or(false, |ext| ext == "tar")
+}
+
+fn parse_virt_dir(s: &str) -> (String, Option<PathBuf>) {
+    match s.find('=') {
+        Some(i) => (s[..i].to_owned(), Some(PathBuf::from(&s[i + 1..]))),
+        None => (s.to_owned(), None),
+    }
+}
+
+fn parse_trace_format(s: &str) -> Result<TraceFormat> {
+    match s {
+        "text" => Ok(TraceFormat::Text),
+        "json" => Ok(TraceFormat::Json),
+        _ => bail!("must be either `text` or `json`"),
+    }
+}
+
 fn parse_dur(s: &str) -> Result<Duration> {
     // assume an integer without a unit specified is a number of seconds ...
     if let Ok(val) = s.parse() {
@@ -59,6 +92,31 @@ fn parse_preloads(s: &str) -> Result<(String, PathBuf)> {
     Ok((parts[0].into(), parts[1].into()))
 }
 
+// The exit statuses of programs which failed because of a limit. Programs can
+// only exit with a status below 126 themselves, and with 128 plus a signal
+// number when they raise a signal, so these never stand for a program's own
+// exit status.
+
+/// The exit status of a program which exceeded `--max-memory-size`.
+pub const EXIT_MEMORY_SIZE: i32 = 200;
+/// The exit status of a program which exceeded `--max-table-elements`.
+pub const EXIT_TABLE_ELEMENTS: i32 = 201;
+/// The exit status of a program which exceeded `--max-instances`.
+pub const EXIT_INSTANCES: i32 = 202;
+/// The exit status of a program which consumed all of its `--fuel`.
+pub const EXIT_FUEL: i32 = 203;
+/// The exit status of a program which exceeded `--max-wasm-stack`.
+pub const EXIT_WASM_STACK: i32 = 204;
+
+/// Returns the flag setting `limit` and the exit status to report it with.
+fn limit_flag(limit: ResourceLimit) -> (&'static str, i32) {
+    match limit {
+        ResourceLimit::MemorySize => ("--max-memory-size", EXIT_MEMORY_SIZE),
+        ResourceLimit::TableElements => ("--max-table-elements", EXIT_TABLE_ELEMENTS),
+        ResourceLimit::Instances => ("--max-instances", EXIT_INSTANCES),
+    }
+}
+
 /// Runs a WebAssembly module
 #[derive(StructOpt)]
 #[structopt(name = "run", setting = AppSettings::TrailingVarArg)]
@@ -78,10 +136,40 @@ pub struct RunCommand {
     #[structopt(long, value_name = "FUNCTION")]
     invoke: Option<String>,
 
-    /// Grant access to a guest directory mapped as a host directory
+    /// Read the arguments of the function to run as a JSON array, and print
+    /// its results as a JSON array
+    #[structopt(long = "invoke-json", requires = "invoke")]
+    invoke_json: bool,
+
+    /// Grant access to a guest directory mapped as a host directory, or
+    /// mounted read-only from a host tar archive if HOST_DIR ends in `.tar`
     #[structopt(long = "mapdir", number_of_values = 1, value_name = "GUEST_DIR::HOST_DIR", parse(try_from_str = parse_map_dirs))]
     map_dirs: Vec<(String, String)>,
 
+    /// Grant access to a guest directory held in memory, which is empty or
+    /// starts out as a copy of a host directory; changes to it are never
+    /// written back to the host
+    #[structopt(long = "virt-dir", number_of_values = 1, value_name = "GUEST_DIR[=HOST_DIR]", parse(from_str = parse_virt_dir))]
+    virt_dirs: Vec<(String, Option<PathBuf>)>,
+
+    /// Read the program's standard input from the given file
+    #[structopt(long = "stdin-file", value_name = "FILE", parse(from_os_str))]
+    stdin_file: Option<PathBuf>,
+
+    /// Write the program's standard output to the given file
+    #[structopt(long = "stdout-file", value_name = "FILE", parse(from_os_str))]
+    stdout_file: Option<PathBuf>,
+
+    /// Pass the host's environment variables to the program, in addition to
+    /// those given by `--env`
+    #[structopt(
+        long = "inherit-env",
+        value_name = "BOOL",
+        default_value = "false",
+        parse(try_from_str)
+    )]
+    inherit_env: bool,
+
     /// The path of the WebAssembly module to run
     #[structopt(
         index = 1,
@@ -91,6 +179,12 @@ pub struct RunCommand {
     )]
     module: PathBuf,
 
+    /// Allow running modules precompiled by `wasmtime compile`. Precompiled
+    /// modules are native code which isn't sandboxed, so only pass this for
+    /// files as trusted as the host itself
+    #[structopt(long = "allow-precompiled")]
+    allow_precompiled: bool,
+
     /// Load the given WebAssembly module before the main module
     #[structopt(
         long = "preload",
@@ -108,6 +202,56 @@ pub struct RunCommand {
     )]
     wasm_timeout: Option<Duration>,
 
+    /// Maximum total size in bytes of the linear memories of the program
+    #[structopt(long = "max-memory-size", value_name = "BYTES")]
+    max_memory_size: Option<usize>,
+
+    /// Maximum number of elements of each table of the program
+    #[structopt(long = "max-table-elements", value_name = "ELEMENTS")]
+    max_table_elements: Option<u32>,
+
+    /// Maximum number of module instances, including preloaded modules
+    #[structopt(long = "max-instances", value_name = "INSTANCES")]
+    max_instances: Option<usize>,
+
+    /// Amount of fuel the program may consume, where most wasm instructions
+    /// consume one unit of fuel
+    #[structopt(long = "fuel", value_name = "FUEL")]
+    fuel: Option<u64>,
+
+    /// Maximum native stack space in bytes available to wasm code
+    #[structopt(long = "max-wasm-stack", value_name = "BYTES")]
+    max_wasm_stack: Option<usize>,
+
+    /// Print every WASI hostcall made by the program to stderr
+    #[structopt(long = "trace-wasi")]
+    trace_wasi: bool,
+
+    /// Format of the `--trace-wasi` output (text or json)
+    #[structopt(
+        long = "trace-wasi-format",
+        value_name = "FORMAT",
+        default_value = "text",
+        parse(try_from_str = parse_trace_format),
+    )]
+    trace_wasi_format: TraceFormat,
+
+    /// Record the nondeterministic inputs of the program (clocks, randomness,
+    /// and the results of filesystem and I/O calls) to the given file. Only
+    /// supported by `wasi_snapshot_preview1` programs
+    #[structopt(
+        long = "record",
+        value_name = "FILE",
+        parse(from_os_str),
+        conflicts_with = "replay"
+    )]
+    record: Option<PathBuf>,
+
+    /// Replay the nondeterministic inputs previously saved with `--record`,
+    /// without accessing files or writing any output on behalf of the program
+    #[structopt(long = "replay", value_name = "FILE", parse(from_os_str))]
+    replay: Option<PathBuf>,
+
     // NOTE: this must come last for trailing varargs
     /// The arguments to pass to the module
     #[structopt(value_name = "ARGS")]
@@ -124,24 +268,54 @@ impl RunCommand {
             pretty_env_logger::init();
         }
 
-        let mut config = self.common.config()?;
+        let mut config = self.common.config(None)?;
         if self.wasm_timeout.is_some() {
             config.interruptable(true);
         }
+        if self.fuel.is_some() {
+            config.consume_fuel(true);
+        }
+        if let Some(size) = self.max_wasm_stack {
+            config.max_wasm_stack(size);
+        }
         let engine = Engine::new(&config);
-        let store = Store::new(&engine);
+        let store = Store::new_with_limits(&engine, &self.store_limits());
+        if let Some(fuel) = self.fuel {
+            store.add_fuel(fuel)?;
+        }
 
         // Make wasi available by default.
-        let preopen_dirs = self.compute_preopen_dirs()?;
+        let wasi_config = self.common.config_file()?.wasi;
+        let file_preopens = wasi_config.preopens();
+        let preopen_dirs = self.compute_preopen_dirs(&file_preopens)?;
+        let virt_dirs = self.compute_virt_dirs(&file_preopens)?;
+        let has_virt_dirs = !virt_dirs.is_empty();
         let argv = self.compute_argv();
+        let vars = self.compute_vars(&wasi_config.env);
+        let journal = self.open_journal()?;
+        let has_journal = journal.is_some();
 
         let mut linker = Linker::new(&store);
-        populate_with_wasi(&mut linker, &preopen_dirs, &argv, &self.vars)?;
+        let trace = if self.trace_wasi {
+            Some(self.trace_wasi_format)
+        } else {
+            None
+        };
+        populate_with_wasi(
+            &mut linker,
+            &preopen_dirs,
+            virt_dirs,
+            &argv,
+            &vars,
+            trace,
+            journal.clone(),
+            self.open_stdio()?,
+        )?;
 
         // Load the preload wasm modules.
         for (name, path) in self.preloads.iter() {
-            // Read the wasm module binary either as `*.wat` or a raw binary
-            let module = Module::from_file(&engine, path)?;
+            let module = load_module(&engine, path, self.allow_precompiled)?;
+            check_snapshot_0_supported(&module, has_virt_dirs, has_journal)?;
 
             // Add the module's functions to the linker.
             linker.module(name, &module).context(format!(
@@ -152,17 +326,62 @@ impl RunCommand {
         }
 
         // Load the main wasm module.
-        match self
-            .load_main_module(&mut linker)
-            .with_context(|| format!("failed to run main module `{}`", self.module.display()))
-        {
+        let result = self
+            .load_main_module(&mut linker, has_virt_dirs, has_journal)
+            .with_context(|| format!("failed to run main module `{}`", self.module.display()));
+
+        if let Some(journal) = &journal {
+            // The journal is buffered, and `process::exit` below doesn't drop it.
+            journal.flush().context("failed to write journal")?;
+
+            // A diverging replay makes the outcome of the program meaningless,
+            // so report it before anything else.
+            if let Some(divergence) = journal.divergence() {
+                bail!("replay diverged from the recorded run: {}", divergence);
+            }
+        }
+
+        match result {
             Ok(()) => (),
             Err(e) => {
+                // If the program failed because it exceeded one of the limits,
+                // report which one, since the error itself may not make it
+                // clear.
+                if let Some(limit) = self.exceeded_limit(&e) {
+                    eprintln!("Error: {:?}", e);
+                    eprintln!("the program exceeded the limit set by `{}`", limit.0);
+                    process::exit(limit.1);
+                }
+                // A program which traps after a memory or table couldn't grow
+                // most likely failed because of it, but one which exits on its
+                // own keeps its exit status.
+                let exit_status = e
+                    .downcast_ref::<Trap>()
+                    .and_then(wasmtime_wasi::exit_status);
+                if let Some(limit) = store.exceeded_limit() {
+                    let (flag, status) = limit_flag(limit);
+                    match exit_status {
+                        Some(0) => {}
+                        Some(_) => eprintln!(
+                            "note: the program hit the limit set by `{}` before exiting",
+                            flag
+                        ),
+                        None => {
+                            eprintln!("Error: {:?}", e);
+                            eprintln!(
+                                "the program failed after exceeding the limit set by `{}`",
+                                flag
+                            );
+                            process::exit(status);
+                        }
+                    }
+                }
+
                 // If the program exited because of a non-zero exit status, print
                 // a message and exit.
                 if let Some(trap) = e.downcast_ref::<Trap>() {
                     // Print the error message in the usual way.
-                    if let Some(status) = trap.i32_exit_status() {
+                    if let Some(I32Exit(status)) = wasmtime_wasi::i32_exit(trap) {
                         // On Windows, exit status 3 indicates an abort (see below),
                         // so return 1 indicating a non-zero status to avoid ambiguity.
                         if cfg!(windows) && status >= 3 {
@@ -171,6 +390,15 @@ impl RunCommand {
                         process::exit(status);
                     }
 
+                    // If the program was terminated by a signal, exit the way a
+                    // native program killed by that signal would appear to.
+                    if let Some(exit) = trap.downcast_ref::<SignalExit>() {
+                        if cfg!(windows) {
+                            process::exit(3);
+                        }
+                        process::exit(exit.exit_status());
+                    }
+
                     eprintln!("Error: {:?}", e);
 
                     // If the program exited because of a trap, return an error code
@@ -192,9 +420,56 @@ impl RunCommand {
         Ok(())
     }
 
-    fn compute_preopen_dirs(&self) -> Result<Vec<(String, File)>> {
+    fn store_limits(&self) -> StoreLimits {
+        let mut limits = StoreLimits::new();
+        if let Some(size) = self.max_memory_size {
+            limits.memory_size(size);
+        }
+        if let Some(elements) = self.max_table_elements {
+            limits.table_elements(elements);
+        }
+        if let Some(instances) = self.max_instances {
+            limits.instances(instances);
+        }
+        limits
+    }
+
+    /// Returns the flag setting the limit the program failed with `error`
+    /// because of, if any, and the exit status to report it with.
+    fn exceeded_limit(&self, error: &anyhow::Error) -> Option<(&'static str, i32)> {
+        let trap = match error.downcast_ref::<Trap>() {
+            Some(trap) => trap,
+            // Instantiating a module failed because of a limit.
+            None => return error.downcast_ref().copied().map(limit_flag),
+        };
+        match trap.trap_code()? {
+            TrapCode::OutOfFuel => Some(("--fuel", EXIT_FUEL)),
+            TrapCode::StackOverflow if self.max_wasm_stack.is_some() => {
+                Some(("--max-wasm-stack", EXIT_WASM_STACK))
+            }
+            _ => None,
+        }
+    }
+
+    /// Preopens the directories given by the configuration file, and then
+    /// those given by `--dir` and `--mapdir`.
+    fn compute_preopen_dirs(
+        &self,
+        file_preopens: &[(String, PathBuf)],
+    ) -> Result<Vec<(String, File)>> {
         let mut preopen_dirs = Vec::new();
 
+        for (guest, host) in file_preopens.iter() {
+            if is_archive(host) {
+                continue;
+            }
+            preopen_dirs.push((
+                guest.clone(),
+                preopen_dir(host)
+                    .with_context(|| format!("failed to open directory '{}'", host.display()))?,
+            ));
+        }
+
         for dir in self.dirs.iter() {
             preopen_dirs.push((
                 dir.clone(),
@@ -203,6 +478,9 @@ impl RunCommand {
         }
 
         for (guest, host) in self.map_dirs.iter() {
+            if is_archive(Path::new(host)) {
+                continue;
+            }
             preopen_dirs.push((
                 guest.clone(),
                 preopen_dir(host)
@@ -213,6 +491,97 @@ impl RunCommand {
         Ok(preopen_dirs)
     }
 
+    /// Returns the virtual directories: the archives mounted by the
+    /// configuration file and `--mapdir`, whose host side is an archive rather
+    /// than a directory, followed by those of `--virt-dir`.
+    fn compute_virt_dirs(
+        &self,
+        file_preopens: &[(String, PathBuf)],
+    ) -> Result<Vec<(String, VirtualDir)>> {
+        let map_dirs = self
+            .map_dirs
+            .iter()
+            .map(|(guest, host)| (guest.clone(), PathBuf::from(host)));
+        let mut virt_dirs = Vec::new();
+        for (guest, host) in file_preopens.iter().cloned().chain(map_dirs) {
+            if !is_archive(&host) {
+                continue;
+            }
+            let archive = File::open(&host)
+                .and_then(VirtualDir::from_tar)
+                .with_context(|| format!("failed to mount archive '{}'", host.display()))?;
+            virt_dirs.push((guest, archive));
+        }
+
+        for (guest, host) in self.virt_dirs.iter() {
+            let dir = match host {
+                Some(host) => VirtualDir::snapshot(host)
+                    .with_context(|| format!("failed to copy directory '{}'", host.display()))?,
+                None => VirtualDir::new(true),
+            };
+            virt_dirs.push((guest.clone(), dir));
+        }
+
+        Ok(virt_dirs)
+    }
+
+    /// Opens the files given by `--stdin-file` and `--stdout-file`.
+    fn open_stdio(&self) -> Result<(Option<File>, Option<File>)> {
+        let stdin = match &self.stdin_file {
+            Some(path) => Some(
+                File::open(path).with_context(|| format!("failed to open '{}'", path.display()))?,
+            ),
+            None => None,
+        };
+        let stdout = match &self.stdout_file {
+            Some(path) => Some(
+                File::create(path)
+                    .with_context(|| format!("failed to create '{}'", path.display()))?,
+            ),
+            None => None,
+        };
+        Ok((stdin, stdout))
+    }
+
+    /// Returns the environment variables of the host if `--inherit-env` is
+    /// set, then those given by the configuration file, and then those given by
+    /// `--env`, each overriding the variables of the same name before them.
+    fn compute_vars(&self, file_vars: &BTreeMap<String, String>) -> Vec<(String, String)> {
+        let host_vars: Vec<(String, String)> = if self.inherit_env {
+            std::env::vars().collect()
+        } else {
+            Vec::new()
+        };
+        let overridden =
+            |name: &str| self.vars.iter().any(|(n, _)| n == name) || file_vars.contains_key(name);
+        host_vars
+            .into_iter()
+            .filter(|(name, _)| !overridden(name))
+            .chain(
+                file_vars
+                    .iter()
+                    .filter(|(name, _)| !self.vars.iter().any(|(n, _)| n == *name))
+                    .map(|(name, value)| (name.clone(), value.clone())),
+            )
+            .chain(self.vars.iter().cloned())
+            .collect()
+    }
+
+    fn open_journal(&self) -> Result<Option<Rc<Journal>>> {
+        let journal = if let Some(path) = &self.record {
+            let file = File::create(path)
+                .with_context(|| format!("failed to create journal '{}'", path.display()))?;
+            Journal::record(file)
+        } else if let Some(path) = &self.replay {
+            File::open(path)
+                .and_then(|file| Journal::replay(BufReader::new(file)))
+                .with_context(|| format!("failed to read journal '{}'", path.display()))?
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(Rc::new(journal)))
+    }
+
     fn compute_argv(&self) -> Vec<String> {
         let mut result = Vec::new();
 
@@ -236,7 +605,12 @@ impl RunCommand {
         result
     }
 
-    fn load_main_module(&self, linker: &mut Linker) -> Result<()> {
+    fn load_main_module(
+        &self,
+        linker: &mut Linker,
+        has_virt_dirs: bool,
+        has_journal: bool,
+    ) -> Result<()> {
         if let Some(timeout) = self.wasm_timeout {
             let handle = linker.store().interrupt_handle()?;
             thread::spawn(move || {
@@ -245,9 +619,13 @@ impl RunCommand {
             });
         }
 
-        // Read the wasm module binary either as `*.wat` or a raw binary.
         // Use "" as a default module name.
-        let module = Module::from_file(linker.store().engine(), &self.module)?;
+        let module = load_module(
+            linker.store().engine(),
+            &self.module,
+            self.allow_precompiled,
+        )?;
+        check_snapshot_0_supported(&module, has_virt_dirs, has_journal)?;
         linker
             .module("", &module)
             .context(format!("failed to instantiate {:?}", self.module))?;
@@ -271,15 +649,40 @@ impl RunCommand {
 
     fn invoke_func(&self, func: Func, name: Option<&str>) -> Result<()> {
         let ty = func.ty();
-        if ty.params().len() > 0 {
-            eprintln!(
-                "warning: using `--invoke` with a function that takes arguments \
-                 is experimental and may break in the future"
-            );
+        let values = if self.invoke_json {
+            self.json_args(&ty)?
+        } else {
+            self.text_args(&ty, name)?
+        };
+
+        // Invoke the function and then afterwards print all the results that came
+        // out, if there are any.
+        let results = func.call(&values).with_context(|| {
+            if let Some(name) = name {
+                format!("failed to invoke `{}`", name)
+            } else {
+                format!("failed to invoke command default")
+            }
+        })?;
+
+        if self.invoke_json {
+            let results = results.iter().map(vals::to_json).collect();
+            println!("{}", serde_json::Value::Array(results));
+        } else {
+            for result in results.iter() {
+                println!("{}", vals::to_string(result));
+            }
         }
+
+        Ok(())
+    }
+
+    /// Parses the arguments of a function from the module arguments, one per
+    /// argument.
+    fn text_args(&self, ty: &FuncType, name: Option<&str>) -> Result<Vec<Val>> {
         let mut args = self.module_args.iter();
         let mut values = Vec::new();
-        for ty in ty.params() {
+        for (i, ty) in ty.params().iter().enumerate() {
             let val = match args.next() {
                 Some(s) => s,
                 None => {
@@ -290,77 +693,156 @@ impl RunCommand {
                     }
                 }
             };
-            values.push(match ty {
-                // TODO: integer parsing here should handle hexadecimal notation
-                // like `0x0...`, but the Rust standard library currently only
-                // parses base-10 representations.
-                ValType::I32 => Val::I32(val.parse()?),
-                ValType::I64 => Val::I64(val.parse()?),
-                ValType::F32 => Val::F32(val.parse()?),
-                ValType::F64 => Val::F64(val.parse()?),
-                t => bail!("unsupported argument type {:?}", t),
-            });
+            values.push(vals::parse(ty, val).with_context(|| format!("invalid argument {}", i))?);
         }
+        Ok(values)
+    }
 
-        // Invoke the function and then afterwards print all the results that came
-        // out, if there are any.
-        let results = func.call(&values).with_context(|| {
-            if let Some(name) = name {
-                format!("failed to invoke `{}`", name)
-            } else {
-                format!("failed to invoke command default")
-            }
-        })?;
-        if !results.is_empty() {
-            eprintln!(
-                "warning: using `--invoke` with a function that returns values \
-                 is experimental and may break in the future"
+    /// Reads the arguments of a function from a JSON array given as the only
+    /// module argument.
+    fn json_args(&self, ty: &FuncType) -> Result<Vec<Val>> {
+        let args = match self.module_args.as_slice() {
+            [] => Vec::new(),
+            [json] => match serde_json::from_str(json).context("invalid JSON arguments")? {
+                serde_json::Value::Array(args) => args,
+                _ => bail!("the JSON arguments must be an array"),
+            },
+            _ => bail!("the JSON arguments must be given as a single array"),
+        };
+        if args.len() != ty.params().len() {
+            bail!(
+                "expected {} arguments, but {} were given",
+                ty.params().len(),
+                args.len()
             );
         }
+        ty.params()
+            .iter()
+            .zip(args.iter())
+            .enumerate()
+            .map(|(i, (ty, arg))| {
+                vals::from_json(ty, arg).with_context(|| format!("invalid argument {}", i))
+            })
+            .collect()
+    }
+}
 
-        for result in results.into_vec() {
-            match result {
-                Val::I32(i) => println!("{}", i),
-                Val::I64(i) => println!("{}", i),
-                Val::F32(f) => println!("{}", f),
-                Val::F64(f) => println!("{}", f),
-                Val::ExternRef(_) => println!("<externref>"),
-                Val::FuncRef(_) => println!("<externref>"),
-                Val::V128(i) => println!("{}", i),
-            }
+/// Loads a module precompiled by `wasmtime compile` if `allow_precompiled`, or
+/// otherwise compiles the wasm module binary either as `*.wat` or a raw binary.
+fn load_module(engine: &Engine, path: &Path, allow_precompiled: bool) -> Result<Module> {
+    let bytes = fs::read(path).with_context(|| format!("failed to read `{}`", path.display()))?;
+    if Module::is_precompiled(&bytes) {
+        if !allow_precompiled {
+            bail!(
+                "`{}` is a precompiled module, which is native code that isn't \
+                 sandboxed; pass `--allow-precompiled` to run it if it is trusted",
+                path.display()
+            );
         }
+        // SAFETY: a precompiled module is native code which runs unsandboxed
+        // and can't be validated, so it must be as trusted as the host. The
+        // user vouched for it with `--allow-precompiled`.
+        unsafe { Module::deserialize(engine, &bytes) }
+            .with_context(|| format!("failed to load precompiled module `{}`", path.display()))
+    } else {
+        Module::from_file(engine, path)
+    }
+}
 
-        Ok(())
+/// Fails if there are virtual directories to mount or a journal to record or
+/// replay but `module` uses WASI snapshot 0, which doesn't support them.
+fn check_snapshot_0_supported(
+    module: &Module,
+    has_virt_dirs: bool,
+    has_journal: bool,
+) -> Result<()> {
+    if !module.imports().any(|i| i.module() == "wasi_unstable") {
+        return Ok(());
     }
+    if has_virt_dirs {
+        bail!(
+            "archives and `--virt-dir` directories can't be mounted for programs \
+             using WASI snapshot 0 (`wasi_unstable`)"
+        );
+    }
+    if has_journal {
+        bail!(
+            "`--record` and `--replay` aren't supported for programs using WASI \
+             snapshot 0 (`wasi_unstable`)"
+        );
+    }
+    Ok(())
 }
 
 /// Populates the given `Linker` with WASI APIs.
+///
+/// The program's standard input and output are the given files, if any, and
+/// otherwise those of the host.
+#[allow(clippy::too_many_arguments)]
 fn populate_with_wasi(
     linker: &mut Linker,
     preopen_dirs: &[(String, File)],
+    virt_dirs: Vec<(String, VirtualDir)>,
     argv: &[String],
     vars: &[(String, String)],
+    trace: Option<TraceFormat>,
+    journal: Option<Rc<Journal>>,
+    (stdin, stdout): (Option<File>, Option<File>),
 ) -> Result<()> {
     // Add the current snapshot to the linker.
     let mut cx = WasiCtxBuilder::new();
     cx.inherit_stdio().args(argv).envs(vars);
 
+    if let Some(file) = &stdin {
+        cx.stdin(OsFile::try_from(file.try_clone()?)?);
+    }
+    if let Some(file) = &stdout {
+        cx.stdout(OsFile::try_from(file.try_clone()?)?);
+    }
+
     for (name, file) in preopen_dirs {
         cx.preopened_dir(file.try_clone()?, name);
     }
 
+    for (name, dir) in virt_dirs {
+        cx.preopened_virt_dir(dir, name);
+    }
+
+    if let Some(format) = trace {
+        cx.trace(Tracer::stderr(format));
+    }
+
+    if let Some(journal) = journal {
+        cx.journal(journal);
+    }
+
     let cx = cx.build()?;
     let wasi = Wasi::new(linker.store(), cx);
     wasi.add_to_linker(linker)?;
 
-    // Repeat the above, but this time for snapshot 0.
+    // Repeat the above, but this time for snapshot 0. Note that snapshot 0
+    // doesn't support virtual directories nor journaling, so programs using
+    // it are rejected if there are archives or `--virt-dir` directories to
+    // mount, or a journal to record or replay (see
+    // `check_snapshot_0_supported`).
     let mut cx = wasi_common::old::snapshot_0::WasiCtxBuilder::new();
     cx.inherit_stdio().args(argv).envs(vars);
 
+    if let Some(file) = stdin {
+        cx.stdin(file);
+    }
+    if let Some(file) = stdout {
+        cx.stdout(file);
+    }
+
     for (name, file) in preopen_dirs {
         cx.preopened_dir(file.try_clone()?, name);
     }
 
+    if let Some(format) = trace {
+        cx.trace(Tracer::stderr(format));
+    }
+
     let cx = cx.build()?;
     let wasi = wasmtime_wasi::old::snapshot_0::Wasi::new(linker.store(), cx);
     wasi.add_to_linker(linker)?;
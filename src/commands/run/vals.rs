@@ -0,0 +1,128 @@
+//! Reading the arguments of functions run with `--invoke` and printing their
+//! results.
+
+use anyhow::{bail, Context as _, Result};
+use serde_json::{json, Value};
+use wasmtime::{Val, ValType};
+use wast::parser::{self, Parse, ParseBuffer};
+
+/// Parses a value of type `ty` written as in the WebAssembly text format.
+///
+/// Integers may be decimal or hexadecimal, and signed or unsigned; floats may
+/// also be hexadecimal, `inf` or `nan:0x...`; `v128` values are written with
+/// their lanes, e.g. `i32x4 1 2 3 4`; and null references are written `null`.
+pub fn parse(ty: &ValType, s: &str) -> Result<Val> {
+    let val = match ty {
+        ValType::I32 => parse_text::<i32>(s).map(Val::I32),
+        ValType::I64 => parse_text::<i64>(s).map(Val::I64),
+        ValType::F32 => parse_text::<wast::Float32>(s).map(|f| Val::F32(f.bits)),
+        ValType::F64 => parse_text::<wast::Float64>(s).map(|f| Val::F64(f.bits)),
+        ValType::V128 => parse_text::<wast::V128Const>(s)
+            .map(|v| Val::V128(u128::from_le_bytes(v.to_le_bytes()))),
+        ValType::ExternRef if s == "null" => Ok(Val::ExternRef(None)),
+        ValType::FuncRef if s == "null" => Ok(Val::FuncRef(None)),
+        ValType::ExternRef | ValType::FuncRef => {
+            bail!("only null `{}` arguments are supported", ty)
+        }
+    };
+    val.with_context(|| format!("invalid {} value `{}`", ty, s))
+}
+
+fn parse_text<T>(s: &str) -> Result<T>
+where
+    T: for<'a> Parse<'a>,
+{
+    let buf = ParseBuffer::new(s)?;
+    Ok(parser::parse::<T>(&buf)?)
+}
+
+/// Formats a value so that `parse` reads it back.
+pub fn to_string(val: &Val) -> String {
+    match val {
+        Val::I32(i) => i.to_string(),
+        Val::I64(i) => i.to_string(),
+        Val::F32(bits) => {
+            let f = f32::from_bits(*bits);
+            if f.is_nan() {
+                nan_to_string(f.is_sign_negative(), u64::from(bits & 0x7f_ffff), 0x40_0000)
+            } else {
+                float_to_string(f.into(), f.to_string())
+            }
+        }
+        Val::F64(bits) => {
+            let f = f64::from_bits(*bits);
+            if f.is_nan() {
+                nan_to_string(
+                    f.is_sign_negative(),
+                    bits & 0xf_ffff_ffff_ffff,
+                    0x8_0000_0000_0000,
+                )
+            } else {
+                float_to_string(f, f.to_string())
+            }
+        }
+        Val::V128(v) => {
+            let lanes = v.to_le_bytes();
+            let lane = |i: usize| {
+                let mut bytes = [0; 4];
+                bytes.copy_from_slice(&lanes[i * 4..i * 4 + 4]);
+                u32::from_le_bytes(bytes)
+            };
+            format!(
+                "i32x4 {:#x} {:#x} {:#x} {:#x}",
+                lane(0),
+                lane(1),
+                lane(2),
+                lane(3)
+            )
+        }
+        Val::ExternRef(None) | Val::FuncRef(None) => "null".to_string(),
+        Val::ExternRef(Some(_)) => "<externref>".to_string(),
+        Val::FuncRef(Some(_)) => "<funcref>".to_string(),
+    }
+}
+
+/// Formats a NaN with its sign and, unless it's the canonical NaN, its payload.
+fn nan_to_string(negative: bool, payload: u64, canonical_payload: u64) -> String {
+    let sign = if negative { "-" } else { "" };
+    if payload == canonical_payload {
+        format!("{}nan", sign)
+    } else {
+        format!("{}nan:{:#x}", sign, payload)
+    }
+}
+
+/// Formats a float other than NaN, given its shortest decimal representation
+/// which reads back as the same float.
+fn float_to_string(f: f64, shortest: String) -> String {
+    if f == 0.0 && f.is_sign_negative() {
+        // Older versions of Rust drop the sign of negative zero.
+        "-0".to_string()
+    } else {
+        shortest
+    }
+}
+
+/// Reads a value of type `ty` from JSON, where it is either a number, a string
+/// in the syntax read by `parse`, or `null` for a null reference.
+pub fn from_json(ty: &ValType, json: &Value) -> Result<Val> {
+    match json {
+        Value::String(s) => parse(ty, s),
+        Value::Number(n) => parse(ty, &n.to_string()),
+        Value::Null => parse(ty, "null"),
+        other => bail!("expected a number, string or null, found `{}`", other),
+    }
+}
+
+/// Writes a value as JSON, as an object with its `type` and its `value` as
+/// formatted by `to_string`, or `null` for a null reference.
+pub fn to_json(val: &Val) -> Value {
+    let value = match val {
+        Val::ExternRef(None) | Val::FuncRef(None) => Value::Null,
+        val => Value::String(to_string(val)),
+    };
+    json!({
+        "type": val.ty().to_string(),
+        "value": value,
+    })
+}
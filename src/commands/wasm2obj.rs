@@ -1,13 +1,12 @@
 //! The module that implements the `wasmtime wasm2obj` command.
 
 use crate::obj::compile_to_obj;
-use crate::{init_file_per_thread_logger, pick_compilation_strategy, CommonOptions};
-use anyhow::{anyhow, Context as _, Result};
+use crate::{init_file_per_thread_logger, parse_target, pick_compilation_strategy, CommonOptions};
+use anyhow::{Context as _, Result};
 use std::{
     fs::File,
     io::Write,
     path::{Path, PathBuf},
-    str::FromStr,
 };
 use structopt::{clap::AppSettings, StructOpt};
 use target_lexicon::Triple;
@@ -17,10 +16,6 @@ use wasmtime_environ::CacheConfig;
 pub const WASM2OBJ_AFTER_HELP: &str = "The translation is dependent on the environment chosen.\n\
      The default is a dummy environment that produces placeholder values.";
 
-fn parse_target(s: &str) -> Result<Triple> {
-    Triple::from_str(&s).map_err(|e| anyhow!(e))
-}
-
 /// Translates a WebAssembly module to native object file
 #[derive(StructOpt)]
 #[structopt(
@@ -60,6 +55,7 @@ impl WasmToObjCommand {
             pretty_env_logger::init();
         }
 
+        let file = self.common.config_file()?;
         let cache_config = if self.common.disable_cache {
             CacheConfig::new_cache_disabled()
         } else {
@@ -73,9 +69,9 @@ impl WasmToObjCommand {
             &data,
             self.target.as_ref(),
             strategy,
-            self.common.enable_simd,
-            self.common.opt_level(),
-            self.common.debug_info,
+            self.common.enable_simd(&file.engine),
+            self.common.opt_level(&file.engine)?,
+            self.common.debug_info(&file.engine),
             &cache_config,
         )?;
 
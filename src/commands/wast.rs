@@ -33,7 +33,7 @@ impl WastCommand {
             pretty_env_logger::init();
         }
 
-        let config = self.common.config()?;
+        let config = self.common.config(None)?;
         let store = Store::new(&Engine::new(&config));
         let mut wast_context = WastContext::new(store);
 
@@ -0,0 +1,112 @@
+//! The engine and WASI settings of the configuration file given by `--config`.
+//!
+//! The same file configures the cache, whose `[cache]` section is read by
+//! `wasmtime_environ::CacheConfig` instead.
+
+use anyhow::{Context as _, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The sections of the configuration file read by the CLI.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ConfigFile {
+    #[serde(rename = "cache")]
+    _cache: Option<toml::Value>,
+    #[serde(default)]
+    pub engine: EngineConfig,
+    #[serde(default)]
+    pub wasi: WasiConfig,
+}
+
+/// The `[engine]` section, with the settings of the flags of the same names.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct EngineConfig {
+    pub debug_info: Option<bool>,
+    pub wasm_simd: Option<bool>,
+    pub wasm_reference_types: Option<bool>,
+    pub wasm_multi_value: Option<bool>,
+    pub wasm_threads: Option<bool>,
+    pub wasm_bulk_memory: Option<bool>,
+    pub opt_level: Option<OptLevel>,
+    #[serde(default)]
+    pub cranelift_flags: BTreeMap<String, toml::Value>,
+    pub cranelift_debug_verifier: Option<bool>,
+    pub cranelift_nan_canonicalization: Option<bool>,
+    pub cranelift_spectre_mitigations: Option<bool>,
+    pub static_memory_maximum_size: Option<u64>,
+    pub static_memory_guard_size: Option<u64>,
+    pub dynamic_memory_guard_size: Option<u64>,
+}
+
+/// An optimization level, written either as a string or an integer.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(crate) enum OptLevel {
+    Integer(u8),
+    String(String),
+}
+
+impl OptLevel {
+    pub fn to_wasmtime(&self) -> Result<wasmtime::OptLevel> {
+        match self {
+            OptLevel::Integer(level) => crate::parse_opt_level(&level.to_string()),
+            OptLevel::String(level) => crate::parse_opt_level(level),
+        }
+    }
+}
+
+/// The `[wasi]` section, used by `wasmtime run`.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct WasiConfig {
+    /// Host directories to preopen under the same name, like `--dir`.
+    #[serde(default)]
+    dirs: Vec<String>,
+    /// Guest directories mapped to host directories or archives, like
+    /// `--mapdir`.
+    #[serde(default)]
+    map_dirs: BTreeMap<String, String>,
+    /// Environment variables to pass to the program, like `--env`.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// The directory of the configuration file, which relative host paths are
+    /// relative to.
+    #[serde(skip)]
+    base: PathBuf,
+}
+
+impl WasiConfig {
+    /// Returns the guest directories to preopen and the host directories or
+    /// archives they are mapped to.
+    pub fn preopens(&self) -> Vec<(String, PathBuf)> {
+        let dirs = self.dirs.iter().map(|dir| (dir, dir));
+        dirs.chain(self.map_dirs.iter())
+            .map(|(guest, host)| (guest.clone(), self.base.join(host)))
+            .collect()
+    }
+}
+
+impl ConfigFile {
+    /// Reads the configuration file at `path`.
+    pub fn load(path: &Path) -> Result<ConfigFile> {
+        let bytes = fs::read(path)
+            .with_context(|| format!("failed to read config file: {}", path.display()))?;
+        let mut config: ConfigFile = toml::from_slice(&bytes)
+            .with_context(|| format!("failed to parse config file: {}", path.display()))?;
+        config.wasi.base = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(config)
+    }
+}
+
+/// Formats the value of a Cranelift flag as the `--cranelift-flags` option
+/// would take it, so that booleans and numbers needn't be quoted.
+pub(crate) fn cranelift_flag_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
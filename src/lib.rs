@@ -25,11 +25,15 @@
 )]
 
 pub mod commands;
+mod config_file;
 mod obj;
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
+use config_file::{cranelift_flag_value, ConfigFile, EngineConfig};
 use std::path::PathBuf;
+use std::str::FromStr;
 use structopt::StructOpt;
+use target_lexicon::Triple;
 use wasmtime::{Config, ProfilingStrategy, Strategy};
 
 pub use obj::compile_to_obj;
@@ -84,7 +88,8 @@ fn init_file_per_thread_logger(prefix: &'static str) {
 /// Common options for commands that translate WebAssembly modules
 #[derive(StructOpt)]
 struct CommonOptions {
-    /// Use specified configuration file
+    /// Use specified configuration file, for the cache and for the settings
+    /// that aren't given as flags
     #[structopt(long, parse(from_os_str), value_name = "CONFIG_PATH")]
     config: Option<PathBuf>,
 
@@ -97,32 +102,32 @@ struct CommonOptions {
     log_to_files: bool,
 
     /// Generate debug information
-    #[structopt(short = "g")]
-    debug_info: bool,
+    #[structopt(short = "g", require_equals = true)]
+    debug_info: Option<Option<bool>>,
 
     /// Disable cache system
     #[structopt(long)]
     disable_cache: bool,
 
     /// Enable support for proposed SIMD instructions
-    #[structopt(long)]
-    enable_simd: bool,
+    #[structopt(long, require_equals = true)]
+    enable_simd: Option<Option<bool>>,
 
     /// Enable support for reference types
-    #[structopt(long)]
-    enable_reference_types: bool,
+    #[structopt(long, require_equals = true)]
+    enable_reference_types: Option<Option<bool>>,
 
     /// Enable support for multi-value functions
     #[structopt(long)]
     enable_multi_value: Option<bool>,
 
     /// Enable support for Wasm threads
-    #[structopt(long)]
-    enable_threads: bool,
+    #[structopt(long, require_equals = true)]
+    enable_threads: Option<Option<bool>>,
 
     /// Enable support for bulk memory instructions
-    #[structopt(long)]
-    enable_bulk_memory: bool,
+    #[structopt(long, require_equals = true)]
+    enable_bulk_memory: Option<Option<bool>>,
 
     /// Enable all experimental Wasm features
     #[structopt(long)]
@@ -145,13 +150,9 @@ struct CommonOptions {
     optimize: bool,
 
     /// Optimization level for generated functions (0 (none), 1, 2 (most), or s
-    /// (size))
-    #[structopt(
-        long,
-        parse(try_from_str = parse_opt_level),
-        default_value = "2",
-    )]
-    opt_level: wasmtime::OptLevel,
+    /// (size)); defaults to 2
+    #[structopt(long, parse(try_from_str = parse_opt_level))]
+    opt_level: Option<wasmtime::OptLevel>,
 
     /// Other Cranelift flags to be passed down to Cranelift.
     #[structopt(long, parse(try_from_str = parse_cranelift_flag))]
@@ -171,29 +172,75 @@ struct CommonOptions {
     dynamic_memory_guard_size: Option<u64>,
 
     /// Enable Cranelift's internal debug verifier (expensive)
-    #[structopt(long)]
-    enable_cranelift_debug_verifier: bool,
+    #[structopt(long, require_equals = true)]
+    enable_cranelift_debug_verifier: Option<Option<bool>>,
 
     /// Enable Cranelift's internal NaN canonicalization
-    #[structopt(long)]
-    enable_cranelift_nan_canonicalization: bool,
+    #[structopt(long, require_equals = true)]
+    enable_cranelift_nan_canonicalization: Option<Option<bool>>,
+
+    /// Harden explicit bounds checks of memories and tables against Spectre
+    /// attacks
+    #[structopt(long, require_equals = true)]
+    enable_cranelift_spectre_mitigations: Option<Option<bool>>,
 }
 
 impl CommonOptions {
-    fn config(&self) -> Result<Config> {
+    /// Reads the configuration file given by `--config`, if any.
+    fn config_file(&self) -> Result<ConfigFile> {
+        match &self.config {
+            Some(path) => ConfigFile::load(path),
+            None => Ok(ConfigFile::default()),
+        }
+    }
+
+    /// Builds the engine configuration for `target`, or for the host with the
+    /// CPU features it supports if `None`.
+    fn config(&self, target: Option<&Triple>) -> Result<Config> {
+        let file = self.config_file()?;
+        let engine = &file.engine;
         let mut config = Config::new();
+        if let Some(target) = target {
+            config.target(&target.to_string())?;
+        }
         config
-            .cranelift_debug_verifier(self.enable_cranelift_debug_verifier)
-            .debug_info(self.debug_info)
-            .wasm_bulk_memory(self.enable_bulk_memory || self.enable_all)
-            .wasm_simd(self.enable_simd || self.enable_all)
-            .wasm_reference_types(self.enable_reference_types || self.enable_all)
-            .wasm_multi_value(self.enable_multi_value.unwrap_or(true) || self.enable_all)
-            .wasm_threads(self.enable_threads || self.enable_all)
-            .cranelift_opt_level(self.opt_level())
+            .cranelift_debug_verifier(
+                flag(self.enable_cranelift_debug_verifier)
+                    .or(engine.cranelift_debug_verifier)
+                    .unwrap_or(false),
+            )
+            .debug_info(self.debug_info(engine))
+            .wasm_bulk_memory(self.wasm_feature(self.enable_bulk_memory, engine.wasm_bulk_memory))
+            .wasm_simd(self.enable_simd(engine))
+            .wasm_reference_types(
+                self.wasm_feature(self.enable_reference_types, engine.wasm_reference_types),
+            )
+            .wasm_multi_value(
+                self.enable_multi_value
+                    .or(self.enable_all())
+                    .or(engine.wasm_multi_value)
+                    .unwrap_or(true),
+            )
+            .wasm_threads(self.wasm_feature(self.enable_threads, engine.wasm_threads))
+            .cranelift_opt_level(self.opt_level(engine)?)
             .strategy(pick_compilation_strategy(self.cranelift, self.lightbeam)?)?
             .profiler(pick_profiling_strategy(self.jitdump, self.vtune)?)?
-            .cranelift_nan_canonicalization(self.enable_cranelift_nan_canonicalization);
+            .cranelift_nan_canonicalization(
+                flag(self.enable_cranelift_nan_canonicalization)
+                    .or(engine.cranelift_nan_canonicalization)
+                    .unwrap_or(false),
+            )
+            .cranelift_spectre_mitigations(
+                flag(self.enable_cranelift_spectre_mitigations)
+                    .or(engine.cranelift_spectre_mitigations)
+                    .unwrap_or(false),
+            );
+        // Flags given on the command line are set last, to override the file.
+        for (name, value) in engine.cranelift_flags.iter() {
+            unsafe {
+                config.cranelift_other_flag(name, &cranelift_flag_value(value))?;
+            }
+        }
         for CraneliftFlag { name, value } in &self.cranelift_flags {
             unsafe {
                 config.cranelift_other_flag(name, value)?;
@@ -209,24 +256,70 @@ impl CommonOptions {
                 }
             }
         }
-        if let Some(max) = self.static_memory_maximum_size {
+        if let Some(max) = self
+            .static_memory_maximum_size
+            .or(engine.static_memory_maximum_size)
+        {
             config.static_memory_maximum_size(max);
         }
-        if let Some(size) = self.static_memory_guard_size {
+        if let Some(size) = self
+            .static_memory_guard_size
+            .or(engine.static_memory_guard_size)
+        {
             config.static_memory_guard_size(size);
         }
-        if let Some(size) = self.dynamic_memory_guard_size {
+        if let Some(size) = self
+            .dynamic_memory_guard_size
+            .or(engine.dynamic_memory_guard_size)
+        {
             config.dynamic_memory_guard_size(size);
         }
         Ok(config)
     }
 
-    fn opt_level(&self) -> wasmtime::OptLevel {
-        match (self.optimize, self.opt_level.clone()) {
-            (true, _) => wasmtime::OptLevel::Speed,
-            (false, other) => other,
+    fn opt_level(&self, engine: &EngineConfig) -> Result<wasmtime::OptLevel> {
+        match (self.optimize, &self.opt_level, &engine.opt_level) {
+            (true, _, _) => Ok(wasmtime::OptLevel::Speed),
+            (false, Some(level), _) => Ok(level.clone()),
+            (false, None, Some(level)) => level.to_wasmtime(),
+            (false, None, None) => Ok(wasmtime::OptLevel::Speed),
         }
     }
+
+    /// Whether a WebAssembly proposal is enabled by its `flag`, by
+    /// `--enable-all`, or by the configuration `file`, in that order.
+    fn wasm_feature(&self, flag_value: Option<Option<bool>>, file: Option<bool>) -> bool {
+        flag(flag_value)
+            .or(self.enable_all())
+            .or(file)
+            .unwrap_or(false)
+    }
+
+    fn enable_all(&self) -> Option<bool> {
+        if self.enable_all {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    fn enable_simd(&self, engine: &EngineConfig) -> bool {
+        self.wasm_feature(self.enable_simd, engine.wasm_simd)
+    }
+
+    fn debug_info(&self, engine: &EngineConfig) -> bool {
+        flag(self.debug_info).or(engine.debug_info).unwrap_or(false)
+    }
+}
+
+/// The value of a boolean flag which may be given as `--flag`, enabling it,
+/// or as `--flag=false`, so that it can override the configuration file.
+fn flag(value: Option<Option<bool>>) -> Option<bool> {
+    value.map(|value| value.unwrap_or(true))
+}
+
+fn parse_target(s: &str) -> Result<Triple> {
+    Triple::from_str(&s).map_err(|e| anyhow!(e))
 }
 
 fn parse_opt_level(opt_level: &str) -> Result<wasmtime::OptLevel> {
@@ -1,11 +1,11 @@
 use anyhow::{bail, Result};
 use std::io::Write;
 use std::path::Path;
-use std::process::{Command, Output};
+use std::process::{Command, Output, Stdio};
 use tempfile::NamedTempFile;
 
-// Run the wasmtime CLI with the provided args and return the `Output`.
-fn run_wasmtime_for_output(args: &[&str]) -> Result<Output> {
+// Build a command running the wasmtime CLI with the provided args.
+fn wasmtime_command(args: &[&str]) -> Result<Command> {
     let runner = std::env::vars()
         .filter(|(k, _v)| k.starts_with("CARGO_TARGET") && k.ends_with("RUNNER"))
         .next();
@@ -28,7 +28,25 @@ fn run_wasmtime_for_output(args: &[&str]) -> Result<Output> {
     } else {
         Command::new(&me)
     };
-    cmd.args(args).output().map_err(Into::into)
+    cmd.args(args);
+    Ok(cmd)
+}
+
+// Run the wasmtime CLI with the provided args and return the `Output`.
+fn run_wasmtime_for_output(args: &[&str]) -> Result<Output> {
+    wasmtime_command(args)?.output().map_err(Into::into)
+}
+
+// Run the wasmtime CLI with the provided args, writing `stdin` to its
+// standard input, and return the `Output`.
+fn run_wasmtime_with_stdin(args: &[&str], stdin: &str) -> Result<Output> {
+    let mut child = wasmtime_command(args)?
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(stdin.as_bytes())?;
+    Ok(child.wait_with_output()?)
 }
 
 // Run the wasmtime CLI with the provided args and, if it succeeds, return
@@ -257,6 +275,21 @@ fn exit126_wasi_snapshot1() -> Result<()> {
     Ok(())
 }
 
+// Terminate with a signal raised through `proc_raise`.
+#[test]
+fn raise_term_wasi_snapshot1() -> Result<()> {
+    let wasm = build_wasm("tests/wasm/raise_term_wasi_snapshot1.wat")?;
+    let output = run_wasmtime_for_output(&[wasm.path().to_str().unwrap(), "--disable-cache"])?;
+    if cfg!(windows) {
+        assert_eq!(output.status.code().unwrap(), 3);
+    } else {
+        assert_eq!(output.status.code().unwrap(), 128 + 15);
+    }
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+    Ok(())
+}
+
 // Run a minimal command program.
 #[test]
 fn minimal_command() -> Result<()> {
@@ -350,3 +383,649 @@ fn greeter_preload_callable_command() -> Result<()> {
     assert_eq!(stdout, "Hello _start\nHello callable greet\nHello done\n");
     Ok(())
 }
+
+// Precompile a module with `wasmtime compile` and run the result.
+#[test]
+#[cfg_attr(target_arch = "aarch64", ignore)] // precompiling needs target flags
+fn compile_and_run_precompiled() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let cwasm = dir.path().join("hello.cwasm");
+    let cwasm = cwasm.to_str().unwrap();
+    run_wasmtime(&[
+        "compile",
+        "tests/wasm/hello_wasi_snapshot1.wat",
+        "--disable-cache",
+        "-o",
+        cwasm,
+    ])?;
+    let stdout = run_wasmtime(&["run", "--allow-precompiled", cwasm, "--disable-cache"])?;
+    assert_eq!(stdout, "Hello, world!\n");
+    Ok(())
+}
+
+// Precompiled modules are native code, and are only run when allowed.
+#[test]
+#[cfg_attr(target_arch = "aarch64", ignore)] // precompiling needs target flags
+fn run_precompiled_requires_opt_in() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let cwasm = dir.path().join("simple.cwasm");
+    let cwasm = cwasm.to_str().unwrap();
+    run_wasmtime(&[
+        "compile",
+        "tests/wasm/simple.wat",
+        "--disable-cache",
+        "-o",
+        cwasm,
+    ])?;
+    let output = run_wasmtime_for_output(&["run", cwasm, "--invoke", "simple", "4"])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--allow-precompiled"), "{}", stderr);
+    Ok(())
+}
+
+// A precompiled module is refused by an engine with different settings.
+#[test]
+#[cfg_attr(target_arch = "aarch64", ignore)] // precompiling needs target flags
+fn run_precompiled_with_different_settings() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let cwasm = dir.path().join("simple.cwasm");
+    let cwasm = cwasm.to_str().unwrap();
+    run_wasmtime(&[
+        "compile",
+        "tests/wasm/simple.wat",
+        "--disable-cache",
+        "-o",
+        cwasm,
+    ])?;
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "--allow-precompiled",
+        cwasm,
+        "--disable-cache",
+        "--opt-level",
+        "0",
+        "--invoke",
+        "simple",
+        "4",
+    ])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("module was precompiled with"), "{}", stderr);
+    Ok(())
+}
+
+// Precompile a module for a target other than the host, which can't run it.
+#[test]
+fn compile_for_other_target() -> Result<()> {
+    let target = if cfg!(target_os = "linux") {
+        "x86_64-unknown-freebsd"
+    } else {
+        "x86_64-unknown-linux-gnu"
+    };
+    let dir = tempfile::tempdir()?;
+    let cwasm = dir.path().join("simple.cwasm");
+    let cwasm = cwasm.to_str().unwrap();
+    run_wasmtime(&[
+        "compile",
+        "tests/wasm/simple.wat",
+        "--disable-cache",
+        "--target",
+        target,
+        "-o",
+        cwasm,
+    ])?;
+
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "--allow-precompiled",
+        cwasm,
+        "--invoke",
+        "simple",
+        "4",
+    ])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("`target`"), "{}", stderr);
+    Ok(())
+}
+
+// Modules can't be precompiled for targets whose flags, such as the CPU
+// features the code may use, aren't reported by their backend.
+#[test]
+fn compile_for_unchecked_target() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let cwasm = dir.path().join("simple.cwasm");
+    let output = run_wasmtime_for_output(&[
+        "compile",
+        "tests/wasm/simple.wat",
+        "--disable-cache",
+        "--target",
+        "aarch64-unknown-linux-gnu",
+        "-o",
+        cwasm.to_str().unwrap(),
+    ])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("can't be checked"), "{}", stderr);
+    assert!(!cwasm.exists());
+    Ok(())
+}
+
+// Explore a module, as text and as an HTML report.
+#[test]
+fn explore() -> Result<()> {
+    let output = run_wasmtime(&["explore", "tests/wasm/simple.wat", "--disable-cache"])?;
+    assert!(output.contains("function 0 (simple)"), "{}", output);
+    assert!(output.contains("local.get 0"), "{}", output);
+
+    let dir = tempfile::tempdir()?;
+    let html = dir.path().join("simple.html");
+    run_wasmtime(&[
+        "explore",
+        "tests/wasm/simple.wat",
+        "--disable-cache",
+        "-o",
+        html.to_str().unwrap(),
+    ])?;
+    let html = std::fs::read_to_string(&html)?;
+    assert!(html.contains("<h2>function 0 (simple)</h2>"), "{}", html);
+    Ok(())
+}
+
+// Invoke functions with arguments and results of various types.
+#[test]
+fn invoke_typed_values() -> Result<()> {
+    let invoke = |func: &str, args: &[&str]| {
+        let mut cmd = vec![
+            "run",
+            "tests/wasm/invoke-types.wat",
+            "--disable-cache",
+            "--enable-simd",
+            "--invoke",
+            func,
+        ];
+        cmd.extend_from_slice(args);
+        run_wasmtime(&cmd)
+    };
+
+    let output = invoke("swap", &["0xffff_ffff_ffff_ffff", "-0x1.8p1"])?;
+    assert_eq!(output, "-3\n-1\n");
+    let output = invoke("f32", &["nan:0x1"])?;
+    assert_eq!(output, "nan:0x1\n");
+    let output = invoke("f32", &["-inf"])?;
+    assert_eq!(output, "-inf\n");
+    let output = invoke("v128", &["i32x4 1 2 3 -1"])?;
+    assert_eq!(output, "i32x4 0x1 0x2 0x3 0xffffffff\n");
+    assert!(invoke("f32", &["1.5.0"]).is_err());
+    Ok(())
+}
+
+// Invoke a function with JSON arguments and results.
+#[test]
+fn invoke_json() -> Result<()> {
+    let output = run_wasmtime(&[
+        "run",
+        "tests/wasm/invoke-types.wat",
+        "--disable-cache",
+        "--enable-simd",
+        "--invoke",
+        "swap",
+        "--invoke-json",
+        r#"[9007199254740993, "0.1"]"#,
+    ])?;
+    assert_eq!(
+        output,
+        "[{\"type\":\"f64\",\"value\":\"0.1\"},{\"type\":\"i64\",\"value\":\"9007199254740993\"}]\n"
+    );
+
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "tests/wasm/invoke-types.wat",
+        "--disable-cache",
+        "--enable-simd",
+        "--invoke",
+        "swap",
+        "--invoke-json",
+        "[1]",
+    ])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("expected 2 arguments"), "{}", stderr);
+    Ok(())
+}
+
+// Give the engine and WASI settings in a configuration file.
+#[test]
+fn run_with_config_file() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let config = dir.path().join("config.toml");
+    std::fs::write(&config, "[engine]\nwasm-simd = true\n")?;
+    let output = run_wasmtime(&[
+        "run",
+        "--config",
+        config.to_str().unwrap(),
+        "tests/wasm/invoke-types.wat",
+        "--invoke",
+        "v128",
+        "i32x4 1 2 3 4",
+    ])?;
+    assert_eq!(output, "i32x4 0x1 0x2 0x3 0x4\n");
+
+    // Flags override the file, in both directions.
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "--config",
+        config.to_str().unwrap(),
+        "--enable-simd=false",
+        "tests/wasm/invoke-types.wat",
+        "--invoke",
+        "v128",
+        "i32x4 1 2 3 4",
+    ])?;
+    assert!(!output.status.success());
+
+    std::fs::write(&config, "[wasi]\ndirs = [\"missing\"]\n")?;
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "--config",
+        config.to_str().unwrap(),
+        "tests/wasm/minimal-command.wat",
+    ])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("failed to open directory"), "{}", stderr);
+
+    std::fs::write(&config, "[engine]\nwasm-smid = true\n")?;
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "--config",
+        config.to_str().unwrap(),
+        "tests/wasm/minimal-command.wat",
+    ])?;
+    assert!(!output.status.success());
+    Ok(())
+}
+
+// Stop a program which runs out of fuel.
+#[test]
+fn fuel_limit() -> Result<()> {
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "tests/wasm/iloop-invoke.wat",
+        "--fuel",
+        "1000",
+        "--disable-cache",
+    ])?;
+    assert_eq!(output.status.code(), Some(203));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("wasm trap: all fuel consumed"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("`--fuel`"), "{}", stderr);
+    Ok(())
+}
+
+// Fail a program whose memory can't be created or grow past
+// `--max-memory-size`.
+#[test]
+fn memory_size_limit() -> Result<()> {
+    run_wasmtime(&[
+        "run",
+        "tests/wasm/grow-memory.wat",
+        "--max-memory-size",
+        "131072",
+        "--disable-cache",
+    ])?;
+
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "tests/wasm/grow-memory.wat",
+        "--max-memory-size",
+        "32768",
+        "--disable-cache",
+    ])?;
+    assert_eq!(output.status.code(), Some(200));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("`--max-memory-size`"), "{}", stderr);
+
+    // A program which traps after its memory couldn't grow is reported as
+    // failing because of the limit.
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "tests/wasm/grow-memory.wat",
+        "--max-memory-size",
+        "65536",
+        "--disable-cache",
+    ])?;
+    assert_eq!(output.status.code(), Some(200));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unreachable"), "{}", stderr);
+    assert!(stderr.contains("`--max-memory-size`"), "{}", stderr);
+    Ok(())
+}
+
+// Fail a program whose table can't be created or grow past
+// `--max-table-elements`.
+#[test]
+#[cfg_attr(not(target_arch = "x86_64"), ignore)] // FIXME(#1886)
+fn table_elements_limit() -> Result<()> {
+    let run = |max: &str| {
+        run_wasmtime_for_output(&[
+            "run",
+            "tests/wasm/grow-table.wat",
+            "--enable-reference-types",
+            "--max-table-elements",
+            max,
+            "--disable-cache",
+        ])
+    };
+    assert!(run("2")?.status.success());
+
+    for max in ["0", "1"].iter() {
+        let output = run(max)?;
+        assert_eq!(output.status.code(), Some(201));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("`--max-table-elements`"), "{}", stderr);
+    }
+    Ok(())
+}
+
+// Fail a program which overflows `--max-wasm-stack`.
+#[test]
+fn wasm_stack_limit() -> Result<()> {
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "tests/wasm/recurse.wat",
+        "--max-wasm-stack",
+        "65536",
+        "--disable-cache",
+    ])?;
+    assert_eq!(output.status.code(), Some(204));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("call stack exhausted"), "{}", stderr);
+    assert!(stderr.contains("`--max-wasm-stack`"), "{}", stderr);
+    Ok(())
+}
+
+// A program exiting with a status of its own keeps it after hitting a limit.
+#[test]
+fn limit_keeps_exit_status() -> Result<()> {
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "tests/wasm/grow-memory-exit.wat",
+        "--max-memory-size",
+        "65536",
+        "--disable-cache",
+    ])?;
+    assert_eq!(output.status.code(), Some(2));
+    Ok(())
+}
+
+// Fail to instantiate more modules than `--max-instances`.
+#[test]
+fn instances_limit() -> Result<()> {
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "tests/wasm/greeter_command.wat",
+        "--preload",
+        "reactor=tests/wasm/greeter_reactor.wat",
+        "--max-instances",
+        "1",
+        "--disable-cache",
+    ])?;
+    assert_eq!(output.status.code(), Some(202));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("instance limit of 1 exceeded"),
+        "{}",
+        stderr
+    );
+    Ok(())
+}
+
+// Load modules in the REPL, call their exports and inspect their state.
+#[test]
+fn repl() -> Result<()> {
+    let output = run_wasmtime_with_stdin(
+        &["repl", "tests/wasm/simple.wat"],
+        r#"
+:exports
+:call simple 4
+(module $m
+  (global (export "g") (mut i32) (i32.const 7))
+  (memory (export "mem") 1)
+  (data (i32.const 16) "hello")
+  (func (export "add") (param i32 i32) (result i32)
+    (i32.add (local.get 0) (local.get 1))))
+(register "m")
+(invoke "add" (i32.const 1) (i32.const 2))
+:call m.add 0x10 1
+:set g 8
+:get m.g
+:memory mem
+:memory mem 16 5
+:call missing
+:quit
+:call add 1 1
+"#,
+    )?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout,
+        "simple: func (param i32) (result i32)\n\
+         4\n\
+         3\n\
+         17\n\
+         8\n\
+         1 pages (65536 bytes)\n\
+         00000010: 68 65 6c 6c 6f                                   hello\n"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no export named `missing`"), "{}", stderr);
+    Ok(())
+}
+
+// Redirect the standard input and output of a program to files.
+#[test]
+fn stdio_files() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let input = dir.path().join("input.txt");
+    let output = dir.path().join("output.txt");
+    std::fs::write(&input, "Hello, files!\n")?;
+    let stdout = run_wasmtime(&[
+        "run",
+        "tests/wasm/cat.wat",
+        "--stdin-file",
+        input.to_str().unwrap(),
+        "--stdout-file",
+        output.to_str().unwrap(),
+        "--disable-cache",
+    ])?;
+    assert_eq!(stdout, "");
+    assert_eq!(std::fs::read_to_string(&output)?, "Hello, files!\n");
+    Ok(())
+}
+
+// Record a program using files, and replay it once they are gone.
+#[test]
+fn record_replay() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let input = dir.path().join("input.txt");
+    let output = dir.path().join("output.txt");
+    let journal = dir.path().join("journal");
+    std::fs::write(&input, "recorded")?;
+    let stdout = run_wasmtime(&[
+        "run",
+        "tests/wasm/virt-dir.wat",
+        "--dir",
+        dir.path().to_str().unwrap(),
+        "--record",
+        journal.to_str().unwrap(),
+        "--disable-cache",
+    ])?;
+    assert_eq!(stdout, "recorded");
+    assert_eq!(std::fs::read_to_string(&output)?, "recorded");
+
+    // Replaying neither reads `input.txt` nor writes anything.
+    std::fs::remove_file(&input)?;
+    std::fs::remove_file(&output)?;
+    let stdout = run_wasmtime(&[
+        "run",
+        "tests/wasm/virt-dir.wat",
+        "--dir",
+        dir.path().to_str().unwrap(),
+        "--replay",
+        journal.to_str().unwrap(),
+        "--disable-cache",
+    ])?;
+    assert_eq!(stdout, "");
+    assert!(!output.exists());
+
+    // A program making other hostcalls than the recorded ones diverges.
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "tests/wasm/hello_wasi_snapshot1.wat",
+        "--replay",
+        journal.to_str().unwrap(),
+        "--disable-cache",
+    ])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("replay diverged"));
+    Ok(())
+}
+
+// Refuse to record or replay programs using snapshot 0, which isn't journaled.
+#[test]
+fn record_replay_snapshot0() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let journal = dir.path().join("journal");
+    run_wasmtime(&[
+        "run",
+        "tests/wasm/hello_wasi_snapshot1.wat",
+        "--record",
+        journal.to_str().unwrap(),
+        "--disable-cache",
+    ])?;
+    for option in &["--replay", "--record"] {
+        let output = run_wasmtime_for_output(&[
+            "run",
+            "tests/wasm/hello_wasi_snapshot0.wat",
+            option,
+            journal.to_str().unwrap(),
+            "--disable-cache",
+        ])?;
+        assert!(!output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+        assert!(String::from_utf8_lossy(&output.stderr).contains("WASI snapshot 0"));
+    }
+    Ok(())
+}
+
+// Run a program in a copy of a host directory, which it can't modify.
+#[test]
+fn virt_dir_snapshot() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("input.txt"), "snapshot")?;
+    let virt_dir = format!("/sandbox={}", dir.path().display());
+    let stdout = run_wasmtime(&[
+        "run",
+        "tests/wasm/virt-dir.wat",
+        "--virt-dir",
+        &virt_dir,
+        "--disable-cache",
+    ])?;
+    assert_eq!(stdout, "snapshot");
+    assert!(!dir.path().join("output.txt").exists());
+
+    // An empty virtual directory has no `input.txt`.
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "tests/wasm/virt-dir.wat",
+        "--virt-dir",
+        "/sandbox",
+        "--disable-cache",
+    ])?;
+    assert!(!output.status.success());
+    Ok(())
+}
+
+// Mount a tar archive with `--mapdir`, which is only done for paths ending in
+// `.tar` and only supported by WASI snapshot 1.
+#[test]
+fn mapdir_archive() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    // An empty archive is just its two zeroed end-of-archive blocks.
+    let archive = dir.path().join("empty.tar");
+    std::fs::write(&archive, &[0; 1024][..])?;
+    let map_dir = format!("/data::{}", archive.display());
+
+    let stdout = run_wasmtime(&[
+        "run",
+        "tests/wasm/hello_wasi_snapshot1.wat",
+        "--mapdir",
+        &map_dir,
+        "--disable-cache",
+    ])?;
+    assert_eq!(stdout, "Hello, world!\n");
+
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "tests/wasm/hello_wasi_snapshot0.wat",
+        "--mapdir",
+        &map_dir,
+        "--disable-cache",
+    ])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("WASI snapshot 0"));
+
+    // Other files aren't archives, and can't be mapped.
+    let file = dir.path().join("empty.txt");
+    std::fs::write(&file, "")?;
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "tests/wasm/hello_wasi_snapshot1.wat",
+        "--mapdir",
+        &format!("/data::{}", file.display()),
+        "--disable-cache",
+    ])?;
+    assert!(!output.status.success());
+    Ok(())
+}
+
+// Trace the hostcalls of a program, including `proc_exit`, which never returns.
+#[test]
+fn trace_wasi() -> Result<()> {
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "tests/wasm/hello_wasi_snapshot1.wat",
+        "--trace-wasi",
+        "--disable-cache",
+    ])?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "Hello, world!\n");
+    assert_eq!(
+        String::from_utf8(output.stderr)?,
+        "fd_write(fd=1, iovs=[{buf=0x0, buf_len=14}], nwritten=0x10) ...\n\
+         <... fd_write resumed> = 0 (success)\n"
+    );
+
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "tests/wasm/exit2_wasi_snapshot1.wat",
+        "--trace-wasi",
+        "--trace-wasi-format",
+        "json",
+        "--disable-cache",
+    ])?;
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8(output.stderr)?,
+        "{\"event\":\"call\",\"module\":\"wasi_snapshot_preview1\",\"func\":\"proc_exit\",\
+         \"args\":{\"rval\":2}}\n"
+    );
+    Ok(())
+}
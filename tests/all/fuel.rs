@@ -0,0 +1,66 @@
+use anyhow::Result;
+use wasmtime::*;
+
+fn fuel_store() -> Store {
+    let engine = Engine::new(Config::new().consume_fuel(true));
+    Store::new(&engine)
+}
+
+#[test]
+fn loops_run_out_of_fuel() -> Result<()> {
+    let store = fuel_store();
+    store.add_fuel(10_000)?;
+    let module = Module::new(store.engine(), r#"(func (export "loop") (loop br 0))"#)?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let iloop = instance.get_func("loop").unwrap().get0::<()>()?;
+    let trap = iloop().unwrap_err();
+    assert_eq!(trap.trap_code(), Some(TrapCode::OutOfFuel));
+    assert!(
+        trap.to_string().contains("wasm trap: all fuel consumed"),
+        "{}",
+        trap
+    );
+    assert!(store.fuel_consumed().unwrap() > 10_000);
+    Ok(())
+}
+
+#[test]
+fn recursion_runs_out_of_fuel() -> Result<()> {
+    let store = fuel_store();
+    store.add_fuel(10_000)?;
+    let module = Module::new(
+        store.engine(),
+        r#"(func $f (export "f") (param i32) (call $f (i32.add (local.get 0) (i32.const 1))))"#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let f = instance.get_func("f").unwrap().get1::<i32, ()>()?;
+    let trap = f(0).unwrap_err();
+    assert_eq!(trap.trap_code(), Some(TrapCode::OutOfFuel));
+    Ok(())
+}
+
+#[test]
+fn fuel_is_consumed_by_instructions() -> Result<()> {
+    let store = fuel_store();
+    store.add_fuel(100)?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (func (export "add") (param i32 i32) (result i32)
+                (i32.add (local.get 0) (local.get 1)))
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let add = instance.get_func("add").unwrap().get2::<i32, i32, i32>()?;
+    assert_eq!(store.fuel_consumed(), Some(0));
+    assert_eq!(add(1, 2)?, 3);
+    assert_eq!(store.fuel_consumed(), Some(3));
+    Ok(())
+}
+
+#[test]
+fn fuel_requires_config() {
+    let store = Store::default();
+    assert!(store.add_fuel(1).is_err());
+    assert_eq!(store.fuel_consumed(), None);
+}
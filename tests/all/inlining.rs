@@ -0,0 +1,137 @@
+use anyhow::Result;
+use wasmtime::*;
+
+fn inlining_store() -> Store {
+    let mut config = Config::new();
+    config.cranelift_inlining(true);
+    Store::new(&Engine::new(&config))
+}
+
+#[test]
+fn small_helpers() -> Result<()> {
+    let store = inlining_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory 1)
+                (global $scale (mut i32) (i32.const 3))
+                (func $get (param i32) (result i32)
+                    (i32.load (local.get 0)))
+                (func $set (param i32 i32)
+                    (i32.store (local.get 0) (local.get 1)))
+                (func $scale (param i32) (result i32)
+                    (i32.mul (local.get 0) (global.get $scale)))
+                (func $divmod (param i32 i32) (result i32 i32)
+                    (i32.div_u (local.get 0) (local.get 1))
+                    (i32.rem_u (local.get 0) (local.get 1)))
+                (func $clamp (param i32) (result i32)
+                    (if (result i32) (i32.gt_s (local.get 0) (i32.const 100))
+                        (then (i32.const 100))
+                        (else (local.get 0))))
+                (func (export "sum") (param i32) (result i32)
+                    (local $i i32)
+                    (local $sum i32)
+                    (block $done
+                        (loop $loop
+                            (br_if $done (i32.ge_u (local.get $i) (local.get 0)))
+                            (call $set
+                                (i32.mul (local.get $i) (i32.const 4))
+                                (call $scale (local.get $i)))
+                            (local.set $sum
+                                (i32.add
+                                    (local.get $sum)
+                                    (call $get (i32.mul (local.get $i) (i32.const 4)))))
+                            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                            (br $loop)))
+                    (call $clamp (local.get $sum)))
+                (func (export "divmod") (param i32 i32) (result i32)
+                    (call $divmod (local.get 0) (local.get 1))
+                    i32.add))
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let sum = instance.get_func("sum").unwrap().get1::<i32, i32>()?;
+    let divmod = instance
+        .get_func("divmod")
+        .unwrap()
+        .get2::<i32, i32, i32>()?;
+
+    assert_eq!(sum(0)?, 0);
+    assert_eq!(sum(4)?, 18);
+    assert_eq!(sum(10)?, 100);
+    assert_eq!(divmod(17, 5)?, 5);
+    Ok(())
+}
+
+#[test]
+fn traps_in_inlined_code() -> Result<()> {
+    let store = inlining_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory 1)
+                (func $load (param i32) (result i32)
+                    (i32.load (local.get 0)))
+                (func $div (param i32 i32) (result i32)
+                    (i32.div_s (local.get 0) (local.get 1)))
+                (func $fail
+                    unreachable)
+                (func (export "load") (param i32) (result i32)
+                    (call $load (local.get 0)))
+                (func (export "div") (param i32 i32) (result i32)
+                    (call $div (local.get 0) (local.get 1)))
+                (func (export "fail")
+                    (call $fail)))
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let load = instance.get_func("load").unwrap().get1::<i32, i32>()?;
+    let div = instance.get_func("div").unwrap().get2::<i32, i32, i32>()?;
+    let fail = instance.get_func("fail").unwrap().get0::<()>()?;
+
+    assert_eq!(load(0)?, 0);
+    let trap = load(65536).unwrap_err();
+    assert_eq!(trap.trap_code(), Some(TrapCode::MemoryOutOfBounds));
+    assert_eq!(div(7, 2)?, 3);
+    let trap = div(1, 0).unwrap_err();
+    assert_eq!(trap.trap_code(), Some(TrapCode::IntegerDivisionByZero));
+    let trap = fail().unwrap_err();
+    assert_eq!(trap.trap_code(), Some(TrapCode::UnreachableCodeReached));
+    Ok(())
+}
+
+#[test]
+fn recursion() -> Result<()> {
+    let store = inlining_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (func $fac (export "fac") (param i64) (result i64)
+                    (if (result i64) (i64.eqz (local.get 0))
+                        (then (i64.const 1))
+                        (else
+                            (i64.mul
+                                (local.get 0)
+                                (call $fac (i64.sub (local.get 0) (i64.const 1)))))))
+                (func $even (export "even") (param i32) (result i32)
+                    (if (result i32) (i32.eqz (local.get 0))
+                        (then (i32.const 1))
+                        (else (call $odd (i32.sub (local.get 0) (i32.const 1))))))
+                (func $odd (param i32) (result i32)
+                    (if (result i32) (i32.eqz (local.get 0))
+                        (then (i32.const 0))
+                        (else (call $even (i32.sub (local.get 0) (i32.const 1)))))))
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let fac = instance.get_func("fac").unwrap().get1::<i64, i64>()?;
+    let even = instance.get_func("even").unwrap().get1::<i32, i32>()?;
+
+    assert_eq!(fac(10)?, 3628800);
+    assert_eq!(even(10)?, 1);
+    assert_eq!(even(7)?, 0);
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use wasmtime::*;
+
+#[test]
+fn memory_limit() -> Result<()> {
+    let store = Store::new_with_limits(
+        &Engine::default(),
+        StoreLimits::new().memory_size(2 * 65536),
+    );
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory (export "m") 1)
+                (func (export "grow") (param i32) (result i32)
+                    (memory.grow (local.get 0))))
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let grow = instance.get_func("grow").unwrap().get1::<i32, i32>()?;
+    assert_eq!(grow(1)?, 1);
+    assert_eq!(store.exceeded_limit(), None);
+    assert_eq!(grow(1)?, -1);
+    assert_eq!(store.exceeded_limit(), Some(ResourceLimit::MemorySize));
+
+    let memory = instance.get_memory("m").unwrap();
+    assert!(memory.grow(1).is_err());
+    assert_eq!(memory.size(), 2);
+
+    let module = Module::new(store.engine(), "(module (memory 3))")?;
+    let err = Instance::new(&store, &module, &[]).err().unwrap();
+    assert!(
+        err.to_string().contains("exceeds the limit"),
+        "{}",
+        err.to_string()
+    );
+    assert_eq!(
+        err.downcast_ref::<ResourceLimit>(),
+        Some(&ResourceLimit::MemorySize)
+    );
+
+    // The limit applies to all the memories of the store together.
+    let module = Module::new(store.engine(), "(module (memory 1))")?;
+    assert!(Instance::new(&store, &module, &[]).is_err());
+    Ok(())
+}
+
+#[test]
+fn table_limit() -> Result<()> {
+    let store = Store::new_with_limits(&Engine::default(), StoreLimits::new().table_elements(5));
+    let module = Module::new(store.engine(), "(module (table (export \"t\") 5 funcref))")?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let table = instance.get_table("t").unwrap();
+    assert!(table.grow(1, Val::FuncRef(None)).is_err());
+    assert_eq!(store.exceeded_limit(), Some(ResourceLimit::TableElements));
+
+    let ty = TableType::new(ValType::FuncRef, Limits::new(6, None));
+    assert!(Table::new(&store, ty, Val::FuncRef(None)).is_err());
+    Ok(())
+}
+
+#[test]
+fn instance_limit() -> Result<()> {
+    let store = Store::new_with_limits(&Engine::default(), StoreLimits::new().instances(2));
+    let module = Module::new(store.engine(), "(module)")?;
+    // Host functions don't count towards the limit.
+    Func::wrap(&store, || {});
+    Instance::new(&store, &module, &[])?;
+    Instance::new(&store, &module, &[])?;
+    assert_eq!(store.exceeded_limit(), None);
+    let err = Instance::new(&store, &module, &[]).err().unwrap();
+    assert!(
+        err.to_string().contains("instance limit of 2 exceeded"),
+        "{}",
+        err.to_string()
+    );
+    assert_eq!(
+        err.downcast_ref::<ResourceLimit>(),
+        Some(&ResourceLimit::Instances)
+    );
+    assert_eq!(store.exceeded_limit(), Some(ResourceLimit::Instances));
+    Ok(())
+}
+
+#[test]
+fn failed_instances_dont_count() -> Result<()> {
+    let store = Store::new_with_limits(&Engine::default(), StoreLimits::new().instances(1));
+    let module = Module::new(store.engine(), "(module (func unreachable) (start 0))")?;
+    assert!(Instance::new(&store, &module, &[]).is_err());
+    let module = Module::new(store.engine(), "(module)")?;
+    Instance::new(&store, &module, &[])?;
+    assert_eq!(store.exceeded_limit(), None);
+    Ok(())
+}
@@ -2,17 +2,21 @@ mod cli_tests;
 mod custom_signal_handler;
 mod debug;
 mod externals;
+mod fuel;
 mod func;
 mod fuzzing;
 mod globals;
 mod iloop;
 mod import_calling_export;
 mod import_indexes;
+mod inlining;
 mod instance;
 mod invoke_func_via_table;
+mod limits;
 mod linker;
 mod memory_creator;
 mod name;
+mod spectre;
 mod stack_overflow;
 mod table;
 mod traps;
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use wasmtime::*;
+
+// Dynamic memories are always bounds-checked explicitly, so these exercise the
+// clamped addresses of both memories and tables.
+fn spectre_store() -> Store {
+    let mut config = Config::new();
+    config
+        .static_memory_maximum_size(0)
+        .cranelift_spectre_mitigations(true);
+    Store::new(&Engine::new(&config))
+}
+
+#[test]
+fn memory_accesses() -> Result<()> {
+    let store = spectre_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory 1)
+                (func (export "store") (param i32 i32)
+                    (i32.store (local.get 0) (local.get 1)))
+                (func (export "load") (param i32) (result i32)
+                    (i32.load (local.get 0))))
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let store_fn = instance.get_func("store").unwrap().get2::<i32, i32, ()>()?;
+    let load = instance.get_func("load").unwrap().get1::<i32, i32>()?;
+
+    store_fn(65532, 42)?;
+    assert_eq!(load(65532)?, 42);
+    assert_eq!(load(0)?, 0);
+
+    for address in &[65533, 65536, -1] {
+        let trap = load(*address).unwrap_err();
+        assert_eq!(trap.trap_code(), Some(TrapCode::MemoryOutOfBounds));
+        let trap = store_fn(*address, 1).unwrap_err();
+        assert_eq!(trap.trap_code(), Some(TrapCode::MemoryOutOfBounds));
+    }
+    Ok(())
+}
+
+#[test]
+fn indirect_calls() -> Result<()> {
+    let store = spectre_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (type $t (func (result i32)))
+                (table 2 funcref)
+                (elem (i32.const 0) $one $two)
+                (func $one (result i32) (i32.const 1))
+                (func $two (result i32) (i32.const 2))
+                (func (export "call") (param i32) (result i32)
+                    (call_indirect (type $t) (local.get 0))))
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let call = instance.get_func("call").unwrap().get1::<i32, i32>()?;
+
+    assert_eq!(call(0)?, 1);
+    assert_eq!(call(1)?, 2);
+    for index in &[2, -1] {
+        let trap = call(*index).unwrap_err();
+        assert_eq!(trap.trap_code(), Some(TrapCode::TableOutOfBounds));
+    }
+    Ok(())
+}
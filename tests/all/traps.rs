@@ -30,6 +30,49 @@ fn test_trap_return() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_trap_downcast_host_error() -> Result<()> {
+    #[derive(Debug)]
+    struct HostError(u32);
+
+    impl std::fmt::Display for HostError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "host error {}", self.0)
+        }
+    }
+
+    impl std::error::Error for HostError {}
+
+    let store = Store::default();
+    let wat = r#"
+        (module
+        (func $hello (import "" "hello"))
+        (func (export "run") (call $hello))
+        )
+    "#;
+
+    let module = Module::new(store.engine(), wat)?;
+    let hello_func = Func::wrap(&store, || -> Result<(), Trap> {
+        Err(Trap::from(
+            Box::new(HostError(42)) as Box<dyn std::error::Error + Send + Sync>
+        ))
+    });
+
+    let instance = Instance::new(&store, &module, &[hello_func.into()])?;
+    let run_func = instance.get_func("run").expect("expected function export");
+
+    let e = run_func
+        .call(&[])
+        .err()
+        .expect("error calling function")
+        .downcast::<Trap>()?;
+
+    assert_eq!(e.downcast_ref::<HostError>().map(|e| e.0), Some(42));
+    assert!(Trap::new("test 123").downcast_ref::<HostError>().is_none());
+
+    Ok(())
+}
+
 #[test]
 #[cfg_attr(target_arch = "aarch64", ignore)] // FIXME(#1642)
 fn test_trap_trace() -> Result<()> {
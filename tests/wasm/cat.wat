@@ -0,0 +1,20 @@
+(module
+  (import "wasi_snapshot_preview1" "fd_read"
+    (func $fd_read (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write"
+    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  ;; Copies standard input to standard output.
+  (func (export "_start")
+    (i32.store (i32.const 0) (i32.const 64))
+    (i32.store (i32.const 4) (i32.const 1024))
+    (loop $copy
+      (if (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8))
+        (then unreachable))
+      (if (i32.load (i32.const 8))
+        (then
+          (i32.store (i32.const 16) (i32.const 64))
+          (i32.store (i32.const 20) (i32.load (i32.const 8)))
+          (if (call $fd_write (i32.const 1) (i32.const 16) (i32.const 1) (i32.const 24))
+            (then unreachable))
+          (br $copy))))))
@@ -0,0 +1,7 @@
+(module
+  (import "wasi_snapshot_preview1" "proc_exit"
+    (func $__wasi_proc_exit (param i32)))
+  (memory 1)
+  (func (export "_start")
+    (if (i32.eq (memory.grow (i32.const 1)) (i32.const -1))
+      (then (call $__wasi_proc_exit (i32.const 2))))))
@@ -0,0 +1,5 @@
+(module
+  (memory 1)
+  (func (export "_start")
+    (if (i32.eq (memory.grow (i32.const 1)) (i32.const -1))
+      (then unreachable))))
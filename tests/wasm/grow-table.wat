@@ -0,0 +1,5 @@
+(module
+  (table 1 externref)
+  (func (export "_start")
+    (if (i32.eq (table.grow (ref.null extern) (i32.const 1)) (i32.const -1))
+      (then unreachable))))
@@ -0,0 +1,12 @@
+(module
+    (func (export "swap") (param i64 f64) (result f64 i64)
+        local.get 1
+        local.get 0
+    )
+    (func (export "f32") (param f32) (result f32)
+        local.get 0
+    )
+    (func (export "v128") (param v128) (result v128)
+        local.get 0
+    )
+)
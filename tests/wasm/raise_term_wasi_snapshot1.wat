@@ -0,0 +1,13 @@
+(module
+  (import "wasi_snapshot_preview1" "proc_raise"
+    (func $__wasi_proc_raise (param i32) (result i32)))
+  (func $_start
+    ;; SIGCHLD is ignored by default, so this returns success.
+    (if (call $__wasi_proc_raise (i32.const 16))
+      (then unreachable))
+    ;; SIGTERM terminates the program.
+    (drop (call $__wasi_proc_raise (i32.const 15)))
+    unreachable
+  )
+  (export "_start" (func $_start))
+)
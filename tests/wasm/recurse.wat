@@ -0,0 +1,3 @@
+(module
+  (func $recurse (export "_start")
+    call $recurse))
@@ -0,0 +1,36 @@
+(module
+  (import "wasi_snapshot_preview1" "path_open"
+    (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_read"
+    (func $fd_read (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write"
+    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 100) "input.txt")
+  (data (i32.const 120) "output.txt")
+  ;; Prints `input.txt` of the first preopened directory, and then writes it to
+  ;; `output.txt` in the same directory.
+  (func (export "_start")
+    (local $fd i32)
+    (local $len i32)
+    ;; Open `input.txt` for reading.
+    (if (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 9)
+          (i32.const 0) (i64.const 2) (i64.const 0) (i32.const 0) (i32.const 8))
+      (then unreachable))
+    (local.set $fd (i32.load (i32.const 8)))
+    (i32.store (i32.const 0) (i32.const 256))
+    (i32.store (i32.const 4) (i32.const 1024))
+    (if (call $fd_read (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8))
+      (then unreachable))
+    (local.set $len (i32.load (i32.const 8)))
+    ;; Print it.
+    (i32.store (i32.const 4) (local.get $len))
+    (if (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8))
+      (then unreachable))
+    ;; Create `output.txt` and write it there.
+    (if (call $path_open (i32.const 3) (i32.const 0) (i32.const 120) (i32.const 10)
+          (i32.const 1) (i64.const 64) (i64.const 0) (i32.const 0) (i32.const 8))
+      (then unreachable))
+    (local.set $fd (i32.load (i32.const 8)))
+    (if (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8))
+      (then unreachable))))